@@ -0,0 +1,293 @@
+//! `restruct`: Pack and unpack binary data using Format Strings from the command line.
+//!
+//! ```text
+//! restruct unpack [--json] <FMT> [FILE]
+//! restruct pack <FMT> [FILE]
+//! restruct diagram <FMT>
+//! ```
+//!
+//! `unpack` reads consecutive records from `FILE` (or stdin) and prints their elements.
+//! `pack` reads one record per line from `FILE` (or stdin), given either as a JSON array or as
+//! comma-separated values, and writes the packed records to stdout. Values containing commas,
+//! quotes or line breaks are quoted as in CSV, e.g. `"a,""b"""`. `diagram` prints the layout on
+//! this platform as an RFC-style packet diagram.
+
+use std::io::{BufRead, Read, Write};
+
+use restruct::{export, layout, parser, DynStruct, Value};
+
+const USAGE: &str = "Usage:
+    restruct unpack [--json] <FMT> [FILE]
+    restruct pack <FMT> [FILE]
+    restruct diagram <FMT>";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Open the given file, or stdin if there is none
+fn input(path: Option<&String>) -> Result<Box<dyn BufRead>> {
+    Ok(match path {
+        Some(path) => Box::new(std::io::BufReader::new(std::fs::File::open(path)?)),
+        None => Box::new(std::io::stdin().lock()),
+    })
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(v) => (*v).into(),
+        Value::I8(v) => (*v).into(),
+        Value::I16(v) => (*v).into(),
+        Value::I32(v) => (*v).into(),
+        Value::I64(v) => (*v).into(),
+        Value::U8(v) => (*v).into(),
+        Value::U16(v) => (*v).into(),
+        Value::U32(v) => (*v).into(),
+        Value::U64(v) => (*v).into(),
+        Value::F32(v) => (*v).into(),
+        Value::F64(v) => (*v).into(),
+        Value::Bytes(v) => v.clone().into(),
+    }
+}
+
+/// Split a row of comma-separated values into cells, quoted as written by `restruct::csv`;
+/// `None` is returned if a quoted cell is not terminated
+fn split_row(row: &str) -> Option<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    (!quoted).then_some(cells)
+}
+
+/// `true` if the record is a JSON array or complete comma-separated values, as opposed to a
+/// quoted cell continuing on the next line
+fn is_complete(record: &str) -> bool {
+    record.trim_start().starts_with('[') || split_row(record).is_some()
+}
+
+/// Convert one record of input, a JSON array or comma-separated values, into values
+fn parse_record(fmt: &DynStruct, line: &str) -> Result<Vec<Value>> {
+    if !line.trim_start().starts_with('[') {
+        return Ok(split_row(line)
+            .ok_or("unterminated quoted value")?
+            .iter()
+            .enumerate()
+            .map(|(i, field)| fmt.parse_value(i, field))
+            .collect::<std::result::Result<_, _>>()?);
+    }
+    let items = match serde_json::from_str(line)? {
+        serde_json::Value::Array(items) => items,
+        _ => unreachable!(),
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            Ok(match item {
+                serde_json::Value::String(s) => fmt.parse_value(i, s)?,
+                serde_json::Value::Array(bytes) => Value::Bytes(
+                    bytes
+                        .iter()
+                        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| format!("element {}: expected an array of bytes", i))?,
+                ),
+                other => fmt.parse_value(i, &other.to_string())?,
+            })
+        })
+        .collect()
+}
+
+fn unpack(fmt: &DynStruct, mut inp: impl Read, json: bool, out: &mut impl Write) -> Result<()> {
+    if fmt.size() == 0 {
+        return Err("the format string describes zero bytes".into());
+    }
+    let mut buf = vec![0; fmt.size()];
+    for n in 0.. {
+        match restruct::read_exact(&mut inp, &mut buf) {
+            Ok(()) => {}
+            Err(restruct::Error::Eof) => break,
+            Err(restruct::Error::Truncated { got, .. }) => {
+                eprintln!("Ignoring {} trailing bytes", got);
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let values = fmt.unpack(&buf)?;
+        if json {
+            let values = values.iter().map(to_json).collect::<Vec<_>>();
+            writeln!(out, "{}", serde_json::Value::Array(values))?;
+            continue;
+        }
+        writeln!(out, "record {} @ {}", n, n * fmt.size())?;
+        for (i, (value, (code, offset, _))) in values.iter().zip(fmt.elements()).enumerate() {
+            writeln!(out, "    {} ({} @ {}): {}", i, code, offset, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn pack(fmt: &DynStruct, inp: impl BufRead, out: &mut impl Write) -> Result<()> {
+    let mut record = String::new();
+    let mut start = 0;
+    for (n, line) in inp.lines().enumerate() {
+        let line = line?;
+        if record.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            start = n + 1;
+        } else {
+            record.push('\n');
+        }
+        record.push_str(&line);
+        if !is_complete(&record) {
+            continue;
+        }
+        let values = parse_record(fmt, &record).map_err(|e| format!("line {}: {}", start, e))?;
+        out.write_all(
+            &fmt.pack(&values)
+                .map_err(|e| format!("line {}: {}", start, e))?,
+        )?;
+        record.clear();
+    }
+    if !record.is_empty() {
+        return Err(format!("line {}: unterminated quoted value", start).into());
+    }
+    Ok(())
+}
+
+fn diagram(fmt: &str, out: &mut impl Write) -> Result<()> {
+    let format = parser::parse(fmt)?;
+    let names = format
+        .names()
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| name.unwrap_or_else(|| format!("field_{}", i)))
+        .collect::<Vec<_>>();
+    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+    let diagram = export::packet_diagram(&format, &layout::Target::host(), &names)?;
+    Ok(out.write_all(diagram.as_bytes())?)
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    match args {
+        [cmd, json, fmt, rest @ ..] if cmd == "unpack" && json == "--json" && rest.len() <= 1 => {
+            unpack(&DynStruct::new(fmt)?, input(rest.first())?, true, &mut out)?
+        }
+        [cmd, fmt, rest @ ..] if cmd == "unpack" && rest.len() <= 1 => {
+            unpack(&DynStruct::new(fmt)?, input(rest.first())?, false, &mut out)?
+        }
+        [cmd, fmt, rest @ ..] if cmd == "pack" && rest.len() <= 1 => {
+            pack(&DynStruct::new(fmt)?, input(rest.first())?, &mut out)?
+        }
+        [cmd, fmt] if cmd == "diagram" => diagram(fmt, &mut out)?,
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+    Ok(out.flush()?)
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_records() {
+        let fmt = DynStruct::new("<H2s").unwrap();
+        let mut out = Vec::new();
+        unpack(
+            &fmt,
+            &[1, 0, b'a', 0, 2, 0, b'b', b'c'][..],
+            false,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "record 0 @ 0
+    0 (H @ 0): 1
+    1 (2s @ 2): b\"a\\x00\"
+record 1 @ 4
+    0 (H @ 0): 2
+    1 (2s @ 2): b\"bc\"
+"
+        );
+
+        let mut out = Vec::new();
+        unpack(&fmt, &[1, 0, b'a', 0, 2][..], true, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "[1,[97,0]]\n");
+    }
+
+    #[test]
+    fn pack_records() {
+        let fmt = DynStruct::new("<H2s?").unwrap();
+        let mut out = Vec::new();
+        pack(
+            &fmt,
+            &b"1,a,true\n\n[2, [98, 99], false]\n[\"0x3\", \"d\", 1]\n"[..],
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            [1, 0, b'a', 0, 1, 2, 0, b'b', b'c', 0, 3, 0, b'd', 0, 1]
+        );
+
+        let e = pack(&fmt, &b"1,a\n"[..], &mut Vec::new()).unwrap_err();
+        assert_eq!(e.to_string(), "line 1: expected 3 values, got 2");
+
+        let mut out = Vec::new();
+        pack(
+            &fmt,
+            &b"1,\",\",0\n2,\"\"\"\",1\n\n3,\"\n\",0\n"[..],
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, [1, 0, b',', 0, 0, 2, 0, b'"', 0, 1, 3, 0, b'\n', 0, 0]);
+
+        let e = pack(&fmt, &b"1,\"a,true\n\n"[..], &mut Vec::new()).unwrap_err();
+        assert_eq!(e.to_string(), "line 1: unterminated quoted value");
+    }
+
+    #[test]
+    fn packet_diagram() {
+        let mut out = Vec::new();
+        diagram("<len:HH", &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            " 0                   1                   2                   3
+ 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|              len              |            field_1            |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+"
+        );
+
+        assert!(diagram("<`Foo`", &mut Vec::new()).is_err());
+    }
+}
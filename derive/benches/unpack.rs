@@ -0,0 +1,65 @@
+//! Compare unpacking in native and in swapped byte order, via the runtime interpreter, and of a
+//! single element of each record, to a plain copy of the buffer.
+//!
+//! Run via `cargo bench -p restruct_derive`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[derive(restruct_derive::Struct)]
+#[fmt = "=4I2Hq"]
+struct Native;
+
+#[cfg(target_endian = "little")]
+#[derive(restruct_derive::Struct)]
+#[fmt = ">4I2Hq"]
+#[restruct(columns)]
+struct Swapped;
+
+#[cfg(target_endian = "big")]
+#[derive(restruct_derive::Struct)]
+#[fmt = "<4I2Hq"]
+#[restruct(columns)]
+struct Swapped;
+
+const RECORDS: usize = 1 << 16;
+
+/// The time per record of the given function, applied to every record in the buffer
+fn measure(buf: &[u8], mut f: impl FnMut(&[u8; Native::SIZE])) -> Duration {
+    let start = Instant::now();
+    for _ in 0..32 {
+        for record in buf.chunks_exact(Native::SIZE) {
+            f(black_box(record.try_into().unwrap()));
+        }
+    }
+    start.elapsed() / (32 * RECORDS as u32)
+}
+
+fn main() {
+    let buf = (0..RECORDS * Native::SIZE)
+        .map(|i| i as u8)
+        .collect::<Vec<_>>();
+    let copy = measure(&buf, |r| {
+        black_box(*r);
+    });
+    let native = measure(&buf, |r| {
+        black_box(Native::unpack(*r));
+    });
+    let swapped = measure(&buf, |r| {
+        black_box(Swapped::unpack(*r));
+    });
+    let fmt = restruct::DynStruct::new(">4I2Hq").unwrap();
+    let dynamic = measure(&buf, |r| {
+        black_box(fmt.unpack(r).unwrap());
+    });
+    println!("copy:    {:?} per record", copy);
+    println!("native:  {:?} per record", native);
+    println!("swapped: {:?} per record", swapped);
+    let start = Instant::now();
+    for _ in 0..32 {
+        black_box(Swapped::extract_field::<6>(black_box(&buf)));
+    }
+    let column = start.elapsed() / (32 * RECORDS as u32);
+    println!("dynamic: {:?} per record", dynamic);
+    println!("column:  {:?} per record, swapped", column);
+}
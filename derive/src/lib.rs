@@ -79,7 +79,10 @@
 //!
 //! The conversion functions are `const` and may therefor be used in a const-context.
 //! As long as endianess does not need to be converted and copying can be elided, packing and
-//! unpacking should usually be free of any runtime cost.
+//! unpacking should usually be free of any runtime cost. This is up to the optimizer and not
+//! guaranteed: Fields are copied as a whole and converted using `from_ne_bytes()` & co, which
+//! usually compile to plain loads if the byte order matches the target's. The `unpack`-benchmark
+//! prints the time taken next to that of a plain copy of the buffer, for comparison.
 //!
 //! # Deriving
 //!
@@ -95,25 +98,544 @@
 //! The `fmt`-attribute can be used multiple times and all fragments are concatenated before being
 //! interpreted.
 //!
+//! The Format String can also be read from a file using the `fmt_file`-attribute, with the path
+//! being relative to the crate's root directory. Leading and trailing whitespace in the file is
+//! ignored. The crate is rebuilt if the file changes.
+//!
+//! ```ignore
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt_file = "layouts/frame_header.fmt"]
+//! struct FrameHeader;
+//! ```
+//!
+//! Likewise, the `fmt_env`-attribute reads the Format String from an environment variable at
+//! compile time, e.g. one set by a build-script via `cargo:rustc-env=...`. The crate is rebuilt
+//! if the variable changes.
+//!
+//! ```ignore
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt_env = "REGISTER_BLOCK_FMT"]
+//! struct RegisterBlock;
+//! ```
+//!
+//! Fragments from `fmt`, `fmt_file` and `fmt_env` are concatenated in the order they appear.
+//!
+//! A fragment given as `#[fmt(cfg(...), "...")]` is only used if the `cfg`-predicate holds for
+//! the target, so a native struct which differs between platforms is described by one type.
+//! Code is generated for every Format String selected by a combination of the predicates, gated
+//! by `#[cfg(...)]`; a predicate and it's negation via `not(...)` count as one, and at most 12
+//! distinct predicates can be given. Combinations giving different values to keys like
+//! `target_os` or `target_pointer_width` are skipped, as they are impossible on any target. A
+//! Format String which is invalid, e.g. as it doesn't match the `names`-attribute, is only
+//! reported if it is selected.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "@dev:Q"]
+//! #[fmt(cfg(target_pointer_width = "64"), "ino:Q")]
+//! #[fmt(cfg(not(target_pointer_width = "64")), "ino:I")]
+//! #[fmt = "mode:I"]
+//! struct Stat;
+//!
+//! assert_eq!(Stat::SIZE, if cfg!(target_pointer_width = "64") { 20 } else { 16 });
+//! ```
+//!
+//! The `extends`-attribute prepends the Format String of another derived type, so a family of
+//! messages can share a common header. Unlike nesting the header via `` `Header` ``, its
+//! elements become elements of the extending type, so there is no nested tuple. Names and
+//! defaults of the base type are carried over, and indices in `default`-attributes refer to the
+//! combined elements. The base type is resolved like any other path, e.g. `#[extends(hdr::Header)]`,
+//! but must be derived in the same crate and opt in via `#[restruct(extendable)]`. Base types
+//! using per-field attributes like `scale`, `transform` or `text`, or a Format String depending
+//! on `cfg`, can't be extended.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<I2H"]
+//! #[names(magic, kind, length)]
+//! #[default(field = 0, value = 0xa1b2c3d4)]
+//! #[restruct(extendable)]
+//! struct Header;
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[extends(Header)]
+//! #[fmt = "Bq"]
+//! #[names(flags, timestamp)]
+//! #[default(field = 1, value = 7)]
+//! #[restruct(builder)]
+//! struct Ping;
+//!
+//! let ping = Ping::builder().length(9).timestamp(1234).build();
+//! assert_eq!(ping, (0xa1b2c3d4, 7, 9, 0, 1234));
+//! assert_eq!(Ping::SIZE, Header::SIZE + 9);
+//! ```
+//!
 //! The proc-macro will add the following items to the given type, among others:
 //!
 //!  * An implementation of [`restruct::Struct`], which will hold the type aliases
-//!  for the packed and unpacked representation. For example,
-//!  `<Foo as restruct::Struct>::Packed` will be a type alias for `[u8; N]`,
-//!  where `N` is some `const`, and `...::Unpacked` will be a tuple.
+//!    for the packed and unpacked representation. For example,
+//!    `<Foo as restruct::Struct>::Packed` will be a type alias for `[u8; N]`,
+//!    where `N` is some `const`, and `...::Unpacked` will be a tuple.
+//!    The trait also provides `SIZE`, `unpack_slice()` and `pack_ref()` for use in generic code,
+//!    e.g. by `restruct::RecordReader` and `restruct::RecordWriter`, which read and write
+//!    consecutive records from an `io::Read` or to an `io::Write`, and by
+//!    `restruct::index::Index`, which indexes a file of records by a key for later lookups.
 //!  * An associated constant `SIZE`, which gives the size in bytes of the packed form.
+//!  * An associated constant `ALIGN`, which gives the largest alignment required by any field;
+//!    this is `1` unless in native mode.
 //!  * An associated constant `FIELDS`, an array of tuples of the form
-//!  `(&'static str, usize, usize, usize)` for the name of the type, the offset,
-//!  the alignment and the total size of each field.
+//!    `(&'static str, usize, usize, usize)` for the name of the type, the offset,
+//!    the alignment and the total size of each field.
+//!  * If the `layout_json`-feature is enabled, an associated constant `LAYOUT_JSON`, which
+//!    describes the layout as JSON for use by external tooling.
+//!  * If the `markdown`-feature is enabled, an associated constant `MARKDOWN`, which describes
+//!    the layout as a Markdown table for protocol documentation: The name or index, type, offset,
+//!    size and endianness of each entry in `FIELDS`, along with a description given via e.g.
+//!    `#[field_doc(field = 0, doc = "The file's signature")]`.
+//!  * An associated constant `PACKET_DIAGRAM`, which draws the layout as an RFC-style packet
+//!    diagram of 32 bits per row, labelled by the elements' names. It is only generated for
+//!    layouts that don't depend on the target, i.e. not in native mode and without `n` or `N`.
 //!  * A `const fn pack()` to convert from unpacked (tuple) into packed (array) form.
+//!  * A `const fn pack_ref()` which does the same as `pack()`, given a reference to the
+//!    unpacked form, avoiding a copy of large tuples.
+//!  * A `fn try_pack()` which converts each element of a tuple of possibly wider types via
+//!    `TryInto` before packing, e.g. `i64` or `usize` into an `H`-field. A value which does not
+//!    fit it's element is reported as a `restruct::PackError` instead of being truncated.
+//!  * A `fn pack_into()` which packs into an existing `&mut [u8]`-slice, and a `fn pack_boxed()`
+//!    and `fn read_boxed_from()` which return the packed form in a `Box`. These avoid a copy of
+//!    the packed form on the stack, e.g. for formats with large `s`-fields.
 //!  * A `const fn unpack()` to convert from packed (array) into unpacked (tuple) form.
+//!  * A `const fn unpack_into()` which does the same as `unpack()`, overwriting an existing
+//!    unpacked value, e.g. to reuse an output slot in a loop.
+//!  * A `const fn zeroed()` which returns an unpacked value with all elements set to zero.
+//!  * An associated constant `DEFAULT`, an unpacked value with all elements set to zero unless
+//!    given a value via the `default`-attribute, or unpacked from a file given via the
+//!    `init_from`-attribute (see below).
+//!  * A `const fn canonicalize()` which sets all padding- and alignment-bytes in the packed
+//!    form to zero, e.g. before hashing a buffer obtained via `from_raw()`.
+//!  * A `const fn packed_eq()` which compares two packed buffers while ignoring padding- and
+//!    alignment-bytes.
+//!  * A `const fn cmp_packed()` which orders two packed buffers by the bytes of their values,
+//!    ignoring padding- and alignment-bytes. For big-endian, unsigned numbers this is the order
+//!    of the values themselves, so records can be sorted without unpacking them.
 //!  * A `fn unpack_slice()` that takes a `&[u8]`-slice and unpacks it's content.
+//!  * A `fn unpack_all()` which unpacks all consecutive records in a `&[u8]`-slice.
+//!  * A `fn try_unpack_slice()` which does the same as `unpack_slice()`, returning an
+//!    `restruct::UnpackError` if the slice is too small.
+//!  * A `fn parse()` which does the same as `try_unpack_slice()`, also returning the rest of the
+//!    slice, so several types can be chained while walking a buffer, e.g.
+//!    `let (header, rest) = Header::parse(buf)?; let (body, rest) = Body::parse(rest)?;`.
+//!  * A `const fn ref_from()` and `const fn ref_from_mut()` which reinterpret the beginning of a
+//!    `&[u8]`-slice as a reference to the packed form without copying, if the slice is large
+//!    enough. As the packed form is a byte-array, there are no alignment requirements.
+//!  * If the `registry`-feature is enabled and the type is not generic, a registration in
+//!    `restruct::registry`, which enumerates all derived types in the final binary along with
+//!    their Format String, size and `FIELDS`.
+//!  * If the `fuzz`-feature is enabled, a `fn fuzz()` which exercises unpacking, re-packing and
+//!    validation of arbitrary input. A cargo-fuzz target is simply
+//!    `fuzz_target!(|data: &[u8]| Foo::fuzz(data));`.
+//!  * For each `#[test_vector(bytes = "DE AD C0 DE 01 90 01", values = (0xdeadc0de, 400, true))]`,
+//!    a `#[test]`-function next to the type, which asserts that the values pack into the given
+//!    bytes and that the bytes unpack into the given values. Golden examples from a
+//!    specification are thereby kept next to the layout. Bytes are given in hexadecimal,
+//!    whitespace being ignored.
+//!  * If the `rand`-feature is enabled, a `fn random()` which draws each element from the given
+//!    `rand::Rng`, e.g. for load tests or to seed property tests. Floating point numbers are
+//!    drawn from `[0, 1)`; a declared checksum is valid.
+//!  * If the `cpython`-feature is enabled and the format has no nested types, `g`, `G` or
+//!    typedefs and does not use `^`, a `fn cpython_check()` which cross-checks the size,
+//!    unpacking and packing against Python's `struct`-module by running `python3`.
 //!    This method will panic if the given slice is smaller than `Self::SIZE`.
-//!  * A `fn read_from()` to read one unpacked instance from an any `io::Read`.
+//!  * A `fn read_from()` to read one unpacked instance from an any `io::Read`. Errors are
+//!    reported as `restruct::Error`, which tells a clean end of input (`Error::Eof`) from a
+//!    truncated record and converts into `io::Error`.
+//!  * A `fn stream()` which iterates over consecutive instances read from any `io::Read`,
+//!    yielding `io::Result`s. The iteration ends cleanly if the input ends at a record boundary
+//!    and yields an error if it ends within a record.
+//!  * A `fn validate()` which applies all declared validators (currently the `checksum`) to a
+//!    packed instance, and `fn read_valid_from()` and `fn iter_valid()` which do the same as
+//!    `read_from()` and `stream()` while validating each instance. Given a
+//!    `restruct::OnInvalid`, `iter_valid()` either stops at or skips invalid instances.
+//!  * A `fn read_from_buf()` which does the same as `read_from()` for any `io::BufRead`,
+//!    unpacking directly from the reader's buffer if possible.
+//!  * A `fn skip_from()` to discard a number of packed instances from any `io::Read`, and a
+//!    `fn skip_from_seekable()` to skip them by seeking any `io::Seek`.
 //!  * A `fn write_to()` to write one unpacked instance to any `io::Write`.
+//!  * A `fn write_to_ref()` which does the same as `write_to()`, given a reference.
+//!  * If the `futures_io`-feature is enabled, `async fn read_from_async()` and
+//!    `async fn write_to_async()`, which do the same as `read_from()` and `write_to()` for any
+//!    `futures::io::AsyncRead` and `AsyncWrite`, e.g. as used by async-std or smol.
+//!  * If the `wasm`-feature is enabled, a `fn pack_to_uint8array()` and a
+//!    `fn unpack_from_uint8array()` to exchange packed instances with JavaScript as a
+//!    `js_sys::Uint8Array`, e.g. in functions exported via `wasm-bindgen`. All generated code
+//!    builds for `wasm32-unknown-unknown`, where `n` and `N` are 4 bytes wide.
+//!  * A `fn pack_hex()` and a `fn unpack_hex()` to convert between unpacked instances and
+//!    hexadecimal text, e.g. for test fixtures and log lines. If the `base64`-feature is
+//!    enabled, a `fn pack_base64()` and a `fn unpack_base64()` do the same for Base64.
 //!  * A `unsafe fn from_raw<T>(ptr: *const T)` to read one unpacked instance from
-//!  a raw pointer.
+//!    a raw pointer. The pointer does not need to be aligned.
+//!  * A `unsafe fn try_from_raw<T>(ptr: *const T)` which does the same, but takes `T` to be the
+//!    C-struct described by the Format String: It fails with a `restruct::UnpackError` if the
+//!    size of `T` is not `SIZE` rounded up to `ALIGN` or if the pointer is not aligned to `ALIGN`,
+//!    instead of reading out of bounds if the Format String does not match the C-struct's layout
+//!    on this platform.
+//!  * A `unsafe fn to_raw<T>(inp, ptr: *mut T)` to write one unpacked instance to a raw
+//!    pointer, e.g. to populate memory-mapped structures.
+//!  * A `unsafe fn from_raw_mut<T>(ptr: *mut T)` to get a mutable view of the packed form
+//!    behind a raw pointer.
+//!  * A `unsafe fn read_volatile<T>(ptr: *const T)` and `unsafe fn write_volatile<T>(inp, ptr:
+//!    *mut T)` which access each element using a volatile load/store at it's offset, e.g. for
+//!    memory-mapped registers. Numbers at aligned addresses are accessed through a pointer to
+//!    their primitive type, so the access has the width of the register. Padding is neither read
+//!    nor written.
+//!  * A `fn display()` which renders an unpacked value via `std::fmt::Display`, labeling each
+//!    element with its index, type and offset.
+//!  * A `fn diff()` which compares two unpacked values, returning a
+//!    `Vec<restruct::FieldDiff>` describing the elements which differ.
+//!  * A `fn fields_of()` which iterates over the entries in `FIELDS` of a packed value,
+//!    pairing each entry's `restruct::FieldInfo` (index, name, type, offset and size) with it's
+//!    bytes, so dump-tools can be written once for all derived types.
+//!  * A `fn raw_field()` and a `fn raw_field_mut()` to access the bytes of an entry in `FIELDS`
+//!    of a packed value by it's index, e.g. for patching tools working off metadata.
+//!  * A `fn value_of()` which decodes a single element of a packed value by it's index in the
+//!    unpacked representation into a `restruct::Value`, for debuggers and scripting layers.
+//!  * A `fn binary_search_by_field()` which binary searches a buffer of packed records, sorted
+//!    by the entry in `FIELDS` given as a const generic, comparing only that entry's bytes to a
+//!    key; e.g. `Entry::binary_search_by_field::<0>(&index, &id.to_be_bytes())`.
+//!  * Given `#[restruct(columns)]`, a `fn extract_field()` and a `fn iter_field()` which decode
+//!    a single element, given by it's index in the unpacked representation as a const generic,
+//!    from every record in a buffer of packed records, reading only that element's bytes; e.g.
+//!    `Sample::extract_field::<2>(&buf)` gives a `Vec` holding the third element of each record.
+//!    The type of each element is `<Sample as restruct::Column<2>>::Type`.
 //!  * An implementation of `std::fmt::Debug`.
+//!  * Given `#[restruct(csv)]`, an implementation of `restruct::csv::CsvRecord`, so buffers and
+//!    streams of records can be written as CSV via `restruct::csv::write_buffer()` and
+//!    `write_records()`; the elements of nested types are flattened into columns named
+//!    `outer.inner`, so nested types must implement `CsvRecord` as well.
+//!
+//! Features which generate code using optional parts of `restruct` must be enabled on both
+//! `restruct_derive` and `restruct`: Cargo builds the proc-macro with it's own set of features,
+//! so enabling them on `restruct_derive` alone fails with an error naming the missing feature.
+//! These are `futures_io`, `base64`, `rand`, `registry`, `wasm` and `cpython`.
+//!
+//! Given `#[restruct(packed)]`, a newtype `...Packed` around the packed representation is
+//! generated next to the type. It implements `TryFrom<&[u8]>`, `AsRef<[u8]>` and `From` conversions from/to the
+//! packed array and the unpacked tuple, so derived formats can be used with generic code:
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<IH"]
+//! #[restruct(packed)]
+//! struct Record;
+//!
+//! let packed = RecordPacked::try_from(&[1, 0, 0, 0, 2, 0][..]).unwrap();
+//! assert_eq!(<(u32, u16)>::from(packed), (1, 2));
+//! assert_eq!(RecordPacked::from((1, 2)), packed);
+//! assert!(RecordPacked::try_from(&[0; 5][..]).is_err());
+//! ```
+//!
+//! The newtype also has `const` instance methods, so packed values can be passed around and
+//! accessed without losing their type: `new()` packs the unpacked representation, `as_bytes()`
+//! and `unpack()` return the packed and unpacked representation, and each element has a getter
+//! and a setter, named after the element if names were given (e.g. `version()` and
+//! `set_version()`), `get_0()` and `set_0()` and so on otherwise. Getters and setters only
+//! unpack or pack their element.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<id:I version:H"]
+//! #[restruct(packed)]
+//! struct Record;
+//!
+//! let mut record = RecordPacked::new((1, 2));
+//! record.set_version(3);
+//! assert_eq!((record.id(), record.version()), (1, 3));
+//! assert_eq!(record.as_bytes(), &[1, 0, 0, 0, 3, 0]);
+//! ```
+//!
+//! The `restruct::roundtrip_tests!`-macro generates tests asserting that packing and unpacking
+//! of the given derived types round-trip.
+//!
+//! The functions converting between packed and unpacked form (`pack()`, `pack_ref()`,
+//! `unpack()`, `unpack_into()` and `unpack_slice()`) are marked `#[inline]`, so they can be
+//! inlined across crates. The `inline_hint`-attribute changes this to `"always"`, `"never"` or
+//! `"none"`, e.g. `#[inline_hint = "always"]` for tight parsing loops.
+//!
+//! The documentation generated for `SIZE`, `pack()` and `unpack()` contains a table describing
+//! the layout of each field, so users of a derived type can learn the layout from `rustdoc`.
+//!
+//! Values for the `DEFAULT`-constant are given by the index of the element in the unpacked
+//! representation, using literals. String literals are parsed as expressions, which allows
+//! values for nested formats. Byte-string literals can be used for `s`-fields.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<I2H4s"]
+//! #[default(field = 0, value = 0xa1b2c3d4)]
+//! #[default(field = 2, value = 4)]
+//! #[default(field = 3, value = b"pcap")]
+//! struct Header;
+//!
+//! let mut header = Header::DEFAULT;
+//! header.1 = 2;
+//! assert_eq!(header, (0xa1b2c3d4, 2, 4, *b"pcap"));
+//! ```
+//!
+//! Instead, the `init_from`-attribute unpacks `DEFAULT` from a file at compile time, relative to
+//! the crate's root directory. Unlike unpacking `*include_bytes!(...)` by hand, compilation fails
+//! if the file does not hold exactly `SIZE` bytes.
+//!
+//! ```ignore
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<2if?"]
+//! #[init_from = "assets/teapot.bin"]
+//! struct Tea;
+//!
+//! const TEAPOT_TEMPERATURE: i32 = Tea::DEFAULT.0;
+//! ```
+//!
+//! The elements of the unpacked representation can be given names using the `names`-attribute.
+//! Given names and `#[restruct(builder)]`, a builder-type `...Builder` is generated, having a
+//! setter for each element. The builder is created via `builder()`, starting from `DEFAULT`. It
+//! derives `Debug` and `PartialEq` unless there are more than twelve elements, for which the
+//! standard library does not implement these traits on tuples:
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<I2H"]
+//! #[names(magic, version_major, version_minor)]
+//! #[default(field = 0, value = 0xa1b2c3d4)]
+//! #[restruct(builder)]
+//! struct Header;
+//!
+//! let header = Header::builder().version_major(2).version_minor(4);
+//! assert_eq!(header.build(), (0xa1b2c3d4, 2, 4));
+//! assert_eq!(header.pack(), Header::pack((0xa1b2c3d4, 2, 4)));
+//! ```
+//!
+//! Names may also be given inline in the Format String, as `name:code`, which keeps them next
+//! to the layout, e.g. in a `fmt_file`. If a code has multiple elements, they are named
+//! `name_0`, `name_1`, ... Either all or no elements must be named, and names can't be given
+//! both inline and via the `names`-attribute.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<magic:I version:2H"]
+//! #[restruct(builder)]
+//! struct Header;
+//!
+//! let header = Header::builder().magic(0xa1b2c3d4).version_0(2).version_1(4);
+//! assert_eq!(header.build(), (0xa1b2c3d4, 2, 4));
+//! ```
+//!
+//! The Rust type an element must resolve to may be ascribed inline as well, as `code:type`
+//! (e.g. `l:u64` or `magic:I:u32`), where the type is one of the primitive integers, `f32`,
+//! `f64` or `bool`. This is a compile-time assertion, useful in native mode, where e.g. `l` is
+//! `libc::c_long`: The derive fails to compile on platforms where the element is of another
+//! type, instead of code relying on the assumption failing somewhere else.
+//!
+//! ```compile_fail
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "@i:i64"] // `c_int` is `i32`
+//! struct Header;
+//! ```
+//!
+//! The `derive`-option of the `restruct`-attribute forwards additional derives onto the generated
+//! `...Packed` and `...Builder`-types, e.g. `#[restruct(derive(Hash, serde::Serialize))]`. Traits
+//! these types always derive are left out.
+//!
+//! The `map_to`-attribute generates conversions via `From` between the unpacked representation
+//! and a user-defined struct, whose fields are in the same order as the elements of the unpacked
+//! representation. If names were given, the fields are matched by name; otherwise a tuple-struct
+//! is expected. The functions `pack_struct()` and `unpack_struct()` use these conversions.
+//!
+//! ```
+//! #[derive(Debug, PartialEq)]
+//! struct Version {
+//!     major: u16,
+//!     minor: u16,
+//! }
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<2H"]
+//! #[names(major, minor)]
+//! #[map_to(Version)]
+//! struct VersionParser;
+//!
+//! let packed = VersionParser::pack_struct(Version { major: 2, minor: 4 });
+//! assert_eq!(packed, [2, 0, 4, 0]);
+//! let version: Version = VersionParser::unpack_struct(packed);
+//! assert_eq!(version, Version { major: 2, minor: 4 });
+//! ```
+//!
+//! The `impl_codec`-attribute implements a serialization trait of another framework, saving
+//! hand-written adapters for every derived type. The trait must have associated types `Value`
+//! and `Error`, which are set to the unpacked representation and `restruct::UnpackError`, a
+//! function encoding a `&Self::Value` by appending to a `&mut Vec<u8>`, and a function decoding
+//! a `&[u8]` into the value and the rest of the input, as `parse()` does. The functions are
+//! named `encode` and `decode`, unless given otherwise.
+//!
+//! ```
+//! trait WireCodec {
+//!     type Value;
+//!     type Error;
+//!     fn write(value: &Self::Value, out: &mut Vec<u8>);
+//!     fn read(inp: &[u8]) -> Result<(Self::Value, &[u8]), Self::Error>;
+//! }
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<IH"]
+//! #[impl_codec(WireCodec, encode = "write", decode = "read")]
+//! struct Message;
+//!
+//! let mut out = Vec::new();
+//! <Message as WireCodec>::write(&(1, 2), &mut out);
+//! assert_eq!(out, [1, 0, 0, 0, 2, 0]);
+//! assert_eq!(<Message as WireCodec>::read(&out), Ok(((1, 2), &[][..])));
+//! ```
+//!
+//! The `upgrade_from`-attribute helps evolving a format, generating a `const fn` which converts
+//! the unpacked representation of an older derived type into the one of this type. The names of
+//! the older type's elements are given in order; they are matched to this type's elements by
+//! name, which therefore requires names. Elements only this type has are taken from the given
+//! defaults; elements of the older type whose names start with `_` are dropped. The function is
+//! named after the older type, e.g. `upgrade_from_header_v1()`.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<IHB"]
+//! struct HeaderV1;
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<magic:I flags:I version:H"]
+//! #[default(field = 1, value = 0x80)]
+//! #[upgrade_from(HeaderV1(magic, version, _reserved))]
+//! struct HeaderV2;
+//!
+//! let v1 = HeaderV1::unpack([0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 0xff]);
+//! let v2 = HeaderV2::upgrade_from_header_v1(v1, HeaderV2::DEFAULT);
+//! assert_eq!(v2, (0xa1b2c3d4, 0x80, 2));
+//! ```
+//!
+//! The `checksum`-attribute declares that the last element holds a checksum over all preceding
+//! bytes of the record. Supported are `"sum8"` (the sum of all bytes, stored in an `u8`),
+//! `"crc16"` (CRC-16/ARC, stored in an `u16`) and `"crc32"` (CRC-32/ISO-HDLC, stored in an
+//! `u32`). The checksum is filled in by `pack()`, ignoring the given value, and verified by
+//! `verify_checksum()` and `unpack_checked()`, the latter reporting a mismatch as a
+//! `restruct::UnpackError`.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<2HB"]
+//! #[checksum = "sum8"]
+//! struct Message;
+//!
+//! let packed = Message::pack((1, 2, 0));
+//! assert_eq!(packed, [1, 0, 2, 0, 3]);
+//! assert_eq!(Message::unpack_checked(packed), Ok((1, 2, 3)));
+//! let e = Message::unpack_checked([1, 0, 2, 0, 4]).unwrap_err();
+//! assert_eq!(e.to_string(), "element 2 at offset 4: expected 0x3, found 0x4");
+//! ```
+//!
+//! The `timestamp`-attribute declares that an element holds a timestamp, generating a
+//! `const fn` which decodes it into a `restruct::time::DateTime`. The function is named after
+//! the element if names were given (e.g. `mtime_datetime()`), or `datetime_0()` and so on
+//! otherwise. Supported kinds are `"dos"`, an MS-DOS date and time as used by FAT and ZIP,
+//! stored in an `u32` with the date in the upper 16 bits, and `"filetime"`, a Windows
+//! `FILETIME` stored in an `u64`. `DateTime::to_dos()` and `DateTime::to_filetime()` encode them
+//! again.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<crc:I mtime:I"]
+//! #[timestamp(field = 1, kind = "dos")]
+//! struct Entry;
+//!
+//! let entry = Entry::unpack([0, 0, 0, 0, 0x4f, 0x6a, 0x21, 0x58]);
+//! assert_eq!(Entry::mtime_datetime(&entry).to_string(), "2024-01-01T13:18:30");
+//! ```
+//!
+//! The `scale`-attribute declares that an integer element holds a value in engineering units,
+//! as `raw * factor + offset`, the offset being optional. Negative numbers are given as strings,
+//! e.g. `offset = "-40"`. A `const fn` converts the element into an `f64` (e.g.
+//! `temp_scaled()`, or `scaled_0()` and so on without names), and another function sets it from
+//! an `f64`, rounding to the nearest raw value (e.g. `set_temp_scaled()`).
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<temp:h"]
+//! #[scale(field = 0, factor = 0.01, offset = "-40")]
+//! struct Reading;
+//!
+//! let mut reading = Reading::unpack([0x6a, 0x18]);
+//! assert_eq!(Reading::temp_scaled(&reading), 22.5);
+//! Reading::set_temp_scaled(&mut reading, 21.0);
+//! assert_eq!(Reading::pack(reading), [0xd4, 0x17]);
+//! ```
+//!
+//! The `text`-attribute declares that a `s`-element holds text, padded by NUL-bytes or, given
+//! `padding = "space"`, by spaces. A function returns the text without the padding as a `&str`,
+//! or an error if it is not valid UTF-8 (e.g. `name_str()`, or `str_0()` and so on without
+//! names); another one sets the element from a `&str`, padding the rest, or returns a
+//! `restruct::PackError` if the text does not fit (e.g. `set_name_str()`).
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<name:8s size:I"]
+//! #[text(field = 0, padding = "space")]
+//! struct Entry;
+//!
+//! let mut entry = Entry::unpack(*b"README  \x01\0\0\0");
+//! assert_eq!(Entry::name_str(&entry), Ok("README"));
+//! Entry::set_name_str(&mut entry, "LICENSE").unwrap();
+//! assert_eq!(Entry::pack(entry), *b"LICENSE \x01\0\0\0");
+//! assert!(Entry::set_name_str(&mut entry, "CHANGELOG").is_err());
+//! ```
+//!
+//! The `transform`-attribute passes an element through user-defined functions after unpacking
+//! and before packing, e.g. to undo a XOR-mask or to reverse a byte-string. Both functions take
+//! and return the element's unpacked type. As `pack()` and `unpack()` are `const fn`, so must be
+//! `decode` and `encode`; they are given as paths, resolved where the type is defined.
+//!
+//! ```
+//! const fn mask(v: u32) -> u32 {
+//!     v ^ 0xa5a5_a5a5
+//! }
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<HI"]
+//! #[transform(field = 1, decode = "mask", encode = "mask")]
+//! struct Obfuscated;
+//!
+//! let packed = [1, 0, 0xa5, 0xa5, 0xa5, 0xa5];
+//! assert_eq!(Obfuscated::unpack(packed), (1, 0));
+//! assert_eq!(Obfuscated::pack((1, 0)), packed);
+//! ```
+//!
+//! Types carrying lots of methods of their own may want to keep the generated ones apart, e.g.
+//! to avoid collisions. Given `#[restruct(module = "wire")]`, all constants, functions and types
+//! are generated into a module `wire` next to the type, on a unit-struct of the same name. The
+//! type itself only implements `restruct::Struct`, so it can still be used with e.g.
+//! `restruct::RecordReader`. Names in the Format String and attributes are resolved from
+//! within the module, which imports everything from the type's module; a type generated into a
+//! module can't be nested in other Format Strings, though, as those refer to it's functions.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<IH"]
+//! #[restruct(module = "wire")]
+//! struct Message;
+//!
+//! impl Message {
+//!     pub fn pack(&self) -> &'static str {
+//!         "my own pack()"
+//!     }
+//! }
+//!
+//! assert_eq!(wire::Message::SIZE, 6);
+//! assert_eq!(wire::Message::pack((1, 2)), [1, 0, 0, 0, 2, 0]);
+//! assert_eq!(Message.pack(), "my own pack()");
+//! ```
 //!
 //!
 //! # Format Strings
@@ -139,6 +661,7 @@
 //! | Character   | Byte order             | Size     | Alignment |
 //! |-------------|------------------------|----------|-----------|
 //! | `@`         | native                 | native   | native    |
+//! | `^`         | native                 | native   | none      |
 //! | `=`         | native                 | standard | none      |
 //! | `<`         | little-endian          | standard | none      |
 //! | `>`         | big-endian             | standard | none      |
@@ -152,10 +675,54 @@
 //! Alignment is never added at the start or end of the packed data; add a type with a repeat count
 //! of zero to add alignment for that type.
 //!
+//! Packed native mode (`^`) uses native sizes without any alignment, matching C structs declared
+//! with `__attribute__((packed))` or compiled under `#pragma pack(1)`: `"^bL"` packs into nine
+//! bytes on the same platform.
+//!
+//! Structs compiled under `#pragma pack(N)` align each element to at most `N` bytes. In native
+//! mode, the `max_align`-attribute does the same, e.g. `#[max_align = 2]`; `N` must be a power of
+//! two.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "@bqb0q"]
+//! #[max_align = 2]
+//! struct Header;
+//!
+//! assert_eq!(Header::SIZE, 12);
+//! ```
+//!
 //! As a general rule, you should use standard types when dealing with data from IO (e.g.
 //! file-formats, protocols, anything persisted and transfered to other platforms, etc.) and native
 //! types when reading data structures from memory.
 //!
+//! As native mode generates `read_from()` and `write_to()` as well, deriving on a type using `@`
+//! or `^` emits a warning, as does using `n` or `N` in standard modes, whose size still depends on
+//! the platform. The warnings are emitted as deprecation-warnings, as there is no other way for a
+//! derive to warn. The `portability`-attribute silences them via `#[portability = "allow"]`, or
+//! turns them into errors via `#[portability = "deny"]`.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "@bhl"]
+//! #[portability = "allow"]
+//! struct InMemory;
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<bhl"]
+//! #[portability = "deny"]
+//! struct OnDisk;
+//! ```
+//!
+//! Without the attribute, the warning fails builds which deny deprecation-warnings:
+//!
+//! ```compile_fail
+//! #![deny(deprecated)]
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "@bhl"]
+//! struct InMemory;
+//! ```
+//!
 //! ## Format Characters
 //!
 //!
@@ -177,14 +744,79 @@
 //! | `N`           | `libc::size_t`      | `usize`       |
 //! | `f`           | `libc::float`       | `f32`         |
 //! | `d`           | `libc::double`      | `f64`         |
+//! | `g`           | _not supported_     | `f64`         |
+//! | `G`           | _not supported_     | `f64`         |
 //! | `s`           | `[u8; _]`           | `[u8; _]`     |
+//! | `{time_t}`, ... | `libc::time_t`, ... | _not supported_ |
 //! | `` `ident` `` | `<ident as restruct::Struct>::Packed` | `<ident as restruct::Struct>::Packed` |
 //!
+//! `g` is an x87 80-bit extended precision number, the `long double` of C compilers on x86,
+//! taking 10 bytes; `G` is an IEEE 754 binary128 ("quad") number, taking 16 bytes. Both are
+//! unpacked to the nearest `f64`, losing precision and turning numbers beyond the range of an
+//! `f64` into zero or infinity; packing an `f64` is exact. As their native size and alignment
+//! differ between platforms and compilers, they require one of the modifiers `=`, `<`, `>` or
+//! `!`, with padding added explicitly if needed.
+//!
+//! Types defined by the operating system are given by name in braces: `{dev_t}`, `{ino_t}`,
+//! `{mode_t}`, `{off_t}`, `{pid_t}`, `{time_t}` and `{uid_t}` are the respective `libc`-types,
+//! with their size, alignment and signedness on the target. As these differ between platforms,
+//! they are only supported in native mode (`@` or `^`), and only by the derive; the runtime interpreter
+//! rejects them.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "@{dev_t}{ino_t}{mode_t}{uid_t}{off_t}{time_t}"]
+//! struct Stat;
+//!
+//! let stat = (1, 2, 0o644, 1000, 4096, 1_700_000_000);
+//! assert_eq!(Stat::unpack(Stat::pack(stat)), stat);
+//! ```
+//!
 //! A Format Character may be preceded by an repeat count. For example,
 //! the format string ``"3x4h2`Foo`"`` means exactly the same as ``"xxx hhhh `Foo` `Foo`"``.
+//! Counts may also be given in hexadecimal, prefixed by `0x` and terminated by a colon, or
+//! suffixed by `k` or `K` to multiply them by 1024: `"0x40:s"` is a 64-byte array, `"4Ks"` one
+//! of 4096 bytes. The colon is required as `b`, `B`, `d` and `f` are also hexadecimal digits;
+//! without it, `"0x10B"` is zero padding bytes followed by ten `u8`s.
+//!
+//! Whitespace characters (including newlines) between formats are ignored; a count and its format
+//! must not contain whitespace. A `#` starts a comment which extends to the end of the line;
+//! comments in one `fmt`-fragment never extend into the next one.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<  # little endian"]
+//! #[fmt = "I  # magic"]
+//! #[fmt = "2H # version major, minor"]
+//! struct PcapHeaderStart;
+//!
+//! assert_eq!(PcapHeaderStart::SIZE, 8);
+//! ```
+//!
+//! Formats with fields at fixed positions can state the absolute offset the next code starts
+//! at, like `@0x40` or `@64`, padding with zeros as needed. The offset must be followed by
+//! whitespace, a comment or the end of the Format String; an offset at the very end pads the
+//! packed representation to that size. An offset behind the end of the preceding fields fails
+//! to compile. A `@` directly followed by a Format Character is still the native modifier.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<I   # magic"]
+//! #[fmt = "@0x10 H  # version"]
+//! #[fmt = "@0x20"]
+//! struct Header;
+//!
+//! assert_eq!(Header::SIZE, 32);
+//! assert_eq!(Header::pack((0xfeed, 2))[16], 2);
+//! ```
 //!
-//! Whitespace characters between formats are ignored; a count and its format must not contain
-//! whitespace.
+//! ```compile_fail
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<Q @4 H"]
+//! struct Overlapping;
+//!
+//! let _ = Overlapping::SIZE;
+//! ```
 //!
 //! Native types are indirected via the `libc` crate to Rust's primitive-types. Therefor
 //! `libc` must be available in the final crate when using native Format Strings. See the
@@ -270,7 +902,7 @@
 //!     pub fn read_header<R>(r: &mut R) -> io::Result<<Self as restruct::Struct>::Unpacked>
 //!      where R: io::Read
 //!     {
-//!         Self::read_from(r)
+//!         Ok(Self::read_from(r)?)
 //!     }
 //! }
 //! ```
@@ -328,6 +960,24 @@
 //! let new_content = String::from_utf8(new_buf).expect("UTF-8 decoding failed");
 //! assert_eq!(new_content, content);
 //! ```
+//!
+//! The `frame`-attribute generates such functions, given the element which holds the size of the
+//! following data: `#[frame(length = 1)]` declares it as the length of a payload in bytes,
+//! generating `write_with_payload()` and `read_with_payload()`; `#[frame(count = 1)]` declares it
+//! as the number of records of another derived type, generating `write_with_records()` and
+//! `read_with_records()`. Writing fills in the element, reading uses it to size the read.
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<IN"]
+//! #[frame(length = 1)]
+//! struct Frame;
+//!
+//! let mut buf = Vec::new();
+//! Frame::write_with_payload((0xdeadc0de, 0), b"The quick brown fox", &mut buf).unwrap();
+//! let ((magic, size), payload) = Frame::read_with_payload(&mut buf.as_slice()).unwrap();
+//! assert_eq!((magic, size), (0xdeadc0de, 19));
+//! assert_eq!(payload, b"The quick brown fox");
+//! ```
 //! ---
 //!
 //! Alignment rules apply in native mode:
@@ -384,7 +1034,9 @@
 //! The `read_magic()` function is defined to return a `u64`, which needs to match the `"L"` used
 //! in the Format String. This will work fine on platforms where `c_ulong` is a `u64` but fail to
 //! compile e.g. on i586-platforms where `c_ulong` is a `u32`. Either use `std::convert::TryFrom`
-//! or make sure to use the type aliases from `libc` when using native mode.
+//! or make sure to use the type aliases from `libc` when using native mode. To state the
+//! assumption in the layout itself, ascribe the type as in `"@L:u64hb"`, which fails to compile
+//! on such platforms right at the derive.
 //!
 //! Also note that the `"@...b"` in the Format String above is aliased via `libc::c_char`; it
 //! resolves to `i8` on x86-platforms but `u8` on ARM because `c_char` is unsigned on that
@@ -404,7 +1056,9 @@
 //! Lets assume that the C-struct we try to match above uses `int32_t` as it's third element.
 //! The layout above will match on 32bit-platforms where `"@...l"` is `i32`. On 64bit-platforms
 //! however `"@...l"` is `i64`, so `from_raw()` will cause an out-of-bounds memory access by four
-//! bytes on those platforms! The correct Format String would have been `"@2di"`.
+//! bytes on those platforms! The correct Format String would have been `"@2di"`. If the C-struct
+//! is available as a Rust type, `try_from_raw()` catches such mistakes by comparing it's size
+//! and the pointer's alignment to the Format String's `SIZE` and `ALIGN`.
 //!
 //! ---
 
@@ -412,18 +1066,91 @@ extern crate proc_macro;
 
 /// Derive packing/unpacking on a given type. See the main documentation on this crate for details.
 ///
-/// * Attribute *fmt* gives the Format String.
+/// * Attribute *fmt* gives the Format String, or a fragment of it which depends on a
+///   `cfg`-predicate.
+/// * Attribute *fmt_file* reads (part of) the Format String from a file, relative to the crate's
+///   root directory.
+/// * Attribute *fmt_env* reads (part of) the Format String from an environment variable at
+///   compile time.
+/// * Attribute *names* gives names to the elements of the unpacked representation.
+/// * Attribute *default* gives the value of an element in `DEFAULT`.
+/// * Attribute *extends* prepends the Format String, names and defaults of another derived type.
+/// * Attribute *init_from* unpacks `DEFAULT` from a file, relative to the crate's root directory.
+/// * Attribute *map_to* generates conversions between the unpacked representation and the given
+///   user-defined structs.
+/// * Attribute *impl_codec* implements a user-defined codec-trait by packing and unpacking.
+/// * Attribute *upgrade_from* generates conversions from the unpacked representation of older
+///   derived types, matching their elements by name.
+/// * Attribute *text* reads and writes a `s`-element as space- or NUL-padded text.
+/// * Attribute *scale* converts an integer element from and into engineering units.
+/// * Attribute *transform* passes an element through user-defined `const fn`s when unpacking
+///   and packing.
+/// * Attribute *frame* declares an element as the size of variable-length data following the
+///   packed representation, generating functions to write and read both.
+/// * Attribute *field_doc* describes an element, for the `MARKDOWN`-constant.
+/// * Attribute *test_vector* generates a `#[test]` asserting that the given values pack into
+///   the given bytes, and vice versa.
+/// * Attribute *restruct* takes further options: `module = "wire"` generates all constants,
+///   functions and types into a module `wire` next to the type, see below. The flags
+///   `builder`, `columns`, `csv`, `packed` and `extendable` opt into the builder-type, the
+///   implementations of `restruct::Column`, of `restruct::csv::CsvRecord`, the newtype
+///   `...Packed` and into being extended via the *extends*-attribute, respectively.
+///   `derive(...)` gives additional traits to derive on the generated types.
+/// * Attribute *portability* silences warnings about layouts depending on the platform, or turns
+///   them into errors.
+/// * Attribute *max_align* caps the alignment of elements in native mode, as `#pragma pack(N)`
+///   does.
 /// * Attribute *debug_output* causes the generated `TokenStream` to be dumped to stderr while
-/// compiling. If the `rustfmt` feature has been activated, the `TokenStream` is formatted.
+///   compiling. The generated code is formatted for readability.
+/// * Attribute *layout_output* causes a byte-map of the layout to be dumped to stderr while
+///   compiling, which helps to verify a Format String against a specification. This is only
+///   possible for layouts not depending on the target, i.e. not using native mode.
 ///
-/// Both attributes can appear multiple times. Format Strings are concatenated before being
+/// All attributes can appear multiple times. Format Strings are concatenated before being
 /// interpreted. The *debug_output* may appear with our without a boolean parameter, with the final
-/// occurance being used.
-#[proc_macro_derive(Struct, attributes(fmt, debug_output))]
+/// occurance being used; the same applies to *layout_output*. Instead of a boolean, both may also
+/// be given a path (relative to the crate's root directory) to write their output to, e.g.
+/// `#[debug_output = "target/foo.rs"]`.
+#[proc_macro_derive(
+    Struct,
+    attributes(
+        fmt,
+        fmt_file,
+        fmt_env,
+        extends,
+        names,
+        default,
+        init_from,
+        map_to,
+        upgrade_from,
+        checksum,
+        timestamp,
+        text,
+        scale,
+        transform,
+        frame,
+        field_doc,
+        test_vector,
+        restruct,
+        impl_codec,
+        inline_hint,
+        max_align,
+        portability,
+        debug_output,
+        layout_output
+    )
+)]
 pub fn derive_parser(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     restruct::derive(input.into()).into()
 }
 
+/// Derive a type extending another one; called by the macro the base type leaves behind.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __extends(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    restruct::derive_extends(input.into()).into()
+}
+
 #[doc = include_str!("../README.md")]
 #[allow(dead_code)]
 type _READMETEST = ();
@@ -8,6 +8,7 @@ macro_rules! strct {
         $(
             #[$meta]
         )*
+        #[allow(dead_code)]
         struct $name;
     };
     ($($fmt:literal)*) => {
@@ -3,21 +3,21 @@ mod common;
 
 #[test]
 fn simple_sizes() {
-    strct!(Foo "i");
-    strct!(Bar "3i");
+    strct!(Foo "i" #portability = "allow");
+    strct!(Bar "3i" #portability = "allow");
     assert_eq!(Foo::SIZE * 3, Bar::SIZE);
 }
 
 #[test]
 fn default_type_names() {
-    strct!();
+    strct!(Foo #portability = "allow");
     type _X = <Foo as restruct::Struct>::Packed;
     type _Y = <Foo as restruct::Struct>::Unpacked;
 }
 
 #[test]
 fn constness() {
-    strct!("ih");
+    strct!(Foo "ih" #portability = "allow");
     const FIX: <Foo as restruct::Struct>::Unpacked = (1, 2);
     const SIZE: usize = Foo::SIZE;
     const BUF: <Foo as restruct::Struct>::Packed = Foo::pack(FIX);
@@ -33,3 +33,80 @@ fn concatenate_fmt() {
     strct!("<" "2" "i");
     assert_eq!(Foo::SIZE, 8);
 }
+
+#[test]
+fn fmt_file() {
+    strct!(Foo #fmt_file = "tests/fmt/header.fmt");
+    strct!(Bar #fmt_file = "tests/fmt/header.fmt" #fmt = "?");
+    assert_eq!(Foo::SIZE, 8);
+    assert_eq!(Foo::pack((1, 2, 3)), [1, 0, 0, 0, 2, 0, 3, 0]);
+    assert_eq!(Bar::SIZE, Foo::SIZE + 1);
+}
+
+strct!(ExtendsBase "!I`ExtendsNested`" #names(magic, nested) #default(field = 0, value = 0xcafe) #restruct(extendable));
+strct!(ExtendsNested "<H" #restruct(extendable));
+strct!(ExtendsMid "# The base's modifier applies\n?" #extends(ExtendsBase) #names(flag) #restruct(extendable));
+strct!(ExtendsTop ">H" #extends(ExtendsMid) #names(len) #default(field = 3, value = 2) #restruct(builder));
+
+#[test]
+fn extends() {
+    assert_eq!(ExtendsMid::SIZE, 7);
+    assert_eq!(ExtendsTop::DEFAULT, (0xcafe, (0,), false, 2));
+    assert_eq!(
+        ExtendsTop::pack(ExtendsTop::builder().flag(true).nested((1,)).build()),
+        [0, 0, 0xca, 0xfe, 1, 0, 1, 0, 2]
+    );
+}
+
+// Bases are resolved by path, regardless of the order of derives
+strct!(ExtendsShort "B" #extends(short::Header));
+strct!(ExtendsLong "B" #extends(long::Header));
+
+mod short {
+    strct!(Header "<H" #restruct(extendable));
+}
+
+mod long {
+    strct!(Header "<I" #restruct(extendable));
+}
+
+#[test]
+fn extends_path() {
+    assert_eq!(ExtendsShort::SIZE, 3);
+    assert_eq!(ExtendsLong::SIZE, 5);
+}
+
+#[test]
+fn cfg_fragments() {
+    strct!(Foo "<I" #fmt(cfg(target_pointer_width = "64"), "Q") #fmt(cfg(not(target_pointer_width = "64")), "I") #fmt = "B");
+    assert_eq!(Foo::SIZE, 5 + std::mem::size_of::<usize>());
+    strct!(Bar #fmt = "H" #fmt(cfg(target_endian = "little"), "B") #extends(ExtendsNested));
+    let size = 4 + cfg!(target_endian = "little") as usize;
+    assert_eq!(Bar::SIZE, size);
+    assert_eq!(Bar::pack(Bar::DEFAULT)[..4], [0; 4]);
+    // At most one of the fragments is selected, which the names must match
+    strct!(Baz "<H" #fmt(cfg(target_pointer_width = "64"), "Q") #fmt(cfg(target_pointer_width = "32"), "I") #names(kind, size) #restruct(builder));
+    assert_eq!(Baz::SIZE, 2 + std::mem::size_of::<usize>());
+    assert_eq!(Baz::builder().size(1).build(), (0, 1));
+}
+
+#[test]
+fn init_from() {
+    strct!(Foo #fmt_file = "tests/fmt/header.fmt" #init_from = "tests/fmt/header.bin");
+    const FOO: <Foo as restruct::Struct>::Unpacked = Foo::DEFAULT;
+    assert_eq!(FOO, (1, 2, 3));
+}
+
+#[test]
+fn comments() {
+    strct!(Foo "<I # magic" "H # version\n" "2?");
+    assert_eq!(Foo::pack((1, 2, true, false)), [1, 0, 0, 0, 2, 0, 1, 0]);
+}
+
+#[test]
+fn portability() {
+    strct!(Foo "<IQ" #portability = "deny");
+    strct!(Bar "@IQ" #portability = "allow");
+    assert_eq!(Foo::SIZE, 12);
+    assert_eq!(Bar::pack((1, 2)).len(), Bar::SIZE);
+}
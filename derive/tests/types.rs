@@ -24,6 +24,137 @@ fn io() {
     assert_eq!(&buffer[..], &fix[..]);
     let c = Foo::read_from(&mut &buffer[..]).unwrap();
     assert_eq!(c, inp);
+    assert_eq!(Foo::try_unpack_slice(&buffer), Ok(inp));
+    assert_eq!(
+        Foo::try_unpack_slice(&buffer[1..]),
+        Err(restruct::UnpackError::Size {
+            expected: Foo::SIZE,
+            found: Foo::SIZE - 1
+        })
+    );
+    let mut r = &buffer[..];
+    assert_eq!(Foo::read_from_buf(&mut r).unwrap(), inp);
+    assert!(r.is_empty());
+    let mut r = std::io::BufReader::with_capacity(4, &buffer[..]);
+    assert_eq!(Foo::read_from_buf(&mut r).unwrap(), inp);
+    assert!(Foo::read_from_buf(&mut r).unwrap_err().is_eof());
+    assert!(matches!(
+        Foo::read_from(&mut &buffer[1..]),
+        Err(restruct::Error::Truncated { .. })
+    ));
+
+    let records = [&buffer[..], &buffer[..], &buffer[..]].concat();
+    let mut r = &records[..];
+    Foo::skip_from(&mut r, 2).unwrap();
+    assert_eq!(Foo::read_from(&mut r).unwrap(), inp);
+    assert!(Foo::skip_from(&mut &records[..], 4).unwrap_err().is_eof());
+    assert!(matches!(
+        Foo::skip_from(&mut &records[..], u64::MAX),
+        Err(restruct::Error::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    let mut r = std::io::Cursor::new(&records);
+    assert_eq!(
+        Foo::skip_from_seekable(&mut r, 2).unwrap(),
+        2 * Foo::SIZE as u64
+    );
+    assert_eq!(Foo::read_from(&mut r).unwrap(), inp);
+    let e = Foo::skip_from_seekable(&mut r, u64::MAX).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+
+    buffer.clear();
+    Foo::write_to_ref(&inp, &mut buffer).unwrap();
+    assert_eq!(&buffer[..], &fix[..]);
+    assert_eq!(Foo::pack_ref(&inp), fix);
+
+    let mut out = Foo::zeroed();
+    Foo::unpack_into(&fix, &mut out);
+    assert_eq!(out, inp);
+}
+
+#[test]
+fn raw() {
+    strct!(Foo "<Ih");
+    let mut mem = [0u64; 2];
+    unsafe {
+        Foo::to_raw((0x1122_3344, -2), mem.as_mut_ptr());
+        assert_eq!(Foo::from_raw(mem.as_ptr()), (0x1122_3344, -2));
+        let view = Foo::from_raw_mut(mem.as_mut_ptr());
+        assert_eq!(view, &[0x44, 0x33, 0x22, 0x11, 0xfe, 0xff]);
+        view[0] = 0;
+        assert_eq!(Foo::from_raw(mem.as_ptr()), (0x1122_3300, -2));
+
+        let unaligned = (mem.as_mut_ptr() as *mut u8).add(1) as *mut u32;
+        Foo::to_raw((7, 8), unaligned);
+        assert_eq!(Foo::from_raw(unaligned), (7, 8));
+    }
+}
+
+#[test]
+fn ascription() {
+    strct!(Foo "@i:i32 h:i16 ?:bool B:u8 d:f64 2H:u16" #portability = "allow");
+    assert_eq!(
+        Foo::unpack(Foo::pack((1, -2, true, 3, 0.5, 4, 5))),
+        (1, -2, true, 3, 0.5, 4, 5)
+    );
+    #[cfg(all(target_pointer_width = "64", not(windows)))]
+    {
+        strct!(Bar "@L:u64 Q:u64 n:isize" #portability = "allow");
+        let _: (u64, u64, isize) = Bar::zeroed();
+    }
+}
+
+#[test]
+fn try_from_raw() {
+    #[repr(C)]
+    struct CHeader {
+        _a: f64,
+        _b: f64,
+        _c: i32,
+    }
+
+    strct!(Good "@2di" #portability = "allow");
+    strct!(Bad "@2dqh" #portability = "allow");
+    assert_eq!(Good::ALIGN, std::mem::align_of::<CHeader>());
+    let mem = [0u64; 4];
+    let header = mem.as_ptr() as *const CHeader;
+    unsafe {
+        assert_eq!(Good::try_from_raw(header), Ok((0.0, 0.0, 0)));
+        assert_eq!(
+            Bad::try_from_raw(header),
+            Err(restruct::UnpackError::Size {
+                expected: 32,
+                found: std::mem::size_of::<CHeader>()
+            })
+        );
+        let unaligned = (mem.as_ptr() as *const u8).add(4) as *const CHeader;
+        assert!(matches!(
+            Good::try_from_raw(unaligned),
+            Err(restruct::UnpackError::Align { expected, .. }) if expected == Good::ALIGN
+        ));
+    }
+}
+
+#[test]
+fn volatile() {
+    strct!(Foo "@BxI?" #portability = "allow");
+    let mut mem = [u32::MAX; 3];
+    unsafe {
+        Foo::write_volatile((1, 2, true), mem.as_mut_ptr());
+        assert_eq!(Foo::read_volatile(mem.as_ptr()), (1, 2, true));
+        // Padding is left untouched
+        assert_eq!(Foo::from_raw_mut(mem.as_mut_ptr())[1..4], [0xff; 3]);
+    }
+    strct!(Bar ">Hd");
+    let mut mem = [0u64; 2];
+    unsafe {
+        // Aligned and unaligned elements, accessed in non-native byte order
+        Bar::write_volatile((0x1234, 1.5), mem.as_mut_ptr());
+        assert_eq!(*(mem.as_ptr() as *const [u8; 10]), Bar::pack((0x1234, 1.5)));
+        assert_eq!(Bar::read_volatile(mem.as_ptr()), (0x1234, 1.5));
+        let ptr = (mem.as_mut_ptr() as *mut u8).add(1);
+        Bar::write_volatile((0x5678, -2.0), ptr);
+        assert_eq!(Bar::read_volatile(ptr), (0x5678, -2.0));
+    }
 }
 
 #[test]
@@ -42,9 +173,42 @@ fn unpack_slice_panics() {
     Foo::unpack_slice(&buf);
 }
 
+#[test]
+fn parse() {
+    strct!(Foo "<HB");
+    strct!(Bar ">I");
+    let data = [1, 0, 2, 0, 0, 0, 3, 4];
+    let (foo, rest) = Foo::parse(&data).unwrap();
+    let (bar, rest) = Bar::parse(rest).unwrap();
+    assert_eq!((foo, bar, rest), ((1, 2), (3,), &[4][..]));
+    assert_eq!(
+        Bar::parse(rest),
+        Err(restruct::UnpackError::Size {
+            expected: 4,
+            found: 1
+        })
+    );
+    assert_eq!(Foo::parse(&data[5..]).unwrap().1, &[0u8; 0]);
+}
+
+#[test]
+fn ref_from() {
+    strct!(Foo "<HB");
+    let mut data = [1, 0, 2, 3];
+    assert_eq!(Foo::ref_from(&data[1..]), Some(&[0, 2, 3]));
+    assert_eq!(Foo::ref_from(&data[2..]), None);
+    const REF: Option<&[u8; 3]> = Foo::ref_from(&[4, 0, 5]);
+    assert_eq!(Foo::unpack(*REF.unwrap()), (4, 5));
+
+    let packed = Foo::ref_from_mut(&mut data).unwrap();
+    Foo::raw_field_mut(packed, 1).unwrap()[0] = 6;
+    assert_eq!(data, [1, 0, 6, 3]);
+    assert!(Foo::ref_from_mut(&mut data[..2]).is_none());
+}
+
 #[test]
 fn empty_fmt() {
-    strct!();
+    strct!(Foo #portability = "allow");
     assert_eq!(Foo::SIZE, 0);
     assert!(Foo::FIELDS.is_empty());
     assert_eq!(Foo::pack(()), [0u8; 0]);
@@ -60,11 +224,36 @@ fn zeroed_format_is_zst() {
     let _: () = Foo::unpack([0u8; 0]);
 }
 
+#[test]
+fn hex_and_suffixed_counts() {
+    strct!("<0x10:s 1Kx 0x2:B");
+    assert_eq!(Foo::SIZE, 16 + 1024 + 2);
+    let _: ([u8; 16], u8, u8) = Foo::unpack([0u8; Foo::SIZE]);
+}
+
+#[test]
+fn typedefs() {
+    #[repr(C)]
+    struct Stat {
+        _flags: libc::c_char,
+        _mtime: libc::time_t,
+        _mode: libc::mode_t,
+        dev: libc::dev_t,
+    }
+    strct!(Foo "@b{time_t}{mode_t}{dev_t}" #portability = "allow");
+    assert_eq!(
+        Foo::SIZE,
+        std::mem::offset_of!(Stat, dev) + std::mem::size_of::<libc::dev_t>()
+    );
+    let stat: (libc::c_char, libc::time_t, libc::mode_t, libc::dev_t) = (1, -2, 0o644, 3);
+    assert_eq!(Foo::unpack(Foo::pack(stat)), stat);
+}
+
 #[test]
 fn aligned_to_different_type() {
-    strct!(Foo "bb");
-    strct!(Bar "bib");
-    strct!(Foobar "b0ib");
+    strct!(Foo "bb" #portability = "allow");
+    strct!(Bar "bib" #portability = "allow");
+    strct!(Foobar "b0ib" #portability = "allow");
     if Foobar::SIZE <= Foo::SIZE {
         unreachable!();
     }
@@ -76,17 +265,49 @@ fn aligned_to_different_type() {
 
 #[test]
 fn tail_aligned() {
-    strct!(Foo "b0q");
-    strct!(Bar "q");
+    strct!(Foo "b0q" #portability = "allow");
+    strct!(Bar "q" #portability = "allow");
     let buf = Foo::pack((100,));
     assert_eq!(buf.len(), Foo::SIZE);
     assert_eq!(Foo::SIZE, Bar::SIZE);
     assert_eq!(Foo::unpack(Foo::pack((100,))), (100,));
 }
 
+#[test]
+fn packed_native() {
+    #[repr(C, packed)]
+    struct Packed {
+        _a: libc::c_char,
+        _b: libc::c_ulong,
+        _c: libc::c_short,
+    }
+    strct!(Foo "^bLh" #portability = "allow");
+    strct!(Bar "^b0L" #portability = "allow");
+    assert_eq!(Foo::SIZE, std::mem::size_of::<Packed>());
+    assert_eq!(Bar::SIZE, 1);
+    assert_eq!(Foo::unpack(Foo::pack((-1, 2, 3))), (-1, 2, 3));
+}
+
+#[test]
+fn max_align() {
+    #[repr(C, packed(4))]
+    struct Packed {
+        _a: libc::c_char,
+        _b: libc::c_longlong,
+        _c: libc::c_short,
+        _d: libc::c_int,
+    }
+    strct!(Foo "@bqhi" #max_align = 4 #portability = "allow");
+    strct!(Bar "@bqhi" #max_align = 1 #portability = "allow");
+    strct!(Baz "^bqhi" #portability = "allow");
+    assert_eq!(Foo::SIZE, std::mem::size_of::<Packed>());
+    assert_eq!(Bar::SIZE, Baz::SIZE);
+    assert_eq!(Foo::unpack(Foo::pack((-1, 2, 3, 4))), (-1, 2, 3, 4));
+}
+
 #[test]
 fn std_aligned_on_zeroed_type() {
-    strct!(Foo "@b0q");
+    strct!(Foo "@b0q" #portability = "allow");
     strct!(Bar "=b0q");
     assert_eq!(Bar::SIZE, 1);
     if Foo::SIZE <= Bar::SIZE {
@@ -96,8 +317,8 @@ fn std_aligned_on_zeroed_type() {
 
 #[test]
 fn zeroed_type_in_front() {
-    strct!(Foo "0ib");
-    strct!(Bar "b");
+    strct!(Foo "0ib" #portability = "allow");
+    strct!(Bar "b" #portability = "allow");
     assert_eq!(Foo::SIZE, Bar::SIZE);
 }
 
@@ -111,8 +332,8 @@ fn nested_size() {
 
 #[test]
 fn nested() {
-    strct!(Foo "Ih");
-    strct!(Bar "`Foo`2H");
+    strct!(Foo "Ih" #portability = "allow");
+    strct!(Bar "`Foo`2H" #portability = "allow");
     let packed = Bar::pack(((3, 592), 43200, 21000));
     let unpacked = Bar::unpack(packed);
     assert_eq!(unpacked, ((3, 592), 43200, 21000));
@@ -120,18 +341,21 @@ fn nested() {
 
 #[test]
 fn deeply_nested() {
-    strct!(MostBottomTurtle "2?");
-    strct!(BottomTurtle "L1`MostBottomTurtle`");
-    strct!(Turtle "`BottomTurtle`2s");
+    strct!(MostBottomTurtle "2?" #portability = "allow");
+    strct!(BottomTurtle "L1`MostBottomTurtle`" #portability = "allow");
+    strct!(Turtle "`BottomTurtle`2s" #portability = "allow");
     let t = ((999, (true, false)), [1, 2]);
     let packed = Turtle::pack(t);
     let unpacked = Turtle::unpack(packed);
     assert_eq!(unpacked, t);
+    let mut out = Turtle::DEFAULT;
+    Turtle::unpack_into(&packed, &mut out);
+    assert_eq!(out, t);
 }
 
 #[test]
 fn only_padding() {
-    strct!("3x");
+    strct!(Foo "3x" #portability = "allow");
     assert_eq!(Foo::SIZE, 3);
     assert_eq!(Foo::pack(()), [0u8; 3]);
 }
@@ -148,8 +372,8 @@ macro_rules! test_native_integer_sizes {
     ($testname:ident, $modifier:literal, $fmt1:literal, $fmt2:literal) => {
         #[test]
         fn $testname() {
-            strct!(Foo $modifier $fmt1);
-            strct!(Bar $modifier $fmt2);
+            strct!(Foo $modifier $fmt1 #portability = "allow");
+            strct!(Bar $modifier $fmt2 #portability = "allow");
             assert_eq!(Foo::SIZE, Bar::SIZE);
         }
     };
@@ -157,6 +381,7 @@ macro_rules! test_native_integer_sizes {
         mod $modname {
             test_native_integer_sizes!(none, "", $fmt1, $fmt2);
             test_native_integer_sizes!(native, "@", $fmt1, $fmt2);
+            test_native_integer_sizes!(packed, "^", $fmt1, $fmt2);
         }
     };
     () => {
@@ -175,8 +400,8 @@ macro_rules! test_relative_sizes {
     ($testname:ident, $modifier:literal, $fmt1:literal, $fmt2:literal) => {
         #[test]
         fn $testname() {
-            strct!(Foo $modifier $fmt1);
-            strct!(Bar $modifier $fmt2);
+            strct!(Foo $modifier $fmt1 #portability = "allow");
+            strct!(Bar $modifier $fmt2 #portability = "allow");
             assert!(Foo::SIZE <= Bar::SIZE);
         }
     };
@@ -204,7 +429,7 @@ macro_rules! test_absolute_sizes {
     ($testname:ident, $modifier:literal, $fmt:literal, $eq:tt $fix:expr) => {
         #[test]
         fn $testname() {
-            strct!($modifier $fmt);
+            strct!(Foo $modifier $fmt #portability = "allow");
             assert!(Foo::SIZE $eq $fix);
         }
     };
@@ -264,3 +489,677 @@ macro_rules! test_std_sizes {
     }
 }
 test_std_sizes!();
+
+#[test]
+fn collapsed_repeats() {
+    strct!(Foo "@b3i?" #portability = "allow");
+    strct!(Bar "@biii?" #portability = "allow");
+    assert_eq!(Foo::SIZE, Bar::SIZE);
+    assert_eq!(Foo::FIELDS, Bar::FIELDS);
+    let inp = (-1, 1, 2, 3, true);
+    assert_eq!(Foo::pack(inp), Bar::pack(inp));
+    assert_eq!(Foo::unpack(Foo::pack(inp)), inp);
+}
+
+#[test]
+fn long_repeat() {
+    strct!(">200H");
+    assert_eq!(Foo::SIZE, 400);
+    assert_eq!(Foo::FIELDS.len(), 200);
+    assert_eq!(Foo::FIELDS[199], ("u16", 398, 0, 2));
+    let unpacked = Foo::unpack([7u8; 400]);
+    assert_eq!(unpacked.199, 0x0707);
+    assert_eq!(Foo::pack(unpacked), [7u8; 400]);
+}
+
+#[test]
+fn many_fields() {
+    strct!(Foo "@bH" #portability = "allow");
+    strct!(Bar #fmt_file = "tests/fmt/many_fields.fmt" #portability = "allow");
+    assert_eq!(Bar::FIELDS.len(), 400);
+    assert_eq!(Bar::SIZE, Foo::SIZE * 200);
+    assert_eq!(Bar::FIELDS[399].1, Foo::SIZE * 199 + Foo::FIELDS[1].1);
+}
+
+#[test]
+fn runtime_layout() {
+    strct!(Foo "@bhlbibqBHLbIbQ3s?d2N" #portability = "allow");
+    let runtime = restruct::DynStruct::new("@bhlbibqBHLbIbQ3s?d2N").unwrap();
+    assert_eq!(runtime.size(), Foo::SIZE);
+    let offsets = runtime.elements().map(|(_, offset, _)| offset);
+    let expected = Foo::FIELDS
+        .iter()
+        .map(|(_, offset, alignment, _)| offset + alignment);
+    assert_eq!(offsets.collect::<Vec<_>>(), expected.collect::<Vec<_>>());
+}
+
+#[test]
+#[cfg(feature = "layout_json")]
+fn layout_json() {
+    strct!(Foo "<Ix?" #names(magic, flag));
+    assert_eq!(
+        Foo::LAYOUT_JSON,
+        r#"{"name":"Foo","endianness":"little","size":6,"fields":[{"type":"u32","offset":0,"alignment":0,"size":4,"padding":false,"name":"magic"},{"type":"[u8; 1]","offset":4,"alignment":0,"size":1,"padding":true,"name":null},{"type":"bool","offset":5,"alignment":0,"size":1,"padding":false,"name":"flag"}]}"#
+    );
+}
+
+#[test]
+fn boxed() {
+    strct!(Inner ">H");
+    strct!(Foo "@b4096s?xd`Inner`I" #checksum = "crc32" #portability = "allow");
+    strct!(Bar "@bI?" #default(field = 0, value = 1) #portability = "allow");
+    let inp = Foo::zeroed();
+    assert_eq!(*Foo::pack_boxed(&inp), Foo::pack_ref(&inp));
+    let inp = (-1, [0xaa; 4096], true, 1.5, (0x1234,), 0);
+    let packed = Foo::pack_boxed(&inp);
+    assert_eq!(*packed, Foo::pack_ref(&inp));
+    assert!(Foo::verify_checksum(&packed));
+    let mut out = [0xff; Bar::SIZE + 1];
+    Bar::pack_into(&Bar::DEFAULT, &mut out);
+    assert_eq!(out[..Bar::SIZE], Bar::pack(Bar::DEFAULT));
+    assert_eq!(out[Bar::SIZE], 0xff);
+    let read = Foo::read_boxed_from(&mut &packed[..]).unwrap();
+    assert_eq!(read, packed);
+}
+
+#[test]
+fn unpack_all() {
+    strct!(Foo ">If");
+    strct!(Bar "<H2h");
+    strct!(Baz "<H?");
+    let data = [
+        0, 0, 0, 1, 0x3f, 0x80, 0, 0, 0, 0, 0, 2, 0x40, 0, 0, 0, 0xff,
+    ];
+    assert_eq!(Foo::unpack_all(&data), [(1, 1.0), (2, 2.0)]);
+    assert_eq!(
+        Bar::unpack_all(&data[..12]),
+        [(0, 256, -32705), (0, 0, 512)]
+    );
+    assert_eq!(Baz::unpack_all(&data[..6]), [(0, false), (0x3f01, true)]);
+    assert!(Foo::unpack_all(&data[..7]).is_empty());
+}
+
+#[test]
+fn record_reader() {
+    use std::io::Read;
+
+    strct!(Foo "<HH");
+    let data = [1, 0, 2, 0, 3, 0, 4, 0, 5];
+    let mut reader = restruct::RecordReader::<_, Foo>::with_capacity(3, &data[..]);
+    assert_eq!(reader.next().unwrap().unwrap(), (1, 2));
+    assert_eq!(reader.next().unwrap().unwrap(), (3, 4));
+    let e = reader.next().unwrap().unwrap_err();
+    assert!(matches!(
+        e,
+        restruct::Error::Truncated {
+            got: 1,
+            expected: 4
+        }
+    ));
+    assert!(reader.next().is_none());
+
+    let data = [1, 0, 2, 0, 0xff, 3, 0, 4, 0];
+    let mut reader = restruct::RecordReader::<_, Foo>::new(&data[..]);
+    reader.skip_records(1).unwrap();
+    assert!(matches!(
+        reader.skip_records(u64::MAX),
+        Err(restruct::Error::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    let mut byte = [0];
+    reader.read_exact(&mut byte).unwrap();
+    assert_eq!(byte, [0xff]);
+    assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), [(3, 4)]);
+}
+
+#[test]
+fn stream() {
+    strct!(Foo "<HH");
+    let data = [1, 0, 2, 0, 3, 0, 4, 0];
+    let records = Foo::stream(&data[..]).collect::<std::io::Result<Vec<_>>>();
+    assert_eq!(records.unwrap(), [(1, 2), (3, 4)]);
+    assert_eq!(Foo::stream(&data[..0]).count(), 0);
+
+    let mut records = Foo::stream(&data[..7]);
+    assert_eq!(records.next().unwrap().unwrap(), (1, 2));
+    let e = records.next().unwrap().unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn frame() {
+    strct!(Frame "<IB" #frame(length = 1));
+    let mut buf = Vec::new();
+    Frame::write_with_payload((0xdeadc0de, 0), b"abc", &mut buf).unwrap();
+    assert_eq!(buf, [0xde, 0xc0, 0xad, 0xde, 3, b'a', b'b', b'c']);
+    let (header, payload) = Frame::read_with_payload(&mut &buf[..]).unwrap();
+    assert_eq!((header, &payload[..]), ((0xdeadc0de, 3), &b"abc"[..]));
+    let e = Frame::write_with_payload((0, 0), &[0; 256], &mut Vec::new()).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(matches!(
+        Frame::read_with_payload(&mut &buf[..7]),
+        Err(restruct::Error::Truncated {
+            got: 2,
+            expected: 3
+        })
+    ));
+
+    strct!(Point "<hh");
+    strct!(Polygon "<h?" #frame(count = 0));
+    let mut buf = Vec::new();
+    Polygon::write_with_records::<Point, _>((0, true), &[(1, 2), (-3, 4)], &mut buf).unwrap();
+    assert_eq!(buf, [2, 0, 1, 1, 0, 2, 0, 0xfd, 0xff, 4, 0]);
+    let (header, points) = Polygon::read_with_records::<Point, _>(&mut &buf[..]).unwrap();
+    assert_eq!((header, &points[..]), ((2, true), &[(1, 2), (-3, 4)][..]));
+    buf[0] = 0xff;
+    buf[1] = 0xff;
+    assert!(matches!(
+        Polygon::read_with_records::<Point, _>(&mut &buf[..]),
+        Err(restruct::Error::Invalid(_))
+    ));
+}
+
+#[test]
+fn follow() {
+    // Yields the given chunks, with the end of input in between
+    struct Growing(Vec<Option<Vec<u8>>>);
+
+    impl std::io::Read for Growing {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.pop() {
+                Some(Some(chunk)) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                Some(None) => Ok(0),
+                None => Err(std::io::ErrorKind::BrokenPipe.into()),
+            }
+        }
+    }
+
+    strct!(Foo "<HH");
+    let mut chunks = vec![
+        Some(vec![1, 0, 2]),
+        Some(vec![0, 3]),
+        None,
+        None,
+        Some(vec![0, 4]),
+        None,
+        Some(vec![0]),
+    ];
+    chunks.reverse();
+    let reader = restruct::RecordReader::<_, Foo>::with_capacity(1, Growing(chunks));
+    let mut records = reader.follow(std::time::Duration::ZERO);
+    assert_eq!(records.next().unwrap().unwrap(), (1, 2));
+    assert_eq!(records.next().unwrap().unwrap(), (3, 4));
+    assert!(matches!(records.next(), Some(Err(restruct::Error::Io(_)))));
+    assert_eq!(records.pending(), 0);
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn resync() {
+    strct!(Foo "<BH");
+    let data = [0xaa, 1, 0, 0xff, 0xff, 0xaa, 2, 0, 0, 0xaa, 3, 0, 1, 2, 3];
+    let reader = restruct::RecordReader::<_, Foo>::new(&data[..]);
+    let mut records = reader.resync(|(magic, _)| *magic == 0xaa);
+    assert_eq!(records.next().unwrap().unwrap(), (0, (0xaa, 1)));
+    assert_eq!(records.next().unwrap().unwrap(), (2, (0xaa, 2)));
+    assert_eq!(records.next().unwrap().unwrap(), (1, (0xaa, 3)));
+    let e = records.next().unwrap().unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "invalid record: no valid record after skipping 1 bytes"
+    );
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn index() {
+    use restruct::index::Index;
+
+    strct!(Foo "<HB");
+    // Keys are written to disk packed as a `Key`
+    strct!(Key "<H");
+    let data = [5, 0, 1, 3, 0, 2, 5, 0, 3, 1, 0, 4, 9, 9];
+    let mut file = std::io::Cursor::new(&data[..]);
+    assert!(Index::<Foo, (u16,)>::build(&mut file, |(id, _)| (*id,)).is_err());
+
+    let mut file = std::io::Cursor::new(&data[..12]);
+    let index = Index::<Foo, (u16,)>::build(&mut file, |(id, _)| (*id,)).unwrap();
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.get(&(5,)).collect::<Vec<_>>(), [0, 2]);
+    assert_eq!(index.get(&(4,)).count(), 0);
+    assert_eq!(
+        index.range((2,)..=(5,)).collect::<Vec<_>>(),
+        [(&(3,), 1), (&(5,), 0), (&(5,), 2)]
+    );
+    assert_eq!(index.lookup(&mut file, &(5,)).unwrap(), [(5, 1), (5, 3)]);
+    assert_eq!(
+        Index::<Foo, (u16,)>::read_record(&mut file, 3).unwrap(),
+        (1, 4)
+    );
+
+    let mut saved = Vec::new();
+    index.write_to::<Key, _>(&mut saved).unwrap();
+    assert_eq!(saved.len(), 4 * 10);
+    let loaded = Index::<Foo, (u16,)>::read_from::<Key, _>(&mut &saved[..]).unwrap();
+    assert_eq!(
+        loaded.iter().collect::<Vec<_>>(),
+        index.iter().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn record_writer() {
+    use std::io::Write;
+
+    strct!(Foo "<HH");
+    let mut writer = restruct::RecordWriter::<_, Foo>::with_capacity(3, Vec::new());
+    writer.write_record(&(1, 2)).unwrap();
+    writer.write_all(&[0xff]).unwrap();
+    writer.write_records(vec![(3, 4), (5, 6)]).unwrap();
+    assert_eq!(
+        writer.into_inner().unwrap(),
+        [1, 0, 2, 0, 0xff, 3, 0, 4, 0, 5, 0, 6, 0]
+    );
+}
+
+#[test]
+fn crc_stream() {
+    use restruct::crc_stream::{CrcReader, CrcWriter};
+
+    strct!(Entry "<IH");
+    let entries = (0..5).map(|i| (i, i as u16 * 2)).collect::<Vec<_>>();
+    let read = |buf: &[u8], block| {
+        let reader = match block {
+            Some(block) => CrcReader::<_, Entry>::with_blocks(buf, block),
+            None => CrcReader::<_, Entry>::new(buf),
+        };
+        reader.collect::<Vec<_>>()
+    };
+    for block in [Some(1), Some(2), Some(5), Some(8), None] {
+        let mut writer = match block {
+            Some(block) => CrcWriter::<_, Entry>::with_blocks(Vec::new(), block),
+            None => CrcWriter::<_, Entry>::new(Vec::new()),
+        };
+        for entry in &entries {
+            writer.write_record(entry).unwrap();
+        }
+        let buf = writer.finish().unwrap();
+        // Complete blocks are followed by the final CRC
+        let crcs = entries.len() / block.unwrap_or(usize::MAX) + 1;
+        assert_eq!(buf.len(), entries.len() * Entry::SIZE + crcs * 4);
+        let res = read(&buf, block);
+        assert_eq!(
+            res.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            entries
+        );
+
+        let mut corrupt = buf.clone();
+        corrupt[Entry::SIZE * 4] ^= 1;
+        let res = read(&corrupt, block);
+        let e = res.last().unwrap().as_ref().unwrap_err().to_string();
+        assert!(e.contains("CRC mismatch"), "{}", e);
+        let res = read(&buf[..buf.len() - 1], block);
+        assert!(matches!(
+            res.last(),
+            Some(Err(restruct::Error::Truncated { .. }))
+        ));
+    }
+    // Dropping or reordering whole blocks or truncating at a block boundary is detected
+    let mut writer = CrcWriter::<_, Entry>::with_blocks(Vec::new(), 1);
+    for entry in &entries {
+        writer.write_record(entry).unwrap();
+    }
+    let buf = writer.finish().unwrap();
+    let block = Entry::SIZE + 4;
+    let dropped = [&buf[..block], &buf[2 * block..]].concat();
+    assert!(read(&dropped, Some(1)).last().unwrap().is_err());
+    let reordered = [&buf[block..2 * block], &buf[..block], &buf[2 * block..]].concat();
+    assert!(read(&reordered, Some(1)).last().unwrap().is_err());
+    let res = read(&buf[..2 * block], Some(1));
+    assert!(matches!(
+        res.last(),
+        Some(Err(restruct::Error::Truncated { got: 0, .. }))
+    ));
+    let empty = CrcWriter::<_, Entry>::new(Vec::new()).finish().unwrap();
+    assert!(read(&empty, None).is_empty());
+    assert!(read(&[], None).last().unwrap().is_err());
+    let res = read(
+        &entries.iter().flat_map(Entry::pack_ref).collect::<Vec<_>>(),
+        Some(8),
+    );
+    assert!(res.last().unwrap().is_err());
+}
+
+#[test]
+fn record_file() {
+    use restruct::file::RecordFile;
+
+    strct!(Header "<4sH");
+    strct!(Entry "<IH");
+    strct!(Footer "<I");
+    type File<S> = RecordFile<S, Header, Entry, Footer>;
+
+    let mut file = File::create(std::io::Cursor::new(Vec::new()), &(*b"LOG1", 0)).unwrap();
+    file.append(&(1, 2)).unwrap();
+    file.finalize(&(0xffff,)).unwrap();
+    file.append(&(3, 4)).unwrap();
+    assert_eq!(file.footer(), None);
+    file.finalize(&(0xeeee,)).unwrap();
+    file.write_header(&(*b"LOG1", 2)).unwrap();
+    let buf = file.into_inner().into_inner();
+    assert_eq!(buf.len(), 6 + 2 * 6 + 4);
+
+    let mut file = File::open(std::io::Cursor::new(buf.clone())).unwrap();
+    assert_eq!(file.header(), (*b"LOG1", 2));
+    assert_eq!(file.footer(), Some((0xeeee,)));
+    assert_eq!(file.len(), 2);
+    let entries = file.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(entries, [(1, 2), (3, 4)]);
+    assert_eq!(file.read_record(1).unwrap(), (3, 4));
+    assert!(file.read_record(2).is_err());
+
+    let e = File::open(std::io::Cursor::new(&buf[1..])).err().unwrap();
+    assert!(e.to_string().contains("file size 21"), "{}", e);
+    assert!(File::open(std::io::Cursor::new(&buf[..9])).is_err());
+}
+
+#[test]
+fn patch_writer() {
+    use restruct::patch::PatchWriter;
+    use std::io::Write;
+
+    strct!(Riff "<4sI4s");
+    strct!(Chunk ">I4s");
+    let mut out = std::io::Cursor::new(b"prefix".to_vec());
+    out.set_position(6);
+    let mut riff = PatchWriter::<_, Riff>::new(&mut out).unwrap();
+    for (tipe, data) in [(*b"fmt ", &b"abc"[..]), (*b"data", &b"defgh"[..])] {
+        let mut chunk = PatchWriter::<_, Chunk>::new(&mut riff).unwrap();
+        chunk.write_all(data).unwrap();
+        assert_eq!(chunk.crc32(), restruct::checksum::crc32(data));
+        let len = chunk.len() as u32;
+        chunk.finish(&(len, tipe)).unwrap();
+    }
+    let len = riff.len() as u32 + 4;
+    riff.finish(&(*b"RIFF", len, *b"WAVE")).unwrap();
+    out.write_all(b"!").unwrap();
+    let mut expected = b"prefixRIFF\x1c\0\0\0WAVE".to_vec();
+    expected.extend(b"\0\0\0\x03fmt abc\0\0\0\x05datadefgh!");
+    assert_eq!(out.into_inner(), expected);
+}
+
+#[test]
+#[cfg(feature = "futures_io")]
+fn async_io() {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    fn ready<T>(fut: impl Future<Output = T>) -> T {
+        let mut fut = std::pin::pin!(fut);
+        match fut.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(res) => res,
+            Poll::Pending => panic!("future is not ready"),
+        }
+    }
+
+    strct!(Foo "<Hx?");
+    let mut buf = Vec::new();
+    ready(Foo::write_to_async((0x1234, true), &mut buf)).unwrap();
+    assert_eq!(buf, [0x34, 0x12, 0, 1]);
+    assert_eq!(
+        ready(Foo::read_from_async(&mut &buf[..])).unwrap(),
+        (0x1234, true)
+    );
+    assert!(ready(Foo::read_from_async(&mut &buf[..3])).is_err());
+}
+
+#[test]
+#[cfg(feature = "wasm")]
+fn wasm() {
+    use restruct::wasm::Uint8Array;
+
+    // `js_sys` can only be called on wasm32, so just check the signatures
+    strct!(Foo "<HI");
+    let _: fn((u16, u32)) -> Uint8Array = Foo::pack_to_uint8array;
+    let _: fn(&Uint8Array) -> Result<(u16, u32), restruct::UnpackError> =
+        Foo::unpack_from_uint8array;
+}
+
+#[test]
+#[cfg(feature = "markdown")]
+fn markdown() {
+    strct!(Foo "<I?x2H" #names(magic, flag, major, minor) #field_doc(field = 0, doc = "Signature, `FOO|`") #field_doc(field = 2, doc = "Version"));
+    assert_eq!(
+        Foo::MARKDOWN,
+        "### Foo
+
+Byte order: little-endian; standard sizes, no alignment. Size: 10 bytes.
+
+| Field | Type | Offset | Size | Endianness | Description |
+|-------|------|--------|------|------------|-------------|
+| `magic` | `u32` | 0 | 4 | little | Signature, `FOO\\|` |
+| `flag` | `bool` | 4 | 1 | - |  |
+| - | `[u8; 1]` | 5 | 1 | - | Padding |
+| `major` | `u16` | 6 | 2 | little | Version |
+| `minor` | `u16` | 8 | 2 | little |  |
+"
+    );
+}
+
+#[test]
+fn packet_diagram() {
+    strct!(Foo ">I?x2H" #names(magic, flag, major, minor));
+    assert_eq!(
+        Foo::PACKET_DIAGRAM,
+        " 0                   1                   2                   3
+ 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                             magic                             |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|     flag      |    padding    |             major             |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|             minor             |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+"
+    );
+}
+
+#[test]
+#[cfg(feature = "registry")]
+fn registry() {
+    strct!(Registered "<I2H" #names(magic, major, minor));
+    let desc = restruct::registry::find("types::Registered").unwrap();
+    assert_eq!((desc.format, desc.size), ("<I2H", 8));
+    assert_eq!(desc.fields, Registered::FIELDS);
+    assert_eq!(desc.names, Some(&["magic", "major", "minor"][..]));
+    assert!(restruct::registry::iter().count() > 1);
+}
+
+#[test]
+#[cfg(all(feature = "registry", feature = "serde_json"))]
+fn registry_json() {
+    strct!(Described "<I?" #names(magic, flag));
+    let desc = restruct::registry::find("types::Described").unwrap();
+    let packed = Described::pack((0xa1b2c3d4, true));
+    let json = desc.to_json(&packed).unwrap();
+    assert_eq!(json.to_string(), r#"{"flag":true,"magic":2712847316}"#);
+    assert_eq!(desc.from_json(&json).unwrap(), packed);
+}
+
+#[test]
+fn extract_field() {
+    strct!(Inner "<H?");
+    strct!(Foo ">b2x`Inner`3Hd" #restruct(columns));
+    let records = [
+        (-1, (2, true), 3, 4, 5, 0.5),
+        (6, (7, false), 8, 9, 10, -0.25),
+    ];
+    let mut buf = records.iter().flat_map(Foo::pack_ref).collect::<Vec<_>>();
+    buf.push(0xff);
+    assert_eq!(Foo::extract_field::<0>(&buf), [-1, 6]);
+    assert_eq!(Foo::extract_field::<1>(&buf), [(2, true), (7, false)]);
+    assert_eq!(Foo::extract_field::<3>(&buf), [4, 9]);
+    assert_eq!(Foo::iter_field::<5>(&buf).len(), 2);
+    assert_eq!(Foo::iter_field::<5>(&buf).sum::<f64>(), 0.25);
+    let _: Vec<<Foo as restruct::Column<4>>::Type> = Foo::extract_field::<4>(&[]);
+}
+
+#[test]
+fn csv() {
+    strct!(Inner "<H1s" #names(a, b) #restruct(csv));
+    strct!(Foo "<b`Inner`2s" #restruct(csv));
+    assert_eq!(
+        restruct::csv::header::<Foo>(),
+        ["field_0", "field_1.a", "field_1.b", "field_2"]
+    );
+    let mut out = Vec::new();
+    let records = [(-1, (2, *b","), *b"a\0"), (3, (4, *b"\""), *b"\"\n")];
+    restruct::csv::write_records::<Foo, _, _>(&mut out, &records).unwrap();
+    let expected =
+        "field_0,field_1.a,field_1.b,field_2\n-1,2,\",\",a\\x00\n3,4,\"\"\"\",\"\"\"\\n\"\n";
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+    let buf = records.iter().flat_map(Foo::pack_ref).collect::<Vec<_>>();
+    let mut out = Vec::new();
+    restruct::csv::write_buffer::<Foo, _>(&mut out, &buf[..buf.len() - 1]).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        expected
+            .lines()
+            .take(2)
+            .map(|l| l.to_owned() + "\n")
+            .collect::<String>()
+    );
+}
+
+#[test]
+#[cfg(all(feature = "registry", feature = "parquet"))]
+fn registry_parquet() {
+    strct!(Sample ">Hh" #names(seq, value));
+    let desc = restruct::registry::find("types::Sample").unwrap();
+    let buf = [Sample::pack((1, -1)), Sample::pack((2, 300))].concat();
+    let batch = desc.record_batch(&buf).unwrap();
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.schema().field(1).name(), "value");
+    let mut out = Vec::new();
+    desc.write_parquet(&buf, &mut out).unwrap();
+    assert!(out.starts_with(b"PAR1") && out.ends_with(b"PAR1"));
+}
+
+#[test]
+#[cfg(feature = "fuzz")]
+fn fuzz() {
+    strct!(Foo "@b?xdH" #checksum = "crc16" #portability = "allow");
+    for data in [&[][..], &[0xff; 64][..], &[0x12; 64][..], &[2; 64][..]] {
+        Foo::fuzz(data);
+    }
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn random() {
+    use rand::SeedableRng;
+
+    strct!(Inner ">H?");
+    strct!(Foo "<b3sx`Inner`dI" #checksum = "crc32");
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let values = (0..16).map(|_| Foo::random(&mut rng)).collect::<Vec<_>>();
+    for value in &values {
+        assert!(Foo::verify_checksum(&Foo::pack_ref(value)));
+        assert!((0.0..1.0).contains(&value.3));
+    }
+    assert!(values.windows(2).all(|w| w[0] != w[1]));
+}
+
+#[test]
+#[cfg(feature = "cpython")]
+fn cpython() {
+    strct!(Native "@b?hiqlxBHILQ3sdnN" #portability = "allow");
+    strct!(Standard "!bh2iqBx3H0IQ5s?d");
+    strct!(SizeT "<N" #portability = "allow");
+    Native::cpython_check(100).unwrap();
+    Standard::cpython_check(100).unwrap();
+    let e = SizeT::cpython_check(1).unwrap_err();
+    assert!(e.contains("bad char in struct format"), "{}", e);
+}
+
+#[test]
+fn offsets() {
+    strct!(Foo "<B @0x10 H @20");
+    assert_eq!(Foo::SIZE, 20);
+    let packed = Foo::pack((1, 0x0302));
+    assert_eq!(packed[..1], [1]);
+    assert_eq!(packed[16..18], [2, 3]);
+    assert!(packed[1..16].iter().chain(&packed[18..]).all(|b| *b == 0));
+    assert_eq!(Foo::unpack(packed), (1, 0x0302));
+
+    strct!(Bar "@b @8 b q" #portability = "allow");
+    assert_eq!(Bar::SIZE, 24);
+    assert_eq!(Bar::unpack(Bar::pack((1, 2, 3))), (1, 2, 3));
+}
+
+// The generated tests are only collected from outside of functions
+strct!(Vector ">IH?"
+    #test_vector(bytes = "DE AD C0 DE 01 90 01", values = (0xdeadc0de, 400, true))
+    #test_vector(bytes = "00000000 0000 00", values = (0, 0, false)));
+
+strct!(Wired "<IH" #restruct(module = "wire", packed) #names(magic, version));
+
+#[test]
+fn module() {
+    assert_eq!(wire::Wired::SIZE, 6);
+    let packed = wire::Wired::pack((0xfeed, 2));
+    assert_eq!(wire::WiredPacked::from((0xfeed, 2)).as_ref(), packed);
+    assert_eq!(
+        <Wired as restruct::Struct>::unpack_slice(&packed),
+        (0xfeed, 2)
+    );
+    let mut records = restruct::RecordReader::<_, Wired>::new(&packed[..]);
+    assert_eq!(records.next().unwrap().unwrap(), (0xfeed, 2));
+}
+
+mod codec {
+    pub trait Codec {
+        type Value;
+        type Error;
+        fn encode(value: &Self::Value, out: &mut Vec<u8>);
+        fn decode(inp: &[u8]) -> Result<(Self::Value, &[u8]), Self::Error>;
+    }
+}
+
+strct!(Coded ">H?" #impl_codec(codec::Codec));
+
+#[test]
+fn impl_codec() {
+    use codec::Codec;
+
+    let mut out = Vec::new();
+    Coded::encode(&(0x102, true), &mut out);
+    Coded::encode(&(3, false), &mut out);
+    assert_eq!(out, [1, 2, 1, 0, 3, 0]);
+    let (first, rest) = Coded::decode(&out).unwrap();
+    assert_eq!((first, rest), ((0x102, true), &out[3..]));
+    assert!(matches!(
+        Coded::decode(&rest[1..]),
+        Err(restruct::UnpackError::Size { .. })
+    ));
+}
+
+#[test]
+fn text() {
+    strct!(Foo "<4s H 6s" #text(field = 0) #text(field = 2, padding = "space"));
+    let mut foo = Foo::unpack(*b"ab\0\0\x01\0 b c  ");
+    assert_eq!(Foo::str_0(&foo), Ok("ab"));
+    assert_eq!(Foo::str_2(&foo), Ok(" b c"));
+    Foo::set_str_0(&mut foo, "abcd").unwrap();
+    Foo::set_str_2(&mut foo, "ä").unwrap();
+    assert_eq!(Foo::pack(foo), *b"abcd\x01\0\xc3\xa4    ");
+    let e = Foo::set_str_2(&mut foo, "abcdefg").unwrap_err();
+    assert_eq!((e.index, e.offset, e.tipe), (2, 6, "[u8; 6]"));
+    foo.0 = *b"\xff\0\0\0";
+    assert!(Foo::str_0(&foo).is_err());
+    foo.0 = [0; 4];
+    assert_eq!(Foo::str_0(&foo), Ok(""));
+}
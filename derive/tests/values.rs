@@ -57,7 +57,7 @@ macro_rules! test_transitiveness {
     ($testname:ident, $modifier:literal, $fmt:literal, $fix:expr) => {
         #[test]
         fn $testname() {
-            strct!($modifier $fmt);
+            strct!(Foo $modifier $fmt #portability = "allow");
             let inp: <Foo as restruct::Struct>::Unpacked = $fix;
             let mut buffer = Vec::new();
             Foo::write_to(inp, &mut buffer).unwrap();
@@ -83,9 +83,9 @@ macro_rules! test_transitiveness {
                 (
                     100,
                     -32000,
-                    i32::min_value(),
-                    i32::min_value().into(),
-                    i64::min_value(),
+                    i32::MIN,
+                    i32::MIN.into(),
+                    i64::MIN,
                     std::f32::consts::PI,
                     std::f64::consts::PI,
                     true
@@ -97,9 +97,9 @@ macro_rules! test_transitiveness {
                 (
                     128,
                     65000,
-                    u32::max_value(),
-                    u32::max_value().into(),
-                    u64::max_value(),
+                    u32::MAX,
+                    u32::MAX.into(),
+                    u64::MAX,
                     std::f32::consts::PI,
                     std::f64::consts::PI,
                     true
@@ -109,3 +109,496 @@ macro_rules! test_transitiveness {
     };
 }
 test_transitiveness!();
+
+#[test]
+fn display() {
+    strct!(">I2x2?3s");
+    let inp = (0xdead_c0de, true, false, *b"foo");
+    assert_eq!(
+        Foo::display(&inp).to_string(),
+        "Foo {
+    0 (u32 @ 0): 3735929054
+    1 (bool @ 6): true
+    2 (bool @ 7): false
+    3 ([u8; 3] @ 8): [102, 111, 111]
+}"
+    );
+}
+
+#[test]
+fn diff() {
+    strct!("<Ih?");
+    assert!(Foo::diff(&(1, 2, true), &(1, 2, true)).is_empty());
+    let diffs = Foo::diff(&(1, 2, true), &(3, 2, false));
+    assert_eq!(diffs.len(), 2);
+    assert_eq!(diffs[0].to_string(), "0 (u32 @ 0): 1 != 3");
+    assert_eq!(diffs[1].index, 2);
+    assert_eq!(diffs[1].offset, 6);
+    assert_eq!((diffs[1].left.as_str(), diffs[1].right.as_str()), ("true", "false"));
+}
+
+#[test]
+fn fields_of() {
+    strct!(Foo "@b2Hx3s" #names(kind, a, b, tag) #portability = "allow");
+    let packed = Foo::pack((-1, 2, 3, *b"abc"));
+    let fields = Foo::fields_of(&packed).collect::<Vec<_>>();
+    assert_eq!(fields.len(), 5);
+    let (info, bytes) = fields[1];
+    assert_eq!(
+        (info.index, info.name, info.offset, info.size),
+        (Some(1), Some("a"), 2, 2)
+    );
+    assert_eq!(bytes, 2u16.to_ne_bytes());
+    assert_eq!((fields[3].0.index, fields[3].1), (None, &[0][..]));
+    assert_eq!((fields[4].0.tipe, fields[4].1), ("[u8; 3]", &b"abc"[..]));
+}
+
+#[test]
+fn value_of() {
+    use restruct::Value;
+
+    strct!(Inner ">H");
+    strct!(Foo ">b2Hx?d2s`Inner`n" #portability = "allow");
+    let packed = Foo::pack((-1, 2, 3, true, 0.5, *b"ab", (4,), -5));
+    assert_eq!(Foo::value_of(&packed, 0), Some(Value::I8(-1)));
+    assert_eq!(Foo::value_of(&packed, 2), Some(Value::U16(3)));
+    assert_eq!(Foo::value_of(&packed, 3), Some(Value::Bool(true)));
+    assert_eq!(Foo::value_of(&packed, 4), Some(Value::F64(0.5)));
+    assert_eq!(
+        Foo::value_of(&packed, 5),
+        Some(Value::Bytes(b"ab".to_vec()))
+    );
+    assert_eq!(Foo::value_of(&packed, 6), Some(Value::Bytes(vec![0, 4])));
+    assert_eq!(Foo::value_of(&packed, 7), Some(Value::I64(-5)));
+    assert_eq!(Foo::value_of(&packed, 8), None);
+}
+
+#[test]
+fn raw_field() {
+    strct!(Foo "@b2Hx3s" #portability = "allow");
+    let mut packed = Foo::pack((-1, 2, 3, *b"abc"));
+    assert_eq!(Foo::raw_field(&packed, 0), Some(&[0xff][..]));
+    assert_eq!(Foo::raw_field(&packed, 4), Some(&b"abc"[..]));
+    assert_eq!(Foo::raw_field(&packed, 5), None);
+    Foo::raw_field_mut(&mut packed, 2)
+        .unwrap()
+        .copy_from_slice(&7u16.to_ne_bytes());
+    assert_eq!(Foo::unpack(packed), (-1, 2, 7, *b"abc"));
+}
+
+#[test]
+fn canonicalize() {
+    strct!(Foo "=b2x?");
+    strct!(Bar "=`Foo`x");
+    assert_eq!(Foo::canonicalize([1, 2, 3, 4]), [1, 0, 0, 4]);
+    assert_eq!(Bar::canonicalize([1, 2, 3, 4, 5]), [1, 0, 0, 4, 0]);
+    const CANONICAL: [u8; 4] = Foo::canonicalize([0xff; 4]);
+    assert_eq!(CANONICAL, [0xff, 0, 0, 0xff]);
+}
+
+#[test]
+fn packed_eq() {
+    strct!(Foo "=b2x?");
+    strct!(Bar "=`Foo`xh");
+    assert!(Foo::packed_eq(&[1, 2, 3, 4], &[1, 0, 0, 4]));
+    assert!(!Foo::packed_eq(&[1, 2, 3, 4], &[1, 2, 3, 5]));
+    assert!(Bar::packed_eq(&[1, 2, 3, 4, 5, 6, 7], &[1, 0, 0, 4, 0, 6, 7]));
+    assert!(!Bar::packed_eq(&[1, 2, 3, 4, 5, 6, 7], &[1, 2, 3, 4, 5, 6, 0]));
+    const { assert!(Foo::packed_eq(&[0; 4], &[0, 1, 1, 0])) };
+}
+
+#[test]
+fn cmp_packed() {
+    use std::cmp::Ordering;
+
+    strct!(Foo ">Hx2s");
+    strct!(Bar ">`Foo`B");
+    assert_eq!(
+        Foo::cmp_packed(&[0, 1, 2, 3, 4], &[0, 1, 0, 3, 4]),
+        Ordering::Equal
+    );
+    assert_eq!(
+        Foo::cmp_packed(&[0, 1, 0, 3, 4], &[1, 0, 0, 0, 0]),
+        Ordering::Less
+    );
+    assert_eq!(
+        Foo::cmp_packed(&[0, 1, 0, 3, 5], &[0, 1, 0, 3, 4]),
+        Ordering::Greater
+    );
+    assert_eq!(
+        Bar::cmp_packed(&[0, 1, 0, 3, 4, 9], &[0, 1, 0, 3, 4, 2]),
+        Ordering::Greater
+    );
+
+    let mut records = [
+        Foo::pack((300, *b"ab")),
+        Foo::pack((2, *b"zz")),
+        Foo::pack((300, *b"aa")),
+    ];
+    records.sort_by(Foo::cmp_packed);
+    assert_eq!(
+        records.iter().map(|r| Foo::unpack(*r)).collect::<Vec<_>>(),
+        [(2, *b"zz"), (300, *b"aa"), (300, *b"ab")]
+    );
+    const { assert!(Foo::cmp_packed(&[0; 5], &[0, 0, 1, 0, 0]).is_eq()) };
+}
+
+#[test]
+fn binary_search_by_field() {
+    strct!(Foo ">xH2s");
+    let records = [(1, *b"ab"), (5, *b"ab"), (9, *b"aa")]
+        .into_iter()
+        .flat_map(Foo::pack)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        Foo::binary_search_by_field::<1>(&records, &5u16.to_be_bytes()),
+        Ok(1)
+    );
+    assert_eq!(
+        Foo::binary_search_by_field::<1>(&records, &6u16.to_be_bytes()),
+        Err(2)
+    );
+    assert_eq!(
+        Foo::binary_search_by_field::<1>(&records[..14], &9u16.to_be_bytes()),
+        Err(2)
+    );
+    assert_eq!(
+        Foo::binary_search_by_field::<2>(&records[..10], b"ab"),
+        Ok(1)
+    );
+}
+
+#[test]
+fn hex() {
+    strct!(Foo "<H?2s");
+    assert_eq!(Foo::pack_hex((0x1234, true, *b"ab")), "3412016162");
+    assert_eq!(
+        Foo::unpack_hex("34 12 01 61 62").unwrap(),
+        (0x1234, true, *b"ab")
+    );
+    assert!(matches!(
+        Foo::unpack_hex("341201"),
+        Err(restruct::Error::Unpack(restruct::UnpackError::Size {
+            expected: 5,
+            found: 3
+        }))
+    ));
+}
+
+#[cfg(feature = "base64")]
+#[test]
+fn base64() {
+    strct!(Foo "<H?2s");
+    assert_eq!(Foo::pack_base64((0x1234, true, *b"ab")), "NBIBYWI=");
+    assert_eq!(
+        Foo::unpack_base64("NBIBYWI=").unwrap(),
+        (0x1234, true, *b"ab")
+    );
+    assert!(Foo::unpack_base64("NBIBYW==").is_err());
+}
+
+#[test]
+fn timestamps() {
+    strct!(Foo "<IQ" #timestamp(field = 0, kind = "dos") #timestamp(field = 1, kind = "filetime"));
+    let foo = (0x5821_6a4f, 133_485_408_001_234_567);
+    assert_eq!(Foo::datetime_0(&foo).to_string(), "2024-01-01T13:18:30");
+    const T: restruct::time::DateTime = Foo::datetime_1(&(0, 133_485_408_001_234_567));
+    assert_eq!(T.to_string(), "2024-01-01T00:00:00.123456700");
+    assert_eq!(T.to_filetime(), Some(foo.1));
+}
+
+#[test]
+fn scales() {
+    strct!(Foo "<hBi" #names(temp, humidity, pressure) #scale(field = 0, factor = 0.01, offset = "-40") #scale(field = 2, factor = 10));
+    let mut foo = (6250, 55, 101_325);
+    assert!((Foo::temp_scaled(&foo) - 22.5).abs() < 1e-9);
+    assert_eq!(Foo::pressure_scaled(&foo), 1_013_250.0);
+    Foo::set_temp_scaled(&mut foo, -12.34);
+    assert_eq!(foo.0, 2766);
+    Foo::set_temp_scaled(&mut foo, 1000.0);
+    assert_eq!(foo.0, i16::MAX);
+    Foo::set_pressure_scaled(&mut foo, 14.0);
+    assert_eq!(foo.2, 1);
+
+    strct!(Bar "<H" #scale(field = 0, factor = 0.5));
+    const B: f64 = Bar::scaled_0(&(3,));
+    assert_eq!(B, 1.5);
+    let mut bar = (0,);
+    Bar::set_scaled_0(&mut bar, -1.0);
+    assert_eq!(bar, (0,));
+}
+
+const fn mask(v: u16) -> u16 {
+    v ^ 0x5a5a
+}
+
+const fn reverse(v: [u8; 3]) -> [u8; 3] {
+    [v[2], v[1], v[0]]
+}
+
+#[test]
+fn transforms() {
+    strct!(Foo "<3H3s" #transform(field = 1, decode = "mask", encode = "mask") #transform(field = 3, decode = "reverse", encode = "reverse"));
+    assert_eq!(Foo::SIZE, 9);
+    let packed = [1, 0, 0x5b, 0x5a, 3, 0, b'a', b'b', b'c'];
+    const UNPACKED: (u16, u16, u16, [u8; 3]) =
+        Foo::unpack([1, 0, 0x5b, 0x5a, 3, 0, b'a', b'b', b'c']);
+    assert_eq!(UNPACKED, (1, 1, 3, *b"cba"));
+    assert_eq!(Foo::pack(UNPACKED), packed);
+    assert_eq!(Foo::unpack_all(&packed), [UNPACKED]);
+}
+
+const fn reverse4(v: [u8; 4]) -> [u8; 4] {
+    [v[3], v[2], v[1], v[0]]
+}
+
+const fn flip(v: (u16, bool)) -> (u16, bool) {
+    (v.0 ^ 0xff, !v.1)
+}
+
+#[test]
+fn transforms_boxed() {
+    strct!(Inner "<H?");
+    strct!(Foo "<4sH`Inner`" #transform(field = 0, decode = "reverse4", encode = "reverse4") #transform(field = 2, decode = "flip", encode = "flip"));
+    let value = (*b"\x01\x02\x03\x04", 7, (1, true));
+    let packed = Foo::pack(value);
+    assert_eq!(packed, [4, 3, 2, 1, 7, 0, 0xfe, 0, 0]);
+    assert_eq!(*Foo::pack_boxed(&value), packed);
+    let mut out = [0xaa; 10];
+    Foo::pack_into(&value, &mut out);
+    assert_eq!(out[..9], packed);
+    assert_eq!(Foo::unpack(packed), value);
+}
+
+#[test]
+fn extended() {
+    strct!(Foo "<gxG");
+    assert_eq!(Foo::SIZE, 27);
+    let packed = Foo::pack((1.0, -0.5));
+    assert_eq!(packed[..10], [0, 0, 0, 0, 0, 0, 0, 0x80, 0xff, 0x3f]);
+    assert_eq!(packed[25..], [0xfe, 0xbf]);
+    assert_eq!(Foo::unpack(packed), (1.0, -0.5));
+
+    strct!(Bar ">g");
+    let pi = [0x40, 0x00, 0xc9, 0x0f, 0xda, 0xa2, 0x21, 0x68, 0xc2, 0x35];
+    assert_eq!(Bar::unpack(pi), (std::f64::consts::PI,));
+    const INF: (f64,) = Bar::unpack([0x7f, 0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(INF, (f64::INFINITY,));
+}
+
+#[test]
+fn try_pack() {
+    strct!(Foo "<hB2s?d");
+    strct!(Bar ">H`Foo`" #names(len, foo));
+    let len: usize = 0x102;
+    assert_eq!(
+        Foo::try_pack((-1i64, 2u64, *b"ab", true, 0.5)),
+        Ok(Foo::pack((-1, 2, *b"ab", true, 0.5)))
+    );
+    assert_eq!(
+        Bar::try_pack((len, (1, 2, *b"ab", false, 0.0))).unwrap()[..3],
+        [1, 2, 1]
+    );
+    assert_eq!(
+        Foo::try_pack((1 << 15, 0, [0; 2], false, 0.0)),
+        Err(restruct::PackError {
+            index: 0,
+            name: None,
+            offset: 0,
+            tipe: "i16"
+        })
+    );
+    let e = Bar::try_pack((-1i32, Foo::zeroed())).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "element 0 (`len`) at offset 0: value does not fit `u16`"
+    );
+}
+
+#[test]
+fn iter_valid() {
+    use restruct::OnInvalid;
+
+    strct!(Foo "<HB" #checksum = "sum8");
+    let data = [1, 0, 1, 2, 0, 0, 3, 0, 3, 4];
+    assert_eq!(Foo::validate(&[1, 0, 1]), Ok(()));
+    assert!(Foo::validate(&[2, 0, 0]).is_err());
+    assert_eq!(Foo::read_valid_from(&mut &data[..]).unwrap(), (1, 1));
+    assert!(matches!(
+        Foo::read_valid_from(&mut &data[3..]),
+        Err(restruct::Error::Unpack(_))
+    ));
+
+    let mut records = Foo::iter_valid(&data[..], OnInvalid::Stop);
+    assert_eq!(records.next().unwrap().unwrap(), (1, 1));
+    assert!(matches!(
+        records.next(),
+        Some(Err(restruct::Error::Unpack(restruct::UnpackError::Value {
+            index: 1,
+            offset: 2,
+            ..
+        })))
+    ));
+    assert!(records.next().is_none());
+
+    let mut records = Foo::iter_valid(&data[..], OnInvalid::Skip);
+    assert_eq!(records.next().unwrap().unwrap(), (1, 1));
+    assert_eq!(records.next().unwrap().unwrap(), (3, 3));
+    assert!(matches!(
+        records.next(),
+        Some(Err(restruct::Error::Truncated { got: 1, .. }))
+    ));
+    assert!(records.next().is_none());
+
+    strct!(Bar "<H");
+    let records = Bar::iter_valid(&data[..4], OnInvalid::Stop);
+    assert_eq!(
+        records.map(Result::unwrap).collect::<Vec<_>>(),
+        [(1,), (0x201,)]
+    );
+}
+
+#[test]
+fn upgrade_from() {
+    strct!(Foo "<H2s");
+    strct!(Bar "<2s?H" #names(tag, flag, id) #upgrade_from(Foo(id, tag)));
+    strct!(Baz "<H" #names(id) #upgrade_from(Foo(id, _tag), Bar(_tag, _flag, id)));
+    assert_eq!(
+        Bar::upgrade_from_foo((7, *b"ab"), Bar::DEFAULT),
+        (*b"ab", false, 7)
+    );
+    const BAZ: (u16,) = Baz::upgrade_from_bar((*b"ab", true, 7), (0,));
+    assert_eq!(BAZ, (7,));
+    assert_eq!(Baz::upgrade_from_foo((7, *b"ab"), (0,)), (7,));
+}
+
+#[test]
+fn defaults() {
+    strct!(Foo ">I?2x3s");
+    assert_eq!(Foo::zeroed(), (0, false, [0; 3]));
+    assert_eq!(Foo::DEFAULT, Foo::zeroed());
+
+    strct!(Bar ">I`Foo`d" #default(field = 0, value = 0xa1b2c3d4) #default(field = 1, value = "(1, true, *b\"abc\")") #default(field = 2, value = 1.5));
+    assert_eq!(Bar::DEFAULT, (0xa1b2_c3d4, (1, true, *b"abc"), 1.5));
+}
+
+#[test]
+fn builder() {
+    strct!(Foo "<I?3s" #names(magic, flag, tag) #default(field = 0, value = 7) #restruct(builder));
+    const FOO: FooBuilder = Foo::builder().flag(true);
+    assert_eq!(FOO.build(), (7, true, [0; 3]));
+    assert_eq!(FOO.tag(*b"abc").magic(1).pack(), [1, 0, 0, 0, 1, b'a', b'b', b'c']);
+}
+
+#[test]
+fn inline_names() {
+    strct!(Foo "<magic:I version:2H flags:B" #restruct(builder));
+    let foo = Foo::builder().magic(1).version_1(2).flags(3).build();
+    assert_eq!(foo, (1, 0, 2, 3));
+    let packed = Foo::pack(foo);
+    let names = Foo::fields_of(&packed)
+        .map(|(info, _)| info.name.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["magic", "version_0", "version_1", "flags"]);
+}
+
+#[test]
+fn derives() {
+    strct!(Foo "<H?" #names(kind, flag) #restruct(builder, packed, derive(Clone, Eq, Hash, PartialOrd)));
+    let builders = [Foo::builder(), Foo::builder().kind(1), Foo::builder()]
+        .into_iter()
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(builders.len(), 2);
+    assert!(Foo::builder() < Foo::builder().flag(true));
+    assert!(FooPacked::from((1, false)) < FooPacked::from((2, false)));
+}
+
+#[test]
+fn map_to() {
+    #[derive(Debug, PartialEq)]
+    struct Pair(u16, bool);
+
+    #[derive(Debug, PartialEq)]
+    struct Named {
+        a: u16,
+        b: bool,
+    }
+
+    strct!(Foo "<H?" #map_to(Pair));
+    strct!(Bar "<H?" #names(a, b) #map_to(Named));
+    assert_eq!(Pair::from((1, true)), Pair(1, true));
+    assert_eq!(<(u16, bool)>::from(Pair(1, true)), (1, true));
+    assert_eq!(Foo::pack_struct(Pair(1, true)), [1, 0, 1]);
+    assert_eq!(
+        Bar::unpack_struct::<Named>([1, 0, 1]),
+        Named { a: 1, b: true }
+    );
+}
+
+#[test]
+fn checksum() {
+    strct!(Foo "<4sxI" #checksum = "crc32");
+    strct!(Bar ">5sH" #checksum = "crc16");
+    let packed = Foo::pack((*b"1234", 0));
+    assert_eq!(Foo::checksum(&packed), restruct::checksum::crc32(b"1234\0"));
+    assert_eq!(
+        Foo::unpack_checked(packed),
+        Ok((*b"1234", Foo::checksum(&packed)))
+    );
+    let packed = Bar::pack((*b"12345", 0));
+    assert_eq!(
+        packed[5..],
+        restruct::checksum::crc16(b"12345").to_be_bytes()
+    );
+    assert!(Bar::verify_checksum(&packed));
+    let mut corrupted = packed;
+    corrupted[0] ^= 1;
+    assert!(!Bar::verify_checksum(&corrupted));
+    let e = Bar::unpack_checked(corrupted).unwrap_err();
+    assert_eq!(e.offset(), Some(5));
+    assert!(matches!(
+        e,
+        restruct::UnpackError::Value {
+            index: 1,
+            name: None,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn packed_wrapper() {
+    strct!(Foo "<hx?" #restruct(packed));
+    let packed = FooPacked::try_from(&[0xff, 0xff, 0, 1][..]).unwrap();
+    assert_eq!(packed.as_ref(), &[0xff, 0xff, 0, 1]);
+    assert_eq!(<(i16, bool)>::from(packed), (-1, true));
+    assert_eq!(
+        <[u8; 4]>::from(FooPacked::from((-1, true))),
+        [0xff, 0xff, 0, 1]
+    );
+    assert!(FooPacked::try_from(&[0; 5][..]).is_err());
+
+    let mut packed = FooPacked::new((-1, true));
+    assert_eq!((packed.get_0(), packed.get_1()), (-1, true));
+    packed.set_1(false);
+    assert_eq!(packed.as_bytes(), &[0xff, 0xff, 0, 0]);
+
+    strct!(Bar "@b2H?`Foo`" #names(a, b, c, d, e) #portability = "allow" #restruct(packed));
+    const BAR: BarPacked = {
+        let mut bar = BarPacked::new((-2, 3, 4, false, (5, false)));
+        bar.set_c(6);
+        bar.set_e((7, true));
+        bar
+    };
+    assert_eq!(
+        (BAR.a(), BAR.b(), BAR.c(), BAR.d(), BAR.e()),
+        (-2, 3, 6, false, (7, true))
+    );
+    assert_eq!(BAR.unpack(), (-2, 3, 6, false, (7, true)));
+}
+
+strct!(RoundtripHeader "<I2H4s" #default(field = 0, value = 0xa1b2c3d4));
+strct!(RoundtripNative "@b?xdH2`RoundtripHeader`" #portability = "allow");
+strct!(InlineAlways "<Hq" #inline_hint = "always");
+strct!(InlineNever "<Hq" #inline_hint = "never");
+restruct::roundtrip_tests!(RoundtripHeader, RoundtripNative, InlineAlways, InlineNever);
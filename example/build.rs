@@ -1,5 +1,14 @@
 fn main() {
     cc::Build::new()
         .file("src/example.c")
-        .compile("example_mod")
+        .compile("example_mod");
+
+    // The same as `#[derive(restruct_derive::Struct)] #[fmt = "<I2H"] pub struct Header;`
+    let code = restruct::codegen(
+        "Header",
+        "<I2H",
+        restruct::Options::default().with_vis("pub"),
+    );
+    let out = std::path::Path::new(&std::env::var_os("OUT_DIR").unwrap()).join("header.rs");
+    std::fs::write(out, code).unwrap();
 }
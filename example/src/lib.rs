@@ -11,9 +11,16 @@ pub struct Example;
 const _EXAMPLE: <Example as restruct::Struct>::Unpacked =
     Example::unpack(*include_bytes!("example.bin"));
 
+/// Generated by the build-script, using `restruct::codegen()`
+#[allow(clippy::all)]
+pub mod generated {
+    include!(concat!(env!("OUT_DIR"), "/header.rs"));
+}
+
 pub mod world {
     #[derive(restruct_derive::Struct)]
     #[fmt = "@bhlbibqBHLbIbQ3s"]
+    #[portability = "allow"]
     pub struct Turtle;
 
     impl Turtle {
@@ -24,10 +31,12 @@ pub mod world {
 
     #[derive(restruct_derive::Struct)]
     #[fmt = "bQh"]
+    #[portability = "allow"]
     pub struct LowerTurtle;
 
     #[derive(restruct_derive::Struct)]
     #[fmt = "i2`LowerTurtle`"]
+    #[portability = "allow"]
     pub struct LowestTurtle;
 
     impl LowestTurtle {
@@ -44,6 +53,13 @@ mod tests {
         assert_eq!(super::_EXAMPLE, (0xdead_c0de, 400, true));
     }
 
+    #[test]
+    fn generated() {
+        use super::generated::Header;
+        assert_eq!(Header::SIZE, 8);
+        assert_eq!(Header::unpack(Header::pack((1, 2, 3))), (1, 2, 3));
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn reach_bottom_most_turtle() {
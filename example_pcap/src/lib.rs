@@ -1,5 +1,15 @@
 #[derive(restruct_derive::Struct)]
 #[fmt = "<I2Hi3I"]
+#[names(
+    magic,
+    version_major,
+    version_minor,
+    ts_correction,
+    ts_accuracy,
+    snaplen,
+    datalink
+)]
+#[map_to(Header)]
 struct PcapHeader;
 
 #[derive(restruct_derive::Struct)]
@@ -19,22 +29,13 @@ pub struct Header {
 
 impl Header {
     fn read_from<R: std::io::Read>(mut inp: R) -> std::io::Result<Self> {
-        let (magic, version_major, version_minor, ts_correction, ts_accuracy, snaplen, datalink) =
-            PcapHeader::read_from(&mut inp)?;
+        let header = Self::from(PcapHeader::read_from(&mut inp)?);
 
-        if magic != 0xa1b2_c3d4 {
+        if header.magic != 0xa1b2_c3d4 {
             panic!("oh noes, we don't support this!");
         }
 
-        Ok(Self {
-            magic,
-            version_major,
-            version_minor,
-            ts_correction,
-            ts_accuracy,
-            snaplen,
-            datalink,
-        })
+        Ok(header)
     }
 }
 
@@ -47,24 +48,29 @@ pub struct Packet {
 }
 
 impl Packet {
-    fn read_from<R: std::io::Read>(mut inp: R) -> std::io::Result<Self> {
-        let (ts_sec, ts_usec, incl_len, orig_len) = PcapPacketHeader::read_from(&mut inp)?;
+    fn read_from<R: std::io::Read>(
+        records: &mut restruct::RecordReader<R, PcapPacketHeader>,
+    ) -> std::io::Result<Option<Self>> {
+        let (ts_sec, ts_usec, incl_len, orig_len) = match records.read_record()? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
         let mut data = vec![0; incl_len as usize];
-        inp.read_exact(&mut data).map(|()| Self {
+        std::io::Read::read_exact(records, &mut data)?;
+        Ok(Some(Self {
             ts_sec,
             ts_usec,
             orig_len,
             data,
-        })
+        }))
     }
 }
 
 pub fn read<R: std::io::Read>(
     mut inp: R,
-) -> std::io::Result<(Header, impl Iterator<Item = Packet>)> {
+) -> std::io::Result<(Header, impl Iterator<Item = std::io::Result<Packet>>)> {
     let head = Header::read_from(&mut inp)?;
-    let reader = std::iter::from_fn(move || {
-        Packet::read_from(&mut inp).ok() /* Yes, we cheat */
-    });
+    let mut records = restruct::RecordReader::new(inp);
+    let reader = std::iter::from_fn(move || Packet::read_from(&mut records).transpose());
     Ok((head, reader))
 }
@@ -0,0 +1,23 @@
+//! The headers at the start of Windows bitmap files.
+//!
+//! A file starts with a `FileHeader`, followed by a `BitmapInfoHeader` (or one of it's larger
+//! successors, telling by `size`). The pixel data starts at `data_offset`.
+
+/// The `magic` of bitmap files
+pub const MAGIC: [u8; 2] = *b"BM";
+
+/// The file header
+#[derive(restruct_derive::Struct)]
+#[fmt = "<magic:2s file_size:I reserved_1:H reserved_2:H data_offset:I"]
+#[default(field = 0, value = b"BM")]
+#[default(field = 4, value = 54)]
+#[restruct(builder)]
+pub struct FileHeader;
+
+/// The `BITMAPINFOHEADER`; a negative `height` means the rows are stored top-down.
+#[derive(restruct_derive::Struct)]
+#[fmt = "<size:I width:i height:i planes:H bit_count:H compression:I image_size:I x_pels_per_meter:i y_pels_per_meter:i colors_used:I colors_important:I"]
+#[default(field = 0, value = 40)]
+#[default(field = 3, value = 1)]
+#[restruct(builder)]
+pub struct BitmapInfoHeader;
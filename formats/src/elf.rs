@@ -0,0 +1,21 @@
+//! The identification at the start of ELF files, which tells the class and byte order of the
+//! remaining headers.
+
+/// The `magic` of ELF files
+pub const MAGIC: [u8; 4] = *b"\x7fELF";
+/// The `class` of 32-bit objects
+pub const CLASS_32: u8 = 1;
+/// The `class` of 64-bit objects
+pub const CLASS_64: u8 = 2;
+/// The `data` of little-endian objects
+pub const DATA_LSB: u8 = 1;
+/// The `data` of big-endian objects
+pub const DATA_MSB: u8 = 2;
+
+/// The `e_ident`-array
+#[derive(restruct_derive::Struct)]
+#[fmt = "<magic:4s class:B data:B version:B os_abi:B abi_version:B 7x"]
+#[default(field = 0, value = b"\x7fELF")]
+#[default(field = 3, value = 1)]
+#[restruct(builder)]
+pub struct Ident;
@@ -0,0 +1,24 @@
+//! The GUID Partition Table, as found in the second logical block of a disk.
+//!
+//! GUIDs are kept as their 16 bytes in mixed-endian order as stored on disk. The `header_crc32`
+//! is computed over the first `header_size` bytes with the element set to zero.
+
+/// The `signature` of GPT headers
+pub const SIGNATURE: [u8; 8] = *b"EFI PART";
+
+/// The partition table header
+#[derive(restruct_derive::Struct)]
+#[fmt = "<signature:8s revision:I header_size:I header_crc32:I 4x current_lba:Q backup_lba:Q first_usable_lba:Q last_usable_lba:Q disk_guid:16s partition_entries_lba:Q num_partition_entries:I partition_entry_size:I partition_entries_crc32:I"]
+#[default(field = 0, value = b"EFI PART")]
+#[default(field = 1, value = 0x0001_0000)]
+#[default(field = 2, value = 92)]
+#[default(field = 10, value = 128)]
+#[default(field = 11, value = 128)]
+#[restruct(builder)]
+pub struct Header;
+
+/// An entry of the partition table; `name` is UTF-16LE, padded with zeros.
+#[derive(restruct_derive::Struct)]
+#[fmt = "<type_guid:16s unique_guid:16s first_lba:Q last_lba:Q attributes:Q name:72s"]
+#[restruct(builder)]
+pub struct PartitionEntry;
@@ -0,0 +1,26 @@
+//! Ready-made definitions of common fixed-size binary headers, derived via `restruct_derive`.
+//!
+//! Each type is a regular derived type with a builder, so everything generated for it
+//! (`read_from()`, `builder()`, `DEFAULT`, ...) is available. Elements are named after the respective
+//! specification; magic numbers are set in `DEFAULT`, so `Foo::builder()` starts from a valid
+//! header. Only the fixed-size parts are covered; variable-length data following a header is up
+//! to the caller.
+//!
+//! ```
+//! use restruct_formats::wav::{RiffHeader, WAVE};
+//!
+//! let data = *b"RIFF\x24\x08\x00\x00WAVE";
+//! let (riff, size, form) = RiffHeader::unpack(data);
+//! assert_eq!((&riff, size, form), (b"RIFF", 2084, WAVE));
+//! ```
+
+// Several `default`-attributes only differ in their values
+#![allow(clippy::duplicated_attributes)]
+
+pub mod bmp;
+pub mod elf;
+pub mod gpt;
+pub mod pcap;
+pub mod pcapng;
+pub mod tar;
+pub mod wav;
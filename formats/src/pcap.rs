@@ -0,0 +1,26 @@
+//! The classic libpcap file format, in little-endian byte order with microsecond timestamps.
+//!
+//! A file consists of a `Header`, followed by packets, each consisting of a `PacketHeader`
+//! followed by `incl_len` bytes of captured data.
+
+/// The magic number of little-endian files with microsecond timestamps
+pub const MAGIC: u32 = 0xa1b2_c3d4;
+/// The magic number of little-endian files with nanosecond timestamps
+pub const MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+/// The header at the start of a file
+#[derive(restruct_derive::Struct)]
+#[fmt = "<magic:I version_major:H version_minor:H thiszone:i sigfigs:I snaplen:I network:I"]
+#[default(field = 0, value = 0xa1b2_c3d4)]
+#[default(field = 1, value = 2)]
+#[default(field = 2, value = 4)]
+#[default(field = 5, value = 65535)]
+#[default(field = 6, value = 1)]
+#[restruct(builder)]
+pub struct Header;
+
+/// The header preceding each packet's data
+#[derive(restruct_derive::Struct)]
+#[fmt = "<ts_sec:I ts_usec:I incl_len:I orig_len:I"]
+#[restruct(builder)]
+pub struct PacketHeader;
@@ -0,0 +1,49 @@
+//! The pcapng file format, in little-endian byte order.
+//!
+//! A file is a sequence of blocks, each starting with a `BlockHeader` and ending with a repetition
+//! of `block_total_length`. The types below cover the fixed-size beginning of common blocks,
+//! including the `BlockHeader`; options and packet data follow.
+
+/// The `block_type` of a Section Header Block
+pub const SECTION_HEADER: u32 = 0x0a0d_0d0a;
+/// The `block_type` of an Interface Description Block
+pub const INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+/// The `block_type` of an Enhanced Packet Block
+pub const ENHANCED_PACKET: u32 = 0x0000_0006;
+/// The `byte_order_magic` as read in the byte order of the section
+pub const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+/// The beginning of any block
+#[derive(restruct_derive::Struct)]
+#[fmt = "<block_type:I block_total_length:I"]
+#[restruct(builder)]
+pub struct BlockHeader;
+
+/// The fixed-size beginning of a Section Header Block; a `section_length` of `-1` means the
+/// length is not given.
+#[derive(restruct_derive::Struct)]
+#[fmt = "<block_type:I block_total_length:I byte_order_magic:I major_version:H minor_version:H section_length:q"]
+#[default(field = 0, value = 0x0a0d_0d0a)]
+#[default(field = 1, value = 28)]
+#[default(field = 2, value = 0x1a2b_3c4d)]
+#[default(field = 3, value = 1)]
+#[default(field = 5, value = -1)]
+#[restruct(builder)]
+pub struct SectionHeader;
+
+/// The fixed-size beginning of an Interface Description Block
+#[derive(restruct_derive::Struct)]
+#[fmt = "<block_type:I block_total_length:I link_type:H 2x snap_len:I"]
+#[default(field = 0, value = 0x0000_0001)]
+#[default(field = 1, value = 20)]
+#[default(field = 2, value = 1)]
+#[restruct(builder)]
+pub struct InterfaceDescription;
+
+/// The fixed-size beginning of an Enhanced Packet Block, followed by `captured_len` bytes of
+/// packet data padded to 32 bits
+#[derive(restruct_derive::Struct)]
+#[fmt = "<block_type:I block_total_length:I interface_id:I timestamp_high:I timestamp_low:I captured_len:I original_len:I"]
+#[default(field = 0, value = 0x0000_0006)]
+#[restruct(builder)]
+pub struct EnhancedPacket;
@@ -0,0 +1,39 @@
+//! The POSIX ustar header, preceding each member of a tar archive in a block of 512 bytes.
+//!
+//! Numeric elements (`mode`, `size`, `mtime`, ...) are stored as nul- or space-terminated octal
+//! text, see `parse_octal()`.
+
+/// The `magic` of POSIX ustar headers
+pub const MAGIC: [u8; 6] = *b"ustar\0";
+
+/// A ustar header
+#[derive(restruct_derive::Struct)]
+#[fmt = "<name:100s mode:8s uid:8s gid:8s size:12s mtime:12s chksum:8s typeflag:1s linkname:100s magic:6s version:2s uname:32s gname:32s devmajor:8s devminor:8s prefix:155s 12x"]
+#[default(field = 9, value = b"ustar\0")]
+#[default(field = 10, value = b"00")]
+#[restruct(builder)]
+pub struct Header;
+
+/// Parse a numeric element, or `None` if it is not octal text.
+pub fn parse_octal(inp: &[u8]) -> Option<u64> {
+    let s = std::str::from_utf8(inp).ok()?;
+    let s = s.trim_matches(|c| c == '\0' || c == ' ');
+    if s.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(s, 8).ok()
+}
+
+/// The checksum of a packed header, the sum of all bytes with `chksum` taken as spaces.
+pub fn checksum(inp: &<Header as restruct::Struct>::Packed) -> u64 {
+    inp.iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if (148..156).contains(&i) {
+                32
+            } else {
+                u64::from(*b)
+            }
+        })
+        .sum()
+}
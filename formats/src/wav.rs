@@ -0,0 +1,34 @@
+//! The RIFF container and the headers of WAVE audio files.
+//!
+//! A file starts with a `RiffHeader`, followed by chunks, each starting with a `ChunkHeader`.
+//! The `fmt `-chunk is covered by `FmtChunk`, including it's chunk header.
+
+/// The `riff` of RIFF files
+pub const RIFF: [u8; 4] = *b"RIFF";
+/// The `form_type` of WAVE files
+pub const WAVE: [u8; 4] = *b"WAVE";
+/// The `audio_format` of uncompressed PCM
+pub const FORMAT_PCM: u16 = 1;
+
+/// The header at the start of a file; `size` is the size of the file minus eight bytes.
+#[derive(restruct_derive::Struct)]
+#[fmt = "<riff:4s size:I form_type:4s"]
+#[default(field = 0, value = b"RIFF")]
+#[default(field = 2, value = b"WAVE")]
+#[restruct(builder)]
+pub struct RiffHeader;
+
+/// The header of any chunk, followed by `size` bytes padded to 16 bits
+#[derive(restruct_derive::Struct)]
+#[fmt = "<id:4s size:I"]
+#[restruct(builder)]
+pub struct ChunkHeader;
+
+/// The `fmt `-chunk of PCM data, including it's chunk header
+#[derive(restruct_derive::Struct)]
+#[fmt = "<id:4s size:I audio_format:H channels:H sample_rate:I byte_rate:I block_align:H bits_per_sample:H"]
+#[default(field = 0, value = b"fmt ")]
+#[default(field = 1, value = 16)]
+#[default(field = 2, value = 1)]
+#[restruct(builder)]
+pub struct FmtChunk;
@@ -0,0 +1,132 @@
+use restruct_formats::{bmp, elf, gpt, pcap, pcapng, tar, wav};
+
+#[test]
+fn pcap() {
+    let data = [
+        0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10, 0, 0, 1, 0, 0, 0,
+    ];
+    let header = pcap::Header::read_from(&mut &data[..]).unwrap();
+    assert_eq!(header, (pcap::MAGIC, 2, 4, 0, 0, 4096, 1));
+    assert_eq!(pcap::Header::builder().snaplen(4096).pack(), data);
+    assert_eq!(pcap::PacketHeader::SIZE, 16);
+}
+
+#[test]
+fn pcapng() {
+    let (block_type, length, magic, major, minor, section_length) = pcapng::SectionHeader::DEFAULT;
+    assert_eq!(
+        (block_type, magic),
+        (pcapng::SECTION_HEADER, pcapng::BYTE_ORDER_MAGIC)
+    );
+    assert_eq!((length, major, minor, section_length), (28, 1, 0, -1));
+    let packed = pcapng::InterfaceDescription::builder()
+        .snap_len(1500)
+        .pack();
+    assert_eq!(packed, [1, 0, 0, 0, 20, 0, 0, 0, 1, 0, 0, 0, 0xdc, 5, 0, 0]);
+    assert_eq!(
+        pcapng::BlockHeader::unpack_slice(&packed),
+        (pcapng::INTERFACE_DESCRIPTION, 20)
+    );
+    assert_eq!(pcapng::EnhancedPacket::SIZE, 28);
+}
+
+#[test]
+fn tar() {
+    let mut header = tar::Header::DEFAULT;
+    header.0[..5].copy_from_slice(b"hello");
+    header.4 = *b"00000001750\0";
+    let mut packed = tar::Header::pack(header);
+    assert_eq!(packed.len(), 512);
+    let checksum = format!("{:06o}\0 ", tar::checksum(&packed));
+    packed[148..156].copy_from_slice(checksum.as_bytes());
+    assert_eq!(
+        tar::checksum(&packed),
+        tar::parse_octal(&packed[148..156]).unwrap()
+    );
+
+    let (name, _, _, _, size, ..) = tar::Header::unpack(packed);
+    assert_eq!(&name[..6], b"hello\0");
+    assert_eq!(tar::parse_octal(&size), Some(1000));
+    assert_eq!(tar::parse_octal(b"\0\0\0"), Some(0));
+    assert_eq!(tar::parse_octal(b"9"), None);
+    assert_eq!(tar::Header::unpack(packed).9, tar::MAGIC);
+}
+
+#[test]
+fn bmp() {
+    let file = bmp::FileHeader::builder().file_size(70).pack();
+    assert_eq!(file, [b'B', b'M', 70, 0, 0, 0, 0, 0, 0, 0, 54, 0, 0, 0]);
+    let info = bmp::BitmapInfoHeader::builder()
+        .width(2)
+        .height(-2)
+        .bit_count(24)
+        .build();
+    let packed = bmp::BitmapInfoHeader::pack(info);
+    assert_eq!(packed[..4], 40u32.to_le_bytes());
+    assert_eq!(packed[8..12], (-2i32).to_le_bytes());
+    assert_eq!(bmp::BitmapInfoHeader::unpack(packed).3, 1);
+}
+
+#[test]
+fn wav() {
+    let fmt = wav::FmtChunk::builder()
+        .channels(2)
+        .sample_rate(44100)
+        .byte_rate(176_400)
+        .block_align(4)
+        .bits_per_sample(16)
+        .build();
+    let mut buf = Vec::new();
+    wav::RiffHeader::write_to(wav::RiffHeader::DEFAULT, &mut buf).unwrap();
+    wav::FmtChunk::write_to(fmt, &mut buf).unwrap();
+    assert_eq!(&buf[8..16], b"WAVEfmt ");
+
+    let mut r = &buf[12..];
+    let (id, size) = wav::ChunkHeader::read_from(&mut r).unwrap();
+    assert_eq!((&id, size), (b"fmt ", 16));
+    assert_eq!(wav::FmtChunk::unpack_slice(&buf[12..]).2, wav::FORMAT_PCM);
+}
+
+#[test]
+fn elf() {
+    let data = *b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+    let (magic, class, endian, version, ..) = elf::Ident::unpack(data);
+    assert_eq!(
+        (magic, class, endian, version),
+        (elf::MAGIC, elf::CLASS_64, elf::DATA_LSB, 1)
+    );
+    assert_eq!(elf::Ident::builder().class(2).data(1).pack(), data);
+}
+
+#[test]
+fn gpt() {
+    assert_eq!(gpt::Header::SIZE, 92);
+    assert_eq!(gpt::PartitionEntry::SIZE, 128);
+    let header = gpt::Header::builder().current_lba(1).build();
+    let packed = gpt::Header::pack(header);
+    assert_eq!(packed[..8], gpt::SIGNATURE);
+    assert_eq!(packed[24..32], 1u64.to_le_bytes());
+    let (signature, revision, size, ..) = gpt::Header::unpack(packed);
+    assert_eq!(
+        (signature, revision, size),
+        (gpt::SIGNATURE, 0x0001_0000, 92)
+    );
+    let default = gpt::Header::DEFAULT;
+    assert_eq!(
+        (default.0, default.1, default.2),
+        (gpt::SIGNATURE, 0x0001_0000, 92)
+    );
+    assert_eq!(
+        (default.9, default.10, default.11, default.12),
+        (0, 128, 128, 0)
+    );
+
+    let mut entry = [0; 128];
+    entry[56..62].copy_from_slice(&[b'E', 0, b'F', 0, b'I', 0]);
+    let (.., name) = gpt::PartitionEntry::unpack(entry);
+    let name = name.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+    let name = char::decode_utf16(name)
+        .map(Result::unwrap)
+        .take_while(|c| *c != '\0');
+    assert_eq!(name.collect::<String>(), "EFI");
+}
@@ -0,0 +1,54 @@
+/* C API around restruct's runtime interpreter, exported if the `capi`-feature is enabled. */
+
+#ifndef RESTRUCT_H
+#define RESTRUCT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RESTRUCT_OK 0
+/* A pointer is null, or the output buffer is too small */
+#define RESTRUCT_EINVAL -1
+/* The input is smaller than the packed representation */
+#define RESTRUCT_ESIZE -2
+/* The number of values does not match the number of elements */
+#define RESTRUCT_ECOUNT -3
+/* A value is not of the element's type, or out of it's range */
+#define RESTRUCT_ETYPE -4
+
+#define RESTRUCT_BOOL 0
+#define RESTRUCT_INT 1
+#define RESTRUCT_UINT 2
+#define RESTRUCT_FLOAT 3
+#define RESTRUCT_BYTES 4
+
+typedef struct restruct_format restruct_format;
+
+/* A single element of an unpacked record. Only the member selected by `kind` is meaningful. */
+typedef struct {
+    int kind;
+    int64_t i;
+    uint64_t u;
+    double f;
+    const uint8_t *bytes;
+    size_t len;
+} restruct_value;
+
+restruct_format *restruct_parse_format(const char *fmt);
+void restruct_free_format(restruct_format *fmt);
+size_t restruct_size(const restruct_format *fmt);
+size_t restruct_len(const restruct_format *fmt);
+int restruct_unpack(const restruct_format *fmt, const uint8_t *data, size_t len,
+                    restruct_value *out, size_t out_len);
+int restruct_pack(const restruct_format *fmt, const restruct_value *values, size_t n,
+                  uint8_t *out, size_t out_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
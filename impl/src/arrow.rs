@@ -0,0 +1,197 @@
+//! Converting records into Arrow `RecordBatch`es and writing them as Parquet, if the `arrow`- and
+//! `parquet`-features are enabled.
+//!
+//! Records are interpreted at runtime, so this works for any Format String without nested
+//! types or typedefs; derived types can be converted via the registry. Each element becomes a
+//! column of it's own, named by the element's name. Integers and floats map to the Arrow type
+//! of the same width, booleans to `Boolean` and byte-strings to `FixedSizeBinary`.
+//!
+//! ```
+//! let fmt = restruct::DynStruct::new("<ts:I len:H")?;
+//! let buf = [1, 0, 0, 0, 2, 0, 3, 0, 0, 0, 4, 0];
+//! let batch = restruct::arrow::record_batch(&fmt, &buf)?;
+//! assert_eq!((batch.num_rows(), batch.num_columns()), (2, 2));
+//! #[cfg(feature = "parquet")]
+//! restruct::arrow::write_parquet(&fmt, &buf, Vec::new())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, FixedSizeBinaryArray, Float32Array, Float64Array, Int16Array,
+    Int32Array, Int64Array, Int8Array, RecordBatch, UInt16Array, UInt32Array, UInt64Array,
+    UInt8Array,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+use crate::{DynStruct, Value};
+
+/// The schema of a record of the given format, naming each column by the element's name (see
+/// `DynStruct::names()`).
+pub fn schema(fmt: &DynStruct) -> Schema {
+    schema_named(fmt, &fmt.names().collect::<Vec<_>>())
+}
+
+pub(crate) fn schema_named<S: AsRef<str>>(fmt: &DynStruct, names: &[S]) -> Schema {
+    Schema::new(
+        names
+            .iter()
+            .zip(fmt.elements())
+            .enumerate()
+            .map(|(index, (name, (_, _, size)))| {
+                let tipe = match fmt.zero_value(index) {
+                    Value::Bool(_) => DataType::Boolean,
+                    Value::I8(_) => DataType::Int8,
+                    Value::I16(_) => DataType::Int16,
+                    Value::I32(_) => DataType::Int32,
+                    Value::I64(_) => DataType::Int64,
+                    Value::U8(_) => DataType::UInt8,
+                    Value::U16(_) => DataType::UInt16,
+                    Value::U32(_) => DataType::UInt32,
+                    Value::U64(_) => DataType::UInt64,
+                    Value::F32(_) => DataType::Float32,
+                    Value::F64(_) => DataType::Float64,
+                    Value::Bytes(_) => DataType::FixedSizeBinary(size as i32),
+                };
+                Field::new(name.as_ref(), tipe, false)
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Convert the consecutive packed records in the given buffer into a `RecordBatch` holding one
+/// column per element. A trailing partial record is ignored.
+pub fn record_batch(fmt: &DynStruct, buf: &[u8]) -> Result<RecordBatch, ArrowError> {
+    record_batch_named(fmt, buf, &fmt.names().collect::<Vec<_>>())
+}
+
+pub(crate) fn record_batch_named<S: AsRef<str>>(
+    fmt: &DynStruct,
+    buf: &[u8],
+    names: &[S],
+) -> Result<RecordBatch, ArrowError> {
+    let schema = Arc::new(schema_named(fmt, names));
+    let records = || buf.chunks_exact(fmt.size().max(1));
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let values = records().map(|record| fmt.unpack_element(index, record));
+            macro_rules! column {
+                ($array:ty, $variant:ident) => {
+                    Arc::new(<$array>::from_iter_values(values.map(|v| match v {
+                        Value::$variant(v) => v,
+                        _ => unreachable!("the element's type is fixed"),
+                    }))) as ArrayRef
+                };
+            }
+            Ok(match field.data_type() {
+                DataType::Boolean => Arc::new(
+                    values
+                        .map(|v| Some(matches!(v, Value::Bool(true))))
+                        .collect::<BooleanArray>(),
+                ),
+                DataType::Int8 => column!(Int8Array, I8),
+                DataType::Int16 => column!(Int16Array, I16),
+                DataType::Int32 => column!(Int32Array, I32),
+                DataType::Int64 => column!(Int64Array, I64),
+                DataType::UInt8 => column!(UInt8Array, U8),
+                DataType::UInt16 => column!(UInt16Array, U16),
+                DataType::UInt32 => column!(UInt32Array, U32),
+                DataType::UInt64 => column!(UInt64Array, U64),
+                DataType::Float32 => column!(Float32Array, F32),
+                DataType::Float64 => column!(Float64Array, F64),
+                DataType::FixedSizeBinary(size) => {
+                    Arc::new(FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                        values.map(|v| match v {
+                            Value::Bytes(v) => Some(v),
+                            _ => unreachable!("the element's type is fixed"),
+                        }),
+                        *size,
+                    )?)
+                }
+                tipe => unreachable!("no column of type {}", tipe),
+            })
+        })
+        .collect::<Result<Vec<_>, ArrowError>>()?;
+    RecordBatch::try_new_with_options(
+        schema,
+        columns,
+        &arrow_array::RecordBatchOptions::new().with_row_count(Some(records().len())),
+    )
+}
+
+/// Write the consecutive packed records in the given buffer as a Parquet file, see
+/// `record_batch()`.
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: std::io::Write + Send>(
+    fmt: &DynStruct,
+    buf: &[u8],
+    w: W,
+) -> Result<(), parquet::errors::ParquetError> {
+    write_batch(record_batch(fmt, buf)?, w)
+}
+
+#[cfg(feature = "parquet")]
+pub(crate) fn write_batch<W: std::io::Write + Send>(
+    batch: RecordBatch,
+    w: W,
+) -> Result<(), parquet::errors::ParquetError> {
+    let mut writer = parquet::arrow::ArrowWriter::try_new(w, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+
+    #[test]
+    fn columns() {
+        let fmt = DynStruct::new("<flag:? x a:h b:2s d").unwrap();
+        assert_eq!(fmt.len(), 4);
+        let buf = [
+            fmt.pack(&[
+                Value::Bool(true),
+                Value::I16(-2),
+                Value::Bytes(b"cd".to_vec()),
+                Value::F64(0.5),
+            ])
+            .unwrap(),
+            vec![0; fmt.size()],
+            vec![0; 3],
+        ]
+        .concat();
+        let batch = record_batch(&fmt, &buf).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        let names = schema.fields().iter().map(|f| f.name().as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["flag", "a", "b", "field_3"]);
+        let b = batch.column(2);
+        let b = b.as_any().downcast_ref::<FixedSizeBinaryArray>().unwrap();
+        assert_eq!((b.value(0), b.value(1)), (&b"cd"[..], &[0, 0][..]));
+        let d = batch.column(3);
+        let d = d.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(d.values(), &[0.5, 0.0]);
+        assert_eq!(record_batch(&fmt, &[]).unwrap().num_rows(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "parquet")]
+    fn parquet() {
+        let fmt = DynStruct::new(">IH").unwrap();
+        let buf = [0, 0, 0, 1, 0, 2, 0, 0, 0, 3, 0, 4];
+        let path = std::env::temp_dir().join(format!("restruct-{}.parquet", std::process::id()));
+        write_parquet(&fmt, &buf, std::fs::File::create(&path).unwrap()).unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let reader =
+            parquet::arrow::arrow_reader::ParquetRecordBatchReader::try_new(file, 16).unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(batches, [record_batch(&fmt, &buf).unwrap()]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Helpers for the asynchronous `read_from_async()` and `write_to_async()`-functions, which are
+//! generated if the `futures_io`-feature is enabled. These work with any runtime implementing
+//! `futures::io::AsyncRead` and `futures::io::AsyncWrite`, e.g. async-std or smol.
+
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+
+pub use futures_io::{AsyncRead, AsyncWrite};
+
+/// Read exactly `buf.len()` bytes from the given reader, distinguishing a clean end of input
+/// from a truncated record.
+pub async fn read_exact<R: AsyncRead + Unpin + ?Sized>(
+    r: &mut R,
+    buf: &mut [u8],
+) -> Result<(), crate::Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match poll_fn(|cx| Pin::new(&mut *r).poll_read(cx, &mut buf[filled..])).await {
+            Ok(0) if filled == 0 => return Err(crate::Error::Eof),
+            Ok(0) => {
+                return Err(crate::Error::Truncated {
+                    got: filled,
+                    expected: buf.len(),
+                })
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Write all of `buf` to the given writer.
+pub async fn write_all<W: AsyncWrite + Unpin + ?Sized>(
+    w: &mut W,
+    mut buf: &[u8],
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *w).poll_write(cx, buf)).await {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => buf = &buf[n..],
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    /// Readers and writers on slices are always ready
+    fn ready<T>(fut: impl Future<Output = T>) -> T {
+        let mut fut = std::pin::pin!(fut);
+        match fut.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(res) => res,
+            Poll::Pending => panic!("future is not ready"),
+        }
+    }
+
+    #[test]
+    fn read_write() {
+        let mut out = Vec::new();
+        ready(write_all(&mut out, b"abc")).unwrap();
+        let mut buf = [0; 2];
+        let mut inp = &out[..];
+        ready(read_exact(&mut inp, &mut buf)).unwrap();
+        assert_eq!((&buf, inp), (b"ab", &b"c"[..]));
+        let e = ready(read_exact(&mut inp, &mut buf)).unwrap_err();
+        assert_eq!(e.to_string(), "truncated record: got 1 of 2 bytes");
+        assert!(ready(read_exact(&mut inp, &mut buf)).unwrap_err().is_eof());
+    }
+}
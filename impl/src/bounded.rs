@@ -0,0 +1,479 @@
+//! A runtime interpreter for Format Strings which never allocates, e.g. for embedded devices
+//! receiving layout descriptors over the wire.
+//!
+//! Other than [`DynStruct`](crate::DynStruct), the layout is described into a buffer provided
+//! by the caller, which bounds the number of elements; values are delivered through a visitor
+//! instead of being collected. The layout rules are the same.
+//!
+//! ```
+//! use restruct::bounded::{BoundedStruct, ElementDesc, ValueRef};
+//!
+//! let mut buf = [ElementDesc::default(); 4];
+//! let header = BoundedStruct::parse("<I2H # magic, major, minor", &mut buf).unwrap();
+//! assert_eq!((header.size(), header.len()), (8, 3));
+//! let mut sum = 0;
+//! header
+//!     .unpack(&[0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0], |index, value| {
+//!         if let (1.., ValueRef::U16(v)) = (index, value) {
+//!             sum += v;
+//!         }
+//!     })
+//!     .unwrap();
+//! assert_eq!(sum, 6);
+//! ```
+
+use crate::layout::Target;
+use crate::parser::FormatChar;
+
+/// A single element of an unpacked record, borrowing byte-strings from the packed buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueRef<'a> {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Bytes(&'a [u8]),
+}
+
+/// The kind of value an element is represented by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Kind {
+    #[default]
+    Bool,
+    Signed,
+    Unsigned,
+    Float,
+    Bytes,
+}
+
+/// Where to find a single element of the unpacked representation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ElementDesc {
+    pub kind: Kind,
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// Errors returned by the non-allocating interpreter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The Format String is invalid at the given byte offset.
+    Format(usize),
+    /// The Format String refers to a nested type, which can't be resolved at runtime.
+    Nested,
+    /// The Format String has more elements than the buffer can describe.
+    Capacity(usize),
+    /// The input is smaller than the packed representation.
+    Size { expected: usize, actual: usize },
+    /// A value is not of the element's type.
+    Type { index: usize },
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::Format(pos) => write!(f, "invalid format string at offset {}", pos),
+            Error::Nested => f.write_str("nested types can't be used at runtime"),
+            Error::Capacity(n) => write!(f, "the format string has more than {} elements", n),
+            Error::Size { expected, actual } => {
+                write!(f, "expected {} bytes, got {}", expected, actual)
+            }
+            Error::Type { index } => write!(f, "element {} is of a different type", index),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A Format String, interpreted at runtime without allocating.
+#[derive(Debug)]
+pub struct BoundedStruct<'b> {
+    elements: &'b [ElementDesc],
+    size: usize,
+    big_endian: bool,
+}
+
+fn format_char(c: u8) -> Option<(FormatChar, Kind)> {
+    Some(match c {
+        b'x' => (FormatChar::Pad, Kind::Bytes),
+        b's' => (FormatChar::Array, Kind::Bytes),
+        b'?' => (FormatChar::Bool, Kind::Bool),
+        b'b' => (FormatChar::Char, Kind::Signed),
+        b'h' => (FormatChar::Short, Kind::Signed),
+        b'i' => (FormatChar::Int, Kind::Signed),
+        b'l' => (FormatChar::Long, Kind::Signed),
+        b'q' => (FormatChar::LongLong, Kind::Signed),
+        b'n' => (FormatChar::Size, Kind::Signed),
+        b'B' => (FormatChar::UChar, Kind::Unsigned),
+        b'H' => (FormatChar::UShort, Kind::Unsigned),
+        b'I' => (FormatChar::UInt, Kind::Unsigned),
+        b'L' => (FormatChar::ULong, Kind::Unsigned),
+        b'Q' => (FormatChar::ULongLong, Kind::Unsigned),
+        b'N' => (FormatChar::USize, Kind::Unsigned),
+        b'f' => (FormatChar::Float, Kind::Float),
+        b'd' => (FormatChar::Double, Kind::Float),
+        b'g' => (FormatChar::Extended, Kind::Float),
+        b'G' => (FormatChar::Quad, Kind::Float),
+        _ => return None,
+    })
+}
+
+impl<'b> BoundedStruct<'b> {
+    /// Parse the given Format String, describing it's elements into the given buffer.
+    pub fn parse(fmt: &str, buf: &'b mut [ElementDesc]) -> Result<Self, Error> {
+        let inp = fmt.as_bytes();
+        let target = Target::host();
+        let skip_space = |mut pos: usize| {
+            while pos < inp.len() {
+                match inp[pos] {
+                    b' ' | b'\t' | b'\r' | b'\n' => pos += 1,
+                    b'#' => {
+                        while pos < inp.len() && inp[pos] != b'\n' {
+                            pos += 1;
+                        }
+                    }
+                    _ => break,
+                }
+            }
+            pos
+        };
+        let mut pos = skip_space(0);
+        let (native_types, aligned, big_endian) = match inp.get(pos) {
+            Some(b'=') => (false, false, cfg!(target_endian = "big")),
+            Some(b'<') => (false, false, false),
+            Some(b'>') | Some(b'!') => (false, false, true),
+            Some(b'^') => (true, false, cfg!(target_endian = "big")),
+            Some(b'@') => (true, true, cfg!(target_endian = "big")),
+            _ => (true, true, cfg!(target_endian = "big")),
+        };
+        if matches!(inp.get(pos), Some(b'=' | b'<' | b'>' | b'!' | b'^' | b'@')) {
+            pos += 1;
+        }
+        let (mut n, mut offset) = (0, 0);
+        loop {
+            pos = skip_space(pos);
+            if pos == inp.len() {
+                break;
+            }
+            // Names given inline (`name:code`) are of no use here
+            let name_end = inp[pos..]
+                .iter()
+                .position(|c| !(c.is_ascii_alphanumeric() || *c == b'_'))
+                .map_or(inp.len(), |n| pos + n);
+            if name_end > pos && !inp[pos].is_ascii_digit() && inp.get(name_end) == Some(&b':') {
+                pos = name_end + 1;
+            }
+            let start = pos;
+            // A hexadecimal count is terminated by a colon; otherwise, `0x` is zero padding bytes
+            let hex_end = if inp[pos..].starts_with(b"0x") {
+                let end = pos
+                    + 2
+                    + inp[pos + 2..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_hexdigit())
+                        .count();
+                (end > pos + 2 && inp.get(end) == Some(&b':')).then_some(end)
+            } else {
+                None
+            };
+            let repeat = if let Some(end) = hex_end {
+                pos = end + 1;
+                Some(
+                    usize::from_str_radix(&fmt[start + 2..end], 16)
+                        .map_err(|_| Error::Format(start))?,
+                )
+            } else {
+                while pos < inp.len() && inp[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+                let factor = match inp.get(pos) {
+                    Some(b'k' | b'K') if pos > start => {
+                        pos += 1;
+                        1024
+                    }
+                    _ => 1,
+                };
+                match fmt[start..pos - (factor > 1) as usize].parse::<usize>() {
+                    Ok(repeat) => Some(repeat.checked_mul(factor).ok_or(Error::Format(start))?),
+                    Err(_) if start == pos => None,
+                    Err(_) => return Err(Error::Format(start)),
+                }
+            };
+            let (chr, kind) = match inp.get(pos) {
+                Some(b'`') => return Err(Error::Nested),
+                Some(c) => format_char(*c).ok_or(Error::Format(pos))?,
+                None => return Err(Error::Format(pos)),
+            };
+            pos += 1;
+            let repeat = repeat.unwrap_or(1);
+            let (size, align) = target.element(&chr, native_types).map_err(|e| match e {
+                crate::layout::Error::Native(_) => Error::Format(pos - 1),
+                _ => Error::Nested,
+            })?;
+            let (element_size, count, data_size) = match chr {
+                FormatChar::Pad => (1, 0, repeat),
+                FormatChar::Array => (repeat, repeat.min(1), repeat),
+                _ => (
+                    size,
+                    repeat,
+                    size.checked_mul(repeat).ok_or(Error::Format(start))?,
+                ),
+            };
+            let align = if aligned { align } else { 1 };
+            if offset > 0 && offset % align != 0 {
+                offset = offset
+                    .checked_add(align - offset % align)
+                    .ok_or(Error::Format(start))?;
+            }
+            let end = offset.checked_add(data_size).ok_or(Error::Format(start))?;
+            for i in 0..count {
+                let desc = buf.get_mut(n).ok_or(Error::Capacity(n))?;
+                *desc = ElementDesc {
+                    kind,
+                    offset: offset + i * element_size,
+                    size: element_size,
+                };
+                n += 1;
+            }
+            offset = end;
+        }
+        Ok(Self {
+            elements: &buf[..n],
+            size: offset,
+            big_endian,
+        })
+    }
+
+    /// The size in bytes of the packed representation.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of elements in the unpacked representation.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// `true` if the unpacked representation has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The elements of the unpacked representation.
+    pub fn elements(&self) -> &[ElementDesc] {
+        self.elements
+    }
+
+    /// Unpack the first `self.size()` bytes of the given input, passing the index and value of
+    /// each element to `visit`.
+    pub fn unpack<'a>(
+        &self,
+        inp: &'a [u8],
+        mut visit: impl FnMut(usize, ValueRef<'a>),
+    ) -> Result<(), Error> {
+        if inp.len() < self.size {
+            return Err(Error::Size {
+                expected: self.size,
+                actual: inp.len(),
+            });
+        }
+        for (index, e) in self.elements.iter().enumerate() {
+            let data = &inp[e.offset..e.offset + e.size];
+            let raw = || {
+                let mut buf = [0u8; 8];
+                if self.big_endian {
+                    buf[8 - data.len()..].copy_from_slice(data);
+                    u64::from_be_bytes(buf)
+                } else {
+                    buf[..data.len()].copy_from_slice(data);
+                    u64::from_le_bytes(buf)
+                }
+            };
+            visit(
+                index,
+                match (e.kind, e.size) {
+                    (Kind::Bool, _) => ValueRef::Bool(data[0] != 0),
+                    (Kind::Bytes, _) => ValueRef::Bytes(data),
+                    (Kind::Signed, 1) => ValueRef::I8(raw() as i8),
+                    (Kind::Signed, 2) => ValueRef::I16(raw() as i16),
+                    (Kind::Signed, 4) => ValueRef::I32(raw() as i32),
+                    (Kind::Signed, _) => ValueRef::I64(raw() as i64),
+                    (Kind::Unsigned, 1) => ValueRef::U8(raw() as u8),
+                    (Kind::Unsigned, 2) => ValueRef::U16(raw() as u16),
+                    (Kind::Unsigned, 4) => ValueRef::U32(raw() as u32),
+                    (Kind::Unsigned, _) => ValueRef::U64(raw()),
+                    (Kind::Float, 4) => ValueRef::F32(f32::from_bits(raw() as u32)),
+                    (Kind::Float, 10) => ValueRef::F64(crate::rt::f80_to_f64(
+                        data.try_into().unwrap(),
+                        self.big_endian,
+                    )),
+                    (Kind::Float, 16) => ValueRef::F64(crate::rt::f128_to_f64(
+                        data.try_into().unwrap(),
+                        self.big_endian,
+                    )),
+                    (Kind::Float, _) => ValueRef::F64(f64::from_bits(raw())),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Pack the values returned by `value` for each element's index into the first
+    /// `self.size()` bytes of `out`. Values must match the elements' types exactly; padding and
+    /// alignment-bytes are zero.
+    pub fn pack<'a>(
+        &self,
+        out: &mut [u8],
+        mut value: impl FnMut(usize) -> ValueRef<'a>,
+    ) -> Result<(), Error> {
+        if out.len() < self.size {
+            return Err(Error::Size {
+                expected: self.size,
+                actual: out.len(),
+            });
+        }
+        out[..self.size].fill(0);
+        for (index, e) in self.elements.iter().enumerate() {
+            let raw = match (e.kind, e.size, value(index)) {
+                (Kind::Bool, _, ValueRef::Bool(v)) => v as u64,
+                (Kind::Signed, 1, ValueRef::I8(v)) => v as u64,
+                (Kind::Signed, 2, ValueRef::I16(v)) => v as u64,
+                (Kind::Signed, 4, ValueRef::I32(v)) => v as u64,
+                (Kind::Signed, 8, ValueRef::I64(v)) => v as u64,
+                (Kind::Unsigned, 1, ValueRef::U8(v)) => v as u64,
+                (Kind::Unsigned, 2, ValueRef::U16(v)) => v as u64,
+                (Kind::Unsigned, 4, ValueRef::U32(v)) => v as u64,
+                (Kind::Unsigned, 8, ValueRef::U64(v)) => v,
+                (Kind::Float, 4, ValueRef::F32(v)) => v.to_bits() as u64,
+                (Kind::Float, 8, ValueRef::F64(v)) => v.to_bits(),
+                (Kind::Float, 10, ValueRef::F64(v)) => {
+                    out[e.offset..e.offset + e.size]
+                        .copy_from_slice(&crate::rt::f64_to_f80(v, self.big_endian));
+                    continue;
+                }
+                (Kind::Float, 16, ValueRef::F64(v)) => {
+                    out[e.offset..e.offset + e.size]
+                        .copy_from_slice(&crate::rt::f64_to_f128(v, self.big_endian));
+                    continue;
+                }
+                (Kind::Bytes, _, ValueRef::Bytes(v)) if v.len() == e.size => {
+                    out[e.offset..e.offset + e.size].copy_from_slice(v);
+                    continue;
+                }
+                _ => return Err(Error::Type { index }),
+            };
+            let data = &mut out[e.offset..e.offset + e.size];
+            if self.big_endian {
+                data.copy_from_slice(&raw.to_be_bytes()[8 - e.size..]);
+            } else {
+                data.copy_from_slice(&raw.to_le_bytes()[..e.size]);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agrees_with_dynstruct() {
+        for fmt in [
+            "<bxI3s?",
+            "@BxI0Q",
+            "^BxI0Q",
+            ">2h",
+            "@b?hiqlxBHILQ3sdnN",
+            "=Hd # comment\n 2s",
+            "<a:I b_1:2H",
+            "<0x10:s 1kx 0x d 0xdd 0x10B",
+            ">?gG",
+        ] {
+            let mut buf = [ElementDesc::default(); 32];
+            let s = BoundedStruct::parse(fmt, &mut buf).unwrap();
+            let d = crate::DynStruct::new(fmt).unwrap();
+            assert_eq!(s.size(), d.size());
+            assert_eq!(
+                s.elements()
+                    .iter()
+                    .map(|e| (e.offset, e.size))
+                    .collect::<Vec<_>>(),
+                d.elements().map(|(_, o, s)| (o, s)).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = [ElementDesc::default(); 5];
+        let s = BoundedStruct::parse(">hQd2s?", &mut buf).unwrap();
+        let values = [
+            ValueRef::I16(-2),
+            ValueRef::U64(1),
+            ValueRef::F64(0.5),
+            ValueRef::Bytes(b"ab"),
+            ValueRef::Bool(true),
+        ];
+        let mut packed = [0xff; 21];
+        s.pack(&mut packed, |i| values[i]).unwrap();
+        assert_eq!(&packed[..4], &[0xff, 0xfe, 0, 0]);
+        let mut n = 0;
+        s.unpack(&packed, |i, v| {
+            assert_eq!(v, values[i]);
+            n += 1;
+        })
+        .unwrap();
+        assert_eq!(n, 5);
+
+        let s = BoundedStruct::parse("=gG", &mut buf).unwrap();
+        let mut packed = [0; 26];
+        let values = [ValueRef::F64(0.1), ValueRef::F64(-3.0)];
+        s.pack(&mut packed, |i| values[i]).unwrap();
+        s.unpack(&packed, |i, v| assert_eq!(v, values[i])).unwrap();
+        assert!(matches!(
+            BoundedStruct::parse("@Hg", &mut buf),
+            Err(Error::Format(2))
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let mut buf = [ElementDesc::default(); 2];
+        assert_eq!(
+            BoundedStruct::parse("<3H", &mut buf).unwrap_err(),
+            Error::Capacity(2)
+        );
+        assert_eq!(
+            BoundedStruct::parse("<H v", &mut buf).unwrap_err(),
+            Error::Format(3)
+        );
+        assert_eq!(
+            BoundedStruct::parse("<`Foo`", &mut buf).unwrap_err(),
+            Error::Nested
+        );
+        assert_eq!(
+            BoundedStruct::parse("<2305843009213693952Q", &mut buf).unwrap_err(),
+            Error::Format(1)
+        );
+        let s = BoundedStruct::parse("<Hs", &mut buf).unwrap();
+        assert_eq!(
+            s.unpack(&[0; 2], |_, _| ()),
+            Err(Error::Size {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            s.pack(&mut [0; 3], |_| ValueRef::U32(1)),
+            Err(Error::Type { index: 0 })
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! A C API around the runtime interpreter, if the `capi`-feature is enabled.
+//!
+//! A `cdylib` or `staticlib` depending on `restruct` with this feature exports the functions
+//! below; `include/restruct.h` declares them for C and C++. Formats are parsed once via
+//! `restruct_parse_format()` and released via `restruct_free_format()`. Functions returning an
+//! `int` return `RESTRUCT_OK` on success and one of the negative `RESTRUCT_E*`-codes otherwise;
+//! panics are caught and never unwind into the caller.
+
+use std::ffi::{c_char, c_int, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{dynamic, DynStruct, Value};
+
+pub const RESTRUCT_OK: c_int = 0;
+/// A pointer is null, or the output buffer is too small
+pub const RESTRUCT_EINVAL: c_int = -1;
+/// The input is smaller than the packed representation
+pub const RESTRUCT_ESIZE: c_int = -2;
+/// The number of values does not match the number of elements
+pub const RESTRUCT_ECOUNT: c_int = -3;
+/// A value is not of the element's type, or out of it's range
+pub const RESTRUCT_ETYPE: c_int = -4;
+
+pub const RESTRUCT_BOOL: c_int = 0;
+pub const RESTRUCT_INT: c_int = 1;
+pub const RESTRUCT_UINT: c_int = 2;
+pub const RESTRUCT_FLOAT: c_int = 3;
+pub const RESTRUCT_BYTES: c_int = 4;
+
+/// A single element of an unpacked record. Only the member selected by `kind` is meaningful.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RestructValue {
+    /// One of the `RESTRUCT_BOOL`, ... constants
+    pub kind: c_int,
+    /// Signed integers; booleans are `0` or `1`
+    pub i: i64,
+    /// Unsigned integers
+    pub u: u64,
+    /// Floating point numbers
+    pub f: f64,
+    /// Byte-strings, pointing into the unpacked buffer
+    pub bytes: *const u8,
+    /// The length of `bytes`
+    pub len: usize,
+}
+
+impl Default for RestructValue {
+    fn default() -> Self {
+        Self {
+            kind: RESTRUCT_BOOL,
+            i: 0,
+            u: 0,
+            f: 0.0,
+            bytes: std::ptr::null(),
+            len: 0,
+        }
+    }
+}
+
+fn error_code(e: &dynamic::Error) -> c_int {
+    match e {
+        dynamic::Error::Size { .. } => RESTRUCT_ESIZE,
+        dynamic::Error::Count { .. } => RESTRUCT_ECOUNT,
+        _ => RESTRUCT_ETYPE,
+    }
+}
+
+/// Run `f`, returning `default` instead of unwinding across the FFI-boundary
+fn guard<T>(default: T, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+/// Convert a value into the type of the element at the given index
+fn from_c(fmt: &DynStruct, index: usize, v: &RestructValue) -> Option<Value> {
+    macro_rules! int {
+        ($variant:ident, $field:ident) => {
+            Value::$variant(v.$field.try_into().ok()?)
+        };
+    }
+    Some(match (fmt.zero_value(index), v.kind) {
+        (Value::Bool(_), RESTRUCT_BOOL) => Value::Bool(v.i != 0),
+        (Value::I8(_), RESTRUCT_INT) => int!(I8, i),
+        (Value::I16(_), RESTRUCT_INT) => int!(I16, i),
+        (Value::I32(_), RESTRUCT_INT) => int!(I32, i),
+        (Value::I64(_), RESTRUCT_INT) => Value::I64(v.i),
+        (Value::U8(_), RESTRUCT_UINT) => int!(U8, u),
+        (Value::U16(_), RESTRUCT_UINT) => int!(U16, u),
+        (Value::U32(_), RESTRUCT_UINT) => int!(U32, u),
+        (Value::U64(_), RESTRUCT_UINT) => Value::U64(v.u),
+        (Value::F32(_), RESTRUCT_FLOAT) => Value::F32(v.f as f32),
+        (Value::F64(_), RESTRUCT_FLOAT) => Value::F64(v.f),
+        (Value::Bytes(_), RESTRUCT_BYTES) if !v.bytes.is_null() || v.len == 0 => {
+            // SAFETY: The caller guarantees that `bytes` points to `len` bytes
+            Value::Bytes(unsafe { std::slice::from_raw_parts(v.bytes, v.len) }.to_vec())
+        }
+        _ => return None,
+    })
+}
+
+/// Parse the given Format String, returning null if it is invalid.
+///
+/// # Safety
+///
+/// `fmt` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn restruct_parse_format(fmt: *const c_char) -> *mut DynStruct {
+    if fmt.is_null() {
+        return std::ptr::null_mut();
+    }
+    guard(std::ptr::null_mut(), || {
+        match CStr::from_ptr(fmt).to_str().map(DynStruct::new) {
+            Ok(Ok(fmt)) => Box::into_raw(Box::new(fmt)),
+            _ => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Release a format returned by `restruct_parse_format()`.
+///
+/// # Safety
+///
+/// `fmt` must be null or returned by `restruct_parse_format()`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn restruct_free_format(fmt: *mut DynStruct) {
+    if !fmt.is_null() {
+        drop(Box::from_raw(fmt));
+    }
+}
+
+/// The size in bytes of the packed representation.
+///
+/// # Safety
+///
+/// `fmt` must be returned by `restruct_parse_format()`.
+#[no_mangle]
+pub unsafe extern "C" fn restruct_size(fmt: *const DynStruct) -> usize {
+    fmt.as_ref().map_or(0, DynStruct::size)
+}
+
+/// The number of elements in the unpacked representation.
+///
+/// # Safety
+///
+/// `fmt` must be returned by `restruct_parse_format()`.
+#[no_mangle]
+pub unsafe extern "C" fn restruct_len(fmt: *const DynStruct) -> usize {
+    fmt.as_ref().map_or(0, DynStruct::len)
+}
+
+/// Unpack the first `restruct_size()` bytes of `data` into `out`, which must have room for
+/// `restruct_len()` values. Byte-strings point into `data`.
+///
+/// # Safety
+///
+/// `fmt` must be returned by `restruct_parse_format()`; `data` must point to `len` bytes and
+/// `out` to `out_len` values.
+#[no_mangle]
+pub unsafe extern "C" fn restruct_unpack(
+    fmt: *const DynStruct,
+    data: *const u8,
+    len: usize,
+    out: *mut RestructValue,
+    out_len: usize,
+) -> c_int {
+    let fmt = match fmt.as_ref() {
+        Some(fmt) if !data.is_null() && !out.is_null() && out_len >= fmt.len() => fmt,
+        _ => return RESTRUCT_EINVAL,
+    };
+    let data = std::slice::from_raw_parts(data, len);
+    let out = std::slice::from_raw_parts_mut(out, out_len);
+    guard(RESTRUCT_EINVAL, || unpack(fmt, data, out))
+}
+
+fn unpack(fmt: &DynStruct, data: &[u8], out: &mut [RestructValue]) -> c_int {
+    let values = match fmt.unpack(data) {
+        Ok(values) => values,
+        Err(e) => return error_code(&e),
+    };
+    for ((o, value), (_, offset, size)) in out.iter_mut().zip(values).zip(fmt.elements()) {
+        let mut v = RestructValue::default();
+        match value {
+            Value::Bool(b) => v.i = b as i64,
+            Value::I8(i) => (v.kind, v.i) = (RESTRUCT_INT, i.into()),
+            Value::I16(i) => (v.kind, v.i) = (RESTRUCT_INT, i.into()),
+            Value::I32(i) => (v.kind, v.i) = (RESTRUCT_INT, i.into()),
+            Value::I64(i) => (v.kind, v.i) = (RESTRUCT_INT, i),
+            Value::U8(u) => (v.kind, v.u) = (RESTRUCT_UINT, u.into()),
+            Value::U16(u) => (v.kind, v.u) = (RESTRUCT_UINT, u.into()),
+            Value::U32(u) => (v.kind, v.u) = (RESTRUCT_UINT, u.into()),
+            Value::U64(u) => (v.kind, v.u) = (RESTRUCT_UINT, u),
+            Value::F32(f) => (v.kind, v.f) = (RESTRUCT_FLOAT, f.into()),
+            Value::F64(f) => (v.kind, v.f) = (RESTRUCT_FLOAT, f),
+            Value::Bytes(_) => {
+                (v.kind, v.bytes, v.len) = (RESTRUCT_BYTES, data[offset..].as_ptr(), size)
+            }
+        }
+        *o = v;
+    }
+    RESTRUCT_OK
+}
+
+/// Pack `n` values into `out`, which must have room for `restruct_size()` bytes. Integers must
+/// fit the element's type; byte-strings must match the element's size exactly.
+///
+/// # Safety
+///
+/// `fmt` must be returned by `restruct_parse_format()`; `values` must point to `n` values and
+/// `out` to `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn restruct_pack(
+    fmt: *const DynStruct,
+    values: *const RestructValue,
+    n: usize,
+    out: *mut u8,
+    out_len: usize,
+) -> c_int {
+    let fmt = match fmt.as_ref() {
+        Some(fmt) if !values.is_null() && !out.is_null() && out_len >= fmt.size() => fmt,
+        _ => return RESTRUCT_EINVAL,
+    };
+    if n != fmt.len() {
+        return RESTRUCT_ECOUNT;
+    }
+    let values = std::slice::from_raw_parts(values, n);
+    let out = std::slice::from_raw_parts_mut(out, out_len);
+    guard(RESTRUCT_EINVAL, || pack(fmt, values, out))
+}
+
+fn pack(fmt: &DynStruct, values: &[RestructValue], out: &mut [u8]) -> c_int {
+    let values = match values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| from_c(fmt, i, v))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(values) => values,
+        None => return RESTRUCT_ETYPE,
+    };
+    match fmt.pack(&values) {
+        Ok(packed) => {
+            out[..packed.len()].copy_from_slice(&packed);
+            RESTRUCT_OK
+        }
+        Err(e) => error_code(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        unsafe {
+            assert!(restruct_parse_format(c"<`Foo`".as_ptr()).is_null());
+            assert!(restruct_parse_format(c"<2305843009213693952Q".as_ptr()).is_null());
+            let fmt = restruct_parse_format(c"<?h2sd".as_ptr());
+            assert_eq!((restruct_size(fmt), restruct_len(fmt)), (13, 4));
+
+            let data = [1, 0xfe, 0xff, b'a', b'b', 0, 0, 0, 0, 0, 0, 0xe0, 0x3f];
+            let mut values = [RestructValue::default(); 4];
+            assert_eq!(
+                restruct_unpack(fmt, data.as_ptr(), 12, values.as_mut_ptr(), 4),
+                RESTRUCT_ESIZE
+            );
+            assert_eq!(
+                restruct_unpack(fmt, data.as_ptr(), data.len(), values.as_mut_ptr(), 4),
+                RESTRUCT_OK
+            );
+            assert_eq!((values[0].kind, values[0].i), (RESTRUCT_BOOL, 1));
+            assert_eq!((values[1].kind, values[1].i), (RESTRUCT_INT, -2));
+            assert_eq!(values[2].bytes, data[3..].as_ptr());
+            assert_eq!((values[3].kind, values[3].f), (RESTRUCT_FLOAT, 0.5));
+
+            let mut out = [0; 13];
+            assert_eq!(
+                restruct_pack(fmt, values.as_ptr(), 4, out.as_mut_ptr(), out.len()),
+                RESTRUCT_OK
+            );
+            assert_eq!(out, data);
+            values[1].i = 1 << 15;
+            assert_eq!(
+                restruct_pack(fmt, values.as_ptr(), 4, out.as_mut_ptr(), out.len()),
+                RESTRUCT_ETYPE
+            );
+            assert_eq!(
+                restruct_pack(fmt, values.as_ptr(), 3, out.as_mut_ptr(), out.len()),
+                RESTRUCT_ECOUNT
+            );
+            restruct_free_format(fmt);
+        }
+    }
+}
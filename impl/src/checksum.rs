@@ -0,0 +1,73 @@
+//! Checksums for the `checksum`-attribute, which are computed over all bytes of a record
+//! preceding the checksum-field.
+
+/// The sum of all bytes, modulo 256.
+pub const fn sum8(data: &[u8]) -> u8 {
+    let mut res = 0u8;
+    let mut i = 0;
+    while i < data.len() {
+        res = res.wrapping_add(data[i]);
+        i += 1;
+    }
+    res
+}
+
+/// CRC-16/ARC, as used e.g. by LHA (polynomial `0x8005`, reflected, initial value `0`).
+pub const fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i] as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// CRC-32/ISO-HDLC, as used e.g. by Ethernet, zlib and PNG (polynomial `0x04c11db7`, reflected).
+pub const fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continue a CRC-32 as computed by `crc32()` over more data, so that
+/// `crc32_update(crc32(a), b)` is the CRC of `a` followed by `b`.
+pub const fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i] as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_values() {
+        assert_eq!(sum8(b"123456789"), 0xdd);
+        assert_eq!(crc16(b"123456789"), 0xbb3d);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!((sum8(&[]), crc16(&[]), crc32(&[])), (0, 0, 0));
+        assert_eq!(crc32_update(crc32(b"1234"), b"56789"), crc32(b"123456789"));
+    }
+}
@@ -0,0 +1,89 @@
+//! Cross-checking derived types against Python's `struct`-module, which `restruct` is modeled
+//! after. Used by the `cpython_check()`-function, which is generated if the `cpython`-feature is
+//! enabled.
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+/// Unpacks and re-packs each line of hex-encoded input, after printing the size of the format
+const SCRIPT: &str = "import struct, sys
+fmt = sys.argv[1]
+print(struct.calcsize(fmt))
+for line in sys.stdin:
+    print(struct.pack(fmt, *struct.unpack(fmt, bytes.fromhex(line))).hex())
+";
+
+fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+/// Check that the size of the given Format String and the result of re-packing `samples`
+/// buffers of pseudo-random bytes agree with Python's `struct`-module, by running `python3`.
+/// `repack` unpacks and packs the given buffer using the derived type.
+pub fn check(
+    fmt: &str,
+    size: usize,
+    samples: usize,
+    repack: impl Fn(&[u8]) -> Vec<u8>,
+) -> Result<(), String> {
+    // xorshift64, so results are reproducible
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let inputs = (0..samples)
+        .map(|_| {
+            (0..size)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    (state >> 32) as u8
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut child = Command::new("python3")
+        .args(["-c", SCRIPT, fmt])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run python3: {}", e))?;
+    let stdin = inputs.iter().map(|i| hex(i) + "\n").collect::<String>();
+    let mut pipe = child.stdin.take().unwrap();
+    // Write from another thread, as python3 may block on a full stdout
+    let writer = std::thread::spawn(move || pipe.write_all(stdin.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run python3: {}", e))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(format!(
+            "python3 failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let calcsize = lines.next().unwrap_or_default();
+    if calcsize != size.to_string() {
+        return Err(format!(
+            "the size is {}, but Python's struct.calcsize() gives {}",
+            size, calcsize
+        ));
+    }
+    for input in &inputs {
+        let (ours, theirs) = (hex(&repack(input)), lines.next().unwrap_or_default());
+        if ours != theirs {
+            return Err(format!(
+                "re-packing {} gives {}, but Python's struct gives {}",
+                hex(input),
+                ours,
+                theirs
+            ));
+        }
+    }
+    Ok(())
+}
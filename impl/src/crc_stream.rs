@@ -0,0 +1,209 @@
+//! Sequences of records protected by a CRC, e.g. for append-only binary logs.
+//!
+//! A [`CrcWriter`] appends the CRC-32 (see `restruct::checksum::crc32()`) of the packed records
+//! as a little-endian `u32`, either once at the end or after every block of a fixed number of
+//! records; a [`CrcReader`] reads the records back and verifies each CRC. Each CRC covers all
+//! records written so far, not just it's block, and the sequence always ends in a CRC following
+//! a partial (possibly empty) block. Unlike the `checksum`-attribute, this covers the sequence as
+//! a whole, so lost, reordered or truncated blocks are detected as well.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<QI"]
+//! struct Entry;
+//!
+//! let mut writer = restruct::crc_stream::CrcWriter::<_, Entry>::with_blocks(Vec::new(), 64);
+//! writer.write_record(&(1, 2))?;
+//! let buf = writer.finish()?;
+//! for entry in restruct::crc_stream::CrcReader::<_, Entry>::with_blocks(&buf[..], 64) {
+//!     println!("{:?}", entry?);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+
+use crate::checksum::crc32_update;
+use crate::{Error, Struct};
+
+/// The size of a CRC in bytes
+const CRC_SIZE: usize = 4;
+
+/// A writer for records of the derived type `T`, followed by their CRC.
+///
+/// Writes are buffered. The final CRC, following the last partial (possibly empty) block, is only
+/// written by `finish()`.
+pub struct CrcWriter<W: Write, T> {
+    inner: BufWriter<W>,
+    /// The number of records per block
+    block: usize,
+    /// The number of records in the current block
+    count: usize,
+    /// The CRC of all records written so far
+    crc: u32,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<W: Write, T: Struct> CrcWriter<W, T> {
+    /// Create a new `CrcWriter`, writing a single CRC over all records when finished.
+    pub fn new(inner: W) -> Self {
+        Self::with_blocks(inner, usize::MAX)
+    }
+
+    /// Create a new `CrcWriter`, writing a CRC after every `records` records.
+    ///
+    /// # Panics
+    ///
+    /// If `records` is zero.
+    pub fn with_blocks(inner: W, records: usize) -> Self {
+        assert!(records > 0, "blocks must hold at least one record");
+        Self {
+            inner: BufWriter::new(inner),
+            block: records,
+            count: 0,
+            crc: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Write the CRC of all records so far, ending the current block
+    fn write_crc(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.crc.to_le_bytes())?;
+        self.count = 0;
+        Ok(())
+    }
+
+    /// Pack the given record, writing the block's CRC if the block is complete.
+    pub fn write_record(&mut self, inp: &T::Unpacked) -> io::Result<()> {
+        let packed = T::pack_ref(inp);
+        self.inner.write_all(packed.as_ref())?;
+        self.crc = crc32_update(self.crc, packed.as_ref());
+        self.count += 1;
+        if self.count == self.block {
+            self.write_crc()?;
+        }
+        Ok(())
+    }
+
+    /// Write the final CRC and unwrap the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_crc()?;
+        self.inner.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+/// An iterator over records of the derived type `T` as written by a [`CrcWriter`], verifying
+/// each CRC.
+///
+/// A record is only yielded once it's block has been verified, and iteration ends after the
+/// first error: A CRC which does not match yields `Error::Invalid`, input which ends within a
+/// record or without the final CRC yields `Error::Truncated`. The reader must be created like the
+/// writer, with the same number of records per block.
+pub struct CrcReader<R, T: Struct> {
+    inner: BufReader<R>,
+    block: usize,
+    /// Records of the current block, verified and yet to be returned
+    records: VecDeque<T::Unpacked>,
+    /// The number of the current block
+    blocks: u64,
+    /// The CRC of all records read so far
+    crc: u32,
+    done: bool,
+}
+
+impl<R: Read, T: Struct> CrcReader<R, T> {
+    /// Create a new `CrcReader` for records followed by a single CRC. As no record is yielded
+    /// before the CRC has been verified, all records are read into memory first.
+    pub fn new(inner: R) -> Self {
+        Self::with_blocks(inner, usize::MAX)
+    }
+
+    /// Create a new `CrcReader` for records followed by a CRC after every `records` records.
+    ///
+    /// # Panics
+    ///
+    /// If `records` is zero.
+    pub fn with_blocks(inner: R, records: usize) -> Self {
+        assert!(records > 0, "blocks must hold at least one record");
+        Self {
+            inner: BufReader::new(inner),
+            block: records,
+            records: Default::default(),
+            blocks: 0,
+            crc: 0,
+            done: false,
+        }
+    }
+
+    /// Read the next block up to and including it's CRC; the last block holds fewer records,
+    /// which is told from the input ending
+    fn read_block(&mut self) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        let mut record = vec![0; T::SIZE];
+        let mut crc = self.crc;
+        let mut count = 0;
+        while count < self.block {
+            // Keep the last `CRC_SIZE` bytes read, as they may be the CRC
+            let want = T::SIZE + CRC_SIZE - buf.len();
+            (&mut self.inner).take(want as u64).read_to_end(&mut buf)?;
+            if buf.len() < T::SIZE + CRC_SIZE {
+                break;
+            }
+            record.copy_from_slice(&buf[..T::SIZE]);
+            buf.drain(..T::SIZE);
+            crc = crc32_update(crc, &record);
+            self.records.push_back(T::unpack_slice(&record));
+            count += 1;
+        }
+        if count < self.block {
+            // The input ended; what is left must be the final CRC
+            if buf.len() != CRC_SIZE {
+                return Err(Error::Truncated {
+                    got: buf.len(),
+                    expected: if buf.is_empty() {
+                        CRC_SIZE
+                    } else {
+                        T::SIZE + CRC_SIZE
+                    },
+                });
+            }
+            self.done = true;
+        } else {
+            let want = CRC_SIZE - buf.len();
+            (&mut self.inner).take(want as u64).read_to_end(&mut buf)?;
+            if buf.len() < CRC_SIZE {
+                return Err(Error::Truncated {
+                    got: buf.len(),
+                    expected: CRC_SIZE,
+                });
+            }
+        }
+        let found = u32::from_le_bytes(buf[..CRC_SIZE].try_into().unwrap());
+        if found != crc {
+            return Err(Error::Invalid(format!(
+                "CRC mismatch in block {}: expected {:#010x}, found {:#010x}",
+                self.blocks, crc, found
+            )));
+        }
+        self.crc = crc;
+        self.blocks += 1;
+        Ok(())
+    }
+}
+
+impl<R: Read, T: Struct> Iterator for CrcReader<R, T> {
+    type Item = Result<T::Unpacked, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.records.is_empty() && !self.done {
+            if let Err(e) = self.read_block() {
+                self.records.clear();
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+        self.records.pop_front().map(Ok)
+    }
+}
@@ -0,0 +1,114 @@
+//! Writing records as CSV, e.g. to look at fixed-record binary files in a spreadsheet.
+//!
+//! Derived types given `#[restruct(csv)]` implement [`CsvRecord`]. The header row holds the
+//! elements' names, or `field_0`, `field_1`, ... for elements without one; the elements of nested
+//! types are flattened into columns of their own, named `outer.inner`. Numbers are written as
+//! such, byte-strings as escaped ASCII.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<HH"]
+//! #[names(x, y)]
+//! #[restruct(csv)]
+//! struct Point;
+//!
+//! let mut out = Vec::new();
+//! restruct::csv::write_buffer::<Point, _>(&mut out, &[1, 0, 2, 0, 3, 0, 4, 0])?;
+//! assert_eq!(out, b"x,y\n1,2\n3,4\n");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::borrow::Borrow;
+use std::io::{self, Write};
+
+use crate::{Struct, Value};
+
+/// Derived types implement this trait to be written as CSV.
+pub trait CsvRecord: Struct {
+    /// Append the column names of all elements, each prefixed by `prefix`.
+    fn csv_header(prefix: &str, out: &mut Vec<String>);
+
+    /// Append the values of all elements, nested types being flattened.
+    fn csv_values(inp: &Self::Unpacked, out: &mut Vec<Value>);
+}
+
+/// The column names of the derived type `T`.
+pub fn header<T: CsvRecord>() -> Vec<String> {
+    let mut out = Vec::new();
+    T::csv_header("", &mut out);
+    out
+}
+
+/// Write a single row, quoting cells as necessary
+fn write_row<W: Write, S: AsRef<str>>(w: &mut W, cells: &[S]) -> io::Result<()> {
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            w.write_all(b",")?;
+        }
+        let cell = cell.as_ref();
+        if cell.contains([',', '"', '\n', '\r']) {
+            write!(w, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            w.write_all(cell.as_bytes())?;
+        }
+    }
+    w.write_all(b"\n")
+}
+
+/// Render a single cell
+fn cell(value: &Value) -> String {
+    match value {
+        // Quotes are left to the quoting of the cell
+        Value::Bytes(v) => v
+            .split_inclusive(|b| matches!(b, b'"' | b'\''))
+            .map(|chunk| match chunk.split_last() {
+                Some((q @ (b'"' | b'\''), rest)) => {
+                    format!("{}{}", rest.escape_ascii(), *q as char)
+                }
+                _ => chunk.escape_ascii().to_string(),
+            })
+            .collect(),
+        v => v.to_string(),
+    }
+}
+
+/// Write the header row, followed by a row for each of the given records.
+pub fn write_records<T, W, I>(mut w: W, records: I) -> io::Result<()>
+where
+    T: CsvRecord,
+    W: Write,
+    I: IntoIterator,
+    I::Item: Borrow<T::Unpacked>,
+{
+    write_row(&mut w, &header::<T>())?;
+    let mut values = Vec::new();
+    let mut cells = Vec::new();
+    for record in records {
+        values.clear();
+        T::csv_values(record.borrow(), &mut values);
+        cells.clear();
+        cells.extend(values.iter().map(cell));
+        write_row(&mut w, &cells)?;
+    }
+    Ok(())
+}
+
+/// Write the header row, followed by a row for each of the consecutive packed records in the
+/// given buffer. A trailing partial record is ignored.
+pub fn write_buffer<T: CsvRecord, W: Write>(w: W, buf: &[u8]) -> io::Result<()> {
+    write_records::<T, _, _>(w, buf.chunks_exact(T::SIZE.max(1)).map(T::unpack_slice))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        let mut out = Vec::new();
+        write_row(&mut out, &["a", "b,c", "d\"e", ""]).unwrap();
+        assert_eq!(out, b"a,\"b,c\",\"d\"\"e\",\n");
+        assert_eq!(cell(&Value::Bytes(b"a\"\0'".to_vec())), "a\"\\x00'");
+        assert_eq!(cell(&Value::F32(0.5)), "0.5");
+    }
+}
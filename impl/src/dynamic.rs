@@ -0,0 +1,757 @@
+//! A runtime interpreter for Format Strings, for when the format is not known at compile time.
+//!
+//! The layout is computed for the running target, following the same rules as the generated
+//! code. Nested types (e.g. `` `Foo` ``) can't be resolved at runtime and are rejected.
+//!
+//! ```
+//! let header = restruct::DynStruct::new("<I2H").unwrap();
+//! assert_eq!(header.size(), 8);
+//! let values = header.unpack(&[0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0]).unwrap();
+//! assert_eq!(
+//!     values,
+//!     [
+//!         restruct::Value::U32(0xa1b2c3d4),
+//!         restruct::Value::U16(2),
+//!         restruct::Value::U16(4)
+//!     ]
+//! );
+//! assert_eq!(header.pack(&values).unwrap(), [0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0]);
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::{layout, parser, rt};
+
+/// A single element of an unpacked record.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    /// The name of this value's type, e.g. `u32` or `[u8]`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::I8(_) => "i8",
+            Value::I16(_) => "i16",
+            Value::I32(_) => "i32",
+            Value::I64(_) => "i64",
+            Value::U8(_) => "u8",
+            Value::U16(_) => "u16",
+            Value::U32(_) => "u32",
+            Value::U64(_) => "u64",
+            Value::F32(_) => "f32",
+            Value::F64(_) => "f64",
+            Value::Bytes(_) => "[u8]",
+        }
+    }
+}
+
+macro_rules! value_from {
+    ($($tipe:ty => $variant:ident),*) => {
+        $(
+            impl From<$tipe> for Value {
+                fn from(v: $tipe) -> Self {
+                    Value::$variant(v.into())
+                }
+            }
+        )*
+    };
+}
+
+value_from!(
+    bool => Bool, i8 => I8, i16 => I16, i32 => I32, i64 => I64,
+    u8 => U8, u16 => U16, u32 => U32, u64 => U64, f32 => F32, f64 => F64
+);
+
+/// `ssize_t` is widened to 64 bits
+impl From<isize> for Value {
+    fn from(v: isize) -> Self {
+        Value::I64(v as i64)
+    }
+}
+
+/// `size_t` is widened to 64 bits
+impl From<usize> for Value {
+    fn from(v: usize) -> Self {
+        Value::U64(v as u64)
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Value {
+    fn from(v: [u8; N]) -> Self {
+        Value::Bytes(v.to_vec())
+    }
+}
+
+impl std::fmt::Display for Value {
+    /// Numbers are rendered as such, byte-strings as escaped ASCII, e.g. `b"abc\x00"`.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::I8(v) => write!(f, "{}", v),
+            Value::I16(v) => write!(f, "{}", v),
+            Value::I32(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "{}", v),
+            Value::U8(v) => write!(f, "{}", v),
+            Value::U16(v) => write!(f, "{}", v),
+            Value::U32(v) => write!(f, "{}", v),
+            Value::U64(v) => write!(f, "{}", v),
+            Value::F32(v) => write!(f, "{}", v),
+            Value::F64(v) => write!(f, "{}", v),
+            Value::Bytes(v) => {
+                f.write_str("b\"")?;
+                for b in v {
+                    write!(f, "{}", std::ascii::escape_default(*b))?;
+                }
+                f.write_str("\"")
+            }
+        }
+    }
+}
+
+/// Errors returned by the runtime interpreter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The Format String could not be parsed.
+    Format(String),
+    /// The Format String refers to a nested type, which can't be resolved at runtime.
+    Nested(String),
+    /// The input is smaller than the packed representation.
+    Size { expected: usize, actual: usize },
+    /// The number of values does not match the number of elements.
+    Count { expected: usize, actual: usize },
+    /// A value is not of the element's type.
+    Type {
+        index: usize,
+        expected: &'static str,
+    },
+    /// A string could not be converted into a value for the element.
+    Parse { index: usize, input: String },
+    /// No value was given for the element of the given name.
+    Missing(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Format(e) => write!(f, "failed to parse format string: {}", e),
+            Error::Nested(ident) => write!(f, "nested type `{}` can't be used at runtime", ident),
+            Error::Size { expected, actual } => {
+                write!(f, "expected {} bytes, got {}", expected, actual)
+            }
+            Error::Count { expected, actual } => {
+                write!(f, "expected {} values, got {}", expected, actual)
+            }
+            Error::Type { index, expected } => {
+                write!(f, "element {} must be of type {}", index, expected)
+            }
+            Error::Parse { index, input } => {
+                write!(f, "element {}: failed to convert {:?}", index, input)
+            }
+            Error::Missing(name) => write!(f, "no value for element `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The operation decoding and encoding a single element, resolved once when the Format String
+/// is compiled so that unpacking and packing don't need to look at the format again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    F80,
+    F128,
+    Bytes,
+}
+
+impl Op {
+    /// The operation for elements of the given type, which take up `size` bytes on the target
+    fn new(chr: &parser::FormatChar, size: usize) -> Self {
+        use parser::FormatChar::*;
+        match (chr, size) {
+            (Bool, _) => Op::Bool,
+            (Array | Pad | Ident(_), _) => Op::Bytes,
+            (Char | Short | Int | Long | LongLong | Size, 1) => Op::I8,
+            (Char | Short | Int | Long | LongLong | Size, 2) => Op::I16,
+            (Char | Short | Int | Long | LongLong | Size, 4) => Op::I32,
+            (Char | Short | Int | Long | LongLong | Size, _) => Op::I64,
+            (UChar | UShort | UInt | ULong | ULongLong | USize, 1) => Op::U8,
+            (UChar | UShort | UInt | ULong | ULongLong | USize, 2) => Op::U16,
+            (UChar | UShort | UInt | ULong | ULongLong | USize, 4) => Op::U32,
+            (UChar | UShort | UInt | ULong | ULongLong | USize, _) => Op::U64,
+            (Float | Double | Extended | Quad, 4) => Op::F32,
+            (Float | Double | Extended | Quad, 10) => Op::F80,
+            (Float | Double | Extended | Quad, 16) => Op::F128,
+            (Float | Double | Extended | Quad, _) => Op::F64,
+            (Typedef(_), _) => unreachable!("typedefs have no layout at runtime"),
+        }
+    }
+}
+
+/// A single element of the unpacked representation and where to find it
+#[derive(Clone, Copy, Debug)]
+struct Element {
+    op: Op,
+    offset: usize,
+    size: usize,
+}
+
+/// A Format String, interpreted at runtime.
+///
+/// The Format String is compiled into a flat list of operations, one per element, which
+/// `unpack()` and `pack()` execute in turn. To avoid compiling the same Format String again and
+/// again, e.g. when it is chosen per record, see `cached()`.
+#[derive(Clone, Debug)]
+pub struct DynStruct {
+    elements: Vec<Element>,
+    /// The fragment of the Format String each element originates from, e.g. `I` or `3s`
+    codes: Vec<String>,
+    /// The name of each element, as given in the Format String or `field_0`, `field_1`, ...
+    names: Vec<String>,
+    size: usize,
+    big_endian: bool,
+}
+
+/// The number of Format Strings `DynStruct::cached()` keeps at most
+pub const CACHE_CAPACITY: usize = 256;
+
+/// Format Strings compiled by `DynStruct::cached()`, along with the time they were last used
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<String, (Arc<DynStruct>, u64)>,
+    clock: u64,
+}
+
+impl Cache {
+    fn get(&mut self, fmt: &str) -> Option<Arc<DynStruct>> {
+        self.clock += 1;
+        let (s, used) = self.entries.get_mut(fmt)?;
+        *used = self.clock;
+        Some(Arc::clone(s))
+    }
+
+    /// Insert a compiled Format String, evicting the least recently used one if full
+    fn insert(&mut self, fmt: &str, s: Arc<DynStruct>) {
+        if self.entries.len() >= CACHE_CAPACITY && !self.entries.contains_key(fmt) {
+            if let Some(lru) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone())
+            {
+                self.entries.remove(&lru);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(fmt.to_owned(), (s, self.clock));
+    }
+}
+
+static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+
+impl DynStruct {
+    /// Parse the given Format String and compute it's layout.
+    pub fn new(fmt: &str) -> Result<Self, Error> {
+        let format = parser::parse(fmt).map_err(|e| Error::Format(e.to_string()))?;
+        let modifier = format.modifier.clone().unwrap_or_default();
+        let big_endian = match modifier.byte_order() {
+            parser::ByteOrder::Native => cfg!(target_endian = "big"),
+            parser::ByteOrder::LittleEndian => false,
+            parser::ByteOrder::BigEndian => true,
+        };
+        if let Some(ident) = format.codes.iter().find_map(|fc| match fc.chr {
+            parser::FormatChar::Ident(ref ident) => Some(ident),
+            _ => None,
+        }) {
+            return Err(Error::Nested(ident.clone()));
+        }
+        let layout = layout::compute(&format, &layout::Target::host())
+            .map_err(|e| Error::Format(e.to_string()))?;
+        let names = format.names().unwrap_or_default();
+        let mut elements = Vec::new();
+        let mut codes = Vec::new();
+        for field in &layout.fields {
+            for n in 0..field.count {
+                elements.push(Element {
+                    op: Op::new(&field.code.chr, field.element_size),
+                    offset: field.element_offset(n),
+                    size: field.element_size,
+                });
+                codes.push(field.code.to_string());
+            }
+        }
+        let names = (0..elements.len())
+            .map(|i| match names.get(i) {
+                Some(Some(name)) => name.clone(),
+                _ => format!("field_{}", i),
+            })
+            .collect();
+        Ok(Self {
+            elements,
+            codes,
+            names,
+            size: layout.size,
+            big_endian,
+        })
+    }
+
+    /// Parse the given Format String like `new()`, reusing the result of earlier calls given the
+    /// same Format String. Invalid Format Strings are not cached. At most `CACHE_CAPACITY` Format
+    /// Strings are kept, evicting the least recently used one.
+    pub fn cached(fmt: &str) -> Result<Arc<Self>, Error> {
+        let cache = CACHE.get_or_init(Default::default);
+        if let Some(s) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(fmt) {
+            return Ok(s);
+        }
+        let s = Arc::new(Self::new(fmt)?);
+        cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(fmt, Arc::clone(&s));
+        Ok(s)
+    }
+
+    /// The size in bytes of the packed representation.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of elements in the unpacked representation.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// `true` if the unpacked representation has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The fragment of the Format String, the offset and the size of each element.
+    pub fn elements(&self) -> impl Iterator<Item = (&str, usize, usize)> {
+        self.codes
+            .iter()
+            .zip(&self.elements)
+            .map(|(code, e)| (code.as_str(), e.offset, e.size))
+    }
+
+    /// The name of each element, as given in the Format String (e.g. `"<magic:I"`), or
+    /// `field_0`, `field_1`, ... for elements without a name.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Decode a single element from it's bytes
+    fn decode(&self, elem: &Element, inp: &[u8]) -> Value {
+        macro_rules! num {
+            ($variant:ident, $tipe:ty) => {{
+                let inp = inp.try_into().unwrap();
+                Value::$variant(if self.big_endian {
+                    <$tipe>::from_be_bytes(inp)
+                } else {
+                    <$tipe>::from_le_bytes(inp)
+                })
+            }};
+        }
+        match elem.op {
+            Op::Bool => Value::Bool(inp[0] != 0),
+            Op::I8 => Value::I8(inp[0] as i8),
+            Op::I16 => num!(I16, i16),
+            Op::I32 => num!(I32, i32),
+            Op::I64 => num!(I64, i64),
+            Op::U8 => Value::U8(inp[0]),
+            Op::U16 => num!(U16, u16),
+            Op::U32 => num!(U32, u32),
+            Op::U64 => num!(U64, u64),
+            Op::F32 => num!(F32, f32),
+            Op::F64 => num!(F64, f64),
+            Op::F80 => Value::F64(rt::f80_to_f64(inp.try_into().unwrap(), self.big_endian)),
+            Op::F128 => Value::F64(rt::f128_to_f64(inp.try_into().unwrap(), self.big_endian)),
+            Op::Bytes => Value::Bytes(inp.to_vec()),
+        }
+    }
+
+    /// The value of the element at the given index if all of it's bytes are zero, which tells
+    /// the element's type
+    pub(crate) fn zero_value(&self, index: usize) -> Value {
+        let elem = &self.elements[index];
+        self.decode(elem, &vec![0; elem.size])
+    }
+
+    /// Decode the element at the given index from a packed record of at least `self.size()` bytes
+    #[cfg(feature = "arrow")]
+    pub(crate) fn unpack_element(&self, index: usize, inp: &[u8]) -> Value {
+        let elem = &self.elements[index];
+        self.decode(elem, &inp[elem.offset..elem.offset + elem.size])
+    }
+
+    /// Encode a single element into the given buffer
+    fn encode(&self, index: usize, value: &Value, out: &mut [u8]) -> Result<(), Error> {
+        let elem = &self.elements[index];
+        macro_rules! num {
+            ($v:expr) => {
+                out.copy_from_slice(&if self.big_endian {
+                    $v.to_be_bytes()
+                } else {
+                    $v.to_le_bytes()
+                })
+            };
+        }
+        match (elem.op, value) {
+            (Op::Bool, Value::Bool(v)) => out[0] = *v as u8,
+            (Op::I8, Value::I8(v)) => out[0] = *v as u8,
+            (Op::I16, Value::I16(v)) => num!(v),
+            (Op::I32, Value::I32(v)) => num!(v),
+            (Op::I64, Value::I64(v)) => num!(v),
+            (Op::U8, Value::U8(v)) => out[0] = *v,
+            (Op::U16, Value::U16(v)) => num!(v),
+            (Op::U32, Value::U32(v)) => num!(v),
+            (Op::U64, Value::U64(v)) => num!(v),
+            (Op::F32, Value::F32(v)) => num!(v),
+            (Op::F64, Value::F64(v)) => num!(v),
+            (Op::F80, Value::F64(v)) => out.copy_from_slice(&rt::f64_to_f80(*v, self.big_endian)),
+            (Op::F128, Value::F64(v)) => out.copy_from_slice(&rt::f64_to_f128(*v, self.big_endian)),
+            (Op::Bytes, Value::Bytes(v)) if v.len() == elem.size => out.copy_from_slice(v),
+            _ => {
+                return Err(Error::Type {
+                    index,
+                    expected: self.zero_value(index).type_name(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// Unpack the first `self.size()` bytes of the given input.
+    pub fn unpack(&self, inp: &[u8]) -> Result<Vec<Value>, Error> {
+        if inp.len() < self.size {
+            return Err(Error::Size {
+                expected: self.size,
+                actual: inp.len(),
+            });
+        }
+        Ok(self
+            .elements
+            .iter()
+            .map(|e| self.decode(e, &inp[e.offset..e.offset + e.size]))
+            .collect())
+    }
+
+    /// Pack the given values, which must match the elements' types exactly. Padding and
+    /// alignment-bytes are zero.
+    pub fn pack(&self, values: &[Value]) -> Result<Vec<u8>, Error> {
+        if values.len() != self.elements.len() {
+            return Err(Error::Count {
+                expected: self.elements.len(),
+                actual: values.len(),
+            });
+        }
+        let mut buf = vec![0; self.size];
+        for (i, (e, v)) in self.elements.iter().zip(values).enumerate() {
+            self.encode(i, v, &mut buf[e.offset..e.offset + e.size])?;
+        }
+        Ok(buf)
+    }
+
+    /// Convert a string into a value for the element at the given index. Integers may be given
+    /// in decimal or, prefixed by `0x`, in hexadecimal; byte-strings are zero-padded to the
+    /// element's size.
+    pub fn parse_value(&self, index: usize, inp: &str) -> Result<Value, Error> {
+        let err = || Error::Parse {
+            index,
+            input: inp.to_owned(),
+        };
+        let elem = self.elements.get(index).ok_or_else(err)?;
+        let s = inp.trim();
+        macro_rules! int {
+            ($variant:ident, $tipe:ty) => {{
+                let (neg, digits) = match s.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, s),
+                };
+                let v = match digits.strip_prefix("0x") {
+                    Some(hex) => <$tipe>::from_str_radix(
+                        &format!("{}{}", if neg { "-" } else { "" }, hex),
+                        16,
+                    ),
+                    None => s.parse::<$tipe>(),
+                };
+                Value::$variant(v.map_err(|_| err())?)
+            }};
+        }
+        Ok(match self.zero_value(index) {
+            Value::Bool(_) => match s {
+                "1" | "true" | "True" => Value::Bool(true),
+                "0" | "false" | "False" => Value::Bool(false),
+                _ => return Err(err()),
+            },
+            Value::I8(_) => int!(I8, i8),
+            Value::I16(_) => int!(I16, i16),
+            Value::I32(_) => int!(I32, i32),
+            Value::I64(_) => int!(I64, i64),
+            Value::U8(_) => int!(U8, u8),
+            Value::U16(_) => int!(U16, u16),
+            Value::U32(_) => int!(U32, u32),
+            Value::U64(_) => int!(U64, u64),
+            Value::F32(_) => Value::F32(s.parse().map_err(|_| err())?),
+            Value::F64(_) => Value::F64(s.parse().map_err(|_| err())?),
+            Value::Bytes(_) => {
+                // Byte-strings are taken verbatim, including surrounding whitespace
+                if inp.len() > elem.size {
+                    return Err(err());
+                }
+                let mut v = inp.as_bytes().to_vec();
+                v.resize(elem.size, 0);
+                Value::Bytes(v)
+            }
+        })
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl DynStruct {
+    /// Unpack the first `self.size()` bytes of the given input into a JSON object, keyed by the
+    /// elements' names (see `names()`). Byte-strings become arrays of numbers.
+    pub fn to_json(&self, inp: &[u8]) -> Result<serde_json::Value, Error> {
+        self.to_json_named(inp, &self.names)
+    }
+
+    /// Pack a JSON object keyed by the elements' names (see `names()`), as produced by
+    /// `to_json()`. Numbers may also be given as strings, as accepted by `parse_value()`;
+    /// byte-strings as arrays of numbers or as strings. Other keys are ignored.
+    pub fn from_json(&self, value: &serde_json::Value) -> Result<Vec<u8>, Error> {
+        self.pack_json_named(value, &self.names)
+    }
+
+    pub(crate) fn to_json_named<S: AsRef<str>>(
+        &self,
+        inp: &[u8],
+        names: &[S],
+    ) -> Result<serde_json::Value, Error> {
+        let values = self.unpack(inp)?;
+        Ok(names
+            .iter()
+            .zip(values)
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::Bool(v) => v.into(),
+                    Value::I8(v) => v.into(),
+                    Value::I16(v) => v.into(),
+                    Value::I32(v) => v.into(),
+                    Value::I64(v) => v.into(),
+                    Value::U8(v) => v.into(),
+                    Value::U16(v) => v.into(),
+                    Value::U32(v) => v.into(),
+                    Value::U64(v) => v.into(),
+                    Value::F32(v) => v.into(),
+                    Value::F64(v) => v.into(),
+                    Value::Bytes(v) => v.into(),
+                };
+                (name.as_ref().to_owned(), value)
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into())
+    }
+
+    pub(crate) fn pack_json_named<S: AsRef<str>>(
+        &self,
+        value: &serde_json::Value,
+        names: &[S],
+    ) -> Result<Vec<u8>, Error> {
+        let values = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let name = name.as_ref();
+                let err = |v: &serde_json::Value| Error::Parse {
+                    index,
+                    input: v.to_string(),
+                };
+                match value.get(name) {
+                    None => Err(Error::Missing(name.to_owned())),
+                    Some(serde_json::Value::String(s)) => self.parse_value(index, s),
+                    Some(v @ serde_json::Value::Array(items)) => items
+                        .iter()
+                        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                        .collect::<Option<Vec<_>>>()
+                        .map(Value::Bytes)
+                        .ok_or_else(|| err(v)),
+                    Some(v @ (serde_json::Value::Object(_) | serde_json::Value::Null)) => {
+                        Err(err(v))
+                    }
+                    Some(v) => self.parse_value(index, &v.to_string()),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.pack(&values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let s = DynStruct::new("<bxI3s?").unwrap();
+        assert_eq!(s.size(), 10);
+        let elements = s.elements().collect::<Vec<_>>();
+        assert_eq!(
+            elements,
+            [("b", 0, 1), ("I", 2, 4), ("3s", 6, 3), ("?", 9, 1)]
+        );
+
+        let s = DynStruct::new("@BxI0Q").unwrap();
+        assert_eq!(s.elements().nth(1), Some(("I", 4, 4)));
+        assert_eq!(s.size(), 8);
+        assert_eq!(s.len(), 2);
+
+        let s = DynStruct::new(">2h").unwrap();
+        assert_eq!(
+            s.elements().collect::<Vec<_>>(),
+            [("2h", 0, 2), ("2h", 2, 2)]
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let s = DynStruct::new(">hQd2s?").unwrap();
+        let values = vec![
+            Value::I16(-2),
+            Value::U64(1),
+            Value::F64(0.5),
+            Value::Bytes(b"ab".to_vec()),
+            Value::Bool(true),
+        ];
+        let packed = s.pack(&values).unwrap();
+        assert_eq!(&packed[..4], &[0xff, 0xfe, 0, 0]);
+        assert_eq!(s.unpack(&packed).unwrap(), values);
+
+        let s = DynStruct::new("<gG").unwrap();
+        let values = vec![Value::F64(-2.5), Value::F64(1e300)];
+        let packed = s.pack(&values).unwrap();
+        assert_eq!(packed.len(), 26);
+        assert_eq!(s.unpack(&packed).unwrap(), values);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(DynStruct::new("<`Foo`"), Err(Error::Nested(_))));
+        assert!(matches!(DynStruct::new("<v"), Err(Error::Format(_))));
+        assert!(matches!(DynStruct::new("@g"), Err(Error::Format(_))));
+        assert!(matches!(DynStruct::new("<I @2 H"), Err(Error::Format(_))));
+        assert!(matches!(
+            DynStruct::new("<2305843009213693952Q"),
+            Err(Error::Format(_))
+        ));
+        let s = DynStruct::new("<Hs").unwrap();
+        assert_eq!(
+            s.unpack(&[0; 2]),
+            Err(Error::Size {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            s.pack(&[Value::U16(1)]),
+            Err(Error::Count {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            s.pack(&[Value::U32(1), Value::Bytes(vec![0])]),
+            Err(Error::Type {
+                index: 0,
+                expected: "u16"
+            })
+        );
+    }
+
+    #[test]
+    fn cached() {
+        let a = DynStruct::cached("<I2H").unwrap();
+        let b = DynStruct::cached("<I2H").unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(a.size(), 8);
+        assert!(matches!(DynStruct::cached("<v"), Err(Error::Format(_))));
+
+        // `a` stays, as it's used in between, `c` is evicted
+        let c = DynStruct::cached("<H").unwrap();
+        for i in 0..CACHE_CAPACITY {
+            DynStruct::cached(&format!("<{}s", i + 1)).unwrap();
+            if i == CACHE_CAPACITY / 2 {
+                DynStruct::cached("<I2H").unwrap();
+            }
+        }
+        assert!(Arc::ptr_eq(&a, &DynStruct::cached("<I2H").unwrap()));
+        assert!(!Arc::ptr_eq(&c, &DynStruct::cached("<H").unwrap()));
+        let cache = CACHE.get().unwrap().lock().unwrap();
+        assert_eq!(cache.entries.len(), CACHE_CAPACITY);
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn json() {
+        let s = DynStruct::new("<magic:I?2s").unwrap();
+        assert_eq!(
+            s.names().collect::<Vec<_>>(),
+            ["magic", "field_1", "field_2"]
+        );
+        let packed = [0xd4, 0xc3, 0xb2, 0xa1, 1, b'a', b'b'];
+        let json = s.to_json(&packed).unwrap();
+        assert_eq!(
+            json.to_string(),
+            r#"{"field_1":true,"field_2":[97,98],"magic":2712847316}"#
+        );
+        assert_eq!(s.from_json(&json).unwrap(), packed);
+        let json = serde_json::json!({"magic": "0xa1b2c3d4", "field_1": 1, "field_2": "ab"});
+        assert_eq!(s.from_json(&json).unwrap(), packed);
+        let json = serde_json::json!({"magic": 1, "field_1": true});
+        assert_eq!(
+            s.from_json(&json),
+            Err(Error::Missing("field_2".to_owned()))
+        );
+        let json = serde_json::json!({"magic": -1, "field_1": true, "field_2": "ab"});
+        assert!(matches!(
+            s.from_json(&json),
+            Err(Error::Parse { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_value() {
+        let s = DynStruct::new("<bH?4s").unwrap();
+        assert_eq!(s.parse_value(0, "-0x10"), Ok(Value::I8(-16)));
+        assert_eq!(s.parse_value(1, " 65535"), Ok(Value::U16(65535)));
+        assert_eq!(s.parse_value(2, "true"), Ok(Value::Bool(true)));
+        assert_eq!(s.parse_value(3, "ab"), Ok(Value::Bytes(b"ab\0\0".to_vec())));
+        assert!(s.parse_value(1, "65536").is_err());
+        assert!(s.parse_value(3, "abcde").is_err());
+        assert!(s.parse_value(4, "0").is_err());
+    }
+}
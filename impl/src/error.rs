@@ -0,0 +1,260 @@
+//! The error types of functions reading and validating records.
+
+use std::io;
+
+/// The error returned when reading a record fails.
+///
+/// Converts into an `io::Error`, so `?` can be used in functions returning `io::Result`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The input ended before the first byte of a record.
+    Eof,
+    /// The input ended within a record.
+    Truncated {
+        /// The number of bytes read
+        got: usize,
+        /// The number of bytes in a record
+        expected: usize,
+    },
+    /// A record was read, but is invalid.
+    Invalid(String),
+    /// A record was read, but failed validation.
+    Unpack(UnpackError),
+    /// Any other IO error.
+    Io(io::Error),
+}
+
+impl Error {
+    /// `true` if the input ended cleanly before the first byte of a record.
+    pub fn is_eof(&self) -> bool {
+        matches!(self, Error::Eof)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Eof => f.write_str("end of input"),
+            Error::Truncated { got, expected } => {
+                write!(f, "truncated record: got {} of {} bytes", got, expected)
+            }
+            Error::Invalid(msg) => write!(f, "invalid record: {}", msg),
+            Error::Unpack(e) => write!(f, "invalid record: {}", e),
+            Error::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Unpack(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<UnpackError> for Error {
+    fn from(e: UnpackError) -> Self {
+        Error::Unpack(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            Error::Eof | Error::Truncated { .. } => io::Error::new(io::ErrorKind::UnexpectedEof, e),
+            Error::Invalid(_) | Error::Unpack(_) => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// The error returned by validating decoders, e.g. `try_unpack_slice()` and `unpack_checked()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnpackError {
+    /// The input is smaller than the packed representation or, if given as text, not of it's
+    /// size. For `try_from_raw()`, the size of the pointee is not that of the packed
+    /// representation, rounded up to it's alignment.
+    Size {
+        /// The size of the packed representation
+        expected: usize,
+        /// The size of the input
+        found: usize,
+    },
+    /// An element does not hold the value it must hold, e.g. a checksum.
+    Value {
+        /// The index of the element in the unpacked representation
+        index: usize,
+        /// The name of the element, if names were given
+        name: Option<&'static str>,
+        /// The offset of the element in the packed representation
+        offset: usize,
+        /// The value the element must hold
+        expected: u64,
+        /// The value the element holds
+        found: u64,
+    },
+    /// A pointer given to `try_from_raw()` is not aligned as required by the Format String.
+    Align {
+        /// The alignment required by the Format String
+        expected: usize,
+        /// The address of the pointer
+        address: usize,
+    },
+}
+
+impl UnpackError {
+    /// The offset of the offending element in the packed representation, if any.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            UnpackError::Size { .. } | UnpackError::Align { .. } => None,
+            UnpackError::Value { offset, .. } => Some(*offset),
+        }
+    }
+}
+
+impl std::fmt::Display for UnpackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UnpackError::Size { expected, found } => {
+                write!(f, "expected {} bytes, found {}", expected, found)
+            }
+            UnpackError::Value {
+                index,
+                name,
+                offset,
+                expected,
+                found,
+            } => {
+                write!(f, "element {}", index)?;
+                if let Some(name) = name {
+                    write!(f, " (`{}`)", name)?;
+                }
+                write!(
+                    f,
+                    " at offset {}: expected {:#x}, found {:#x}",
+                    offset, expected, found
+                )
+            }
+            UnpackError::Align { expected, address } => {
+                write!(
+                    f,
+                    "address {:#x} is not aligned to {} bytes",
+                    address, expected
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnpackError {}
+
+/// The error returned by `try_pack()` if a value does not fit the type of it's element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackError {
+    /// The index of the element in the unpacked representation
+    pub index: usize,
+    /// The name of the element, if names were given
+    pub name: Option<&'static str>,
+    /// The offset of the element in the packed representation
+    pub offset: usize,
+    /// The type of the element, e.g. `u16`
+    pub tipe: &'static str,
+}
+
+impl std::fmt::Display for PackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "element {}", self.index)?;
+        if let Some(name) = self.name {
+            write!(f, " (`{}`)", name)?;
+        }
+        write!(
+            f,
+            " at offset {}: value does not fit `{}`",
+            self.offset, self.tipe
+        )
+    }
+}
+
+impl std::error::Error for PackError {}
+
+/// Fill the given buffer from the given reader, distinguishing a clean end of input from a
+/// truncated record.
+pub fn read_exact<R: io::Read + ?Sized>(r: &mut R, buf: &mut [u8]) -> Result<(), Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Err(Error::Eof),
+            Ok(0) => {
+                return Err(Error::Truncated {
+                    got: filled,
+                    expected: buf.len(),
+                })
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let mut buf = [0; 2];
+        let mut inp = &[1, 2, 3][..];
+        read_exact(&mut inp, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2]);
+        let e = read_exact(&mut inp, &mut buf).unwrap_err();
+        assert_eq!(e.to_string(), "truncated record: got 1 of 2 bytes");
+        assert!(read_exact(&mut inp, &mut buf).unwrap_err().is_eof());
+        let e = io::Error::from(Error::Eof);
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn unpack_error() {
+        let e = UnpackError::Value {
+            index: 2,
+            name: Some("crc"),
+            offset: 4,
+            expected: 0x1f,
+            found: 0x20,
+        };
+        assert_eq!(e.offset(), Some(4));
+        assert_eq!(
+            e.to_string(),
+            "element 2 (`crc`) at offset 4: expected 0x1f, found 0x20"
+        );
+        let e = io::Error::from(Error::from(e));
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn pack_error() {
+        let e = PackError {
+            index: 1,
+            name: None,
+            offset: 2,
+            tipe: "i16",
+        };
+        assert_eq!(
+            e.to_string(),
+            "element 1 at offset 2: value does not fit `i16`"
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! Rendering a layout as a description for other tools (Kaitai Struct, 010 Editor, Wireshark),
+//! e.g. from a build-script, or as an RFC-style packet diagram.
+//!
+//! Alignment is rendered as explicit padding, using the sizes and alignments of the given
+//! [`Target`]; nested types are referred to by name and need to be described separately.
+//!
+//! ```
+//! use restruct::export;
+//! use restruct::layout::Target;
+//!
+//! let format = restruct::parser::parse("<I2H").unwrap();
+//! let ksy = export::kaitai("PcapHeader", &format, &Target::host(), &["magic"]).unwrap();
+//! assert!(ksy.starts_with("meta:\n  id: pcap_header\n  endian: le\n"));
+//! let bt = export::template_010("PcapHeader", &format, &Target::host(), &["magic"]).unwrap();
+//! assert!(bt.contains("    uint32 magic;\n"));
+//! ```
+
+use std::fmt::Write;
+
+use crate::layout::{self, Target};
+use crate::parser::{self, FormatChar};
+
+/// A single entry in the rendered description
+enum Entry<'a> {
+    /// A number of the given type and size
+    Number(&'a FormatChar, usize),
+    Bytes(usize),
+    Padding(usize),
+    /// A nested type of the given name and size
+    Nested(&'a str, usize),
+}
+
+/// Flatten the layout into named entries and their offsets, rendering alignment as padding
+fn entries<'a>(
+    format: &'a parser::Format,
+    target: &Target,
+    names: &[&str],
+) -> Result<Vec<(String, usize, Entry<'a>)>, layout::Error> {
+    let layout = layout::compute(format, target)?;
+    let mut names = names.iter().map(|n| n.to_string());
+    let mut element = 0;
+    let mut padding = 0;
+    let mut res = Vec::new();
+    let mut pad = |res: &mut Vec<_>, offset, size| {
+        res.push((format!("padding_{}", padding), offset, Entry::Padding(size)));
+        padding += 1;
+    };
+    for (field, fc) in layout.fields.iter().zip(
+        // Repeated nested types are one field per repetition
+        format.codes.iter().flat_map(|fc| {
+            let n = match fc.chr {
+                FormatChar::Ident(_) => fc.repeat.unwrap_or(1).max(1),
+                _ => 1,
+            };
+            std::iter::repeat_n(fc, n)
+        }),
+    ) {
+        if field.alignment > 0 {
+            pad(&mut res, field.offset, field.alignment);
+        }
+        if let FormatChar::Pad = fc.chr {
+            if field.size > 0 {
+                pad(&mut res, field.offset, field.size);
+            }
+            continue;
+        }
+        for n in 0..field.count {
+            let name = names.next().unwrap_or_else(|| format!("field_{}", element));
+            element += 1;
+            let entry = match fc.chr {
+                // None of the tools knows about extended precision numbers
+                FormatChar::Array | FormatChar::Extended | FormatChar::Quad => {
+                    Entry::Bytes(field.element_size)
+                }
+                FormatChar::Ident(ref ident) => Entry::Nested(ident, field.element_size),
+                ref chr => Entry::Number(chr, field.element_size),
+            };
+            res.push((name, field.element_offset(n), entry));
+        }
+    }
+    Ok(res)
+}
+
+/// `true` if the given type is a signed integer
+fn signed(chr: &FormatChar) -> bool {
+    matches!(
+        chr,
+        FormatChar::Char
+            | FormatChar::Short
+            | FormatChar::Int
+            | FormatChar::Long
+            | FormatChar::LongLong
+            | FormatChar::Size
+    )
+}
+
+/// `true` if numbers are big-endian, taking native byte order from the target
+fn big_endian(format: &parser::Format, target: &Target) -> bool {
+    match format.modifier.clone().unwrap_or_default().byte_order() {
+        parser::ByteOrder::Native => target.big_endian,
+        parser::ByteOrder::LittleEndian => false,
+        parser::ByteOrder::BigEndian => true,
+    }
+}
+
+/// Convert e.g. `PcapHeader` into `pcap_header`
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut res = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            res.push('_');
+        }
+        res.extend(c.to_lowercase());
+    }
+    res
+}
+
+/// Render the layout as a Kaitai Struct (`.ksy`) description. Names are given for the elements
+/// of the unpacked representation; missing names are replaced by `field_0`, `field_1`, ...
+pub fn kaitai(
+    name: &str,
+    format: &parser::Format,
+    target: &Target,
+    names: &[&str],
+) -> Result<String, layout::Error> {
+    let mut res = format!(
+        "meta:\n  id: {}\n  endian: {}\nseq:\n",
+        snake_case(name),
+        if big_endian(format, target) {
+            "be"
+        } else {
+            "le"
+        }
+    );
+    for (id, _, entry) in entries(format, target, names)? {
+        let _ = writeln!(res, "  - id: {}", id);
+        let _ = match entry {
+            Entry::Number(chr, size) => {
+                let prefix = match chr {
+                    FormatChar::Float | FormatChar::Double => "f",
+                    chr if signed(chr) => "s",
+                    _ => "u",
+                };
+                writeln!(res, "    type: {}{}", prefix, size)
+            }
+            Entry::Bytes(size) | Entry::Padding(size) => writeln!(res, "    size: {}", size),
+            Entry::Nested(ident, _) => writeln!(res, "    type: {}", snake_case(ident)),
+        };
+    }
+    Ok(res)
+}
+
+/// Render the layout as an 010 Editor binary template (`.bt`). Names are given for the elements
+/// of the unpacked representation; missing names are replaced by `field_0`, `field_1`, ...
+pub fn template_010(
+    name: &str,
+    format: &parser::Format,
+    target: &Target,
+    names: &[&str],
+) -> Result<String, layout::Error> {
+    let mut res = format!(
+        "{}();\n\ntypedef struct {{\n",
+        if big_endian(format, target) {
+            "BigEndian"
+        } else {
+            "LittleEndian"
+        }
+    );
+    for (id, _, entry) in entries(format, target, names)? {
+        let _ = match entry {
+            Entry::Number(chr, size) => {
+                let signed = signed(chr);
+                let tipe = match (chr, size) {
+                    (FormatChar::Float, _) => "float",
+                    (FormatChar::Double, _) => "double",
+                    (_, 1) if signed => "char",
+                    (_, 2) if signed => "int16",
+                    (_, 4) if signed => "int32",
+                    (_, _) if signed => "int64",
+                    (_, 1) => "uchar",
+                    (_, 2) => "uint16",
+                    (_, 4) => "uint32",
+                    (_, _) => "uint64",
+                };
+                writeln!(res, "    {} {};", tipe, id)
+            }
+            Entry::Bytes(size) => writeln!(res, "    char {}[{}];", id, size),
+            Entry::Padding(size) => writeln!(res, "    uchar {}[{}] <hidden=true>;", id, size),
+            Entry::Nested(ident, _) => writeln!(res, "    {} {};", ident, id),
+        };
+    }
+    let _ = writeln!(res, "}} {};", name);
+    Ok(res)
+}
+
+/// Render the layout as a Wireshark dissector skeleton in Lua, to be completed by registering it
+/// e.g. for some port. Names are given for the elements of the unpacked representation; missing
+/// names are replaced by `field_0`, `field_1`, ... Nested types are shown as bytes.
+pub fn wireshark_lua(
+    name: &str,
+    format: &parser::Format,
+    target: &Target,
+    names: &[&str],
+) -> Result<String, layout::Error> {
+    let proto = snake_case(name);
+    let size = layout::compute(format, target)?.size;
+    let entries = entries(format, target, names)?
+        .into_iter()
+        .filter(|(_, _, entry)| !matches!(entry, Entry::Padding(_)))
+        .collect::<Vec<_>>();
+    let mut res = format!(
+        "local {proto} = Proto(\"{proto}\", \"{name}\")\n\nlocal fields = {{\n",
+        proto = proto,
+        name = name
+    );
+    for (id, _, entry) in &entries {
+        let tipe = match entry {
+            Entry::Number(FormatChar::Float, _) => "float".to_owned(),
+            Entry::Number(FormatChar::Double, _) => "double".to_owned(),
+            Entry::Number(FormatChar::Bool, _) => "bool".to_owned(),
+            Entry::Number(chr, size) if signed(chr) => format!("int{}", size * 8),
+            Entry::Number(_, size) => format!("uint{}", size * 8),
+            Entry::Bytes(_) | Entry::Padding(_) | Entry::Nested(_, _) => "bytes".to_owned(),
+        };
+        let _ = writeln!(
+            res,
+            "    {id} = ProtoField.{tipe}(\"{proto}.{id}\", \"{id}\"),",
+            id = id,
+            tipe = tipe,
+            proto = proto
+        );
+    }
+    let _ = writeln!(res, "}}\n{proto}.fields = {{", proto = proto);
+    for (id, _, _) in &entries {
+        let _ = writeln!(res, "    fields.{},", id);
+    }
+    let _ = write!(
+        res,
+        "}}
+
+function {proto}.dissector(buffer, pinfo, tree)
+    if buffer:len() < {size} then return 0 end
+    pinfo.cols.protocol = \"{name}\"
+    local subtree = tree:add({proto}, buffer(0, {size}), \"{name}\")
+",
+        proto = proto,
+        size = size,
+        name = name
+    );
+    let add = if big_endian(format, target) {
+        "add"
+    } else {
+        "add_le"
+    };
+    for (id, offset, entry) in &entries {
+        let size = match entry {
+            Entry::Number(_, size)
+            | Entry::Bytes(size)
+            | Entry::Padding(size)
+            | Entry::Nested(_, size) => size,
+        };
+        let _ = writeln!(
+            res,
+            "    subtree:{}(fields.{}, buffer({}, {}))",
+            add, id, offset, size
+        );
+    }
+    let _ = writeln!(res, "    return {}\nend", size);
+    Ok(res)
+}
+
+/// Render the layout as a packet diagram in the style of RFCs, 32 bits per row. Names are given
+/// for the elements of the unpacked representation; missing names are replaced by `field_0`,
+/// `field_1`, ... Fields spanning more than three rows are shortened, marked by `~`.
+pub fn packet_diagram(
+    format: &parser::Format,
+    target: &Target,
+    names: &[&str],
+) -> Result<String, layout::Error> {
+    let size = layout::compute(format, target)?.size;
+    let mut labels = Vec::new();
+    // The index into `labels` of the field each byte belongs to
+    let mut owner = vec![0; size];
+    for (i, (id, offset, entry)) in entries(format, target, names)?.into_iter().enumerate() {
+        let (len, label) = match entry {
+            Entry::Padding(size) => (size, "padding".to_owned()),
+            Entry::Number(_, size) | Entry::Bytes(size) | Entry::Nested(_, size) => (size, id),
+        };
+        owner[offset..offset + len].fill(i);
+        labels.push((offset, label));
+    }
+    let rows = size.div_ceil(4);
+    let width = |r: usize| (size - r * 4).min(4);
+    // The field owning all bytes of the given row, if any
+    let full = |r: usize| match &owner[r * 4..r * 4 + width(r)] {
+        [first, rest @ ..] if rest.len() == 3 && rest.iter().all(|o| o == first) => Some(*first),
+        _ => None,
+    };
+    // The line above the given row; bytes of a field continuing from the row above are left open
+    let separator = |r: usize| {
+        let len = if r == 0 { width(0) } else { width(r - 1) };
+        let dashed = |b: usize| {
+            r == 0 || r == rows || b >= width(r) || owner[r * 4 - 4 + b] != owner[r * 4 + b]
+        };
+        let mut line = "+".to_owned();
+        for b in 0..len {
+            if dashed(b) {
+                line.push_str("-+-+-+-+-+-+-+-+");
+            } else {
+                line.push_str(&" ".repeat(15));
+                line.push(if b + 1 == len || dashed(b + 1) {
+                    '+'
+                } else {
+                    ' '
+                });
+            }
+        }
+        line
+    };
+    let mut res = " 0                   1                   2                   3\n".to_owned();
+    res.push_str(" 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1\n");
+    let mut shortened = false;
+    for r in 0..rows {
+        if r > 0
+            && r + 1 < rows
+            && full(r).is_some()
+            && full(r - 1) == full(r)
+            && full(r + 1) == full(r)
+        {
+            if !shortened {
+                let _ = writeln!(res, "~{}~", " ".repeat(63));
+                shortened = true;
+            }
+            continue;
+        }
+        shortened = false;
+        let _ = writeln!(res, "{}", separator(r));
+        res.push('|');
+        let mut b = r * 4;
+        while b < r * 4 + width(r) {
+            let field = owner[b];
+            let len = owner[b..r * 4 + width(r)]
+                .iter()
+                .take_while(|o| **o == field)
+                .count();
+            let (offset, label) = &labels[field];
+            let label = if *offset >= b && *offset < b + len {
+                label.as_str()
+            } else {
+                ""
+            };
+            let w = len * 16 - 1;
+            let label = &label[..label.len().min(w)];
+            let _ = write!(res, "{:^w$}|", label, w = w);
+            b += len;
+        }
+        res.push('\n');
+    }
+    if rows > 0 {
+        let _ = writeln!(res, "{}", separator(rows));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kaitai() {
+        let format = parser::parse("@bxI2H?3s").unwrap();
+        let ksy = super::kaitai("Foo", &format, &Target::LP64, &["flag"]).unwrap();
+        assert_eq!(
+            ksy,
+            "meta:
+  id: foo
+  endian: le
+seq:
+  - id: flag
+    type: s1
+  - id: padding_0
+    size: 1
+  - id: padding_1
+    size: 2
+  - id: field_1
+    type: u4
+  - id: field_2
+    type: u2
+  - id: field_3
+    type: u2
+  - id: field_4
+    type: u1
+  - id: field_5
+    size: 3
+"
+        );
+        let target = Target::LP64.with_big_endian(true);
+        let ksy = super::kaitai("Foo", &format, &target, &[]).unwrap();
+        assert!(ksy.starts_with("meta:\n  id: foo\n  endian: be\n"));
+        let format = parser::parse("<I").unwrap();
+        let ksy = super::kaitai("Foo", &format, &target, &[]).unwrap();
+        assert!(ksy.starts_with("meta:\n  id: foo\n  endian: le\n"));
+    }
+
+    #[test]
+    fn template_010() {
+        let format = parser::parse(">qd2`Bar`").unwrap();
+        let target = Target::LP64.with_nested("Bar", 3, 1);
+        let bt = super::template_010("Foo", &format, &target, &[]).unwrap();
+        assert_eq!(
+            bt,
+            "BigEndian();
+
+typedef struct {
+    int64 field_0;
+    double field_1;
+    Bar field_2;
+    Bar field_3;
+} Foo;
+"
+        );
+    }
+
+    #[test]
+    fn wireshark_lua() {
+        let format = parser::parse(">Hx?2s").unwrap();
+        let lua = super::wireshark_lua("MyProto", &format, &Target::LP64, &["kind"]).unwrap();
+        assert_eq!(
+            lua,
+            r#"local my_proto = Proto("my_proto", "MyProto")
+
+local fields = {
+    kind = ProtoField.uint16("my_proto.kind", "kind"),
+    field_1 = ProtoField.bool("my_proto.field_1", "field_1"),
+    field_2 = ProtoField.bytes("my_proto.field_2", "field_2"),
+}
+my_proto.fields = {
+    fields.kind,
+    fields.field_1,
+    fields.field_2,
+}
+
+function my_proto.dissector(buffer, pinfo, tree)
+    if buffer:len() < 6 then return 0 end
+    pinfo.cols.protocol = "MyProto"
+    local subtree = tree:add(my_proto, buffer(0, 6), "MyProto")
+    subtree:add(fields.kind, buffer(0, 2))
+    subtree:add(fields.field_1, buffer(3, 1))
+    subtree:add(fields.field_2, buffer(4, 2))
+    return 6
+end
+"#
+        );
+    }
+
+    #[test]
+    fn packet_diagram() {
+        let format = parser::parse(">I2H?x2s").unwrap();
+        let diagram = super::packet_diagram(&format, &Target::LP64, &["magic"]).unwrap();
+        assert_eq!(
+            diagram,
+            " 0                   1                   2                   3
+ 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                             magic                             |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|            field_1            |            field_2            |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|    field_3    |    padding    |            field_4            |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+"
+        );
+
+        let format = parser::parse("<bq20sH").unwrap();
+        let names = ["kind", "id", "name"];
+        let diagram = super::packet_diagram(&format, &Target::LP64, &names).unwrap();
+        assert_eq!(
+            diagram,
+            " 0                   1                   2                   3
+ 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|     kind      |                      id                       |
++-+-+-+-+-+-+-+-+                                               +
+|                                                               |
++               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|               |                     name                      |
++-+-+-+-+-+-+-+-+                                               +
+|                                                               |
+~                                                               ~
++                                                               +
+|                                                               |
++               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|               |            field_3            |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+"
+        );
+    }
+
+    #[test]
+    fn snake_case() {
+        assert_eq!(super::snake_case("PcapHeader"), "pcap_header");
+        assert_eq!(super::snake_case("foo"), "foo");
+    }
+}
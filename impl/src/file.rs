@@ -0,0 +1,183 @@
+//! Files made of a header, a number of fixed-size records and a footer.
+//!
+//! Many simple binary formats have exactly this shape: A header of the derived type `H` at
+//! offset zero, followed by consecutive records of the derived type `R`, followed by a footer of
+//! the derived type `F` at the end of the file. The number of records is not stored, but told
+//! from the size of the file. Formats without a footer can use a type of no elements (e.g.
+//! `#[fmt = ""]`) for `F`.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<4sH"]
+//! struct Header;
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<HH"]
+//! struct Entry;
+//!
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<H"]
+//! struct Footer;
+//!
+//! type File<S> = restruct::file::RecordFile<S, Header, Entry, Footer>;
+//!
+//! let mut file = File::create(std::io::Cursor::new(Vec::new()), &(*b"MAGC", 1))?;
+//! file.append(&(1, 2))?;
+//! file.finalize(&(0xffff,))?;
+//!
+//! let mut file = File::open(std::io::Cursor::new(file.into_inner().into_inner()))?;
+//! assert_eq!(file.len(), 1);
+//! for entry in file.iter() {
+//!     println!("{:?}", entry?);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::{Error, Struct};
+
+/// A file holding a header of type `H`, records of type `R` and a footer of type `F`.
+pub struct RecordFile<S, H: Struct, R, F: Struct> {
+    inner: S,
+    header: H::Packed,
+    /// The number of records
+    len: u64,
+    /// The footer, unless records were appended since it was read or written
+    footer: Option<F::Packed>,
+    _marker: std::marker::PhantomData<fn() -> R>,
+}
+
+impl<S, H: Struct, R: Struct, F: Struct> RecordFile<S, H, R, F> {
+    /// The offset of the `n`th record
+    fn offset(n: u64) -> u64 {
+        H::SIZE as u64 + n * R::SIZE as u64
+    }
+
+    /// The header, as read or last written.
+    pub fn header(&self) -> H::Unpacked {
+        H::unpack_slice(self.header.as_ref())
+    }
+
+    /// The footer, as read or last written; `None` if records were appended since.
+    pub fn footer(&self) -> Option<F::Unpacked> {
+        self.footer
+            .as_ref()
+            .map(|footer| F::unpack_slice(footer.as_ref()))
+    }
+
+    /// The number of records.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// `true` if the file holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Unwrap the underlying file.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Read + Seek, H: Struct, R: Struct, F: Struct> RecordFile<S, H, R, F> {
+    /// Read the header and the footer of an existing file. Fails with `Error::Invalid` if the
+    /// size of the file is not that of the header, the footer and a whole number of records.
+    pub fn open(mut inner: S) -> Result<Self, Error> {
+        let size = inner.seek(SeekFrom::End(0))?;
+        let records = size
+            .checked_sub((H::SIZE + F::SIZE) as u64)
+            .filter(|n| R::SIZE == 0 || n % R::SIZE as u64 == 0)
+            .ok_or_else(|| {
+                Error::Invalid(format!(
+                    "file size {} is not that of a header ({} bytes), a footer ({} bytes) and records of {} bytes",
+                    size,
+                    H::SIZE,
+                    F::SIZE,
+                    R::SIZE
+                ))
+            })?;
+        let len = records.checked_div(R::SIZE as u64).unwrap_or(0);
+        inner.seek(SeekFrom::Start(0))?;
+        let mut buf = vec![0; H::SIZE];
+        crate::read_exact(&mut inner, &mut buf)?;
+        let header = H::pack_ref(&H::unpack_slice(&buf));
+        inner.seek(SeekFrom::Start(Self::offset(len)))?;
+        let mut buf = vec![0; F::SIZE];
+        crate::read_exact(&mut inner, &mut buf)?;
+        Ok(Self {
+            inner,
+            header,
+            len,
+            footer: Some(F::pack_ref(&F::unpack_slice(&buf))),
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Read the `n`th record.
+    pub fn read_record(&mut self, n: u64) -> Result<R::Unpacked, Error> {
+        if n >= self.len {
+            return Err(Error::Invalid(format!(
+                "record {} is out of bounds, there are {} records",
+                n, self.len
+            )));
+        }
+        self.inner.seek(SeekFrom::Start(Self::offset(n)))?;
+        let mut buf = vec![0; R::SIZE];
+        crate::read_exact(&mut self.inner, &mut buf)?;
+        Ok(R::unpack_slice(&buf))
+    }
+
+    /// Iterate over all records, in order.
+    pub fn iter(&mut self) -> impl Iterator<Item = Result<R::Unpacked, Error>> + '_ {
+        (0..self.len).map(|n| self.read_record(n))
+    }
+}
+
+impl<S: Write + Seek, H: Struct, R: Struct, F: Struct> RecordFile<S, H, R, F> {
+    /// Start a new file holding the given header and no records. The footer is written by
+    /// `finalize()`.
+    pub fn create(mut inner: S, header: &H::Unpacked) -> io::Result<Self> {
+        let header = H::pack_ref(header);
+        inner.seek(SeekFrom::Start(0))?;
+        inner.write_all(header.as_ref())?;
+        Ok(Self {
+            inner,
+            header,
+            len: 0,
+            footer: None,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Overwrite the header, e.g. to update a count of records.
+    pub fn write_header(&mut self, header: &H::Unpacked) -> io::Result<()> {
+        let header = H::pack_ref(header);
+        self.inner.seek(SeekFrom::Start(0))?;
+        self.inner.write_all(header.as_ref())?;
+        self.header = header;
+        Ok(())
+    }
+
+    /// Append a record after the last one, overwriting the footer until `finalize()` writes it
+    /// again.
+    pub fn append(&mut self, record: &R::Unpacked) -> io::Result<()> {
+        self.inner.seek(SeekFrom::Start(Self::offset(self.len)))?;
+        self.inner.write_all(R::pack_ref(record).as_ref())?;
+        self.len += 1;
+        self.footer = None;
+        Ok(())
+    }
+
+    /// Write the given footer after the last record and flush the file.
+    pub fn finalize(&mut self, footer: &F::Unpacked) -> io::Result<()> {
+        let footer = F::pack_ref(footer);
+        self.inner.seek(SeekFrom::Start(Self::offset(self.len)))?;
+        self.inner.write_all(footer.as_ref())?;
+        self.inner.flush()?;
+        self.footer = Some(footer);
+        Ok(())
+    }
+}
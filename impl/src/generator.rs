@@ -3,26 +3,29 @@
 //
 // * For every instance of `Format` a `const FIELDx_OFFSET: usize`, `..._ALIGNMENT` and `...SIZE`
 // is created.
-// * The first field always has it's offset and alignment set to 0; it's size is
-// some const-expression which yields the number of bytes required in packed form. For example,
-// a `Format::Short` will yield `i16::min_value().to_ne_bytes().len()`, a `Format::Array(5)` will
-// yield `5`.
-// * All following fields have their offset set to the previous offset plus the previous size; their
-// alignment is determined using the generated `const fn align<T>`; their size is their alignment
-// plus the const-expression as mentioned above.
+// * Repeated primitive types (e.g. `"1024i"`) are collapsed into one instance, which holds all of
+// the elements back to back; the elements' offsets are derived from that instance's constants.
+// * Each field's required alignment and some const-expression which yields the number of bytes
+// required in packed form are put in a table. For example, a `Format::Short` will yield
+// `i16::MIN.to_ne_bytes().len()`, a `Format::Array(5)` will yield `5`.
+// * The generated `const fn layout` walks that table once, producing `const LAYOUT`: The first
+// field always has it's offset and alignment set to 0. All following fields have their offset set
+// to the previous offset plus the previous size; their alignment is determined using the generated
+// `const fn align`. Each field's size is its alignment plus the const-expression mentioned above.
+// The field's constants are read from `LAYOUT`.
 // * The size of the array-representation (`Self::SIZE`) is the last field's offset plus it's size.
-// * The pack function create one large tuple with {`[0u8; _]` for alignment and the const-expression
-// mentioned above (referencing the input)} for every field; the entire tuple is then transmuted
-// into a [u8; Self::SIZE]. The unpack function does the same in reverse.
-// * When time comes to compile, we let the const-folding-pass do it's job, following the chain of
-// consts down to `FIELD0`. If everything adds up, it compiles.
+// * The pack function starts with a `[0u8; Self::SIZE]` and copies the const-expression mentioned
+// above (referencing the input) for every field into it, starting at the field's offset plus its
+// alignment. The unpack function does the same in reverse, reading `[u8; _]`-arrays from the
+// input and converting them into primitive types.
+// * When time comes to compile, we let the const-evaluator do it's job. If everything adds up, it
+// compiles.
 
-// The big assumption here is that any tuple can be transmuted into a `[u8; _]` as long as their
-// sizes match. This does not *have* to be true for all future versions of Rust, as the compiler
-// might decide to reorder fields. We probably need one indirection via a repr(C)-struct, which
-// should compile to nothing in most cases; the same is true for alignment-calculation.
+// Copying is done using `restruct::rt::read_field`/`write_field`; we used to transmute
+// a tuple of arrays into a `[u8; _]` and back, which broke as soon as the compiler started to
+// reorder tuple fields.
 
-// Make liberal use of the `rustfmt`-feature and the `#[debug_output]`-attribute.
+// Make liberal use of the `#[debug_output]`-attribute.
 
 use crate::parser;
 use quote::quote;
@@ -33,14 +36,17 @@ enum Format {
     Bool,
     Char,
     Double,
+    Extended,
     Float,
     Ident(syn::Ident),
     Int,
     Long,
     LongLong,
     Pad(usize),
+    Quad,
     Short,
     Size,
+    Typedef(parser::Typedef),
     UChar,
     UInt,
     ULong,
@@ -57,6 +63,8 @@ impl From<parser::FormatCode> for Format {
             Bool => Format::Bool,
             Char => Format::Char,
             Double => Format::Double,
+            Extended => Format::Extended,
+            Quad => Format::Quad,
             Float => Format::Float,
             Int => Format::Int,
             Long => Format::Long,
@@ -70,6 +78,7 @@ impl From<parser::FormatCode> for Format {
             ULongLong => Format::ULongLong,
             UShort => Format::UShort,
             USize => Format::USize,
+            Typedef(typedef) => Format::Typedef(typedef),
             Ident(ref name) => Format::Ident(syn::Ident::new(name, proc_macro2::Span::call_site())),
         }
     }
@@ -79,7 +88,17 @@ impl From<parser::FormatCode> for Format {
 struct Field {
     ident: syn::Ident,
     fmt: Format,
+    /// The fragment of the Format String this field originates from
+    code: String,
+    /// The number of consecutive elements of the same type described by this field
+    count: usize,
     materialize: bool,
+    /// The functions applied to the (single) element when unpacking and packing
+    transform: Option<Transform>,
+    /// The type the elements must resolve to, as ascribed in the Format String
+    ascription: Option<syn::Type>,
+    /// The absolute offset this field starts at, as given in the Format String
+    at: Option<usize>,
 }
 
 impl Field {
@@ -107,6 +126,16 @@ impl Field {
         )
     }
 
+    /// A (const) expression telling if the byte order is big-endian, for conversions done by
+    /// `restruct::rt`
+    fn big_endian(order: &parser::ByteOrder) -> syn::Expr {
+        match order {
+            parser::ByteOrder::Native => syn::parse_quote! { cfg!(target_endian = "big") },
+            parser::ByteOrder::LittleEndian => syn::parse_quote! { false },
+            parser::ByteOrder::BigEndian => syn::parse_quote! { true },
+        }
+    }
+
     /// The name of the constant which holds the offset of this fields
     fn offset_ident(&self) -> syn::Ident {
         syn::Ident::new(&format!("{}_OFFSET", self.ident), self.ident.span())
@@ -122,7 +151,7 @@ impl Field {
         syn::Ident::new(&format!("{}_SIZE", self.ident), self.ident.span())
     }
 
-    /// An (const) expression yielding the size in bytes of this field
+    /// An (const) expression yielding the size in bytes of a single element of this field
     fn size_expr(&self, modifier: &parser::Modifier) -> syn::Expr {
         let tipe = self.tipe(modifier.native_types());
         let tob = Self::bytes(&modifier.byte_order());
@@ -148,19 +177,26 @@ impl Field {
             | (true, Format::LongLong)
             | (true, Format::ULongLong)
             | (true, Format::Short)
-            | (true, Format::UShort) => {
-                syn::parse_quote! { #tipe::min_value().#tob().len() }
+            | (true, Format::UShort)
+            | (true, Format::Typedef(_)) => {
+                syn::parse_quote! { #tipe::MIN.#tob().len() }
             }
             (true, Format::Float) => {
                 syn::parse_quote! {
-                    unsafe { std::mem::transmute::<f32, u32>(0.0) }.#tob().len()
+                    0f32.to_bits().#tob().len()
                 }
             }
             (true, Format::Double) => {
                 syn::parse_quote! {
-                    unsafe { std::mem::transmute::<f64, u64>(0.0) }.#tob().len()
+                    0f64.to_bits().#tob().len()
                 }
             }
+            (true, Format::Extended) => {
+                syn::parse_quote! { 10 }
+            }
+            (true, Format::Quad) => {
+                syn::parse_quote! { 16 }
+            }
             (true, Format::Array(sz)) => {
                 syn::parse_quote! {
                     #sz
@@ -177,14 +213,58 @@ impl Field {
         }
     }
 
+    /// An (const) expression yielding the size in bytes of all elements of this field
+    fn total_size_expr(&self, modifier: &parser::Modifier) -> syn::Expr {
+        let size_expr = self.size_expr(modifier);
+        let count = self.count;
+        if count == 1 {
+            size_expr
+        } else {
+            syn::parse_quote! { #count * (#size_expr) }
+        }
+    }
+
+    /// The size in bytes of all elements of this field, if it is known while generating code
+    fn static_size(&self, native_types: bool) -> Option<usize> {
+        let size = match (native_types, &self.fmt) {
+            (_, _) if !self.materialize => match self.fmt {
+                Format::Pad(sz) => return Some(sz),
+                _ => return Some(0),
+            },
+            (_, Format::Array(sz)) | (_, Format::Pad(sz)) => return Some(*sz),
+            (_, Format::Bool) => 1,
+            (_, Format::Extended) => 10,
+            (_, Format::Quad) => 16,
+            (_, Format::Ident(_)) | (_, Format::Typedef(_)) | (true, _) => return None,
+            (false, Format::Char) | (false, Format::UChar) => 1,
+            (false, Format::Short) | (false, Format::UShort) => 2,
+            (false, Format::Int)
+            | (false, Format::UInt)
+            | (false, Format::Long)
+            | (false, Format::ULong)
+            | (false, Format::Float) => 4,
+            (false, Format::LongLong) | (false, Format::ULongLong) | (false, Format::Double) => 8,
+            (false, Format::Size) | (false, Format::USize) => return None,
+        };
+        Some(size * self.count)
+    }
+
     /// The type this field is represented by, e.g. i32 / [u8; 3] / libc::c_uint
     fn tipe(&self, native_types: bool) -> syn::Type {
         match (native_types, &self.fmt) {
-            (_, Format::Array(sz)) | (_, Format::Pad(sz)) => syn::parse_quote! { [u8; #sz] },
+            (_, Format::Array(sz)) | (_, Format::Pad(sz)) => {
+                let sz = proc_macro2::Literal::usize_unsuffixed(*sz);
+                syn::parse_quote! { [u8; #sz] }
+            }
             (_, Format::Bool) => syn::parse_quote! { bool },
+            (_, Format::Extended) | (_, Format::Quad) => syn::parse_quote! { f64 },
             (_, Format::Ident(ident)) => {
                 syn::parse_quote! { <#ident as restruct::Struct>::Unpacked }
             }
+            (_, Format::Typedef(typedef)) => {
+                let ident = syn::Ident::new(typedef.name(), proc_macro2::Span::call_site());
+                syn::parse_quote! { libc::#ident }
+            }
             (false, Format::Char) => syn::parse_quote! { i8 },
             (false, Format::Double) => syn::parse_quote! { f64 },
             (false, Format::Float) => syn::parse_quote! { f32 },
@@ -216,14 +296,44 @@ impl Field {
         }
     }
 
+    /// The primitive type to access the packed representation of a number through, such that it
+    /// is loaded and stored at once; `None` for all other types
+    fn access_tipe(&self, native_types: bool) -> Option<syn::Type> {
+        match self.fmt {
+            Format::Float => Some(syn::parse_quote! { u32 }),
+            Format::Double => Some(syn::parse_quote! { u64 }),
+            Format::Char
+            | Format::UChar
+            | Format::Int
+            | Format::UInt
+            | Format::Size
+            | Format::USize
+            | Format::Long
+            | Format::ULong
+            | Format::LongLong
+            | Format::ULongLong
+            | Format::Short
+            | Format::UShort
+            | Format::Typedef(_) => Some(self.tipe(native_types)),
+            _ => None,
+        }
+    }
+
     /// A (const) expression yielding the array-representation
     fn pack_expr(&self, modifier: &parser::Modifier, access: &syn::Expr) -> syn::Expr {
+        let encoded: syn::Expr;
+        let access = match &self.transform {
+            Some(Transform { encode, .. }) => {
+                encoded = syn::parse_quote! { #encode(#access) };
+                &encoded
+            }
+            None => access,
+        };
         let tob = Self::bytes(&modifier.byte_order());
-        let tipe = self.tipe(modifier.native_types());
         match self.fmt {
             Format::Bool => {
                 syn::parse_quote! {
-                    #access as #tipe
+                    [#access as u8]
                 }
             }
             Format::Pad(sz) => {
@@ -242,32 +352,48 @@ impl Field {
             | Format::LongLong
             | Format::ULongLong
             | Format::Short
-            | Format::UShort => {
+            | Format::UShort
+            | Format::Typedef(_) => {
                 syn::parse_quote! { #access.#tob() }
             }
-            Format::Float => {
-                // TODO This special handling for f32/f64 can go away once .to_bits() arrives in
-                // const-land
+            Format::Float | Format::Double => {
                 syn::parse_quote! {
-                    unsafe { std::mem::transmute::<f32, u32>(#access) }.#tob()
+                    #access.to_bits().#tob()
                 }
             }
-            Format::Double => {
+            Format::Extended => {
+                let big_endian = Self::big_endian(&modifier.byte_order());
+                syn::parse_quote! {
+                    restruct::rt::f64_to_f80(#access, #big_endian)
+                }
+            }
+            Format::Quad => {
+                let big_endian = Self::big_endian(&modifier.byte_order());
                 syn::parse_quote! {
-                    unsafe { std::mem::transmute::<f64, u64>(#access) }.#tob()
+                    restruct::rt::f64_to_f128(#access, #big_endian)
                 }
             }
             Format::Array(_) => access.clone(),
             Format::Ident(ref ident) => {
                 syn::parse_quote! {
-                    #ident::pack(#access)
+                    #ident::pack_ref(&#access)
                 }
             }
         }
     }
 
-    /// A (const) expression yielding the primitive-type representation
+    /// A (const) expression yielding the primitive-type representation, decoded by the
+    /// transform if there is one
     fn unpack_expr(&self, modifier: &parser::Modifier, access: &syn::Expr) -> syn::Expr {
+        let expr = self.unpack_raw_expr(modifier, access);
+        match &self.transform {
+            Some(Transform { decode, .. }) => syn::parse_quote! { #decode(#expr) },
+            None => expr,
+        }
+    }
+
+    /// A (const) expression yielding the primitive-type representation
+    fn unpack_raw_expr(&self, modifier: &parser::Modifier, access: &syn::Expr) -> syn::Expr {
         let tipe = self.tipe(modifier.native_types());
         let fob = Self::from_bytes(&modifier.byte_order());
         match self.fmt {
@@ -292,17 +418,30 @@ impl Field {
             | Format::LongLong
             | Format::ULongLong
             | Format::Short
-            | Format::UShort => {
+            | Format::UShort
+            | Format::Typedef(_) => {
                 syn::parse_quote! { #tipe::#fob(#access) }
             }
             Format::Float => {
                 syn::parse_quote! {
-                    unsafe { std::mem::transmute(u32::#fob(#access)) }
+                    f32::from_bits(u32::#fob(#access))
                 }
             }
             Format::Double => {
                 syn::parse_quote! {
-                    unsafe { std::mem::transmute(u64::#fob(#access)) }
+                    f64::from_bits(u64::#fob(#access))
+                }
+            }
+            Format::Extended => {
+                let big_endian = Self::big_endian(&modifier.byte_order());
+                syn::parse_quote! {
+                    restruct::rt::f80_to_f64(#access, #big_endian)
+                }
+            }
+            Format::Quad => {
+                let big_endian = Self::big_endian(&modifier.byte_order());
+                syn::parse_quote! {
+                    restruct::rt::f128_to_f64(#access, #big_endian)
                 }
             }
             Format::Array(_) => access.clone(),
@@ -314,35 +453,453 @@ impl Field {
         }
     }
 
+    /// An (const) expression yielding the size in bytes of a single element of this field,
+    /// in terms of the field's constants
+    fn element_size_expr(&self) -> syn::Expr {
+        let a_id = self.align_ident();
+        let s_id = self.size_ident();
+        let count = self.count;
+        if count == 1 {
+            syn::parse_quote! { Self::#s_id - Self::#a_id }
+        } else {
+            syn::parse_quote! { (Self::#s_id - Self::#a_id) / #count }
+        }
+    }
+
+    /// An (const) expression yielding the offset of the data of the `n`th element of this field
+    fn element_offset_expr(&self, n: usize) -> syn::Expr {
+        let o_id = self.offset_ident();
+        let a_id = self.align_ident();
+        if n == 0 {
+            syn::parse_quote! { Self::#o_id + Self::#a_id }
+        } else {
+            let element_size = self.element_size_expr();
+            syn::parse_quote! { Self::#o_id + Self::#a_id + #n * (#element_size) }
+        }
+    }
+
     /// A tuple-expression containing the name of the type, the offset, the alignment and the total
-    /// size for this field
-    fn fields_ary_entry(&self, modifier: &parser::Modifier) -> syn::Expr {
+    /// size for the `n`th element of this field
+    fn fields_ary_entry(&self, modifier: &parser::Modifier, n: usize) -> syn::Expr {
         let o_id = self.offset_ident();
         let a_id = self.align_ident();
         let s_id = self.size_ident();
         let tipe = self.tipe(modifier.native_types());
-        syn::parse_quote! {
-            (stringify!(#tipe), Self::#o_id, Self::#a_id, Self::#s_id)
+        if self.count == 1 {
+            syn::parse_quote! {
+                (stringify!(#tipe), Self::#o_id, Self::#a_id, Self::#s_id)
+            }
+        } else if n == 0 {
+            let element_size = self.element_size_expr();
+            syn::parse_quote! {
+                (stringify!(#tipe), Self::#o_id, Self::#a_id, Self::#a_id + #element_size)
+            }
+        } else {
+            let offset = self.element_offset_expr(n);
+            let element_size = self.element_size_expr();
+            syn::parse_quote! {
+                (stringify!(#tipe), #offset, 0, #element_size)
+            }
+        }
+    }
+}
+
+/// Render a type as it would be written in source code
+fn type_name(tipe: &syn::Type) -> String {
+    quote!(#tipe)
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(" ;", ";")
+        .replace("< ", "<")
+        .replace(" >", ">")
+}
+
+/// Wrap code using the given optional part of `restruct` in a check that the runtime crate has the
+/// feature enabled, as cargo may build the proc-macro with other features than the runtime crate
+fn gated(feature: &str, tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let gate = quote::format_ident!("__feature_{}", feature);
+    quote! { restruct::#gate! { #tokens } }
+}
+
+/// Options given via attributes, other than the Format String. Created via `Options::default()`
+/// and the `with_...()`-functions, which panic if given invalid Rust syntax.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Options {
+    /// The visibility of the type being derived on, used for generated types
+    pub(crate) vis: syn::Visibility,
+    /// Names for the elements of the unpacked representation
+    pub(crate) names: Option<Vec<syn::Ident>>,
+    /// Values for elements of the unpacked representation in `DEFAULT`, by index
+    pub(crate) defaults: Vec<(usize, syn::Expr)>,
+    /// A file holding the packed representation of `DEFAULT`, instead of `defaults`
+    pub(crate) init_from: Option<String>,
+    /// User-defined structs to generate conversions from/to the unpacked representation for
+    pub(crate) map_to: Vec<syn::Path>,
+    /// Older derived types to generate upgrades from, along with the names of their elements
+    pub(crate) upgrade_from: Vec<(syn::Path, Vec<syn::Ident>)>,
+    /// Additional traits to derive on generated types, next to the ones always derived
+    pub(crate) derives: Vec<syn::Path>,
+    /// The checksum stored in the last element, computed over all preceding bytes
+    pub(crate) checksum: Option<Checksum>,
+    /// Elements of the unpacked representation holding timestamps, by index
+    pub(crate) timestamps: Vec<(usize, Timestamp)>,
+    /// Elements of the unpacked representation holding scaled integers, by index
+    pub(crate) scales: Vec<(usize, Scale)>,
+    /// Elements of the unpacked representation holding padded text, by index
+    pub(crate) texts: Vec<(usize, Padding)>,
+    /// Functions decoding and encoding elements of the unpacked representation, by index
+    pub(crate) transforms: Vec<(usize, Transform)>,
+    /// The element holding the size of variable-length data following the packed representation
+    pub(crate) frame: Option<Frame>,
+    /// The inlining hint on functions converting between packed and unpacked form
+    pub(crate) inline: Inline,
+    /// The maximum alignment of elements in native mode, as given by `#pragma pack(N)`
+    pub(crate) max_align: Option<usize>,
+    /// How to report layouts which differ between platforms
+    pub(crate) portability: Portability,
+    /// Descriptions of elements of the unpacked representation, by index
+    pub(crate) field_docs: Vec<(usize, String)>,
+    /// Known packed representations and the values they unpack to, each becoming a `#[test]`
+    pub(crate) test_vectors: Vec<(Vec<u8>, syn::Expr)>,
+    /// The module to generate all constants, functions and types into, instead of the type
+    pub(crate) module: Option<syn::Ident>,
+    /// User-defined codec-traits to implement by packing and unpacking
+    pub(crate) codecs: Vec<Codec>,
+    /// Whether to generate the builder-type
+    pub(crate) builder: bool,
+    /// Whether to implement `restruct::Column` and generate the functions relying on it
+    pub(crate) columns: bool,
+    /// Whether to implement `restruct::csv::CsvRecord`
+    pub(crate) csv: bool,
+    /// Whether to generate the newtype around the packed representation
+    pub(crate) packed: bool,
+}
+
+/// How to report layouts which differ between platforms.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Portability {
+    /// Don't report anything
+    Allow,
+    /// Emit a warning
+    #[default]
+    Warn,
+    /// Fail to compile
+    Deny,
+}
+
+/// The inlining hint emitted on functions converting between packed and unpacked form.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Inline {
+    /// No attribute
+    None,
+    /// `#[inline]`, which allows inlining across crates
+    #[default]
+    Hint,
+    /// `#[inline(always)]`
+    Always,
+    /// `#[inline(never)]`
+    Never,
+}
+
+impl quote::ToTokens for Inline {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            Inline::None => quote! {},
+            Inline::Hint => quote! { #[inline] },
+            Inline::Always => quote! { #[inline(always)] },
+            Inline::Never => quote! { #[inline(never)] },
+        });
+    }
+}
+
+/// A checksum over all bytes of a record preceding the last element, which holds the checksum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    /// The sum of all bytes, stored in an `u8`
+    Sum8,
+    /// CRC-16/ARC, stored in an `u16`
+    Crc16,
+    /// CRC-32/ISO-HDLC, stored in an `u32`
+    Crc32,
+}
+
+impl Checksum {
+    /// The function in `restruct::checksum` and the type it returns
+    fn function(self) -> (syn::Ident, syn::Type) {
+        let (f, tipe): (&str, syn::Type) = match self {
+            Checksum::Sum8 => ("sum8", syn::parse_quote! { u8 }),
+            Checksum::Crc16 => ("crc16", syn::parse_quote! { u16 }),
+            Checksum::Crc32 => ("crc32", syn::parse_quote! { u32 }),
+        };
+        (syn::Ident::new(f, proc_macro2::Span::call_site()), tipe)
+    }
+}
+
+/// The encoding of a timestamp held by an element, decoded into a `restruct::time::DateTime`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamp {
+    /// An MS-DOS date and time, stored in an `u32`
+    Dos,
+    /// A Windows `FILETIME`, stored in an `u64`
+    FileTime,
+}
+
+/// The padding of text held by a `s`-element.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Padding {
+    /// Padded by NUL-bytes, as C does
+    #[default]
+    Nul,
+    /// Padded by spaces, as e.g. TAR and FITS do
+    Space,
+}
+
+/// The conversion of an integer element into engineering units, as `raw * factor + offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale {
+    pub factor: f64,
+    pub offset: f64,
+}
+
+/// The size of variable-length data following the packed representation, held by an element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frame {
+    /// The element at the given index holds the length of the payload in bytes
+    Length(usize),
+    /// The element at the given index holds the number of records of another derived type
+    Count(usize),
+}
+
+/// A user-defined trait to implement by packing and unpacking, having associated types `Value`
+/// and `Error`, an encoding function `fn(&Self::Value, &mut Vec<u8>)` and a decoding function
+/// `fn(&[u8]) -> Result<(Self::Value, &[u8]), Self::Error>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Codec {
+    pub path: syn::Path,
+    pub encode: syn::Ident,
+    pub decode: syn::Ident,
+}
+
+/// User-defined `const fn`s applied to an element after unpacking and before packing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transform {
+    pub decode: syn::Path,
+    pub encode: syn::Path,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            vis: syn::Visibility::Inherited,
+            names: None,
+            defaults: Vec::new(),
+            init_from: None,
+            map_to: Vec::new(),
+            upgrade_from: Vec::new(),
+            derives: Vec::new(),
+            checksum: None,
+            timestamps: Vec::new(),
+            scales: Vec::new(),
+            texts: Vec::new(),
+            transforms: Vec::new(),
+            frame: None,
+            inline: Inline::default(),
+            max_align: None,
+            portability: Portability::default(),
+            field_docs: Vec::new(),
+            test_vectors: Vec::new(),
+            module: None,
+            codecs: Vec::new(),
+            builder: false,
+            columns: false,
+            csv: false,
+            packed: false,
         }
     }
 }
 
+impl Options {
+    /// The visibility of the type, e.g. `"pub"` or `"pub(crate)"`.
+    pub fn with_vis(mut self, vis: &str) -> Self {
+        self.vis = syn::parse_str(vis).expect("Failed to parse visibility");
+        self
+    }
+
+    /// Names for the elements of the unpacked representation, as per `names`-attribute.
+    pub fn with_names<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.names = Some(
+            names
+                .into_iter()
+                .map(|n| syn::parse_str(n).expect("Failed to parse name"))
+                .collect(),
+        );
+        self
+    }
+
+    /// The value of an element in `DEFAULT`, given as an expression, as per `default`-attribute.
+    pub fn with_default(mut self, field: usize, value: &str) -> Self {
+        let value = syn::parse_str(value).expect("Failed to parse default value");
+        self.defaults.push((field, value));
+        self
+    }
+
+    /// An additional trait to derive on generated types, as per `restruct(derive(...))`-attribute.
+    pub fn with_derive(mut self, path: &str) -> Self {
+        self.derives
+            .push(syn::parse_str(path).expect("Failed to parse path"));
+        self
+    }
+
+    /// The checksum stored in the last element, as per `checksum`-attribute.
+    pub fn with_checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
+    /// The encoding of a timestamp held by an element, as per `timestamp`-attribute.
+    pub fn with_timestamp(mut self, field: usize, timestamp: Timestamp) -> Self {
+        self.timestamps.push((field, timestamp));
+        self
+    }
+
+    /// The conversion of an integer element into engineering units, as per `scale`-attribute.
+    pub fn with_scale(mut self, field: usize, scale: Scale) -> Self {
+        self.scales.push((field, scale));
+        self
+    }
+
+    /// The padding of text held by a `s`-element, as per `text`-attribute.
+    pub fn with_text(mut self, field: usize, padding: Padding) -> Self {
+        self.texts.push((field, padding));
+        self
+    }
+
+    /// Paths to `const fn`s applied to an element after unpacking and before packing, as per
+    /// `transform`-attribute.
+    pub fn with_transform(mut self, field: usize, decode: &str, encode: &str) -> Self {
+        let path = |p| syn::parse_str(p).expect("Failed to parse path");
+        self.transforms.push((
+            field,
+            Transform {
+                decode: path(decode),
+                encode: path(encode),
+            },
+        ));
+        self
+    }
+
+    /// The inlining hint, as per `inline_hint`-attribute.
+    pub fn with_inline(mut self, inline: Inline) -> Self {
+        self.inline = inline;
+        self
+    }
+
+    /// The maximum alignment of elements in native mode, as per `max_align`-attribute.
+    pub fn with_max_align(mut self, max_align: usize) -> Self {
+        self.max_align = Some(max_align);
+        self
+    }
+
+    /// How to report layouts which differ between platforms, as per `portability`-attribute.
+    pub fn with_portability(mut self, portability: Portability) -> Self {
+        self.portability = portability;
+        self
+    }
+
+    /// A description of an element, as per `field_doc`-attribute.
+    pub fn with_field_doc(mut self, field: usize, doc: &str) -> Self {
+        self.field_docs.push((field, doc.to_owned()));
+        self
+    }
+
+    /// The module to generate all items into, as per `restruct(module = "...")`-attribute.
+    pub fn with_module(mut self, module: &str) -> Self {
+        self.module = Some(syn::parse_str(module).expect("Failed to parse module name"));
+        self
+    }
+
+    /// Generate the builder-type, as per `restruct(builder)`-attribute.
+    pub fn with_builder(mut self) -> Self {
+        self.builder = true;
+        self
+    }
+
+    /// Implement `restruct::Column` and generate `extract_field()` and `iter_field()`, as per
+    /// `restruct(columns)`-attribute.
+    pub fn with_columns(mut self) -> Self {
+        self.columns = true;
+        self
+    }
+
+    /// Implement `restruct::csv::CsvRecord`, as per `restruct(csv)`-attribute.
+    pub fn with_csv(mut self) -> Self {
+        self.csv = true;
+        self
+    }
+
+    /// Generate the newtype `...Packed` around the packed representation, as per
+    /// `restruct(packed)`-attribute.
+    pub fn with_packed(mut self) -> Self {
+        self.packed = true;
+        self
+    }
+}
+
 #[derive(Debug)]
 pub struct Compilation {
     name: proc_macro2::Ident,
     fields: Vec<Field>,
     modifier: parser::Modifier,
     generics: syn::Generics,
+    options: Options,
+    /// The Format String, without whitespace and comments
+    format: String,
 }
 
 impl Compilation {
-    pub fn new(name: proc_macro2::Ident, generics: syn::Generics, format: parser::Format) -> Self {
+    pub fn new(
+        name: proc_macro2::Ident,
+        generics: syn::Generics,
+        format: parser::Format,
+        options: Options,
+    ) -> Self {
+        if options.module.is_some() && !generics.params.is_empty() {
+            panic!("module attribute is not supported on generic types.");
+        }
         let mut fields = Vec::new();
+        let format_string = format.to_string();
         let modifier = format.modifier.unwrap_or_default();
         let mut fieldcounter =
             (0..).map(|i| syn::Ident::new(&format!("FIELD{}", i), proc_macro2::Span::call_site()));
+        // The index of the next element of the unpacked representation
+        let mut element = 0;
+        let transform = |element: usize| {
+            options
+                .transforms
+                .iter()
+                .find(|(idx, _)| *idx == element)
+                .map(|(_, t)| t.clone())
+        };
         for fc in format.codes {
             let repeat = fc.repeat.unwrap_or(1);
+            let code = fc.to_string();
+            let ascription = fc.ascription.as_deref().map(|a| {
+                if matches!(
+                    fc.chr,
+                    parser::FormatChar::Array
+                        | parser::FormatChar::Pad
+                        | parser::FormatChar::Ident(_)
+                ) {
+                    panic!(
+                        "`{}` can't be ascribed the type `{}`; only numbers and booleans can.",
+                        code, a
+                    );
+                }
+                syn::parse_str::<syn::Type>(a).unwrap()
+            });
+            let at = fc.offset;
+            let first = fields.len();
             let fmt = Format::from(fc);
             let materialize = match (&fmt, repeat) {
                 (Format::Pad(_), _) | (_, 0) => false,
@@ -353,35 +910,295 @@ impl Compilation {
                     let f = Field {
                         ident: fieldcounter.next().unwrap(),
                         fmt,
+                        code: code.clone(),
+                        count: 1,
                         materialize,
+                        transform: materialize.then(|| transform(element)).flatten(),
+                        ascription: ascription.clone(),
+                        at: None,
                     };
+                    element += materialize as usize;
                     fields.push(f);
                 }
-                fmt => {
+                // Nested types may require alignment between elements, so they are not collapsed
+                fmt @ Format::Ident(_) => {
                     for _ in 0..std::cmp::max(1, repeat) {
                         let f = Field {
                             ident: fieldcounter.next().unwrap(),
                             fmt: fmt.clone(),
+                            code: code.clone(),
+                            count: 1,
                             materialize,
+                            transform: materialize.then(|| transform(element)).flatten(),
+                            ascription: ascription.clone(),
+                            at: None,
                         };
+                        element += materialize as usize;
                         fields.push(f);
                     }
                 }
+                // Repeated primitive types are laid out back to back, their size being a multiple
+                // of their alignment. Such runs are collapsed into a single field, unless an
+                // element is transformed.
+                fmt if materialize
+                    && (element..element + repeat).any(|e| transform(e).is_some()) =>
+                {
+                    for _ in 0..repeat {
+                        let f = Field {
+                            ident: fieldcounter.next().unwrap(),
+                            fmt: fmt.clone(),
+                            code: code.clone(),
+                            count: 1,
+                            materialize,
+                            transform: transform(element),
+                            ascription: ascription.clone(),
+                            at: None,
+                        };
+                        element += 1;
+                        fields.push(f);
+                    }
+                }
+                fmt => {
+                    let f = Field {
+                        ident: fieldcounter.next().unwrap(),
+                        fmt,
+                        code,
+                        count: std::cmp::max(1, repeat),
+                        materialize,
+                        transform: None,
+                        ascription: ascription.clone(),
+                        at: None,
+                    };
+                    if materialize {
+                        element += f.count;
+                    }
+                    fields.push(f);
+                }
             }
+            fields[first].at = at;
         }
-        Self {
+        if modifier.native_types() {
+            if let Some(f) = fields
+                .iter()
+                .find(|f| matches!(f.fmt, Format::Extended | Format::Quad))
+            {
+                panic!(
+                    "`{}` has no native size and alignment; use one of `=`, `<`, `>` or `!`.",
+                    f.code
+                );
+            }
+        } else if let Some(f) = fields.iter().find(|f| matches!(f.fmt, Format::Typedef(_))) {
+            panic!(
+                "`{}` has no standard size; it requires native mode (`@` or `^`).",
+                f.code
+            );
+        }
+        if options.max_align.is_some() && !modifier.aligned() {
+            panic!("max_align attribute requires native mode (`@`).");
+        }
+        let comp = Self {
             name,
             fields,
             modifier,
             generics,
+            options,
+            format: format_string,
+        };
+        if comp.options.portability == Portability::Deny {
+            if let Some(problem) = comp.portability_problems().first() {
+                panic!("{}", problem);
+            }
+        }
+        let elements = comp.materialized_elements().count();
+        if let Some(names) = &comp.options.names {
+            if names.len() != elements {
+                panic!(
+                    "names attribute gives {} names, but there are {} fields.",
+                    names.len(),
+                    elements
+                );
+            }
+        }
+        if let Some(checksum) = comp.options.checksum {
+            let (_, expected) = checksum.function();
+            let found = comp.materialized_elements().last().map(|(_, f, _)| &f.fmt);
+            let valid = match (checksum, found) {
+                (Checksum::Sum8, Some(Format::UChar)) => true,
+                (Checksum::Crc16, Some(Format::UShort)) => true,
+                (Checksum::Crc32, Some(Format::UInt)) => true,
+                (Checksum::Crc32, Some(Format::ULong)) => !comp.modifier.native_types(),
+                _ => false,
+            };
+            if !valid {
+                panic!(
+                    "checksum attribute requires the last field to be of type `{}`.",
+                    quote!(#expected)
+                );
+            }
+        }
+        for (_, old_names) in &comp.options.upgrade_from {
+            let names = comp
+                .options
+                .names
+                .as_ref()
+                .unwrap_or_else(|| panic!("upgrade_from attribute requires names."));
+            for old_name in old_names {
+                if !old_name.to_string().starts_with('_') && !names.contains(old_name) {
+                    panic!(
+                        "upgrade_from attribute: `{}` is not the name of an element; prefix it with `_` to drop it.",
+                        old_name
+                    );
+                }
+            }
+        }
+        for (idx, timestamp) in &comp.options.timestamps {
+            let found = comp
+                .materialized_elements()
+                .nth(*idx)
+                .map(|(_, f, _)| &f.fmt);
+            let (valid, expected) = match (timestamp, found) {
+                (_, None) => panic!(
+                    "timestamp attribute refers to field {}, but there are only {} fields.",
+                    idx, elements
+                ),
+                (Timestamp::Dos, Some(Format::UInt)) => (true, "u32"),
+                (Timestamp::Dos, Some(Format::ULong)) => (!comp.modifier.native_types(), "u32"),
+                (Timestamp::Dos, _) => (false, "u32"),
+                (Timestamp::FileTime, Some(Format::ULongLong)) => (true, "u64"),
+                (Timestamp::FileTime, _) => (false, "u64"),
+            };
+            if !valid {
+                panic!(
+                    "timestamp attribute requires field {} to be of type `{}`.",
+                    idx, expected
+                );
+            }
+        }
+        for (n, (idx, _)) in comp.options.scales.iter().enumerate() {
+            let found = comp
+                .materialized_elements()
+                .nth(*idx)
+                .map(|(_, f, _)| &f.fmt);
+            match found {
+                None => panic!(
+                    "scale attribute refers to field {}, but there are only {} fields.",
+                    idx, elements
+                ),
+                Some(
+                    Format::Char
+                    | Format::UChar
+                    | Format::Short
+                    | Format::UShort
+                    | Format::Int
+                    | Format::UInt
+                    | Format::Long
+                    | Format::ULong
+                    | Format::LongLong
+                    | Format::ULongLong
+                    | Format::Size
+                    | Format::USize
+                    | Format::Typedef(_),
+                ) => {}
+                Some(_) => panic!("scale attribute requires field {} to be an integer.", idx),
+            }
+            if comp.options.scales[..n].iter().any(|(i, _)| i == idx) {
+                panic!("scale attribute is given more than once for field {}.", idx);
+            }
+        }
+        for (n, (idx, _)) in comp.options.texts.iter().enumerate() {
+            match comp
+                .materialized_elements()
+                .nth(*idx)
+                .map(|(_, f, _)| &f.fmt)
+            {
+                None => panic!(
+                    "text attribute refers to field {}, but there are only {} fields.",
+                    idx, elements
+                ),
+                Some(Format::Array(_)) => {}
+                Some(_) => panic!("text attribute requires field {} to be a `s`.", idx),
+            }
+            if comp.options.texts[..n].iter().any(|(i, _)| i == idx) {
+                panic!("text attribute is given more than once for field {}.", idx);
+            }
+        }
+        if let Some(Frame::Length(idx) | Frame::Count(idx)) = comp.options.frame {
+            match comp
+                .materialized_elements()
+                .nth(idx)
+                .map(|(_, f, _)| &f.fmt)
+            {
+                None => panic!(
+                    "frame attribute refers to field {}, but there are only {} fields.",
+                    idx, elements
+                ),
+                Some(
+                    Format::Char
+                    | Format::UChar
+                    | Format::Short
+                    | Format::UShort
+                    | Format::Int
+                    | Format::UInt
+                    | Format::Long
+                    | Format::ULong
+                    | Format::LongLong
+                    | Format::ULongLong
+                    | Format::Size
+                    | Format::USize
+                    | Format::Typedef(_),
+                ) => {}
+                Some(_) => panic!("frame attribute requires field {} to be an integer.", idx),
+            }
+        }
+        for (n, (idx, _)) in comp.options.transforms.iter().enumerate() {
+            if *idx >= elements {
+                panic!(
+                    "transform attribute refers to field {}, but there are only {} fields.",
+                    idx, elements
+                );
+            }
+            if comp.options.transforms[..n].iter().any(|(i, _)| i == idx) {
+                panic!(
+                    "transform attribute is given more than once for field {}.",
+                    idx
+                );
+            }
+        }
+        for (idx, _) in &comp.options.field_docs {
+            if *idx >= elements {
+                panic!(
+                    "field_doc attribute refers to field {}, but there are only {} fields.",
+                    idx, elements
+                );
+            }
+        }
+        if comp.options.init_from.is_some() && !comp.options.defaults.is_empty() {
+            panic!("init_from and default attributes can't be used together.");
         }
+        for (idx, _) in &comp.options.defaults {
+            if *idx >= elements {
+                panic!(
+                    "default attribute refers to field {}, but there are only {} fields.",
+                    idx, elements
+                );
+            }
+        }
+        comp
     }
 
-    fn materialized_fields(&self) -> impl Iterator<Item = (usize, &Field)> {
+    /// Every element of every field, in the order of `FIELDS`, along with their index in `FIELDS`
+    fn elements(&self) -> impl Iterator<Item = (usize, &Field, usize)> {
         self.fields
             .iter()
+            .flat_map(|f| (0..f.count).map(move |n| (f, n)))
             .enumerate()
-            .filter_map(|(i, f)| if f.materialize { Some((i, f)) } else { None })
+            .map(|(i, (f, n))| (i, f, n))
+    }
+
+    /// Every element of every materialized field, in the order of the unpacked representation,
+    /// along with their index in `FIELDS`
+    fn materialized_elements(&self) -> impl Iterator<Item = (usize, &Field, usize)> {
+        self.elements().filter(|(_, f, _)| f.materialize)
     }
 
     fn packed_type(&self) -> syn::Type {
@@ -393,132 +1210,2060 @@ impl Compilation {
 
     fn unpacked_type(&self) -> syn::Type {
         let types = self
-            .materialized_fields()
-            .map(|(_, f)| f.tipe(self.modifier.native_types()));
+            .materialized_elements()
+            .map(|(_, f, _)| f.tipe(self.modifier.native_types()));
         syn::parse_quote! {
             (#(#types ,)*)
         }
     }
 
-    /// The unpack method, going from array to tuple
-    fn unpack(&self) -> syn::ItemFn {
-        let fieldbuffers = (0..self.fields.len()).map(|i| {
-            quote! {
-                [u8; Self::FIELDS[#i].2], [u8; Self::FIELDS[#i].3 - Self::FIELDS[#i].2]
+    /// The byte order, sizes and alignment, e.g. `Byte order: little-endian; standard sizes, no
+    /// alignment.`
+    fn layout_summary(&self) -> String {
+        let native_types = self.modifier.native_types();
+        format!(
+            "Byte order: {}; {}.",
+            match self.modifier.byte_order() {
+                parser::ByteOrder::Native => "native",
+                parser::ByteOrder::LittleEndian => "little-endian",
+                parser::ByteOrder::BigEndian => "big-endian",
+            },
+            match (
+                native_types,
+                self.modifier.aligned(),
+                self.options.max_align
+            ) {
+                (false, _, _) => "standard sizes, no alignment".to_owned(),
+                (true, false, _) => "native sizes, no alignment".to_owned(),
+                (true, true, Some(max)) => format!("native sizes, alignment of at most {}", max),
+                (true, true, None) => "native sizes and alignment".to_owned(),
             }
-        });
-        let fieldbuffer: syn::TypeTuple = syn::parse_quote! {
-            (#(#fieldbuffers),*)
-        };
-        let fieldvalues = self.materialized_fields().map(|(i, f)| {
-            let m = syn::Member::Unnamed((i * 2 + 1).into());
-            f.unpack_expr(&self.modifier, &syn::parse_quote! { __STRUCT.#m })
-        });
-        syn::parse_quote! {
-            pub const fn unpack(inp: <Self as restruct::Struct>::Packed) -> <Self as restruct::Struct>::Unpacked {
-                let __STRUCT: #fieldbuffer;
-                __STRUCT = unsafe { std::mem::transmute(inp) };
-                (#(#fieldvalues ,)*)
+        )
+    }
+
+    /// A human-readable description of the layout, used in generated documentation
+    fn layout_doc(&self) -> String {
+        let native_types = self.modifier.native_types();
+        let mut doc = format!("{}\n\n", self.layout_summary());
+        if self.fields.is_empty() {
+            doc.push_str("The format is empty.");
+            return doc;
+        }
+        doc.push_str("| Element | Format | Type | Offset | Size |\n");
+        doc.push_str("|---------|--------|------|--------|------|\n");
+        let names = self.options.names.as_deref().unwrap_or_default();
+        let mut element = 0;
+        let mut offset = Some(0);
+        for f in &self.fields {
+            let elements = match (f.materialize, f.count) {
+                (false, _) => "-".to_owned(),
+                (true, 1) => match names.get(element) {
+                    Some(name) => format!("{} (`{}`)", element, name),
+                    None => element.to_string(),
+                },
+                (true, count) => format!("{}..={}", element, element + count - 1),
+            };
+            if f.materialize {
+                element += f.count;
             }
+            let tipe = match (f.materialize, &f.fmt) {
+                (false, Format::Pad(_)) => "padding".to_owned(),
+                (false, _) => "alignment only".to_owned(),
+                (true, _) => {
+                    let tipe = f.tipe(native_types);
+                    format!("`{}`", type_name(&tipe))
+                }
+            };
+            let size = f.static_size(native_types);
+            let fmt_opt = |o: Option<usize>| o.map_or_else(|| "-".to_owned(), |o| o.to_string());
+            doc.push_str(&format!(
+                "| {} | `{}` | {} | {} | {} |\n",
+                elements,
+                f.code,
+                tipe,
+                fmt_opt(offset),
+                fmt_opt(size)
+            ));
+            offset = match (native_types, offset, size) {
+                (false, Some(o), Some(s)) => Some(o + s),
+                _ => None,
+            };
         }
+        doc
     }
 
-    /// The pack-method, going from tuple to array
-    fn pack(&self) -> syn::ItemFn {
-        let mut mi: usize = 0;
-        let exprs = self.fields.iter().enumerate().map(|(i, f)| {
-            let pack_expr = if f.materialize {
-                let m = syn::Member::Unnamed(mi.into());
-                let e = f.pack_expr(&self.modifier, &syn::parse_quote! {inp.#m});
-                mi += 1;
-                e
-            } else {
-                syn::parse_quote! {
-                    [0u8; Self::FIELDS[#i].3 - Self::FIELDS[#i].2]
+    /// An ASCII byte-map of the layout, used by the `layout_output`-attribute. Every byte is
+    /// labeled with the index of the element in the unpacked representation it belongs to; padding
+    /// is shown as `xx`.
+    pub fn layout_diagram(&self) -> String {
+        let native_types = self.modifier.native_types();
+        let mut cells = Vec::new();
+        let mut legend = String::new();
+        let mut element: usize = 0;
+        for f in &self.fields {
+            let size = match f.static_size(native_types) {
+                Some(size) => size,
+                None => {
+                    return format!(
+                        "The layout depends on the target and can't be shown while compiling.\n\n{}",
+                        self.layout_doc()
+                    )
                 }
             };
-            quote! {
-                [0u8; Self::FIELDS[#i].2], #pack_expr
+            if !f.materialize {
+                cells.extend(std::iter::repeat_n(None, size));
+                continue;
             }
-        });
-        syn::parse_quote! {
-            pub const fn pack(inp: <Self as restruct::Struct>::Unpacked) -> <Self as restruct::Struct>::Packed {
-                let __STRUCT = (#(#exprs, )*);
-                unsafe { std::mem::transmute(__STRUCT) }
+            let element_size = size / f.count;
+            for _ in 0..f.count {
+                legend.push_str(&format!(
+                    "{:>4}: `{}` {} (offset {}, size {})\n",
+                    element,
+                    f.code,
+                    type_name(&f.tipe(native_types)),
+                    cells.len(),
+                    element_size
+                ));
+                cells.extend(std::iter::repeat_n(Some(element), element_size));
+                element += 1;
+            }
+        }
+        let width = std::cmp::max(2, element.saturating_sub(1).to_string().len());
+        let mut diagram = format!("{} bytes\n      ", cells.len());
+        for col in 0..16 {
+            diagram.push_str(&format!(" {:>width$x}", col, width = width));
+        }
+        for (row, chunk) in cells.chunks(16).enumerate() {
+            diagram.push_str(&format!("\n{:04x}: ", row * 16));
+            for cell in chunk {
+                match cell {
+                    Some(e) => diagram.push_str(&format!(" {:>width$}", e, width = width)),
+                    None => diagram.push_str(&format!(" {:>width$}", "xx", width = width)),
+                }
             }
         }
+        diagram.push('\n');
+        diagram.push_str(&legend);
+        diagram
     }
 
-    /// The `const SIZE: usize`-item resolving to the size of the packed buffer
-    fn size(&self) -> syn::ItemConst {
-        let size: syn::Expr = self.fields.last().map_or_else(
-            || syn::parse_quote! { 0 },
-            |f| {
-                let o_id = f.offset_ident();
-                let s_id = f.size_ident();
-                syn::parse_quote! {
-                    Self::#o_id + Self::#s_id
-                }
-            },
+    /// The unpack method, going from array to tuple
+    fn unpack(&self) -> syn::ItemFn {
+        let fieldvalues = self.materialized_elements().map(|(_, f, n)| {
+            let element_size = f.element_size_expr();
+            let offset = f.element_offset_expr(n);
+            f.unpack_expr(
+                &self.modifier,
+                &syn::parse_quote! {
+                    restruct::rt::read_field::<{ #element_size }>(&inp, #offset)
+                },
+            )
+        });
+        let doc = format!(
+            "Unpack the given buffer into a tuple.\n\n{}",
+            self.layout_doc()
         );
+        let inline = self.options.inline;
         syn::parse_quote! {
-            pub const SIZE: usize = #size;
+            #[doc = #doc]
+            #inline
+            pub const fn unpack(inp: <Self as restruct::Struct>::Packed) -> <Self as restruct::Struct>::Unpacked {
+                (#(#fieldvalues ,)*)
+            }
         }
     }
 
-    /// The `const FIELDS: [...; n]`-item holding tuples describing each field
-    fn fields_array(&self) -> syn::ItemConst {
-        let elemens = self
-            .fields
-            .iter()
-            .map(|f| f.fields_ary_entry(&self.modifier));
-        let size = self.fields.len();
-        syn::parse_quote! {
-            pub const FIELDS: [(&'static str, usize, usize, usize); #size] = [#(#elemens),*];
+    /// The `unpack_all()`-function
+    fn unpack_all(&self) -> proc_macro2::TokenStream {
+        quote! {
+            /// Unpack all consecutive records in the given slice. Trailing bytes which don't make
+            /// up a whole record are ignored.
+            pub fn unpack_all(inp: &[u8]) -> Vec<<Self as restruct::Struct>::Unpacked> {
+                inp.chunks_exact(Self::SIZE).map(Self::unpack_slice).collect()
+            }
         }
     }
 
-    /// Generate all the required constants which will resolve to the offset, the alignment
-    /// and the total size of each field.
-    fn fields(&self) -> Vec<syn::ItemConst> {
-        let mut res = Vec::with_capacity(self.fields.len() * 3);
-
-        macro_rules! push {
-            ($name:tt, $($tt:tt)*) => {
-                res.push(syn::parse_quote!{
-                    const #$name: usize = $($tt)*;
+    /// Functions computing and verifying the checksum, if the `checksum`-attribute is given
+    fn checksum(&self) -> Option<proc_macro2::TokenStream> {
+        let (function, tipe) = self.options.checksum?.function();
+        let index = self.materialized_elements().count() - 1;
+        let (_, f, n) = self.materialized_elements().last().unwrap();
+        let offset = f.element_offset_expr(n);
+        let size = f.element_size_expr();
+        let pack_expr = f.pack_expr(&self.modifier, &syn::parse_quote! { Self::checksum(inp) });
+        let unpack_expr = f.unpack_expr(
+            &self.modifier,
+            &syn::parse_quote! { restruct::rt::read_field(&inp, #offset) },
+        );
+        let name = match self.options.names {
+            Some(ref names) => {
+                let name = names[index].to_string();
+                quote! { Some(#name) }
+            }
+            None => quote! { None },
+        };
+        let doc = format!(
+            "Compute the `{}`-checksum over all bytes preceding the last element.",
+            function
+        );
+        Some(quote! {
+            #[doc = #doc]
+            pub const fn checksum(inp: &<Self as restruct::Struct>::Packed) -> #tipe {
+                restruct::checksum::#function(inp.split_at(#offset).0)
+            }
+
+            /// `true` if the checksum stored in the given buffer is correct.
+            pub const fn verify_checksum(inp: &<Self as restruct::Struct>::Packed) -> bool {
+                let expected = restruct::rt::write_field(*inp, #offset, #pack_expr);
+                restruct::rt::range_eq(&expected, inp, #offset, #size)
+            }
+
+            /// Unpack the given buffer if it's checksum is correct.
+            pub const fn unpack_checked(inp: <Self as restruct::Struct>::Packed) -> Result<<Self as restruct::Struct>::Unpacked, restruct::UnpackError> {
+                if Self::verify_checksum(&inp) {
+                    Ok(Self::unpack(inp))
+                } else {
+                    Err(restruct::UnpackError::Value {
+                        index: #index,
+                        name: #name,
+                        offset: #offset,
+                        expected: Self::checksum(&inp) as u64,
+                        found: #unpack_expr as u64,
+                    })
+                }
+            }
+        })
+    }
+
+    /// The validating functions, applying all declared validators to each record
+    fn validation(&self) -> proc_macro2::TokenStream {
+        let body = match self.options.checksum {
+            Some(_) => quote! { Self::unpack_checked(*inp).map(|_| ()) },
+            None => quote! {
+                let _ = inp;
+                Ok(())
+            },
+        };
+        quote! {
+            /// Apply all declared validators, e.g. the `checksum`, to the given buffer.
+            pub fn validate(inp: &<Self as restruct::Struct>::Packed) -> Result<(), restruct::UnpackError> {
+                #body
+            }
+
+            /// Read exactly `Self::SIZE` bytes from the given reader, validate them via
+            /// `validate()` and unpack them.
+            pub fn read_valid_from<T: std::io::Read>(r: &mut T) -> Result<<Self as restruct::Struct>::Unpacked, restruct::Error> {
+                let mut buf = [0; Self::SIZE];
+                restruct::read_exact(r, &mut buf)?;
+                Self::validate(&buf)?;
+                Ok(Self::unpack(buf))
+            }
+
+            /// Read consecutive records from the given reader like `stream()`, validating each
+            /// via `validate()`. Invalid records either end iteration or are skipped, according to
+            /// the given policy. Iteration ends after the first error.
+            pub fn iter_valid<T: std::io::Read>(mut r: T, policy: restruct::OnInvalid) -> impl Iterator<Item = Result<<Self as restruct::Struct>::Unpacked, restruct::Error>> {
+                let mut done = false;
+                std::iter::from_fn(move || {
+                    while !done {
+                        let mut buf = [0; Self::SIZE];
+                        let res = match restruct::read_exact(&mut r, &mut buf) {
+                            Ok(()) => match Self::validate(&buf) {
+                                Ok(()) => return Some(Ok(Self::unpack(buf))),
+                                Err(_) if policy == restruct::OnInvalid::Skip => continue,
+                                Err(e) => restruct::Error::Unpack(e),
+                            },
+                            Err(e) => e,
+                        };
+                        done = true;
+                        if !res.is_eof() {
+                            return Some(Err(res));
+                        }
+                    }
+                    None
+                })
+            }
+        }
+    }
+
+    /// The registration in `restruct::registry`, if the `registry`-feature is enabled and the type
+    /// is not generic
+    fn registry(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "registry") || !self.generics.params.is_empty() {
+            return None;
+        }
+        let name = &self.name;
+        let format = &self.format;
+        let names = match &self.options.names {
+            Some(names) => {
+                let names = names.iter().map(|n| n.to_string());
+                quote! { Some(&[#(#names),*]) }
+            }
+            None => quote! { None },
+        };
+        Some(gated(
+            "registry",
+            quote! {
+                restruct::registry::inventory::submit! {
+                    restruct::registry::Descriptor {
+                        name: concat!(module_path!(), "::", stringify!(#name)),
+                        format: #format,
+                        size: #name::SIZE,
+                        fields: &#name::FIELDS,
+                        names: #names,
+                    }
+                }
+            },
+        ))
+    }
+
+    /// The fuzzing entry point, if the `fuzz`-feature is enabled
+    fn fuzz(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "fuzz") {
+            return None;
+        }
+        let checksum = self.options.checksum.map(|_| {
+            quote! {
+                let _ = Self::unpack_checked(restruct::rt::read_field(data, 0));
+                assert!(Self::verify_checksum(&packed));
+            }
+        });
+        Some(quote! {
+            /// Exercise unpacking, packing and validation with arbitrary input, asserting that
+            /// re-packing is stable. Meant to be called from a fuzz target, e.g. via
+            /// `fuzz_target!(|data: &[u8]| Foo::fuzz(data));`.
+            pub fn fuzz(data: &[u8]) {
+                let res = match Self::try_unpack_slice(data) {
+                    Ok(res) => res,
+                    Err(_) => return,
+                };
+                let packed = Self::pack_ref(&res);
+                assert_eq!(Self::pack(Self::unpack(packed)), packed);
+                let _ = Self::display(&res).to_string();
+                #checksum
+            }
+        })
+    }
+
+    /// The `random()`-function, if the `rand`-feature is enabled
+    fn random(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "rand") {
+            return None;
+        }
+        let native_types = self.modifier.native_types();
+        let values = self.materialized_elements().map(|(_, f, _)| match f.fmt {
+            Format::Array(sz) => quote! {
+                {
+                    let mut buf = [0u8; #sz];
+                    restruct::rand::RngCore::fill_bytes(rng, &mut buf);
+                    buf
+                }
+            },
+            Format::Ident(ref ident) => quote! { #ident::random(rng) },
+            // `rand` does not draw pointer-sized integers, which would be unportable
+            Format::Size | Format::USize => {
+                let tipe = f.tipe(native_types);
+                quote! { restruct::rand::Rng::random::<u64>(rng) as #tipe }
+            }
+            _ => {
+                let tipe = f.tipe(native_types);
+                quote! { restruct::rand::Rng::random::<#tipe>(rng) }
+            }
+        });
+        // Packing computes the checksum, if any
+        let res = match self.options.checksum {
+            Some(_) => quote! { Self::unpack(Self::pack(res)) },
+            None => quote! { res },
+        };
+        Some(gated(
+            "rand",
+            quote! {
+                /// A value where each element is drawn from the given random number generator.
+                pub fn random<R: restruct::rand::Rng + ?Sized>(rng: &mut R) -> <Self as restruct::Struct>::Unpacked {
+                    let res = (#(#values ,)*);
+                    #res
+                }
+            },
+        ))
+    }
+
+    /// The cross-check against Python's `struct`-module, if the `cpython`-feature is enabled and
+    /// the format has no nested types, extended precision numbers, typedefs, absolute offsets or
+    /// packed native mode, which Python doesn't know about
+    fn cpython_check(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "cpython")
+            || self.modifier == parser::Modifier::NativePacked
+            || self.fields.iter().any(|f| f.at.is_some())
+            || self.fields.iter().any(|f| {
+                matches!(
+                    f.fmt,
+                    Format::Ident(_) | Format::Extended | Format::Quad | Format::Typedef(_)
+                )
+            })
+        {
+            return None;
+        }
+        let format = &self.format;
+        Some(gated(
+            "cpython",
+            quote! {
+                /// Cross-check the size, unpacking and packing against Python's `struct`-module by
+                /// running `python3`, using `samples` buffers of pseudo-random bytes.
+                pub fn cpython_check(samples: usize) -> Result<(), String> {
+                    restruct::cpython::check(#format, Self::SIZE, samples, |buf| Self::pack(Self::unpack_slice(buf)).to_vec())
+                }
+            },
+        ))
+    }
+
+    /// Asynchronous IO-functions, if the `futures_io`-feature is enabled
+    fn async_io(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "futures_io") {
+            return None;
+        }
+        Some(gated(
+            "futures_io",
+            quote! {
+                /// Pack the given input and write it to the given asynchronous writer.
+                pub async fn write_to_async<T: restruct::async_io::AsyncWrite + Unpin>(inp: <Self as restruct::Struct>::Unpacked, w: &mut T) -> std::io::Result<()> {
+                    restruct::async_io::write_all(w, &Self::pack(inp)).await
+                }
+
+                /// Read exactly `Self::SIZE` bytes from the given asynchronous reader and unpack them.
+                pub async fn read_from_async<T: restruct::async_io::AsyncRead + Unpin>(r: &mut T) -> Result<<Self as restruct::Struct>::Unpacked, restruct::Error> {
+                    let mut buf = [0; Self::SIZE];
+                    restruct::async_io::read_exact(r, &mut buf).await?;
+                    Ok(Self::unpack(buf))
+                }
+            },
+        ))
+    }
+
+    /// Conversions from and to JavaScript, if the `wasm`-feature is enabled
+    fn wasm(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "wasm") {
+            return None;
+        }
+        Some(gated(
+            "wasm",
+            quote! {
+                /// Pack the given input into a new JavaScript `Uint8Array`.
+                pub fn pack_to_uint8array(inp: <Self as restruct::Struct>::Unpacked) -> restruct::wasm::Uint8Array {
+                    restruct::wasm::Uint8Array::from(&Self::pack(inp)[..])
+                }
+
+                /// Unpack the first `Self::SIZE` bytes of the given JavaScript `Uint8Array`.
+                pub fn unpack_from_uint8array(inp: &restruct::wasm::Uint8Array) -> Result<<Self as restruct::Struct>::Unpacked, restruct::UnpackError> {
+                    Ok(Self::unpack(restruct::wasm::read_array(inp)?))
+                }
+            },
+        ))
+    }
+
+    /// Conversions from and to hexadecimal and, if the `base64`-feature is enabled, Base64 text
+    fn text(&self) -> proc_macro2::TokenStream {
+        let base64 = if cfg!(feature = "base64") {
+            gated(
+                "base64",
+                quote! {
+                    /// Pack the given input into a string in standard Base64.
+                    pub fn pack_base64(inp: <Self as restruct::Struct>::Unpacked) -> String {
+                        restruct::rt::to_base64(&Self::pack(inp))
+                    }
+
+                    /// Unpack a string in standard Base64, which must encode exactly `Self::SIZE`
+                    /// bytes.
+                    pub fn unpack_base64(inp: &str) -> Result<<Self as restruct::Struct>::Unpacked, restruct::Error> {
+                        Ok(Self::unpack(restruct::rt::from_base64(inp)?))
+                    }
+                },
+            )
+        } else {
+            quote! {}
+        };
+        quote! {
+            /// Pack the given input into a string of lowercase hexadecimal digits.
+            pub fn pack_hex(inp: <Self as restruct::Struct>::Unpacked) -> String {
+                restruct::rt::to_hex(&Self::pack(inp))
+            }
+
+            /// Unpack a string of hexadecimal digits, which must give exactly `Self::SIZE` bytes.
+            /// ASCII whitespace between pairs of digits is ignored.
+            pub fn unpack_hex(inp: &str) -> Result<<Self as restruct::Struct>::Unpacked, restruct::Error> {
+                Ok(Self::unpack(restruct::rt::from_hex(inp)?))
+            }
+
+            #base64
+        }
+    }
+
+    /// The `LAYOUT_JSON`-constant, if the `layout_json`-feature is enabled
+    fn layout_json(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "layout_json") {
+            return None;
+        }
+        let native_types = self.modifier.native_types();
+        let mut names = self.options.names.iter().flatten().map(|n| n.to_string());
+        // An upper bound for the length of the JSON string
+        let mut capacity = 128 + self.name.to_string().len();
+        let elements = self
+            .elements()
+            .map(|(_, f, _)| {
+                let tipe = f.tipe(native_types);
+                let name = if f.materialize { names.next() } else { None };
+                capacity +=
+                    128 + quote!(#tipe).to_string().len() + name.as_ref().map_or(0, |n| n.len());
+                let materialize = f.materialize;
+                match name {
+                    Some(name) => quote! { (#materialize, Some(#name)) },
+                    None => quote! { (#materialize, None) },
+                }
+            })
+            .collect::<Vec<_>>();
+        let name = self.name.to_string();
+        let endianness = match self.modifier.byte_order() {
+            parser::ByteOrder::Native => quote! {
+                if cfg!(target_endian = "big") { "big" } else { "little" }
+            },
+            parser::ByteOrder::LittleEndian => quote! { "little" },
+            parser::ByteOrder::BigEndian => quote! { "big" },
+        };
+        Some(quote! {
+            const LAYOUT_JSON_WRITER: restruct::json::ConstWriter<#capacity> = restruct::json::layout(
+                #name,
+                #endianness,
+                Self::SIZE,
+                &Self::FIELDS,
+                &[#(#elements),*],
+            );
+
+            /// The layout as JSON: The name, the endianness (`"little"` or `"big"`), the size and
+            /// an entry for each field in `FIELDS`, giving it's type, offset, alignment and size,
+            /// if it is padding and it's name.
+            pub const LAYOUT_JSON: &'static str = restruct::json::ConstWriter::as_str(&Self::LAYOUT_JSON_WRITER);
+        })
+    }
+
+    /// The `MARKDOWN`-constant, if the `markdown`-feature is enabled
+    fn markdown(&self) -> Option<proc_macro2::TokenStream> {
+        if !cfg!(feature = "markdown") {
+            return None;
+        }
+        let names = self.options.names.as_deref().unwrap_or_default();
+        let summary = self.layout_summary();
+        // An upper bound for the length of the Markdown string
+        let mut capacity = 256 + self.name.to_string().len() + summary.len();
+        let mut element = 0;
+        let elements = self
+            .elements()
+            .map(|(_, f, _)| {
+                let tipe = f.tipe(self.modifier.native_types());
+                let (label, doc) = if f.materialize {
+                    let label = match names.get(element) {
+                        Some(name) => format!("`{}`", name),
+                        None => element.to_string(),
+                    };
+                    let doc = self
+                        .options
+                        .field_docs
+                        .iter()
+                        .filter(|(idx, _)| *idx == element)
+                        .map(|(_, doc)| doc.replace('|', "\\|").replace('\n', " "))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    element += 1;
+                    (label, doc)
+                } else {
+                    ("-".to_owned(), "Padding".to_owned())
+                };
+                capacity += 128 + quote!(#tipe).to_string().len() + label.len() + doc.len();
+                let endianness = match (&f.fmt, self.modifier.byte_order()) {
+                    (
+                        Format::Array(_)
+                        | Format::Pad(_)
+                        | Format::Bool
+                        | Format::Char
+                        | Format::UChar
+                        | Format::Ident(_),
+                        _,
+                    ) => quote! { "-" },
+                    _ if !f.materialize => quote! { "-" },
+                    (_, parser::ByteOrder::Native) => quote! {
+                        if cfg!(target_endian = "big") { "big" } else { "little" }
+                    },
+                    (_, parser::ByteOrder::LittleEndian) => quote! { "little" },
+                    (_, parser::ByteOrder::BigEndian) => quote! { "big" },
+                };
+                quote! { (#label, #endianness, #doc) }
+            })
+            .collect::<Vec<_>>();
+        let name = self.name.to_string();
+        Some(quote! {
+            const MARKDOWN_WRITER: restruct::json::ConstWriter<#capacity> = restruct::markdown::table(
+                #name,
+                #summary,
+                Self::SIZE,
+                &Self::FIELDS,
+                &[#(#elements),*],
+            );
+
+            /// The layout as a Markdown table: For each field in `FIELDS`, it's name or index,
+            /// type, offset, size, endianness and it's description given via `field_doc`.
+            pub const MARKDOWN: &'static str = restruct::json::ConstWriter::as_str(&Self::MARKDOWN_WRITER);
+        })
+    }
+
+    /// The `PACKET_DIAGRAM`-constant, if the layout does not depend on the target
+    fn packet_diagram(&self) -> Option<proc_macro2::TokenStream> {
+        if self.modifier.native_types()
+            || self
+                .fields
+                .iter()
+                .any(|f| matches!(f.fmt, Format::Size | Format::USize))
+        {
+            return None;
+        }
+        let names = self.options.names.as_deref().unwrap_or_default();
+        let names = names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+        let format = parser::parse(&self.format).ok()?;
+        let diagram =
+            crate::export::packet_diagram(&format, &crate::layout::Target::LP64, &names).ok()?;
+        Some(quote! {
+            /// The layout as an RFC-style packet diagram, 32 bits per row.
+            pub const PACKET_DIAGRAM: &'static str = #diagram;
+        })
+    }
+
+    /// The unpack_into-method, overwriting each element of an existing tuple
+    fn unpack_into(&self) -> syn::ItemFn {
+        let assignments = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (_, f, n))| {
+                let m = syn::Member::Unnamed(mi.into());
+                let element_size = f.element_size_expr();
+                let offset = f.element_offset_expr(n);
+                let value = f.unpack_expr(
+                    &self.modifier,
+                    &syn::parse_quote! {
+                        restruct::rt::read_field::<{ #element_size }>(inp, #offset)
+                    },
+                );
+                quote! { out.#m = #value; }
+            });
+        let inline = self.options.inline;
+        syn::parse_quote! {
+            /// Unpack the given buffer into an existing tuple, like `unpack()` but without
+            /// constructing a new value.
+            #[allow(unused_variables)]
+            #inline
+            pub const fn unpack_into(inp: &<Self as restruct::Struct>::Packed, out: &mut <Self as restruct::Struct>::Unpacked) {
+                #(#assignments)*
+            }
+        }
+    }
+
+    /// The read_volatile/write_volatile-methods, accessing each element on it's own
+    fn volatile(&self) -> proc_macro2::TokenStream {
+        let elements = self.materialized_elements().collect::<Vec<_>>();
+        let native_types = self.modifier.native_types();
+        let reads = elements.iter().map(|(_, f, n)| {
+            let element_size = f.element_size_expr();
+            let offset = f.element_offset_expr(*n);
+            // Numbers are loaded as their primitive type at once, if aligned
+            let access: syn::Expr = match f.access_tipe(native_types) {
+                Some(tipe) => syn::parse_quote! {{
+                    let ptr = ptr.add(#offset);
+                    if ptr as usize % std::mem::align_of::<#tipe>() == 0 {
+                        std::ptr::read_volatile(ptr as *const #tipe).to_ne_bytes()
+                    } else {
+                        std::ptr::read_volatile(ptr as *const [u8; #element_size])
+                    }
+                }},
+                None => syn::parse_quote! {
+                    std::ptr::read_volatile(ptr.add(#offset) as *const [u8; #element_size])
+                },
+            };
+            f.unpack_expr(&self.modifier, &access)
+        });
+        let writes = elements.iter().enumerate().map(|(mi, (_, f, n))| {
+            let m = syn::Member::Unnamed(mi.into());
+            let element_size = f.element_size_expr();
+            let offset = f.element_offset_expr(*n);
+            let pack_expr = f.pack_expr(&self.modifier, &syn::parse_quote! {inp.#m});
+            match f.access_tipe(native_types) {
+                Some(tipe) => quote! {{
+                    let (ptr, buf) = (ptr.add(#offset), #pack_expr);
+                    if ptr as usize % std::mem::align_of::<#tipe>() == 0 {
+                        std::ptr::write_volatile(ptr as *mut #tipe, #tipe::from_ne_bytes(buf));
+                    } else {
+                        std::ptr::write_volatile(ptr as *mut [u8; #element_size], buf);
+                    }
+                }},
+                None => quote! {
+                    std::ptr::write_volatile(ptr.add(#offset) as *mut [u8; #element_size], #pack_expr);
+                },
+            }
+        });
+        quote! {
+            /// Act as if the input was pointing to an `[u8; Self::SIZE]`-array and unpack it,
+            /// using one volatile read per element. Numbers at aligned addresses are read as
+            /// their primitive type, at their full width. Padding is not read.
+            #[allow(unused_variables)]
+            pub unsafe fn read_volatile<T>(ptr: *const T) -> <Self as restruct::Struct>::Unpacked {
+                let ptr = ptr as *const u8;
+                (#(#reads ,)*)
+            }
+
+            /// Act as if the output was pointing to an `[u8; Self::SIZE]`-array and pack the
+            /// given input into it, using one volatile write per element. Numbers at aligned
+            /// addresses are written as their primitive type, at their full width. Padding is not
+            /// written.
+            #[allow(unused_variables)]
+            pub unsafe fn write_volatile<T>(inp: <Self as restruct::Struct>::Unpacked, ptr: *mut T) {
+                let ptr = ptr as *mut u8;
+                #(#writes)*
+            }
+        }
+    }
+
+    /// The pack-methods, going from tuple to array
+    fn pack(&self) -> proc_macro2::TokenStream {
+        let writes = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (_, f, n))| {
+                let m = syn::Member::Unnamed(mi.into());
+                let offset = f.element_offset_expr(n);
+                let pack_expr = f.pack_expr(&self.modifier, &syn::parse_quote! {inp.#m});
+                quote! {
+                    buf = restruct::rt::write_field(buf, #offset, #pack_expr);
+                }
+            });
+        let doc = format!(
+            "Pack the given tuple into a buffer.\n\n{}",
+            self.layout_doc()
+        );
+        let checksum = self.options.checksum.map(|_| {
+            let (_, f, n) = self.materialized_elements().last().unwrap();
+            let offset = f.element_offset_expr(n);
+            let pack_expr =
+                f.pack_expr(&self.modifier, &syn::parse_quote! { Self::checksum(&buf) });
+            quote! {
+                buf = restruct::rt::write_field(buf, #offset, #pack_expr);
+            }
+        });
+        let inline = self.options.inline;
+        quote! {
+            #[doc = #doc]
+            #inline
+            pub const fn pack(inp: <Self as restruct::Struct>::Unpacked) -> <Self as restruct::Struct>::Packed {
+                Self::pack_ref(&inp)
+            }
+
+            /// Pack the given tuple into a buffer, like `pack()` but without moving the tuple.
+            #inline
+            pub const fn pack_ref(inp: &<Self as restruct::Struct>::Unpacked) -> <Self as restruct::Struct>::Packed {
+                let mut buf = [0u8; Self::SIZE];
+                #(#writes)*
+                #checksum
+                buf
+            }
+        }
+    }
+
+    /// The try_pack-method, converting each element from any type via `TryInto` before packing
+    fn try_pack(&self) -> proc_macro2::TokenStream {
+        let native_types = self.modifier.native_types();
+        let params = (0..self.materialized_elements().count())
+            .map(|i| quote::format_ident!("T{}", i))
+            .collect::<Vec<_>>();
+        let bounds = self
+            .materialized_elements()
+            .zip(&params)
+            .map(|((_, f, _), p)| {
+                let tipe = f.tipe(native_types);
+                quote! { #p: core::convert::TryInto<#tipe> }
+            });
+        let conversions = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (_, f, n))| {
+                let m = syn::Member::Unnamed(mi.into());
+                let offset = f.element_offset_expr(n);
+                let tipe = type_name(&f.tipe(native_types));
+                let name = match self.options.names {
+                    Some(ref names) => {
+                        let name = names[mi].to_string();
+                        quote! { Some(#name) }
+                    }
+                    None => quote! { None },
+                };
+                quote! {
+                    match core::convert::TryInto::try_into(inp.#m) {
+                        Ok(v) => v,
+                        Err(_) => {
+                            return Err(restruct::PackError {
+                                index: #mi,
+                                name: #name,
+                                offset: #offset,
+                                tipe: #tipe,
+                            })
+                        }
+                    },
+                }
+            });
+        quote! {
+            /// Convert each element of the given tuple via `TryInto`, e.g. from `i64` or `usize`
+            /// into narrower integers, and pack the result. A value not fitting it's element is
+            /// reported as an error instead of being truncated.
+            pub fn try_pack<#(#bounds),*>(inp: (#(#params,)*)) -> Result<<Self as restruct::Struct>::Packed, restruct::PackError> {
+                let res = (#(#conversions)*);
+                Ok(Self::pack(res))
+            }
+        }
+    }
+
+    /// Functions packing into existing or heap-allocated buffers, avoiding a copy of the packed
+    /// form on the stack
+    fn boxed(&self) -> proc_macro2::TokenStream {
+        let writes = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (_, f, n))| {
+                let m = syn::Member::Unnamed(mi.into());
+                let offset = f.element_offset_expr(n);
+                let size = f.element_size_expr();
+                // Transformed elements are encoded on the stack, as their packed form depends on it
+                match f.fmt {
+                    Format::Array(_) if f.transform.is_none() => quote! {
+                        out[#offset..#offset + #size].copy_from_slice(&inp.#m);
+                    },
+                    Format::Ident(ref ident) if f.transform.is_none() => quote! {
+                        #ident::pack_into(&inp.#m, &mut out[#offset..]);
+                    },
+                    _ => {
+                        let pack_expr = f.pack_expr(&self.modifier, &syn::parse_quote! {inp.#m});
+                        quote! {
+                            out[#offset..#offset + #size].copy_from_slice(&#pack_expr);
+                        }
+                    }
+                }
+            });
+        let checksum = self.options.checksum.map(|checksum| {
+            let (function, _) = checksum.function();
+            let (_, f, n) = self.materialized_elements().last().unwrap();
+            let offset = f.element_offset_expr(n);
+            let size = f.element_size_expr();
+            let pack_expr = f.pack_expr(&self.modifier, &syn::parse_quote! { checksum });
+            quote! {
+                let checksum = restruct::checksum::#function(&out[..#offset]);
+                out[#offset..#offset + #size].copy_from_slice(&#pack_expr);
+            }
+        });
+        quote! {
+            /// Pack the given tuple into the first `Self::SIZE` bytes of the given slice, like
+            /// `pack_ref()` but without a copy of the packed form on the stack.
+            ///
+            /// # Panics
+            ///
+            /// The function will panic if the slice is smaller than `Self::SIZE`
+            pub fn pack_into(inp: &<Self as restruct::Struct>::Unpacked, out: &mut [u8]) {
+                out[..Self::SIZE].fill(0);
+                #(#writes)*
+                #checksum
+            }
+
+            /// Pack the given tuple into a heap-allocated buffer, avoiding a copy of the packed
+            /// form on the stack, e.g. for large formats on small stacks.
+            pub fn pack_boxed(inp: &<Self as restruct::Struct>::Unpacked) -> Box<<Self as restruct::Struct>::Packed> {
+                let mut buf = vec![0u8; Self::SIZE].into_boxed_slice();
+                Self::pack_into(inp, &mut buf);
+                match std::convert::TryInto::<Box<<Self as restruct::Struct>::Packed>>::try_into(buf) {
+                    Ok(buf) => buf,
+                    Err(_) => unreachable!(),
+                }
+            }
+
+            /// Read exactly `Self::SIZE` bytes from the given reader into a heap-allocated buffer,
+            /// avoiding a copy of the packed form on the stack.
+            pub fn read_boxed_from<T: std::io::Read>(r: &mut T) -> Result<Box<<Self as restruct::Struct>::Packed>, restruct::Error> {
+                let mut buf = vec![0u8; Self::SIZE].into_boxed_slice();
+                restruct::read_exact(r, &mut buf)?;
+                match std::convert::TryInto::<Box<<Self as restruct::Struct>::Packed>>::try_into(buf) {
+                    Ok(buf) => Ok(buf),
+                    Err(_) => unreachable!(),
+                }
+            }
+        }
+    }
+
+    /// Functions decoding elements holding timestamps, as given via the `timestamp`-attribute
+    fn timestamps(&self) -> proc_macro2::TokenStream {
+        let fns = self.options.timestamps.iter().map(|(idx, timestamp)| {
+            let m = syn::Member::Unnamed((*idx).into());
+            let ident = match &self.options.names {
+                Some(names) => quote::format_ident!("{}_datetime", names[*idx]),
+                None => quote::format_ident!("datetime_{}", idx),
+            };
+            let (decode, kind) = match timestamp {
+                Timestamp::Dos => (quote! { from_dos }, "MS-DOS timestamp"),
+                Timestamp::FileTime => (quote! { from_filetime }, "`FILETIME`"),
+            };
+            let doc = format!("Decode element {}, holding a {}.", idx, kind);
+            quote! {
+                #[doc = #doc]
+                pub const fn #ident(inp: &<Self as restruct::Struct>::Unpacked) -> restruct::time::DateTime {
+                    restruct::time::DateTime::#decode(inp.#m)
+                }
+            }
+        });
+        quote! { #(#fns)* }
+    }
+
+    /// Functions converting elements holding scaled integers from and into engineering units
+    fn scales(&self) -> proc_macro2::TokenStream {
+        let native_types = self.modifier.native_types();
+        let fns = self.options.scales.iter().map(|(idx, scale)| {
+            let m = syn::Member::Unnamed((*idx).into());
+            let (get, set) = match &self.options.names {
+                Some(names) => (
+                    quote::format_ident!("{}_scaled", names[*idx]),
+                    quote::format_ident!("set_{}_scaled", names[*idx]),
+                ),
+                None => (
+                    quote::format_ident!("scaled_{}", idx),
+                    quote::format_ident!("set_scaled_{}", idx),
+                ),
+            };
+            let (_, f, _) = self.materialized_elements().nth(*idx).unwrap();
+            let tipe = f.tipe(native_types);
+            let factor = proc_macro2::Literal::f64_suffixed(scale.factor);
+            let offset = proc_macro2::Literal::f64_suffixed(scale.offset);
+            let get_doc = format!(
+                "Element {} in engineering units, as `raw * {} + {}`.",
+                idx, scale.factor, scale.offset
+            );
+            let set_doc = format!(
+                "Set element {} from a value in engineering units, rounding to the nearest raw value. Values out of the element's range saturate.",
+                idx
+            );
+            quote! {
+                #[doc = #get_doc]
+                pub const fn #get(inp: &<Self as restruct::Struct>::Unpacked) -> f64 {
+                    inp.#m as f64 * #factor + #offset
+                }
+
+                #[doc = #set_doc]
+                pub fn #set(inp: &mut <Self as restruct::Struct>::Unpacked, value: f64) {
+                    inp.#m = ((value - #offset) / #factor).round() as #tipe;
+                }
+            }
+        });
+        quote! { #(#fns)* }
+    }
+
+    /// Functions reading and writing elements holding padded text, as given via the
+    /// `text`-attribute
+    fn texts(&self) -> proc_macro2::TokenStream {
+        let fns = self.options.texts.iter().map(|(idx, padding)| {
+            let m = syn::Member::Unnamed((*idx).into());
+            let (get, set, name) = match &self.options.names {
+                Some(names) => {
+                    let name = names[*idx].to_string();
+                    (
+                        quote::format_ident!("{}_str", names[*idx]),
+                        quote::format_ident!("set_{}_str", names[*idx]),
+                        quote! { Some(#name) },
+                    )
+                }
+                None => (
+                    quote::format_ident!("str_{}", idx),
+                    quote::format_ident!("set_str_{}", idx),
+                    quote! { None },
+                ),
+            };
+            let (_, f, n) = self.materialized_elements().nth(*idx).unwrap();
+            let offset = f.element_offset_expr(n);
+            let tipe = type_name(&f.tipe(self.modifier.native_types()));
+            let (pad, kind) = match padding {
+                Padding::Nul => (quote! { 0u8 }, "NUL"),
+                Padding::Space => (quote! { b' ' }, "space"),
+            };
+            let get_doc = format!(
+                "Element {} as text, without the trailing {}-padding; an error if it is not valid UTF-8.",
+                idx, kind
+            );
+            let set_doc = format!(
+                "Set element {} to the given text, {}-padded; an error if it does not fit.",
+                idx, kind
+            );
+            quote! {
+                #[doc = #get_doc]
+                pub fn #get(inp: &<Self as restruct::Struct>::Unpacked) -> Result<&str, std::str::Utf8Error> {
+                    restruct::rt::text(&inp.#m, #pad)
+                }
+
+                #[doc = #set_doc]
+                pub fn #set(inp: &mut <Self as restruct::Struct>::Unpacked, value: &str) -> Result<(), restruct::PackError> {
+                    if restruct::rt::set_text(&mut inp.#m, value, #pad) {
+                        Ok(())
+                    } else {
+                        Err(restruct::PackError {
+                            index: #idx,
+                            name: #name,
+                            offset: #offset,
+                            tipe: #tipe,
+                        })
+                    }
+                }
+            }
+        });
+        quote! { #(#fns)* }
+    }
+
+    /// Functions reading and writing the packed representation followed by variable-length data,
+    /// if the `frame`-attribute is given
+    fn frame(&self) -> Option<proc_macro2::TokenStream> {
+        let (Frame::Length(idx) | Frame::Count(idx)) = self.options.frame?;
+        let m = syn::Member::Unnamed(idx.into());
+        let (_, f, _) = self.materialized_elements().nth(idx).unwrap();
+        let tipe = f.tipe(self.modifier.native_types());
+        let set_len = quote! {
+            inp.#m = <#tipe as std::convert::TryFrom<usize>>::try_from(len).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("a length of {} does not fit element {}", len, #idx),
+                )
+            })?;
+        };
+        let get_len = quote! {
+            let len = <usize as std::convert::TryFrom<#tipe>>::try_from(inp.#m)
+                .map_err(|_| restruct::Error::Invalid(format!("element {} holds an invalid length", #idx)))?;
+        };
+        Some(match self.options.frame? {
+            Frame::Length(_) => quote! {
+                /// Write the given input followed by the payload, setting the element holding
+                /// the payload's length.
+                pub fn write_with_payload<W: std::io::Write>(mut inp: <Self as restruct::Struct>::Unpacked, payload: &[u8], w: &mut W) -> std::io::Result<()> {
+                    let len = payload.len();
+                    #set_len
+                    w.write_all(&Self::pack(inp))?;
+                    w.write_all(payload)
+                }
+
+                /// Read the packed representation, followed by as many bytes of payload as given
+                /// by the element holding the payload's length.
+                pub fn read_with_payload<R: std::io::Read>(r: &mut R) -> Result<(<Self as restruct::Struct>::Unpacked, Vec<u8>), restruct::Error> {
+                    let inp = Self::read_from(r)?;
+                    #get_len
+                    Ok((inp, restruct::rt::read_payload(r, len)?))
+                }
+            },
+            Frame::Count(_) => quote! {
+                /// Write the given input followed by the packed records, setting the element
+                /// holding the number of records.
+                pub fn write_with_records<Rec: restruct::Struct, W: std::io::Write>(mut inp: <Self as restruct::Struct>::Unpacked, records: &[Rec::Unpacked], w: &mut W) -> std::io::Result<()> {
+                    let len = records.len();
+                    #set_len
+                    w.write_all(&Self::pack(inp))?;
+                    records.iter().try_for_each(|rec| w.write_all(Rec::pack_ref(rec).as_ref()))
+                }
+
+                /// Read the packed representation, followed by as many records as given by the
+                /// element holding the number of records.
+                pub fn read_with_records<Rec: restruct::Struct, R: std::io::Read>(r: &mut R) -> Result<(<Self as restruct::Struct>::Unpacked, Vec<Rec::Unpacked>), restruct::Error> {
+                    let inp = Self::read_from(r)?;
+                    #get_len
+                    let size = len.checked_mul(Rec::SIZE).ok_or_else(|| {
+                        restruct::Error::Invalid(format!("element {} holds an invalid length", #idx))
+                    })?;
+                    let buf = restruct::rt::read_payload(r, size)?;
+                    Ok((inp, buf.chunks_exact(Rec::SIZE.max(1)).take(len).map(Rec::unpack_slice).collect()))
+                }
+            },
+        })
+    }
+
+    /// The `const DEFAULT`-item, with all elements zeroed unless given a value via attributes
+    fn default_const(&self) -> syn::ItemConst {
+        if let Some(ref path) = self.options.init_from {
+            return syn::parse_quote! {
+                /// The value unpacked from the file given via the `init_from`-attribute.
+                pub const DEFAULT: <Self as restruct::Struct>::Unpacked =
+                    Self::unpack(restruct::rt::read_field(include_bytes!(#path), 0));
+            };
+        }
+        let assignments = self.options.defaults.iter().map(|(idx, value)| {
+            let m = syn::Member::Unnamed((*idx).into());
+            quote! { res.#m = #value; }
+        });
+        syn::parse_quote! {
+            /// A value where all elements are zero, except for those given via the
+            /// `default`-attribute.
+            pub const DEFAULT: <Self as restruct::Struct>::Unpacked = {
+                #[allow(unused_mut)]
+                let mut res = Self::zeroed();
+                #(#assignments)*
+                res
+            };
+        }
+    }
+
+    /// A compile-time check that the file given via the `init_from`-attribute holds exactly the
+    /// packed representation
+    fn init_from_check(&self) -> Option<proc_macro2::TokenStream> {
+        let path = self.options.init_from.as_ref()?;
+        let name = &self.name;
+        let msg = format!(
+            "init_from attribute: `{}` must hold exactly `{}::SIZE` bytes.",
+            path, name
+        );
+        Some(quote! {
+            const _: () = assert!(include_bytes!(#path).len() == #name::SIZE, #msg);
+        })
+    }
+
+    /// Problems with the portability of the layout, e.g. native mode being used for IO
+    fn portability_problems(&self) -> Vec<String> {
+        let mut res = Vec::new();
+        if self.modifier.native_types() {
+            res.push(format!(
+                "`{}` uses native mode (`{}`), so it's packed representation depends on the platform, yet `read_from()` and `write_to()` exchange it via IO; use one of `=`, `<`, `>` or `!` for data crossing platforms.",
+                self.name, self.modifier
+            ));
+        } else if self
+            .fields
+            .iter()
+            .any(|f| matches!(f.fmt, Format::Size | Format::USize))
+        {
+            res.push(format!(
+                "`{}` uses `n` or `N`, whose size depends on the platform even in standard mode; use `q` or `Q` instead.",
+                self.name
+            ));
+        }
+        res
+    }
+
+    /// Compile-time checks that elements resolve to the types ascribed in the Format String,
+    /// e.g. that `c_ulong` is `u64` given `l:u64`
+    fn ascription_checks(&self) -> proc_macro2::TokenStream {
+        let native_types = self.modifier.native_types();
+        let checks = self.fields.iter().filter_map(|f| {
+            let ascription = f.ascription.as_ref()?;
+            let tipe = f.tipe(native_types);
+            Some(quote! {
+                const _: () = {
+                    let _: Option<#ascription> = None::<#tipe>;
+                };
+            })
+        });
+        quote! { #(#checks)* }
+    }
+
+    /// A `#[test]` for each test vector, asserting that the values pack into the given bytes and
+    /// that the bytes unpack into the given values
+    fn test_vectors(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let tests = self
+            .options
+            .test_vectors
+            .iter()
+            .enumerate()
+            .map(|(i, (bytes, values))| {
+                let ident = quote::format_ident!(
+                    "{}_test_vector_{}",
+                    crate::export::snake_case(&name.to_string()),
+                    i
+                );
+                let message = format!(
+                    "test vector {} of `{}` has {} bytes, but `{}::SIZE` is {{}}",
+                    i,
+                    name,
+                    bytes.len(),
+                    name
+                );
+                quote! {
+                    #[cfg(test)]
+                    #[test]
+                    fn #ident() {
+                        const BYTES: &[u8] = &[#(#bytes),*];
+                        assert_eq!(BYTES.len(), #name::SIZE, #message, #name::SIZE);
+                        assert_eq!(#name::pack(#values)[..], *BYTES, "packing");
+                        assert_eq!(#name::unpack_slice(BYTES), #values, "unpacking");
+                    }
+                }
+            });
+        quote! { #(#tests)* }
+    }
+
+    /// Warnings about portability problems, emitted as deprecation-warnings as there is no other
+    /// way for a proc-macro to warn on stable Rust
+    fn portability_warnings(&self) -> Option<proc_macro2::TokenStream> {
+        if self.options.portability != Portability::Warn {
+            return None;
+        }
+        let warnings = self.portability_problems().into_iter().map(|problem| {
+            let note = format!(
+                "{} Use `#[portability = \"allow\"]` to silence this.",
+                problem
+            );
+            let ident = syn::Ident::new("not_portable", self.name.span());
+            quote! {
+                #[allow(non_camel_case_types, dead_code)]
+                const _: () = {
+                    #[deprecated(note = #note)]
+                    struct #ident;
+                    let _ = #ident;
+                };
+            }
+        });
+        Some(quote! { #(#warnings)* })
+    }
+
+    /// Implementations of user-defined codec-traits, as per `impl_codec`-attribute
+    fn codecs(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let impls = self.options.codecs.iter().map(|codec| {
+            let Codec {
+                path,
+                encode,
+                decode,
+            } = codec;
+            quote! {
+                impl #path for #name {
+                    type Value = <#name as restruct::Struct>::Unpacked;
+                    type Error = restruct::UnpackError;
+
+                    fn #encode(value: &Self::Value, out: &mut Vec<u8>) {
+                        out.extend_from_slice(&#name::pack_ref(value));
+                    }
+
+                    fn #decode(inp: &[u8]) -> Result<(Self::Value, &[u8]), Self::Error> {
+                        #name::parse(inp)
+                    }
+                }
+            }
+        });
+        quote! { #(#impls)* }
+    }
+
+    /// The visibility of generated types. When generating into a module, this is widened by one
+    /// level, so they are as visible as the type itself.
+    fn vis(&self) -> syn::Visibility {
+        if self.options.module.is_none() {
+            return self.options.vis.clone();
+        }
+        match &self.options.vis {
+            syn::Visibility::Inherited => syn::parse_quote! { pub(super) },
+            syn::Visibility::Restricted(r) if r.path.is_ident("self") => {
+                syn::parse_quote! { pub(super) }
+            }
+            syn::Visibility::Restricted(r) if r.path.is_ident("super") => {
+                syn::parse_quote! { pub(in super::super) }
+            }
+            vis => vis.clone(),
+        }
+    }
+
+    /// Wrap the generated items into the given module, which holds a unit-struct of the same
+    /// name carrying them. The type itself only implements `restruct::Struct`, delegating to it.
+    fn module(
+        &self,
+        module: &syn::Ident,
+        items: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let vis = &self.options.vis;
+        let inner_vis = self.vis();
+        let inline = self.options.inline;
+        let mod_doc = format!(
+            "The generated constants, functions and types of [`{}`].",
+            name
+        );
+        let doc = format!(
+            "Packing and unpacking of [`super::{}`], as described by `\"{}\"`.",
+            name, self.format
+        );
+        quote! {
+            #[doc = #mod_doc]
+            #vis mod #module {
+                #[allow(unused_imports)]
+                use super::*;
+
+                #[doc = #doc]
+                #inner_vis struct #name;
+
+                #items
+            }
+
+            impl restruct::Struct for #name {
+                type Packed = <#module::#name as restruct::Struct>::Packed;
+                type Unpacked = <#module::#name as restruct::Struct>::Unpacked;
+                const SIZE: usize = <#module::#name as restruct::Struct>::SIZE;
+
+                #inline
+                fn unpack_slice(inp: &[u8]) -> Self::Unpacked {
+                    <#module::#name as restruct::Struct>::unpack_slice(inp)
+                }
+
+                #inline
+                fn pack_ref(inp: &Self::Unpacked) -> Self::Packed {
+                    <#module::#name as restruct::Struct>::pack_ref(inp)
+                }
+            }
+        }
+    }
+
+    /// The builder-type, having a setter for each named element, if the `builder`-option is given
+    fn builder(&self) -> Option<proc_macro2::TokenStream> {
+        if !self.options.builder {
+            return None;
+        }
+        let names = self
+            .options
+            .names
+            .as_ref()
+            .expect("restruct attribute: `builder` requires the elements to be named.");
+        let name = &self.name;
+        let vis = &self.vis();
+        let builder = quote::format_ident!("{}Builder", name);
+        let types = self
+            .materialized_elements()
+            .map(|(_, f, _)| f.tipe(self.modifier.native_types()));
+        let setters = names.iter().zip(types).enumerate().map(|(i, (n, tipe))| {
+            let m = syn::Member::Unnamed(i.into());
+            let doc = format!("Set `{}`, element {} of the unpacked representation.", n, i);
+            quote! {
+                #[doc = #doc]
+                pub const fn #n(mut self, value: #tipe) -> Self {
+                    self.0.#m = value;
+                    self
+                }
+            }
+        });
+        let doc = format!(
+            "A builder for the unpacked representation of [`{}`], starting from `{}::DEFAULT`.",
+            name, name
+        );
+        // The standard library implements `Debug` and `PartialEq` for tuples of up to twelve
+        // elements only
+        let (always, always_derives) = if names.len() <= 12 {
+            (
+                &["Clone", "Copy", "Debug", "PartialEq"][..],
+                quote! { #[derive(Clone, Copy, Debug, PartialEq)] },
+            )
+        } else {
+            (&["Clone", "Copy"][..], quote! { #[derive(Clone, Copy)] })
+        };
+        let derives = self.derives(always);
+        Some(quote! {
+            #[doc = #doc]
+            #always_derives
+            #derives
+            #vis struct #builder(<#name as restruct::Struct>::Unpacked);
+
+            impl #builder {
+                #(#setters)*
+
+                /// Return the unpacked representation.
+                pub const fn build(self) -> <#name as restruct::Struct>::Unpacked {
+                    self.0
+                }
+
+                /// Pack the unpacked representation.
+                pub const fn pack(self) -> <#name as restruct::Struct>::Packed {
+                    #name::pack(self.0)
+                }
+            }
+
+            impl #name {
+                /// Start building an unpacked value from `DEFAULT`.
+                pub const fn builder() -> #builder {
+                    #builder(Self::DEFAULT)
+                }
+            }
+        })
+    }
+
+    /// The `derive`-attribute for a generated type, leaving out the traits it always derives
+    fn derives(&self, always: &[&str]) -> Option<proc_macro2::TokenStream> {
+        let derives = self
+            .options
+            .derives
+            .iter()
+            .filter(|p| {
+                p.segments
+                    .last()
+                    .is_none_or(|s| !always.iter().any(|a| s.ident == a))
+            })
+            .collect::<Vec<_>>();
+        if derives.is_empty() {
+            return None;
+        }
+        Some(quote! { #[derive(#(#derives),*)] })
+    }
+
+    /// A newtype around the packed representation, implementing the standard conversion traits
+    /// from/to slices, arrays and the unpacked representation.
+    fn packed_wrapper(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let vis = &self.vis();
+        let wrapper = quote::format_ident!("{}Packed", name);
+        let doc = format!(
+            "The packed representation of [`{}`], implementing `TryFrom<&[u8]>` and `From` the unpacked representation.",
+            name
+        );
+        let derives = self.derives(&["Clone", "Copy", "Debug", "PartialEq", "Eq", "Hash"]);
+        let accessors = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(i, (fi, f, _))| {
+                let (getter, setter) = match self.options.names {
+                    Some(ref names) => (
+                        names[i].clone(),
+                        quote::format_ident!("set_{}", names[i]),
+                    ),
+                    None => (
+                        quote::format_ident!("get_{}", i),
+                        quote::format_ident!("set_{}", i),
+                    ),
+                };
+                let tipe = f.tipe(self.modifier.native_types());
+                let unpack_expr = f.unpack_expr(
+                    &self.modifier,
+                    &syn::parse_quote! {
+                        restruct::rt::read_field::<{ #name::FIELDS[#fi].3 - #name::FIELDS[#fi].2 }>(&self.0, offset + alignment)
+                    },
+                );
+                let pack_expr = f.pack_expr(&self.modifier, &syn::parse_quote! { value });
+                let doc = format!("Unpack element {} of the unpacked representation.", i);
+                let set_doc = format!("Pack element {} of the unpacked representation in place.", i);
+                quote! {
+                    #[doc = #doc]
+                    pub const fn #getter(&self) -> #tipe {
+                        let (_, offset, alignment, _) = #name::FIELDS[#fi];
+                        #unpack_expr
+                    }
+
+                    #[doc = #set_doc]
+                    pub const fn #setter(&mut self, value: #tipe) {
+                        let (_, offset, alignment, _) = #name::FIELDS[#fi];
+                        self.0 = restruct::rt::write_field(self.0, offset + alignment, #pack_expr);
+                    }
+                }
+            });
+        quote! {
+            #[doc = #doc]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            #derives
+            #vis struct #wrapper(pub <#name as restruct::Struct>::Packed);
+
+            impl #wrapper {
+                /// Pack the given input.
+                pub const fn new(inp: <#name as restruct::Struct>::Unpacked) -> Self {
+                    Self(#name::pack(inp))
+                }
+
+                /// The packed representation.
+                pub const fn as_bytes(&self) -> &<#name as restruct::Struct>::Packed {
+                    &self.0
+                }
+
+                /// Unpack all elements.
+                pub const fn unpack(self) -> <#name as restruct::Struct>::Unpacked {
+                    #name::unpack(self.0)
+                }
+
+                #(#accessors)*
+            }
+
+            impl std::convert::TryFrom<&[u8]> for #wrapper {
+                type Error = std::array::TryFromSliceError;
+
+                /// Copy a slice of exactly `SIZE` bytes.
+                fn try_from(inp: &[u8]) -> Result<Self, Self::Error> {
+                    std::convert::TryFrom::try_from(inp).map(Self)
+                }
+            }
+
+            impl From<<#name as restruct::Struct>::Packed> for #wrapper {
+                fn from(inp: <#name as restruct::Struct>::Packed) -> Self {
+                    Self(inp)
+                }
+            }
+
+            impl From<#wrapper> for <#name as restruct::Struct>::Packed {
+                fn from(inp: #wrapper) -> Self {
+                    inp.0
+                }
+            }
+
+            impl From<<#name as restruct::Struct>::Unpacked> for #wrapper {
+                fn from(inp: <#name as restruct::Struct>::Unpacked) -> Self {
+                    Self(#name::pack(inp))
+                }
+            }
+
+            impl From<#wrapper> for <#name as restruct::Struct>::Unpacked {
+                fn from(inp: #wrapper) -> Self {
+                    #name::unpack(inp.0)
+                }
+            }
+
+            impl AsRef<[u8]> for #wrapper {
+                fn as_ref(&self) -> &[u8] {
+                    &self.0
+                }
+            }
+        }
+    }
+
+    /// Conversions between the unpacked representation and user-defined structs. Elements are
+    /// mapped to fields by name if names were given, to fields of a tuple-struct otherwise.
+    fn map_to(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let members = (0..self.materialized_elements().count())
+            .map(|i| syn::Member::Unnamed(i.into()))
+            .collect::<Vec<_>>();
+        let fields = match self.options.names {
+            Some(ref names) => names.iter().cloned().map(syn::Member::Named).collect(),
+            None => members.clone(),
+        };
+        let mut res = proc_macro2::TokenStream::new();
+        for target in &self.options.map_to {
+            res.extend(quote! {
+                impl From<<#name as restruct::Struct>::Unpacked> for #target {
+                    fn from(inp: <#name as restruct::Struct>::Unpacked) -> Self {
+                        Self { #(#fields: inp.#members),* }
+                    }
+                }
+
+                impl From<#target> for <#name as restruct::Struct>::Unpacked {
+                    fn from(inp: #target) -> Self {
+                        (#(inp.#fields,)*)
+                    }
+                }
+            });
+        }
+        if !self.options.map_to.is_empty() {
+            res.extend(quote! {
+                impl #name {
+                    /// Pack the given struct, converting it into the unpacked representation.
+                    pub fn pack_struct<T: Into<<Self as restruct::Struct>::Unpacked>>(inp: T) -> <Self as restruct::Struct>::Packed {
+                        Self::pack(inp.into())
+                    }
+
+                    /// Unpack the given buffer, converting the unpacked representation into a struct.
+                    pub fn unpack_struct<T: From<<Self as restruct::Struct>::Unpacked>>(inp: <Self as restruct::Struct>::Packed) -> T {
+                        T::from(Self::unpack(inp))
+                    }
+                }
+            });
+        }
+        res
+    }
+
+    /// The upgrade-methods, converting the unpacked representation of older types by name
+    fn upgrade_from(&self) -> proc_macro2::TokenStream {
+        let names = match self.options.names {
+            Some(ref names) => names,
+            None => return quote! {},
+        };
+        let fns = self.options.upgrade_from.iter().map(|(old, old_names)| {
+            let old_ident = &old.segments.last().unwrap().ident;
+            let ident = quote::format_ident!(
+                "upgrade_from_{}",
+                crate::export::snake_case(&old_ident.to_string())
+            );
+            let assignments = old_names.iter().enumerate().filter_map(|(i, old_name)| {
+                let idx = names.iter().position(|n| n == old_name)?;
+                let m = syn::Member::Unnamed(idx.into());
+                let old_m = syn::Member::Unnamed(i.into());
+                Some(quote! { res.#m = inp.#old_m; })
+            });
+            let doc = format!(
+                "Upgrade the unpacked representation of [`{}`], taking elements it does not have from `defaults`, e.g. `Self::DEFAULT`.",
+                quote!(#old).to_string().replace(' ', "")
+            );
+            quote! {
+                #[doc = #doc]
+                pub const fn #ident(inp: <#old as restruct::Struct>::Unpacked, defaults: <Self as restruct::Struct>::Unpacked) -> <Self as restruct::Struct>::Unpacked {
+                    let mut res = defaults;
+                    #(#assignments)*
+                    res
+                }
+            }
+        });
+        quote! { #(#fns)* }
+    }
+
+    /// The canonicalize-method, zeroing all padding- and alignment-bytes
+    fn canonicalize(&self) -> syn::ItemFn {
+        let copies = self.materialized_elements().map(|(_, f, n)| {
+            let element_size = f.element_size_expr();
+            let offset = f.element_offset_expr(n);
+            let read: syn::Expr = syn::parse_quote! {
+                restruct::rt::read_field::<{ #element_size }>(&inp, #offset)
+            };
+            let value = match f.fmt {
+                Format::Ident(ref ident) => quote! { #ident::canonicalize(#read) },
+                _ => quote! { #read },
+            };
+            quote! {
+                buf = restruct::rt::write_field(buf, #offset, #value);
+            }
+        });
+        syn::parse_quote! {
+            /// Set all padding- and alignment-bytes in the given buffer to zero, leaving the bytes
+            /// of all values untouched.
+            pub const fn canonicalize(inp: <Self as restruct::Struct>::Packed) -> <Self as restruct::Struct>::Packed {
+                let mut buf = [0u8; Self::SIZE];
+                #(#copies)*
+                buf
+            }
+        }
+    }
+
+    /// The packed_eq-method, comparing two packed buffers while ignoring padding- and
+    /// alignment-bytes
+    fn packed_eq(&self) -> syn::ItemFn {
+        let comparisons = self.materialized_elements().map(|(_, f, n)| {
+            let element_size = f.element_size_expr();
+            let offset = f.element_offset_expr(n);
+            match f.fmt {
+                Format::Ident(ref ident) => quote! {
+                    #ident::packed_eq(
+                        &restruct::rt::read_field::<{ #element_size }>(a, #offset),
+                        &restruct::rt::read_field::<{ #element_size }>(b, #offset),
+                    )
+                },
+                _ => quote! {
+                    restruct::rt::range_eq(a, b, #offset, #element_size)
+                },
+            }
+        });
+        syn::parse_quote! {
+            /// Compare two packed buffers, considering only the bytes of values; padding- and
+            /// alignment-bytes are ignored.
+            pub const fn packed_eq(a: &<Self as restruct::Struct>::Packed, b: &<Self as restruct::Struct>::Packed) -> bool {
+                true #(&& #comparisons)*
+            }
+        }
+    }
+
+    /// The cmp_packed-method, ordering two packed buffers by the bytes of their values
+    fn cmp_packed(&self) -> syn::ItemFn {
+        let comparisons = self.materialized_elements().map(|(_, f, n)| {
+            let element_size = f.element_size_expr();
+            let offset = f.element_offset_expr(n);
+            let cmp = match f.fmt {
+                Format::Ident(ref ident) => quote! {
+                    #ident::cmp_packed(
+                        &restruct::rt::read_field::<{ #element_size }>(a, #offset),
+                        &restruct::rt::read_field::<{ #element_size }>(b, #offset),
+                    )
+                },
+                _ => quote! {
+                    restruct::rt::range_cmp(a, b, #offset, #element_size)
+                },
+            };
+            quote! {
+                match #cmp {
+                    core::cmp::Ordering::Equal => {}
+                    ord => return ord,
+                }
+            }
+        });
+        syn::parse_quote! {
+            /// Order two packed buffers by comparing the bytes of each value in turn; padding- and
+            /// alignment-bytes are ignored. This is the order of the values themselves if all
+            /// numbers are big-endian and unsigned, which allows sorting packed records without
+            /// unpacking them.
+            pub const fn cmp_packed(a: &<Self as restruct::Struct>::Packed, b: &<Self as restruct::Struct>::Packed) -> core::cmp::Ordering {
+                #(#comparisons)*
+                core::cmp::Ordering::Equal
+            }
+        }
+    }
+
+    /// The display-method, rendering each value of the unpacked representation
+    fn display(&self) -> syn::ItemFn {
+        let name = &self.name;
+        let lines = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (i, _, _))| {
+                let m = syn::Member::Unnamed(mi.into());
+                quote! {
+                    writeln!(
+                        f,
+                        "    {} ({} @ {}): {:?}",
+                        #mi,
+                        Self::FIELDS[#i].0,
+                        Self::FIELDS[#i].1 + Self::FIELDS[#i].2,
+                        inp.#m
+                    )?;
+                }
+            });
+        syn::parse_quote! {
+            /// Render the given unpacked value, labeling each element with its index, type and
+            /// offset in the packed representation.
+            pub fn display(inp: &<Self as restruct::Struct>::Unpacked) -> impl std::fmt::Display + '_ {
+                restruct::FnDisplay(move |f: &mut std::fmt::Formatter| {
+                    writeln!(f, "{} {{", stringify!(#name))?;
+                    #(#lines)*
+                    write!(f, "}}")
                 })
             }
         }
+    }
+
+    /// The implementation of `restruct::csv::CsvRecord`, flattening nested types
+    fn csv_record(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let names = self.options.names.as_deref().unwrap_or_default();
+        let (headers, values): (Vec<_>, Vec<_>) = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (_, f, _))| {
+                let m = syn::Member::Unnamed(mi.into());
+                let column = match names.get(mi) {
+                    Some(name) => name.to_string(),
+                    None => format!("field_{}", mi),
+                };
+                match f.fmt {
+                    Format::Ident(ref ident) => (
+                        quote! {
+                            <#ident as restruct::csv::CsvRecord>::csv_header(&format!("{}{}.", prefix, #column), out);
+                        },
+                        quote! {
+                            <#ident as restruct::csv::CsvRecord>::csv_values(&inp.#m, out);
+                        },
+                    ),
+                    _ => (
+                        quote! { out.push(format!("{}{}", prefix, #column)); },
+                        quote! { out.push(restruct::Value::from(inp.#m)); },
+                    ),
+                }
+            })
+            .unzip();
+        quote! {
+            impl restruct::csv::CsvRecord for #name {
+                fn csv_header(prefix: &str, out: &mut Vec<String>) {
+                    let _ = (prefix, &out);
+                    #(#headers)*
+                }
+
+                fn csv_values(inp: &Self::Unpacked, out: &mut Vec<restruct::Value>) {
+                    let _ = (inp, &out);
+                    #(#values)*
+                }
+            }
+        }
+    }
+
+    /// The fields_of-method, pairing each entry in `FIELDS` with it's bytes
+    fn fields_of(&self) -> syn::ItemFn {
+        let mut names = self.options.names.iter().flatten().map(|n| n.to_string());
+        let mut index = 0usize;
+        let infos = self
+            .elements()
+            .map(|(i, f, _)| {
+                let (index, name) = if f.materialize {
+                    let mi = index;
+                    index += 1;
+                    let name = match names.next() {
+                        Some(name) => quote! { Some(#name) },
+                        None => quote! { None },
+                    };
+                    (quote! { Some(#mi) }, name)
+                } else {
+                    (quote! { None }, quote! { None })
+                };
+                quote! {
+                    restruct::FieldInfo {
+                        index: #index,
+                        name: #name,
+                        tipe: Self::FIELDS[#i].0,
+                        offset: Self::FIELDS[#i].1 + Self::FIELDS[#i].2,
+                        size: Self::FIELDS[#i].3 - Self::FIELDS[#i].2,
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let n = infos.len();
+        syn::parse_quote! {
+            /// Iterate over the entries in `FIELDS`, including padding, pairing each entry's
+            /// metadata with it's bytes in the given buffer.
+            pub fn fields_of(inp: &<Self as restruct::Struct>::Packed) -> impl Iterator<Item = (restruct::FieldInfo, &[u8])> + '_ {
+                let infos: [restruct::FieldInfo; #n] = [#(#infos),*];
+                std::iter::IntoIterator::into_iter(infos).map(move |info| (info, &inp[info.offset..info.offset + info.size]))
+            }
+        }
+    }
+
+    /// The raw_field-methods, giving access to the bytes of an entry in `FIELDS` by index
+    fn raw_field(&self) -> proc_macro2::TokenStream {
+        quote! {
+            /// The bytes of the `i`th entry in `FIELDS`, excluding alignment, or `None` if there
+            /// is no such entry.
+            pub fn raw_field(inp: &<Self as restruct::Struct>::Packed, i: usize) -> Option<&[u8]> {
+                let (_, offset, alignment, size) = *Self::FIELDS.get(i)?;
+                Some(&inp[offset + alignment..offset + size])
+            }
+
+            /// The same as `raw_field()`, allowing to patch the bytes in place.
+            pub fn raw_field_mut(inp: &mut <Self as restruct::Struct>::Packed, i: usize) -> Option<&mut [u8]> {
+                let (_, offset, alignment, size) = *Self::FIELDS.get(i)?;
+                Some(&mut inp[offset + alignment..offset + size])
+            }
+        }
+    }
+
+    /// The binary_search_by_field-method, searching sorted packed records by an entry in `FIELDS`
+    fn binary_search_by_field(&self) -> syn::ItemFn {
+        syn::parse_quote! {
+            /// Binary search a buffer of consecutive packed records, sorted by the bytes of the
+            /// `FIELD`th entry in `FIELDS`, for a record whose bytes in that entry equal `key`.
+            /// The result is that of `slice::binary_search()`, counted in records; a trailing
+            /// partial record is ignored. Byte-wise order is the order of the values for
+            /// big-endian, unsigned numbers and byte-strings.
+            ///
+            /// # Panics
+            ///
+            /// If there is no `FIELD`th entry in `FIELDS`.
+            pub fn binary_search_by_field<const FIELD: usize>(records: &[u8], key: &[u8]) -> Result<usize, usize> {
+                let (_, offset, alignment, size) = Self::FIELDS[FIELD];
+                restruct::rt::binary_search_records(records, Self::SIZE, offset + alignment..offset + size, key)
+            }
+        }
+    }
+
+    /// The implementations of `restruct::Column`, one per element of the unpacked representation
+    fn columns(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let native_types = self.modifier.native_types();
+        let inline = self.options.inline;
+        let impls = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (_, f, n))| {
+                let tipe = f.tipe(native_types);
+                let element_size = f.element_size_expr();
+                let offset = f.element_offset_expr(n);
+                let access: syn::Expr = syn::parse_quote! {
+                    restruct::rt::read_field::<{ #element_size }>(inp, #offset)
+                };
+                let expr = f.unpack_expr(&self.modifier, &access);
+                quote! {
+                    impl restruct::Column<#mi> for #name {
+                        type Type = #tipe;
+
+                        #inline
+                        fn unpack_column(inp: &[u8]) -> Self::Type {
+                            #expr
+                        }
+                    }
+                }
+            });
+        quote! { #(#impls)* }
+    }
+
+    /// The extract_field- and iter_field-methods, decoding a single element of many records
+    fn extract_field(&self) -> proc_macro2::TokenStream {
+        quote! {
+            /// Decode the `I`th element of the unpacked representation from each of the
+            /// consecutive packed records in the given buffer, reading only that element's
+            /// bytes. A trailing partial record is ignored.
+            pub fn extract_field<const I: usize>(records: &[u8]) -> Vec<<Self as restruct::Column<I>>::Type>
+            where
+                Self: restruct::Column<I>,
+            {
+                Self::iter_field::<I>(records).collect()
+            }
+
+            /// Like `extract_field()`, decoding the element lazily.
+            pub fn iter_field<const I: usize>(records: &[u8]) -> impl ExactSizeIterator<Item = <Self as restruct::Column<I>>::Type> + '_
+            where
+                Self: restruct::Column<I>,
+            {
+                records
+                    .chunks_exact(<Self as restruct::Struct>::SIZE)
+                    .map(<Self as restruct::Column<I>>::unpack_column)
+            }
+        }
+    }
+
+    /// The value_of-method, decoding a single element of the unpacked representation by index
+    fn value_of(&self) -> syn::ItemFn {
+        let arms = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (_, f, n))| {
+                let element_size = f.element_size_expr();
+                let offset = f.element_offset_expr(n);
+                let access: syn::Expr = syn::parse_quote! {
+                    restruct::rt::read_field::<{ #element_size }>(inp, #offset)
+                };
+                let value = match f.fmt {
+                    // Nested types have no representation as a `Value`
+                    Format::Ident(_) => quote! { restruct::Value::Bytes(#access.to_vec()) },
+                    _ => {
+                        let expr = f.unpack_expr(&self.modifier, &access);
+                        quote! { restruct::Value::from(#expr) }
+                    }
+                };
+                quote! { #mi => Some(#value), }
+            });
+        syn::parse_quote! {
+            /// Decode the `i`th element of the unpacked representation from the given buffer, or
+            /// `None` if there is no such element. Nested types are returned as bytes.
+            pub fn value_of(inp: &<Self as restruct::Struct>::Packed, i: usize) -> Option<restruct::Value> {
+                match i {
+                    #(#arms)*
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// The diff-method, comparing two unpacked values element by element
+    fn diff(&self) -> syn::ItemFn {
+        let checks = self
+            .materialized_elements()
+            .enumerate()
+            .map(|(mi, (i, _, _))| {
+                let m = syn::Member::Unnamed(mi.into());
+                quote! {
+                    if a.#m != b.#m {
+                        diffs.push(restruct::FieldDiff {
+                            index: #mi,
+                            tipe: Self::FIELDS[#i].0,
+                            offset: Self::FIELDS[#i].1 + Self::FIELDS[#i].2,
+                            left: format!("{:?}", a.#m),
+                            right: format!("{:?}", b.#m),
+                        });
+                    }
+                }
+            });
+        syn::parse_quote! {
+            /// Compare two unpacked values, returning the elements which differ.
+            pub fn diff(a: &<Self as restruct::Struct>::Unpacked, b: &<Self as restruct::Struct>::Unpacked) -> Vec<restruct::FieldDiff> {
+                let mut diffs = Vec::new();
+                #(#checks)*
+                diffs
+            }
+        }
+    }
+
+    /// An expression resolving to the alignment required by the given field
+    fn align_expr(&self, f: &Field) -> proc_macro2::TokenStream {
+        if self.modifier.aligned() {
+            let tipe = f.tipe(true);
+            match self.options.max_align {
+                Some(max) => quote! {
+                    { let a = std::mem::align_of::<#tipe>(); if a < #max { a } else { #max } }
+                },
+                None => quote! { std::mem::align_of::<#tipe>() },
+            }
+        } else {
+            quote! { 1 }
+        }
+    }
+
+    /// The `const ALIGN: usize`-item resolving to the largest alignment required by any field
+    fn align(&self) -> syn::ItemConst {
+        let aligns = self.fields.iter().map(|f| self.align_expr(f));
+        syn::parse_quote! {
+            /// The largest alignment required by any field, `1` unless in native mode. A pointer
+            /// to a C-struct described by the Format String is aligned to this.
+            pub const ALIGN: usize = restruct::rt::max_alignment([#(#aligns),*]);
+        }
+    }
+
+    /// The `const SIZE: usize`-item resolving to the size of the packed buffer
+    fn size(&self) -> syn::ItemConst {
+        let size: syn::Expr = self.fields.last().map_or_else(
+            || syn::parse_quote! { 0 },
+            |f| {
+                let o_id = f.offset_ident();
+                let s_id = f.size_ident();
+                syn::parse_quote! {
+                    Self::#o_id + Self::#s_id
+                }
+            },
+        );
+        let doc = format!(
+            "The size in bytes of the packed representation.\n\n{}",
+            self.layout_doc()
+        );
+        syn::parse_quote! {
+            #[doc = #doc]
+            pub const SIZE: usize = #size;
+        }
+    }
 
-        if let Some(first_field) = self.fields.first() {
-            let o_id = first_field.offset_ident();
-            let a_id = first_field.align_ident();
-            let s_id = first_field.size_ident();
-            let s_expr = first_field.size_expr(&self.modifier);
-            push!(o_id, 0);
-            push!(a_id, 0);
-            push!(s_id, Self::#a_id + #s_expr);
+    /// The `const FIELDS: [...; n]`-item holding tuples describing each field
+    fn fields_array(&self) -> syn::ItemConst {
+        let elemens = self
+            .elements()
+            .map(|(_, f, n)| f.fields_ary_entry(&self.modifier, n))
+            .collect::<Vec<_>>();
+        let size = elemens.len();
+        syn::parse_quote! {
+            pub const FIELDS: [(&'static str, usize, usize, usize); #size] = [#(#elemens),*];
         }
+    }
 
-        for (prev, cur) in self.fields.iter().zip(self.fields.iter().skip(1)) {
-            let po_id = prev.offset_ident();
-            let ps_id = prev.size_ident();
-            let co_id = cur.offset_ident();
-            let ca_id = cur.align_ident();
-            let cs_id = cur.size_ident();
-            let cs_expr = cur.size_expr(&self.modifier);
-            let tipe = cur.tipe(self.modifier.native_types());
+    /// Generate all the required constants which will resolve to the offset, the alignment
+    /// and the total size of each field.
+    ///
+    /// The layout of all fields is computed in one go by `const fn layout`, yielding a
+    /// `const LAYOUT`-table; each field's constants simply index into that table. This avoids
+    /// long chains of constants depending on each other, which would otherwise hit the
+    /// compiler's recursion limits on large formats.
+    fn fields(&self) -> Vec<syn::ItemConst> {
+        let mut res = Vec::with_capacity(self.fields.len() * 3 + 1);
 
-            push!(co_id, Self::#po_id + Self::#ps_id);
+        macro_rules! push {
+            ($name:tt, $($tt:tt)*) => {
+                res.push(syn::parse_quote!{
+                    const #$name: usize = $($tt)*;
+                })
+            }
+        }
 
-            if self.modifier.native_types() {
-                push!(ca_id, Self::align::<#tipe>(Self::#co_id));
-            } else {
-                push!(ca_id, 0);
+        // Absolute offsets are only passed along if there are any, keeping the common case simple
+        let absolute = self.fields.iter().any(|f| f.at.is_some());
+        let entries = self.fields.iter().map(|f| {
+            let s_expr = f.total_size_expr(&self.modifier);
+            let a_expr = self.align_expr(f);
+            match f.at {
+                Some(at) if absolute => quote! { (#a_expr, #s_expr, Some(#at)) },
+                None if absolute => quote! { (#a_expr, #s_expr, None) },
+                _ => quote! { (#a_expr, #s_expr) },
             }
+        });
+        let len = self.fields.len();
+        let layout = if absolute {
+            quote! { layout_at }
+        } else {
+            quote! { layout }
+        };
+        res.push(syn::parse_quote! {
+            const LAYOUT: [(usize, usize, usize); #len] = restruct::rt::#layout([#(#entries),*]);
+        });
 
-            push!(cs_id, Self::#ca_id + #cs_expr);
+        for (i, f) in self.fields.iter().enumerate() {
+            let o_id = f.offset_ident();
+            let a_id = f.align_ident();
+            let s_id = f.size_ident();
+            push!(o_id, Self::LAYOUT[#i].0);
+            push!(a_id, Self::LAYOUT[#i].1);
+            push!(s_id, Self::LAYOUT[#i].2);
         }
 
         res
@@ -556,86 +3301,326 @@ impl quote::ToTokens for Compilation {
         let name = &self.name;
         let fields = self.fields();
         let size = self.size();
+        let align = self.align();
         let fields_ary = self.fields_array();
         let packed_type = self.packed_type();
         let unpacked_type = self.unpacked_type();
         let pack_fn = self.pack();
+        let try_pack_fn = self.try_pack();
         let unpack_fn = self.unpack();
+        let unpack_into_fn = self.unpack_into();
+        let volatile_fns = self.volatile();
+        let async_fns = self.async_io();
+        let wasm_fns = self.wasm();
+        let text_fns = self.text();
+        let checksum_fns = self.checksum();
+        let fuzz_fn = self.fuzz();
+        let unpack_all_fn = self.unpack_all();
+        let boxed_fns = self.boxed();
+        let inline = self.options.inline;
+        let cpython_fn = self.cpython_check();
+        let random_fn = self.random();
+        let layout_json = self.layout_json();
+        let markdown = self.markdown();
+        let packet_diagram = self.packet_diagram();
+        let default_const = self.default_const();
+        let init_from_check = self.init_from_check();
+        let portability_warnings = self.portability_warnings();
+        let ascription_checks = self.ascription_checks();
+        let test_vectors = self.test_vectors();
+        let registration = self.registry();
+        let timestamp_fns = self.timestamps();
+        let scale_fns = self.scales();
+        let padded_text_fns = self.texts();
+        let frame_fns = self.frame();
+        let validation_fns = self.validation();
+        let upgrade_fns = self.upgrade_from();
+        let canonicalize_fn = self.canonicalize();
+        let packed_eq_fn = self.packed_eq();
+        let cmp_packed_fn = self.cmp_packed();
+        let display_fn = self.display();
+        let diff_fn = self.diff();
+        let fields_of_fn = self.fields_of();
+        let raw_field_fns = self.raw_field();
+        let value_of_fn = self.value_of();
+        let binary_search_fn = self.binary_search_by_field();
+        let columns = self.options.columns.then(|| self.columns());
+        let extract_field = self.options.columns.then(|| self.extract_field());
         let debug_impl = self.debug_impl(&impl_generics, &ty_generics, where_clause);
+        let builder = self.builder();
+        let map_to = self.map_to();
+        let codecs = self.codecs();
+        let packed_wrapper = self.options.packed.then(|| self.packed_wrapper());
+        let csv_record = self.options.csv.then(|| self.csv_record());
 
         let res = quote! {
             impl restruct::Struct for #name {
                 type Packed = #packed_type;
                 type Unpacked = #unpacked_type;
+                const SIZE: usize = #name::SIZE;
+
+                #inline
+                fn unpack_slice(inp: &[u8]) -> Self::Unpacked {
+                    #name::unpack_slice(inp)
+                }
+
+                #inline
+                fn pack_ref(inp: &Self::Unpacked) -> Self::Packed {
+                    #name::pack_ref(inp)
+                }
             }
 
-            #[allow(clippy::transmute_int_to_float)]
+            #csv_record
+
+            #columns
+
+            #[allow(clippy::unused_unit)]
             impl #impl_generics #name #ty_generics #where_clause {
-                const fn align<T>(ptr: usize) -> usize {
-                    let align = std::mem::align_of::<T>();
-                    let offset = ptr % align;
-                    (offset != 0) as usize * (align - offset)
-                }
                 #(#fields)*
                 #size
+                #align
                 #fields_ary
+                #layout_json
+                #markdown
+                #packet_diagram
                 #pack_fn
+                #try_pack_fn
                 #unpack_fn
+                #unpack_into_fn
+                #checksum_fns
+
+                /// A value where all elements are zero (`false` for booleans).
+                pub const fn zeroed() -> <Self as restruct::Struct>::Unpacked {
+                    Self::unpack([0u8; Self::SIZE])
+                }
+                #default_const
+                #timestamp_fns
+                #scale_fns
+                #padded_text_fns
+                #upgrade_fns
+                #canonicalize_fn
+                #packed_eq_fn
+                #cmp_packed_fn
+                #display_fn
+                #diff_fn
+                #fields_of_fn
+                #raw_field_fns
+                #value_of_fn
+                #binary_search_fn
+                #extract_field
 
                 /// Unpack the bytes from the given slice.
                 ///
                 /// # Panics
                 ///
                 /// The function will panic if the slice is smaller than `Self::SIZE`
+                #inline
                 pub fn unpack_slice(inp: &[u8]) -> <Self as restruct::Struct>::Unpacked {
-                    let mut __BUFFER = [0u8; Self::SIZE];
-                    __BUFFER.copy_from_slice(&inp[..Self::SIZE]);
-                    Self::unpack(__BUFFER)
+                    let mut buf = [0u8; Self::SIZE];
+                    buf.copy_from_slice(&inp[..Self::SIZE]);
+                    Self::unpack(buf)
+                }
+
+                /// Unpack the bytes from the given slice, if it holds at least `Self::SIZE` bytes.
+                pub fn try_unpack_slice(inp: &[u8]) -> Result<<Self as restruct::Struct>::Unpacked, restruct::UnpackError> {
+                    if inp.len() < Self::SIZE {
+                        return Err(restruct::UnpackError::Size {
+                            expected: Self::SIZE,
+                            found: inp.len(),
+                        });
+                    }
+                    Ok(Self::unpack_slice(inp))
+                }
+
+                /// Unpack the first `Self::SIZE` bytes of the given slice, like
+                /// `try_unpack_slice()`, and return them along with the rest of the slice.
+                pub fn parse(inp: &[u8]) -> Result<(<Self as restruct::Struct>::Unpacked, &[u8]), restruct::UnpackError> {
+                    let res = Self::try_unpack_slice(inp)?;
+                    Ok((res, &inp[Self::SIZE..]))
+                }
+
+                /// Reinterpret the first `Self::SIZE` bytes of the given slice as the packed
+                /// representation without copying, if it holds at least `Self::SIZE` bytes.
+                pub const fn ref_from(inp: &[u8]) -> Option<&<Self as restruct::Struct>::Packed> {
+                    inp.first_chunk()
+                }
+
+                /// Reinterpret the first `Self::SIZE` bytes of the given slice as the packed
+                /// representation, like `ref_from()`, allowing them to be modified in place.
+                pub const fn ref_from_mut(inp: &mut [u8]) -> Option<&mut <Self as restruct::Struct>::Packed> {
+                    inp.first_chunk_mut()
                 }
+                #unpack_all_fn
+                #boxed_fns
+                #fuzz_fn
+                #random_fn
+                #cpython_fn
 
                 /// Pack the given input and write it directly to the given writer.
                 pub fn write_to<T: std::io::Write>(inp: <Self as restruct::Struct>::Unpacked, w: &mut T) -> std::io::Result<()> {
                     w.write_all(&Self::pack(inp))
                 }
 
+                /// Pack the given input and write it directly to the given writer, like
+                /// `write_to()` but without moving the input.
+                pub fn write_to_ref<T: std::io::Write>(inp: &<Self as restruct::Struct>::Unpacked, w: &mut T) -> std::io::Result<()> {
+                    w.write_all(&Self::pack_ref(inp))
+                }
+
                 /// Read exactly `Self::SIZE` bytes from the given reader and unpack them.
-                pub fn read_from<T: std::io::Read>(r: &mut T) -> std::io::Result<<Self as restruct::Struct>::Unpacked> {
-                    let mut __BUFFER = [0; Self::SIZE];
-                    r.read_exact(&mut __BUFFER)?;
-                    Ok(Self::unpack(__BUFFER))
+                pub fn read_from<T: std::io::Read>(r: &mut T) -> Result<<Self as restruct::Struct>::Unpacked, restruct::Error> {
+                    let mut buf = [0; Self::SIZE];
+                    restruct::read_exact(r, &mut buf)?;
+                    Ok(Self::unpack(buf))
+                }
+
+                /// Read consecutive records from the given reader until it ends. Reaching the end
+                /// exactly at a record boundary ends iteration; reaching it within a record yields
+                /// an error of kind `UnexpectedEof`. Iteration ends after the first error. Reads are
+                /// not buffered; see `restruct::RecordReader` for a buffered alternative.
+                pub fn stream<T: std::io::Read>(mut r: T) -> impl Iterator<Item = std::io::Result<<Self as restruct::Struct>::Unpacked>> {
+                    let mut done = false;
+                    std::iter::from_fn(move || {
+                        if done {
+                            return None;
+                        }
+                        let mut buf = [0; Self::SIZE];
+                        match restruct::read_exact(&mut r, &mut buf) {
+                            Ok(()) => Some(Ok(Self::unpack(buf))),
+                            Err(restruct::Error::Eof) => {
+                                done = true;
+                                None
+                            }
+                            Err(e) => {
+                                done = true;
+                                Some(Err(e.into()))
+                            }
+                        }
+                    })
+                }
+
+                /// Read exactly `Self::SIZE` bytes from the given buffered reader and unpack them,
+                /// like `read_from()`. If the reader's buffer holds enough bytes, they are unpacked
+                /// directly from the buffer.
+                pub fn read_from_buf<T: std::io::BufRead>(r: &mut T) -> Result<<Self as restruct::Struct>::Unpacked, restruct::Error> {
+                    let buf = r.fill_buf()?;
+                    if buf.len() < Self::SIZE {
+                        return Self::read_from(r);
+                    }
+                    let res = Self::unpack_slice(buf);
+                    r.consume(Self::SIZE);
+                    Ok(res)
+                }
+
+                /// Discard `n` records of `Self::SIZE` bytes from the given reader without
+                /// unpacking them. If their size overflows an `u64`, an error of kind
+                /// `InvalidInput` is returned.
+                pub fn skip_from<T: std::io::Read>(r: &mut T, n: u64) -> Result<(), restruct::Error> {
+                    let len = n
+                        .checked_mul(Self::SIZE as u64)
+                        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+                    let skipped = std::io::copy(&mut std::io::Read::take(r, len), &mut std::io::sink())?;
+                    match (skipped % Self::SIZE.max(1) as u64) as usize {
+                        _ if skipped == len => Ok(()),
+                        0 => Err(restruct::Error::Eof),
+                        got => Err(restruct::Error::Truncated { got, expected: Self::SIZE }),
+                    }
+                }
+
+                /// Skip `n` records of `Self::SIZE` bytes by seeking forward, returning the new
+                /// position. Seeking beyond the end is not an error.
+                pub fn skip_from_seekable<T: std::io::Seek>(r: &mut T, n: u64) -> std::io::Result<u64> {
+                    let len = n
+                        .checked_mul(Self::SIZE as u64)
+                        .and_then(|len| <i64 as std::convert::TryFrom<u64>>::try_from(len).ok())
+                        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+                    r.seek(std::io::SeekFrom::Current(len))
                 }
+                #frame_fns
+                #validation_fns
+                #async_fns
+                #wasm_fns
+                #text_fns
 
-                /// Act as if the input was pointing to an `[u8; Self::SIZE]`-array and unpack it
+                /// Act as if the input was pointing to an `[u8; Self::SIZE]`-array and unpack it.
+                /// The pointer does not need to be aligned for `T`.
+                /// In debug builds, this panics if the pointer is null.
                 pub unsafe fn from_raw<T>(ptr: *const T) -> <Self as restruct::Struct>::Unpacked {
+                    debug_assert!(!ptr.is_null(), "null pointer given to from_raw()");
                     let ptr = ptr as *const [u8; Self::SIZE];
-                    Self::unpack(*ptr)
+                    Self::unpack(std::ptr::read_unaligned(ptr))
+                }
+
+                /// Like `from_raw()`, taking `T` to be the C-struct described by the Format
+                /// String: Fails if the size of `T` is not `SIZE` rounded up to `ALIGN`, as
+                /// it's size is in C, or if the pointer is not aligned to `ALIGN`. Either means
+                /// that the Format String does not match the layout of `T` on this platform, and
+                /// that `from_raw()` may read out of bounds.
+                pub unsafe fn try_from_raw<T>(ptr: *const T) -> Result<<Self as restruct::Struct>::Unpacked, restruct::UnpackError> {
+                    if std::mem::size_of::<T>() != Self::SIZE.next_multiple_of(Self::ALIGN) {
+                        return Err(restruct::UnpackError::Size {
+                            expected: Self::SIZE.next_multiple_of(Self::ALIGN),
+                            found: std::mem::size_of::<T>(),
+                        });
+                    }
+                    if (ptr as *const u8).align_offset(Self::ALIGN) != 0 {
+                        return Err(restruct::UnpackError::Align {
+                            expected: Self::ALIGN,
+                            address: ptr as usize,
+                        });
+                    }
+                    Ok(Self::from_raw(ptr))
+                }
+
+                /// Act as if the output was pointing to an `[u8; Self::SIZE]`-array and pack the
+                /// given input into it. The pointer does not need to be aligned for `T`.
+                /// In debug builds, this panics if the pointer is null.
+                pub unsafe fn to_raw<T>(inp: <Self as restruct::Struct>::Unpacked, ptr: *mut T) {
+                    debug_assert!(!ptr.is_null(), "null pointer given to to_raw()");
+                    let ptr = ptr as *mut [u8; Self::SIZE];
+                    std::ptr::write_unaligned(ptr, Self::pack(inp));
+                }
+
+                /// Act as if the input was pointing to an `[u8; Self::SIZE]`-array and return a
+                /// mutable view of it. In debug builds, this panics if the pointer is null.
+                pub unsafe fn from_raw_mut<'a, T>(ptr: *mut T) -> &'a mut <Self as restruct::Struct>::Packed {
+                    debug_assert!(!ptr.is_null(), "null pointer given to from_raw_mut()");
+                    &mut *(ptr as *mut [u8; Self::SIZE])
                 }
+                #volatile_fns
             }
             #debug_impl
+            #init_from_check
+            #portability_warnings
+            #ascription_checks
+            #test_vectors
+            #registration
+            #builder
+            #packed_wrapper
+            #map_to
+            #codecs
+        };
+        let res = match &self.options.module {
+            Some(module) => self.module(module, res),
+            None => res,
         };
         use quote::TokenStreamExt;
         tokens.append_all(res);
     }
 }
 
-impl std::string::ToString for Compilation {
-    #[cfg(feature = "rustfmt")]
-    fn to_string(&self) -> String {
-        use quote::ToTokens;
-        let txt = self.into_token_stream().to_string();
-        let mut cfg = rustfmt_nightly::Config::default();
-        cfg.override_value("emit_mode", "stdout");
-        let mut buf = Vec::new();
-        {
-            let mut session = rustfmt_nightly::Session::new(cfg, Some(&mut buf));
-            session.format(rustfmt_nightly::Input::Text(txt)).unwrap();
-        }
-        String::from_utf8(buf).unwrap()
+/// Format the given tokens as Rust source code, falling back to the raw tokens if they don't
+/// parse as a file
+pub fn pretty(tokens: proc_macro2::TokenStream) -> String {
+    match syn::parse2::<syn::File>(tokens.clone()) {
+        Ok(file) => prettyplease::unparse(&file),
+        Err(_) => tokens.to_string(),
     }
+}
 
-    #[cfg(not(feature = "rustfmt"))]
-    fn to_string(&self) -> String {
+impl std::fmt::Display for Compilation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use quote::ToTokens;
-        self.into_token_stream().to_string()
+        f.write_str(&pretty(self.into_token_stream()))
     }
 }
@@ -0,0 +1,138 @@
+//! Indexing files of consecutive records by a key, for lookups without scanning the file again.
+//!
+//! An [`Index`] is built by reading all records once, remembering each record's key and number.
+//! Lookups by key or by a range of keys yield record numbers in key order, which
+//! [`Index::read_record()`] resolves by seeking in the file. The index itself can be written to
+//! and read back from disk, packing keys via another derived type.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<IH"]
+//! struct Entry;
+//!
+//! let mut file = std::io::Cursor::new([3, 0, 0, 0, 1, 0, 1, 0, 0, 0, 2, 0]);
+//! let index = restruct::index::Index::<Entry, u32>::build(&mut file, |(id, _)| *id)?;
+//! assert_eq!(index.lookup(&mut file, &3)?, [(3, 1)]);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use crate::{Error, RecordReader, Struct};
+
+/// The keys of records of the derived type `T`, sorted, along with each record's number.
+pub struct Index<T, K> {
+    entries: Vec<(K, u64)>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Struct, K: Ord> Index<T, K> {
+    /// Read all records from the given reader, keying each record via the given function.
+    pub fn build<R: Read, F: FnMut(&T::Unpacked) -> K>(
+        inner: R,
+        mut key: F,
+    ) -> Result<Self, Error> {
+        let entries = RecordReader::<R, T>::new(inner)
+            .zip(0..)
+            .map(|(record, n)| record.map(|record| (key(&record), n)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_entries(entries))
+    }
+
+    fn from_entries(mut entries: Vec<(K, u64)>) -> Self {
+        entries.sort();
+        Self {
+            entries,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of indexed records.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// `true` if no records are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All keys and record numbers, in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, u64)> + '_ {
+        self.entries.iter().map(|(k, n)| (k, *n))
+    }
+
+    /// The numbers of all records having the given key, in ascending order.
+    pub fn get(&self, key: &K) -> impl Iterator<Item = u64> + '_ {
+        let start = self.entries.partition_point(|(k, _)| k < key);
+        let end = self.entries.partition_point(|(k, _)| k <= key);
+        self.entries[start..end].iter().map(|(_, n)| *n)
+    }
+
+    /// All keys within the given range and their record numbers, in key order.
+    pub fn range<B: RangeBounds<K>>(&self, range: B) -> impl Iterator<Item = (&K, u64)> + '_ {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.entries.partition_point(|(k, _)| k < key),
+            Bound::Excluded(key) => self.entries.partition_point(|(k, _)| k <= key),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.entries.partition_point(|(k, _)| k <= key),
+            Bound::Excluded(key) => self.entries.partition_point(|(k, _)| k < key),
+            Bound::Unbounded => self.entries.len(),
+        };
+        self.entries[start..end.max(start)]
+            .iter()
+            .map(|(k, n)| (k, *n))
+    }
+
+    /// Read the record of the given number from a file of consecutive records.
+    pub fn read_record<S: Read + Seek>(source: &mut S, n: u64) -> Result<T::Unpacked, Error> {
+        source.seek(SeekFrom::Start(n * T::SIZE as u64))?;
+        let mut buf = vec![0; T::SIZE];
+        crate::read_exact(source, &mut buf)?;
+        Ok(T::unpack_slice(&buf))
+    }
+
+    /// Read all records having the given key from a file of consecutive records.
+    pub fn lookup<S: Read + Seek>(
+        &self,
+        source: &mut S,
+        key: &K,
+    ) -> Result<Vec<T::Unpacked>, Error> {
+        self.get(key)
+            .map(|n| Self::read_record(source, n))
+            .collect()
+    }
+
+    /// Write the index, packing each key via the derived type `KS`, followed by the record's
+    /// number as a little-endian `u64`.
+    pub fn write_to<KS: Struct<Unpacked = K>, W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        for (k, n) in &self.entries {
+            w.write_all(KS::pack_ref(k).as_ref())?;
+            w.write_all(&n.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Read an index written by `write_to()`.
+    pub fn read_from<KS: Struct<Unpacked = K>, R: Read>(r: &mut R) -> Result<Self, Error> {
+        let mut buf = vec![0; KS::SIZE + 8];
+        let mut entries = Vec::new();
+        loop {
+            match crate::read_exact(r, &mut buf) {
+                Ok(()) => {}
+                Err(Error::Eof) => break,
+                Err(e) => return Err(e),
+            }
+            let (k, n) = buf.split_at(KS::SIZE);
+            entries.push((
+                KS::unpack_slice(k),
+                u64::from_le_bytes(n.try_into().unwrap()),
+            ));
+        }
+        Ok(Self::from_entries(entries))
+    }
+}
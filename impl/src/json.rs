@@ -0,0 +1,165 @@
+//! Helpers for the `LAYOUT_JSON`-constant, which is generated if the `layout_json`-feature is
+//! enabled.
+
+/// A fixed-capacity string which can be built in `const`-context.
+///
+/// # Panics
+///
+/// Writing beyond the capacity panics, which is a compile-time error in `const`-context.
+#[derive(Clone, Copy)]
+pub struct ConstWriter<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ConstWriter<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Append the given string verbatim.
+    pub const fn str(mut self, s: &str) -> Self {
+        let s = s.as_bytes();
+        let mut i = 0;
+        while i < s.len() {
+            self.buf[self.len] = s[i];
+            self.len += 1;
+            i += 1;
+        }
+        self
+    }
+
+    /// Append the given string as a JSON string, quoted and escaped.
+    pub const fn json_str(mut self, s: &str) -> Self {
+        self = self.str("\"");
+        let s = s.as_bytes();
+        let mut i = 0;
+        while i < s.len() {
+            if s[i] == b'"' || s[i] == b'\\' {
+                self.buf[self.len] = b'\\';
+                self.len += 1;
+            }
+            self.buf[self.len] = s[i];
+            self.len += 1;
+            i += 1;
+        }
+        self.str("\"")
+    }
+
+    /// Append the given number in decimal.
+    pub const fn usize(mut self, mut v: usize) -> Self {
+        let mut digits = [0u8; 20];
+        let mut n = 0;
+        loop {
+            digits[n] = b'0' + (v % 10) as u8;
+            n += 1;
+            v /= 10;
+            if v == 0 {
+                break;
+            }
+        }
+        while n > 0 {
+            n -= 1;
+            self.buf[self.len] = digits[n];
+            self.len += 1;
+        }
+        self
+    }
+
+    /// Append `true` or `false`.
+    pub const fn bool(self, v: bool) -> Self {
+        self.str(if v { "true" } else { "false" })
+    }
+
+    /// The string written so far.
+    pub const fn as_str(&self) -> &str {
+        match std::str::from_utf8(self.buf.split_at(self.len).0) {
+            Ok(s) => s,
+            Err(_) => panic!("Only valid UTF-8 is ever written"),
+        }
+    }
+}
+
+impl<const N: usize> Default for ConstWriter<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Describe a layout as JSON. `fields` are the entries of the generated `FIELDS`-constant;
+/// `elements` tells for each of these entries if it is part of the unpacked representation and
+/// it's name, if any.
+pub const fn layout<const N: usize>(
+    name: &str,
+    endianness: &str,
+    size: usize,
+    fields: &[(&str, usize, usize, usize)],
+    elements: &[(bool, Option<&str>)],
+) -> ConstWriter<N> {
+    let mut w = ConstWriter::new()
+        .str("{\"name\":")
+        .json_str(name)
+        .str(",\"endianness\":")
+        .json_str(endianness)
+        .str(",\"size\":")
+        .usize(size)
+        .str(",\"fields\":[");
+    let mut i = 0;
+    while i < fields.len() {
+        if i > 0 {
+            w = w.str(",");
+        }
+        w = w
+            .str("{\"type\":")
+            .json_str(fields[i].0)
+            .str(",\"offset\":")
+            .usize(fields[i].1)
+            .str(",\"alignment\":")
+            .usize(fields[i].2)
+            .str(",\"size\":")
+            .usize(fields[i].3)
+            .str(",\"padding\":")
+            .bool(!elements[i].0)
+            .str(",\"name\":");
+        w = match elements[i].1 {
+            Some(name) => w.json_str(name),
+            None => w.str("null"),
+        };
+        w = w.str("}");
+        i += 1;
+    }
+    w.str("]}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writer() {
+        const W: ConstWriter<32> = ConstWriter::new()
+            .usize(0)
+            .str(" ")
+            .usize(1234567890)
+            .json_str("a\"b");
+        assert_eq!(W.as_str(), "0 1234567890\"a\\\"b\"");
+    }
+
+    #[test]
+    fn layout_json() {
+        const W: ConstWriter<256> = layout(
+            "Foo",
+            "little",
+            5,
+            &[("u32", 0, 0, 4), ("[u8; 1]", 4, 0, 1)],
+            &[(true, Some("magic")), (false, None)],
+        );
+        assert_eq!(
+            W.as_str(),
+            r#"{"name":"Foo","endianness":"little","size":5,"fields":[{"type":"u32","offset":0,"alignment":0,"size":4,"padding":false,"name":"magic"},{"type":"[u8; 1]","offset":4,"alignment":0,"size":1,"padding":true,"name":null}]}"#
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Computing the layout of a Format String without generating any code.
+//!
+//! The rules are the same as for the generated code: In native mode (`@`), each field is aligned
+//! to the alignment of it's type, as given by a [`Target`]; in packed native mode (`^`), native
+//! sizes are used without alignment; in all other modes, there is no alignment and standard sizes
+//! are used. The first field is never aligned.
+//!
+//! ```
+//! use restruct::layout::{compute, Target};
+//!
+//! let format = restruct::parser::parse("@bq").unwrap();
+//! let layout = compute(&format, &Target::LP64).unwrap();
+//! assert_eq!((layout.fields[1].offset, layout.fields[1].alignment), (1, 7));
+//! assert_eq!(layout.size, 16);
+//! assert_eq!(compute(&format, &Target::ILP32).unwrap().size, 12);
+//! ```
+
+use crate::parser::{self, FormatChar};
+
+/// The size and alignment of C types on some target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    /// `short`
+    pub short: (usize, usize),
+    /// `int`
+    pub int: (usize, usize),
+    /// `long`
+    pub long: (usize, usize),
+    /// `long long`
+    pub long_long: (usize, usize),
+    /// `size_t` and `ssize_t`, which are also used for `n` and `N` in non-native modes
+    pub size: (usize, usize),
+    /// `float`
+    pub float: (usize, usize),
+    /// `double`
+    pub double: (usize, usize),
+    /// The size and alignment of nested types, by name
+    pub nested: Vec<(String, usize, usize)>,
+    /// `true` if numbers are big-endian in native byte order
+    pub big_endian: bool,
+}
+
+impl Target {
+    /// 32bit Unix targets following the i386 System V ABI, e.g. `i686-unknown-linux-gnu`, where
+    /// `long long` and `double` are aligned to 4 bytes.
+    pub const ILP32: Self = Self {
+        short: (2, 2),
+        int: (4, 4),
+        long: (4, 4),
+        long_long: (8, 4),
+        size: (4, 4),
+        float: (4, 4),
+        double: (8, 4),
+        nested: Vec::new(),
+        big_endian: false,
+    };
+
+    /// 64bit Unix targets, e.g. `x86_64-unknown-linux-gnu`.
+    pub const LP64: Self = Self {
+        short: (2, 2),
+        int: (4, 4),
+        long: (8, 8),
+        long_long: (8, 8),
+        size: (8, 8),
+        float: (4, 4),
+        double: (8, 8),
+        nested: Vec::new(),
+        big_endian: false,
+    };
+
+    /// 64bit Windows targets, e.g. `x86_64-pc-windows-msvc`.
+    pub const LLP64: Self = Self {
+        short: (2, 2),
+        int: (4, 4),
+        long: (4, 4),
+        long_long: (8, 8),
+        size: (8, 8),
+        float: (4, 4),
+        double: (8, 8),
+        nested: Vec::new(),
+        big_endian: false,
+    };
+
+    /// The target this code is running on.
+    pub fn host() -> Self {
+        use std::mem::{align_of, size_of};
+        use std::os::raw;
+
+        fn of<T>() -> (usize, usize) {
+            (size_of::<T>(), align_of::<T>())
+        }
+        Self {
+            short: of::<raw::c_short>(),
+            int: of::<raw::c_int>(),
+            long: of::<raw::c_long>(),
+            long_long: of::<raw::c_longlong>(),
+            size: of::<usize>(),
+            float: of::<raw::c_float>(),
+            double: of::<raw::c_double>(),
+            nested: Vec::new(),
+            big_endian: cfg!(target_endian = "big"),
+        }
+    }
+
+    /// Add the size and alignment of a nested type.
+    pub fn with_nested(mut self, name: &str, size: usize, alignment: usize) -> Self {
+        self.nested.push((name.to_owned(), size, alignment));
+        self
+    }
+
+    /// Set the native byte order.
+    pub fn with_big_endian(mut self, big_endian: bool) -> Self {
+        self.big_endian = big_endian;
+        self
+    }
+
+    /// The size and alignment of a single element of the given type.
+    pub(crate) fn element(
+        &self,
+        chr: &FormatChar,
+        native_types: bool,
+    ) -> Result<(usize, usize), Error> {
+        let (size, align) = match chr {
+            FormatChar::Array | FormatChar::Pad | FormatChar::Bool => (1, 1),
+            FormatChar::Char | FormatChar::UChar => (1, 1),
+            FormatChar::Short | FormatChar::UShort if native_types => self.short,
+            FormatChar::Int | FormatChar::UInt if native_types => self.int,
+            FormatChar::Long | FormatChar::ULong if native_types => self.long,
+            FormatChar::LongLong | FormatChar::ULongLong if native_types => self.long_long,
+            FormatChar::Float if native_types => self.float,
+            FormatChar::Double if native_types => self.double,
+            FormatChar::Extended | FormatChar::Quad if native_types => {
+                return Err(Error::Native(chr.clone()))
+            }
+            FormatChar::Typedef(typedef) => return Err(Error::Typedef(*typedef)),
+            FormatChar::Size | FormatChar::USize => self.size,
+            FormatChar::Short | FormatChar::UShort => (2, 2),
+            FormatChar::Int | FormatChar::UInt | FormatChar::Long | FormatChar::ULong => (4, 4),
+            FormatChar::Float => (4, 4),
+            FormatChar::LongLong | FormatChar::ULongLong | FormatChar::Double => (8, 8),
+            FormatChar::Extended => (10, 1),
+            FormatChar::Quad => (16, 1),
+            FormatChar::Ident(ref name) => self
+                .nested
+                .iter()
+                .find(|(n, _, _)| n == name)
+                .map(|(_, size, align)| (*size, *align))
+                .ok_or_else(|| Error::Nested(name.clone()))?,
+        };
+        Ok((size, if native_types { align } else { 1 }))
+    }
+}
+
+/// The error returned if a layout can't be computed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The size and alignment of the given nested type is unknown.
+    Nested(String),
+    /// The given type has no native size and alignment, requiring standard sizes.
+    Native(FormatChar),
+    /// The given type is defined by the operating system, it's size only known to `libc`.
+    Typedef(parser::Typedef),
+    /// The given absolute offset lies before the given end of the preceding fields.
+    Offset(usize, usize),
+    /// The size of the layout overflows `usize` at the given code.
+    Overflow(parser::FormatCode),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Nested(name) => write!(f, "size and alignment of `{}` are unknown", name),
+            Error::Native(chr) => write!(
+                f,
+                "`{}` has no native size and alignment; use one of `=`, `<`, `>` or `!`",
+                parser::FormatCode::new(chr.clone())
+            ),
+            Error::Typedef(typedef) => write!(
+                f,
+                "`{{{}}}` is only supported by the derive, in native mode (`@` or `^`)",
+                typedef.name()
+            ),
+            Error::Offset(at, offset) => write!(
+                f,
+                "offset `@{}` lies before the end of the preceding fields at {}",
+                at, offset
+            ),
+            Error::Overflow(code) => write!(f, "the size of the layout overflows at `{}`", code),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The layout of a single field. Repeated primitive types are a single field; repeated nested
+/// types are one field per repetition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldLayout {
+    /// The code this field originates from
+    pub code: parser::FormatCode,
+    /// The offset of this field, including alignment
+    pub offset: usize,
+    /// The number of alignment-bytes preceding the data
+    pub alignment: usize,
+    /// The total size of this field, including alignment
+    pub size: usize,
+    /// The size of a single element of this field
+    pub element_size: usize,
+    /// The number of elements in the unpacked representation; `0` for padding
+    pub count: usize,
+}
+
+impl FieldLayout {
+    /// The offset of the data of the `n`th element of this field.
+    pub fn element_offset(&self, n: usize) -> usize {
+        self.offset + self.alignment + n * self.element_size
+    }
+}
+
+/// The layout of a Format String.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    /// The layout of each field, in order
+    pub fields: Vec<FieldLayout>,
+    /// The size of the packed representation
+    pub size: usize,
+}
+
+/// Compute the layout of the given Format String for the given target.
+pub fn compute(format: &parser::Format, target: &Target) -> Result<Layout, Error> {
+    let modifier = format.modifier.clone().unwrap_or_default();
+    let native_types = modifier.native_types();
+    let mut fields = Vec::new();
+    let mut offset = 0;
+    for fc in &format.codes {
+        let repeat = fc.repeat.unwrap_or(1);
+        let overflow = || Error::Overflow(fc.clone());
+        let (size, align) = target.element(&fc.chr, native_types)?;
+        let align = if modifier.aligned() { align } else { 1 };
+        // The number of fields, the size of each element, the number of elements per field and
+        // the size of the data per field
+        let (fields_n, element_size, count, data_size) = match fc.chr {
+            FormatChar::Pad => (1, 1, 0, repeat),
+            FormatChar::Array => (1, repeat, repeat.min(1), repeat),
+            // Nested types may require alignment between elements
+            FormatChar::Ident(_) => {
+                // All repetitions must fit, even before alignment
+                size.checked_mul(repeat).ok_or_else(overflow)?;
+                (repeat.max(1), size, repeat.min(1), size * repeat.min(1))
+            }
+            _ => (
+                1,
+                size,
+                repeat,
+                size.checked_mul(repeat).ok_or_else(overflow)?,
+            ),
+        };
+        for i in 0..fields_n {
+            let alignment = match (fc.offset, offset % align) {
+                // Only the first of repeated nested types starts at the given offset
+                (Some(at), _) if i == 0 => {
+                    at.checked_sub(offset).ok_or(Error::Offset(at, offset))?
+                }
+                _ if fields.is_empty() => 0,
+                (_, 0) => 0,
+                (_, rem) => align - rem,
+            };
+            let size = alignment.checked_add(data_size).ok_or_else(overflow)?;
+            fields.push(FieldLayout {
+                code: fc.clone(),
+                offset,
+                alignment,
+                size,
+                element_size,
+                count,
+            });
+            offset = offset.checked_add(size).ok_or_else(overflow)?;
+        }
+    }
+    Ok(Layout {
+        fields,
+        size: offset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(fmt: &str, target: &Target) -> Vec<(usize, usize, usize)> {
+        compute(&parser::parse(fmt).unwrap(), target)
+            .unwrap()
+            .fields
+            .iter()
+            .map(|f| (f.offset, f.alignment, f.size))
+            .collect()
+    }
+
+    #[test]
+    fn targets() {
+        assert_eq!(layout("@bl", &Target::LP64), [(0, 0, 1), (1, 7, 15)]);
+        assert_eq!(layout("@bl", &Target::LLP64), [(0, 0, 1), (1, 3, 7)]);
+        assert_eq!(layout("<bl", &Target::LP64), [(0, 0, 1), (1, 0, 4)]);
+        assert_eq!(layout("^bl", &Target::LP64), [(0, 0, 1), (1, 0, 8)]);
+        assert_eq!(layout("=N", &Target::ILP32), [(0, 0, 4)]);
+        assert_eq!(
+            layout("@bqd", &Target::ILP32),
+            [(0, 0, 1), (1, 3, 11), (12, 0, 8)]
+        );
+    }
+
+    #[test]
+    fn repeats() {
+        assert_eq!(
+            layout("@b3Hx0I", &Target::ILP32),
+            [(0, 0, 1), (1, 1, 7), (8, 0, 1), (9, 3, 3)]
+        );
+        let f = &compute(&parser::parse("@b3H").unwrap(), &Target::ILP32)
+            .unwrap()
+            .fields[1];
+        assert_eq!((f.count, f.element_offset(2)), (3, 6));
+    }
+
+    #[test]
+    fn nested() {
+        let format = parser::parse("@b2`Foo`").unwrap();
+        assert_eq!(
+            compute(&format, &Target::ILP32),
+            Err(Error::Nested("Foo".to_owned()))
+        );
+        let target = Target::ILP32.with_nested("Foo", 5, 4);
+        assert_eq!(
+            layout("@b2`Foo`", &target),
+            [(0, 0, 1), (1, 3, 8), (9, 3, 8)]
+        );
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(
+            layout("@b @8 H 2x @0x10 i", &Target::ILP32),
+            [(0, 0, 1), (1, 7, 9), (10, 0, 2), (12, 4, 8)]
+        );
+        let target = Target::ILP32.with_nested("Foo", 5, 4);
+        assert_eq!(
+            layout("<b @4 2`Foo` @16", &target),
+            [(0, 0, 1), (1, 3, 8), (9, 0, 5), (14, 2, 2)]
+        );
+        let format = parser::parse("<I @2 H").unwrap();
+        let err = compute(&format, &Target::host()).unwrap_err();
+        assert_eq!(err, Error::Offset(2, 4));
+        assert_eq!(
+            err.to_string(),
+            "offset `@2` lies before the end of the preceding fields at 4"
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        for fmt in [
+            "<2305843009213693952Q",
+            "<Q 18446744073709551615x",
+            "<@16 18446744073709551610s",
+        ] {
+            let format = parser::parse(fmt).unwrap();
+            let err = compute(&format, &Target::LP64).unwrap_err();
+            assert!(matches!(err, Error::Overflow(_)), "{}", fmt);
+        }
+        let format = parser::parse("<2305843009213693952Q").unwrap();
+        assert_eq!(
+            compute(&format, &Target::LP64).unwrap_err().to_string(),
+            "the size of the layout overflows at `2305843009213693952Q`"
+        );
+    }
+
+    #[test]
+    fn typedef() {
+        let format = parser::parse("@b{time_t}").unwrap();
+        let err = compute(&format, &Target::host()).unwrap_err();
+        assert_eq!(err, Error::Typedef(parser::Typedef::Time));
+        assert_eq!(
+            err.to_string(),
+            "`{time_t}` is only supported by the derive, in native mode (`@` or `^`)"
+        );
+    }
+}
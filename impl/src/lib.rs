@@ -35,82 +35,1353 @@ type _READMETEST = ();
 
 use quote::ToTokens;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "futures_io")]
+pub mod async_io;
+pub mod bounded;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod checksum;
+#[cfg(feature = "cpython")]
+pub mod cpython;
+pub mod crc_stream;
+pub mod csv;
+pub mod dynamic;
+mod error;
+pub mod export;
+pub mod file;
 mod generator;
-mod parser;
+pub mod index;
+pub mod json;
+pub mod layout;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+pub mod parser;
+pub mod patch;
+#[cfg(feature = "pyo3")]
+pub mod python;
+mod records;
+#[cfg(feature = "registry")]
+pub mod registry;
+#[doc(hidden)]
+pub mod rt;
+pub mod time;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "rand")]
+#[doc(hidden)]
+pub use rand;
+
+/// Define a macro `$gate!` per feature, which expands to the given items if the feature is
+/// enabled, and to an error otherwise. The derive wraps code using optional parts of this crate
+/// in these, as cargo may build the proc-macro with other features than this crate.
+macro_rules! feature_gates {
+    ($d:tt $($feature:literal => $gate:ident),* $(,)?) => {$(
+        #[cfg(feature = $feature)]
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! $gate {
+            ($d($d items:tt)*) => { $d($d items)* };
+        }
+
+        #[cfg(not(feature = $feature))]
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! $gate {
+            ($d($d items:tt)*) => {
+                compile_error!(concat!(
+                    "The `", $feature, "`-feature of `restruct_derive` requires the same feature of `restruct` to be enabled."
+                ));
+            };
+        }
+    )*};
+}
+
+feature_gates! {$
+    "futures_io" => __feature_futures_io,
+    "base64" => __feature_base64,
+    "rand" => __feature_rand,
+    "registry" => __feature_registry,
+    "wasm" => __feature_wasm,
+    "cpython" => __feature_cpython,
+}
+
+pub use dynamic::{DynStruct, Value};
+pub use error::{read_exact, Error, PackError, UnpackError};
+pub use generator::{Checksum, Inline, Options, Padding, Portability, Scale, Timestamp};
+pub use records::{Follow, OnInvalid, RecordReader, RecordWriter, Resync};
 
 /// Types derived using this crate implement this trait. One can refer to the
 /// types use for packing/unpacking using e.g.
 /// `<Self as restruct::Struct>::Packed`
 pub trait Struct {
     /// The type used for the packed form, a [u8; _]-array.
-    type Packed;
+    type Packed: AsRef<[u8]>;
     /// The type used for the unpacked form, a tuple.
     type Unpacked;
+    /// The size in bytes of the packed form.
+    const SIZE: usize;
+
+    /// Unpack the bytes from the given slice.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if the slice is smaller than `Self::SIZE`
+    fn unpack_slice(inp: &[u8]) -> Self::Unpacked;
+
+    /// Pack the given input, without moving it.
+    fn pack_ref(inp: &Self::Unpacked) -> Self::Packed;
+}
+
+/// Derived types given `#[restruct(columns)]` implement this trait for each element `I` of the
+/// unpacked representation, to decode that element alone, as done by the generated
+/// `extract_field()`-function.
+pub trait Column<const I: usize>: Struct {
+    /// The type of the element.
+    type Type;
+
+    /// Decode the element from the given packed record, not looking at any other bytes.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if the slice is smaller than `Self::SIZE`
+    fn unpack_column(inp: &[u8]) -> Self::Type;
+}
+
+/// An element which differs between two unpacked values, as returned by the generated
+/// `diff()`-function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// The index of the element in the unpacked representation.
+    pub index: usize,
+    /// The name of the element's type.
+    pub tipe: &'static str,
+    /// The offset of the element in the packed representation.
+    pub offset: usize,
+    /// The left-hand value, rendered via `std::fmt::Debug`.
+    pub left: String,
+    /// The right-hand value, rendered via `std::fmt::Debug`.
+    pub right: String,
+}
+
+impl std::fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} @ {}): {} != {}",
+            self.index, self.tipe, self.offset, self.left, self.right
+        )
+    }
+}
+
+/// Describes a single entry in `FIELDS`, as returned by the generated `fields_of()`-function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldInfo {
+    /// The index of the element in the unpacked representation; `None` for padding.
+    pub index: Option<usize>,
+    /// The name of the element, if names were given.
+    pub name: Option<&'static str>,
+    /// The name of the element's type.
+    pub tipe: &'static str,
+    /// The offset of the element's data in the packed representation, excluding alignment.
+    pub offset: usize,
+    /// The size of the element's data.
+    pub size: usize,
+}
+
+/// Implements `std::fmt::Display` by calling the given closure. Used by the generated code.
+pub struct FnDisplay<F>(pub F)
+where
+    F: Fn(&mut std::fmt::Formatter) -> std::fmt::Result;
+
+impl<F> std::fmt::Display for FnDisplay<F>
+where
+    F: Fn(&mut std::fmt::Formatter) -> std::fmt::Result,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        (self.0)(f)
+    }
+}
+
+/// Generate round-trip tests for the given derived types, in a module `roundtrip_tests` having
+/// one `#[test]` per type. Each test asserts that
+///
+///  * `SIZE` agrees with the sizes given in `FIELDS`,
+///  * unpacking a packed value yields the value (for `zeroed()` and `DEFAULT`),
+///  * packing an unpacked buffer yields the buffer, ignoring padding (for buffers filled with
+///    `0x00` and `0x01`).
+///
+/// ```
+/// #[derive(restruct_derive::Struct)]
+/// #[fmt = "<I2H"]
+/// struct Header;
+///
+/// #[cfg(test)]
+/// restruct::roundtrip_tests!(Header);
+/// ```
+#[macro_export]
+macro_rules! roundtrip_tests {
+    ($($t:ident),+ $(,)?) => {
+        mod roundtrip_tests {
+            use super::*;
+            $(
+                #[test]
+                #[allow(non_snake_case)]
+                fn $t() {
+                    let size = $t::FIELDS.iter().map(|(_, _, _, size)| size).sum::<usize>();
+                    assert_eq!(size, <$t as $crate::Struct>::SIZE, "SIZE disagrees with FIELDS");
+                    for value in [$t::zeroed(), $t::DEFAULT] {
+                        assert_eq!($t::unpack($t::pack_ref(&value)), value);
+                    }
+                    for byte in [0x00, 0x01] {
+                        let buf = [byte; <$t as $crate::Struct>::SIZE];
+                        let packed = $t::pack($t::unpack(buf));
+                        assert!($t::packed_eq(&packed, &buf), "{:?} != {:?}", packed, buf);
+                    }
+                }
+            )+
+        }
+    };
+}
+
+/// Split the byte order, size and alignment modifier from a Format String
+fn split_modifier(format: &str) -> (Option<char>, &str) {
+    let format = format.trim_start();
+    match format.chars().next() {
+        Some(c @ ('@' | '^' | '=' | '<' | '>' | '!')) => (Some(c), &format[1..]),
+        _ => (None, format),
+    }
+}
+
+/// The name of the macro a derived type leaves behind for types extending it
+fn extends_macro(name: &syn::Ident) -> syn::Ident {
+    quote::format_ident!("__restruct_extends_{}", name)
+}
+
+/// A macro forwarding the format, names and defaults of the derived type to the derive of
+/// types extending it, as per `extends`-attribute. This way, the base type is resolved like any
+/// other path, instead of by name.
+fn extendable(derive: &Derive) -> proc_macro2::TokenStream {
+    let name = &derive.name;
+    let macro_name = extends_macro(name);
+    let unsupported = [
+        ("fmt(cfg(...))", !derive.cfg_fragments.is_empty()),
+        ("init_from", derive.init_from.is_some()),
+        ("checksum", derive.checksum.is_some()),
+        ("timestamp", !derive.timestamps.is_empty()),
+        ("text", !derive.texts.is_empty()),
+        ("scale", !derive.scales.is_empty()),
+        ("transform", !derive.transforms.is_empty()),
+        ("frame", derive.frame.is_some()),
+        ("field_doc", !derive.field_docs.is_empty()),
+    ];
+    let body = match unsupported.iter().find(|(_, given)| *given) {
+        Some((attr, _)) => {
+            let msg = format!(
+                "extends attribute: `{}` can't be extended, as it uses the {} attribute.",
+                name, attr
+            );
+            quote::quote! { compile_error!(#msg); }
+        }
+        None => {
+            let fmt = &derive.format;
+            let names = derive.names.as_ref().map(|names| {
+                quote::quote! { #[names(#(#names),*)] }
+            });
+            let defaults = derive.defaults.iter().map(|(field, value)| {
+                let value = value.to_token_stream().to_string();
+                quote::quote! { #[default(field = #field, value = #value)] }
+            });
+            quote::quote! {
+                restruct_derive::__extends! {
+                    { #[fmt = #fmt] #names #(#defaults)* struct #name; }
+                    $($input)*
+                }
+            }
+        }
+    };
+    quote::quote! {
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #macro_name {
+            ($($input:tt)*) => { #body };
+        }
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use #macro_name;
+    }
+}
+
+/// Prepend the format, names and defaults of the given base type, as per `extends`-attribute
+fn extend(derive: &mut Derive, base: &Derive) {
+    let base_name = &base.name;
+    let (base_modifier, base_format) = split_modifier(&base.format);
+    let (modifier, format) = split_modifier(&derive.format);
+    let normalize = |c: Option<char>| c.map(|c| if c == '!' { '>' } else { c });
+    let modifier = match (base_modifier, modifier) {
+        (Some(b), Some(m)) if normalize(Some(b)) != normalize(Some(m)) => panic!(
+            "extends attribute: `{}` uses the modifier `{}`, but the Format String uses `{}`.",
+            base_name, b, m
+        ),
+        (b, m) => b.or(m),
+    };
+    let mut combined = modifier.map(String::from).unwrap_or_default();
+    push_fragment(&mut combined, base_format);
+    combined.push(' ');
+    // The Format String without it's modifier is a suffix of the original one; move the positions
+    // of conditional fragments along with it
+    let (old_start, new_start) = (derive.format.len() - format.len(), combined.len());
+    for (pos, _) in &mut derive.cfg_fragments {
+        *pos = new_start + pos.saturating_sub(old_start);
+    }
+    push_fragment(&mut combined, format);
+    derive.format = combined;
+
+    if let Some(ref base_names) = base.names {
+        let mut names = base_names.clone();
+        names.extend(derive.names.take().unwrap_or_default());
+        derive.names = Some(names);
+    }
+    let mut defaults = base.defaults.clone();
+    defaults.append(&mut derive.defaults);
+    derive.defaults = defaults;
 }
 
 pub fn derive(input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
-    let derive = Derive::new(syn::parse2(input).expect("Syn failed to parse input tokens."))
-        .expect("Failed to parse input tokens.");
+    let derive =
+        Derive::new(syn::parse2(input.clone()).expect("Syn failed to parse input tokens."))
+            .expect("Failed to parse input tokens.");
+    match derive.extends {
+        // The base type's macro calls `derive_extends()` with it's format, names and defaults
+        Some(ref base) => {
+            let mut path = base.clone();
+            let last = path.segments.last_mut().unwrap();
+            last.ident = extends_macro(&last.ident);
+            quote::quote! { #path! { #input } }
+        }
+        None => expand(derive),
+    }
+}
 
-    let format = parser::parse(&derive.format).expect("Failed to parse format-string");
+/// Derive a type extending another one, given the base type's attributes in braces followed by
+/// the type's own input.
+pub fn derive_extends(input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let mut tokens = input.into_iter();
+    let base = match tokens.next() {
+        Some(proc_macro2::TokenTree::Group(g)) => g.stream(),
+        _ => panic!("Expected the base type's attributes in braces."),
+    };
+    let base = Derive::new(syn::parse2(base).expect("Syn failed to parse base tokens."))
+        .expect("Failed to parse base tokens.");
+    let mut derive =
+        Derive::new(syn::parse2(tokens.collect()).expect("Syn failed to parse input tokens."))
+            .expect("Failed to parse input tokens.");
+    derive.extends = None;
+    extend(&mut derive, &base);
+    expand(derive)
+}
 
-    let comp = crate::generator::Compilation::new(derive.name.clone(), derive.generics, format);
+fn expand(derive: Derive) -> proc_macro2::TokenStream {
+    let mut tokens = if derive.cfg_fragments.is_empty() {
+        compile(&derive, &derive.format)
+    } else {
+        compile_cfg(&derive)
+    };
+    // Make the compiler track the files the Format String was read from, so changes to them
+    // trigger a rebuild.
+    for path in &derive.fmt_files {
+        tokens.extend(quote::quote! {
+            const _: &str = include_str!(#path);
+        });
+    }
+    // Same for environment variables
+    for var in &derive.fmt_envs {
+        tokens.extend(quote::quote! {
+            const _: &str = env!(#var);
+        });
+    }
+    if derive.extendable {
+        tokens.extend(extendable(&derive));
+    }
+    tokens
+}
 
-    if derive.debug_output {
-        eprintln!(
-            "Token stream for `{}`, format \"{}\":\n {}",
-            derive.name,
-            &derive.format,
-            &comp.to_string()
+/// The maximum number of distinct predicates given via `#[fmt(cfg(...), ...)]`
+const MAX_CFG_PREDICATES: usize = 12;
+
+/// `cfg`-keys which have exactly one value on any target
+const EXCLUSIVE_CFG_KEYS: &[&str] = &[
+    "target_arch",
+    "target_endian",
+    "target_env",
+    "target_os",
+    "target_pointer_width",
+    "target_vendor",
+];
+
+/// The key and value of a predicate like `target_pointer_width = "64"`, if the key is exclusive
+fn exclusive_cfg(predicate: &syn::NestedMeta) -> Option<(String, String)> {
+    match predicate {
+        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+            path,
+            lit: syn::Lit::Str(value),
+            ..
+        })) => {
+            let key = path.get_ident()?.to_string();
+            EXCLUSIVE_CFG_KEYS
+                .contains(&key.as_str())
+                .then(|| (key, value.value()))
+        }
+        _ => None,
+    }
+}
+
+/// Generate the code for each Format String selected by a combination of the predicates given
+/// via `#[fmt(cfg(...), ...)]`, gated by a `#[cfg(...)]` on those combinations. Combinations
+/// giving two values to the same exclusive key can't be selected and are skipped. Errors are
+/// reported only if the variant is selected, as other variants may not be valid.
+fn compile_cfg(derive: &Derive) -> proc_macro2::TokenStream {
+    let mut predicates = Vec::<&syn::NestedMeta>::new();
+    for (_, f) in &derive.cfg_fragments {
+        if !predicates.contains(&&f.predicate) {
+            predicates.push(&f.predicate);
+        }
+    }
+    if predicates.len() > MAX_CFG_PREDICATES {
+        panic!(
+            "fmt attribute: At most {} distinct `cfg`-predicates can be given.",
+            MAX_CFG_PREDICATES
         );
     }
+    let exclusive = predicates
+        .iter()
+        .map(|p| exclusive_cfg(p))
+        .collect::<Vec<_>>();
+    // The Format Strings, along with the combinations selecting them
+    let mut variants = Vec::<(String, Vec<proc_macro2::TokenStream>)>::new();
+    for combination in 0..1usize << predicates.len() {
+        let holds = |i: usize| combination & (1 << i) != 0;
+        let held = (0..predicates.len())
+            .filter(|i| holds(*i))
+            .filter_map(|i| exclusive[i].as_ref())
+            .collect::<Vec<_>>();
+        if held
+            .iter()
+            .any(|(k, v)| held.iter().any(|(k2, v2)| k == k2 && v != v2))
+        {
+            continue;
+        }
+        let mut format = String::new();
+        let mut last = 0;
+        for (pos, f) in &derive.cfg_fragments {
+            format.push_str(&derive.format[last..*pos]);
+            last = *pos;
+            let i = predicates.iter().position(|p| **p == f.predicate).unwrap();
+            if holds(i) != f.negated {
+                push_fragment(&mut format, &f.fragment);
+                format.push(' ');
+            }
+        }
+        format.push_str(&derive.format[last..]);
+        let cfg = predicates.iter().enumerate().map(|(i, p)| {
+            if holds(i) {
+                quote::quote! { #p }
+            } else {
+                quote::quote! { not(#p) }
+            }
+        });
+        let cfg = quote::quote! { all(#(#cfg),*) };
+        match variants.iter_mut().find(|(f, _)| *f == format) {
+            Some((_, cfgs)) => cfgs.push(cfg),
+            None => variants.push((format, vec![cfg])),
+        }
+    }
+    let mut res = proc_macro2::TokenStream::new();
+    for (format, cfgs) in variants {
+        let items = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            compile(derive, &format)
+        })) {
+            Ok(tokens) => syn::parse2::<syn::File>(tokens)
+                .unwrap()
+                .items
+                .into_iter()
+                .map(ToTokens::into_token_stream)
+                .collect(),
+            Err(e) => {
+                let msg = e
+                    .downcast_ref::<String>()
+                    .map(String::as_str)
+                    .or_else(|| e.downcast_ref::<&str>().copied())
+                    .unwrap_or("Failed to derive");
+                vec![quote::quote! { compile_error!(#msg); }]
+            }
+        };
+        for item in items {
+            res.extend(quote::quote! {
+                #[cfg(any(#(#cfgs),*))]
+                #item
+            });
+        }
+    }
+    res
+}
+
+/// Generate the code for the given type and Format String
+fn compile(derive: &Derive, format_str: &str) -> proc_macro2::TokenStream {
+    let format = parser::parse(format_str).expect("Failed to parse format-string");
+    let names = match (derive.names.clone(), format.names()) {
+        (Some(_), Some(_)) => {
+            panic!("names are given both in the Format String and via the names attribute.")
+        }
+        (names, None) => names,
+        (None, Some(inline)) => Some(
+            inline
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| match name {
+                    Some(name) => syn::parse_str(&name).unwrap_or_else(|_| {
+                        panic!("`{}` can't be used as a name in the Format String.", name)
+                    }),
+                    None => panic!(
+                        "The Format String names some elements, but not element {}.",
+                        i
+                    ),
+                })
+                .collect(),
+        ),
+    };
+
+    let options = generator::Options {
+        vis: derive.vis.clone(),
+        names,
+        defaults: derive.defaults.clone(),
+        init_from: derive.init_from.clone(),
+        map_to: derive.map_to.clone(),
+        upgrade_from: derive.upgrade_from.clone(),
+        derives: derive.derives.clone(),
+        checksum: derive.checksum,
+        timestamps: derive.timestamps.clone(),
+        texts: derive.texts.clone(),
+        scales: derive.scales.clone(),
+        transforms: derive.transforms.clone(),
+        frame: derive.frame,
+        inline: derive.inline,
+        max_align: derive.max_align,
+        portability: derive.portability,
+        field_docs: derive.field_docs.clone(),
+        test_vectors: derive.test_vectors.clone(),
+        module: derive.module.clone(),
+        codecs: derive.codecs.clone(),
+        builder: derive.builder,
+        columns: derive.columns,
+        csv: derive.csv,
+        packed: derive.packed,
+    };
+    let comp = generator::Compilation::new(
+        derive.name.clone(),
+        derive.generics.clone(),
+        format,
+        options,
+    );
+
+    derive.debug_output.emit("debug_output", || {
+        format!(
+            "// Token stream for `{}`, format {:?}\n{}\n",
+            derive.name,
+            format_str,
+            &comp.to_string()
+        )
+    });
+    derive.layout_output.emit("layout_output", || {
+        format!(
+            "Layout of `{}`, format {:?}\n{}",
+            derive.name,
+            format_str,
+            comp.layout_diagram()
+        )
+    });
     comp.into_token_stream()
 }
 
+/// Generate the same code as `#[derive(restruct_derive::Struct)]` would, including the
+/// definition of a unit-struct `name`. This can be used from a build-script to write the code
+/// into `OUT_DIR` and `include!()` it, instead of using the proc-macro. Since the code is no
+/// longer hidden behind a macro, lints like `clippy::identity_op` apply; consider including it
+/// into a module with `#[allow(clippy::all)]`.
+///
+/// # Panics
+///
+/// The function will panic if `name` is not a valid identifier, if the Format String can't be
+/// parsed or if the options don't match the format.
+///
+/// ```
+/// let code = restruct::codegen(
+///     "Header",
+///     "<I2H",
+///     restruct::Options::default().with_vis("pub"),
+/// );
+/// assert!(code.starts_with("pub struct Header;"));
+/// ```
+pub fn codegen(name: &str, fmt: &str, options: Options) -> String {
+    let name = syn::Ident::new(name, proc_macro2::Span::call_site());
+    let format = parser::parse(fmt).expect("Failed to parse format-string");
+    let vis = options.vis.clone();
+    let mut tokens = quote::quote! {
+        #vis struct #name;
+    };
+    generator::Compilation::new(name, syn::Generics::default(), format, options)
+        .to_tokens(&mut tokens);
+    generator::pretty(tokens)
+}
+
 struct Derive {
     pub name: syn::Ident,
+    pub vis: syn::Visibility,
     pub generics: syn::Generics,
     pub format: String,
-    pub debug_output: bool,
+    /// Fragments of the Format String given via `#[fmt(cfg(...), "...")]`, along with their
+    /// position in `format`
+    pub cfg_fragments: Vec<(usize, CfgFragment)>,
+    pub fmt_files: Vec<String>,
+    pub fmt_envs: Vec<String>,
+    pub debug_output: Output,
+    pub layout_output: Output,
+    pub names: Option<Vec<syn::Ident>>,
+    pub defaults: Vec<(usize, syn::Expr)>,
+    pub init_from: Option<String>,
+    pub extends: Option<syn::Path>,
+    pub map_to: Vec<syn::Path>,
+    pub upgrade_from: Vec<(syn::Path, Vec<syn::Ident>)>,
+    pub derives: Vec<syn::Path>,
+    pub checksum: Option<generator::Checksum>,
+    pub timestamps: Vec<(usize, generator::Timestamp)>,
+    pub texts: Vec<(usize, generator::Padding)>,
+    pub scales: Vec<(usize, generator::Scale)>,
+    pub transforms: Vec<(usize, generator::Transform)>,
+    pub frame: Option<generator::Frame>,
+    pub inline: generator::Inline,
+    pub max_align: Option<usize>,
+    pub portability: generator::Portability,
+    pub field_docs: Vec<(usize, String)>,
+    pub test_vectors: Vec<(Vec<u8>, syn::Expr)>,
+    pub module: Option<syn::Ident>,
+    pub codecs: Vec<generator::Codec>,
+    pub builder: bool,
+    pub columns: bool,
+    pub csv: bool,
+    pub packed: bool,
+    /// Whether to leave a macro behind for types extending this one
+    pub extendable: bool,
+}
+
+/// Where to dump diagnostic output to while compiling, if at all
+enum Output {
+    Off,
+    Stderr,
+    File(std::path::PathBuf),
+}
+
+impl Output {
+    /// Parse the value of an attribute like `#[debug_output = ...]`
+    fn from_lit(attr: &str, lit: &syn::Lit) -> Self {
+        match lit {
+            syn::Lit::Bool(b) if b.value => Output::Stderr,
+            syn::Lit::Bool(_) => Output::Off,
+            syn::Lit::Str(string) => Output::File(manifest_path(&string.value())),
+            _ => panic!("{} attribute must be a bool or a path.", attr),
+        }
+    }
+
+    fn emit(&self, attr: &str, content: impl FnOnce() -> String) {
+        match self {
+            Output::Off => {}
+            Output::Stderr => eprintln!("{}", content()),
+            Output::File(path) => std::fs::write(path, content()).unwrap_or_else(|e| {
+                panic!("Failed to write {} `{}`: {}", attr, path.display(), e)
+            }),
+        }
+    }
+}
+
+/// Append a fragment of a Format String, making sure that a trailing comment in the fragment does
+/// not swallow the next one.
+fn push_fragment(format: &mut String, fragment: &str) {
+    format.push_str(fragment);
+    if fragment.lines().last().is_some_and(|l| l.contains('#')) {
+        format.push('\n');
+    }
+}
+
+/// A fragment of a Format String which is only used if the given predicate holds
+struct CfgFragment {
+    /// The predicate, with a leading `not(...)` removed
+    predicate: syn::NestedMeta,
+    negated: bool,
+    fragment: String,
+}
+
+/// Parse a `#[fmt(cfg(...), "...")]`-attribute
+fn parse_cfg_fragment(l: &syn::MetaList) -> CfgFragment {
+    const USAGE: &str = "Expected `#[fmt=\"...\"]` or `#[fmt(cfg(...), \"...\")]`.";
+    let (cfg, fragment) = match l.nested.iter().collect::<Vec<_>>()[..] {
+        [syn::NestedMeta::Meta(syn::Meta::List(cfg)), syn::NestedMeta::Lit(syn::Lit::Str(s))]
+            if cfg.path.is_ident("cfg") && cfg.nested.len() == 1 =>
+        {
+            (cfg, s.value())
+        }
+        _ => panic!(
+            "fmt attribute takes a list only to give a `cfg`-predicate. {}",
+            USAGE
+        ),
+    };
+    let (predicate, negated) = match &cfg.nested[0] {
+        syn::NestedMeta::Meta(syn::Meta::List(not))
+            if not.path.is_ident("not") && not.nested.len() == 1 =>
+        {
+            (not.nested[0].clone(), true)
+        }
+        predicate => (predicate.clone(), false),
+    };
+    CfgFragment {
+        predicate,
+        negated,
+        fragment,
+    }
+}
+
+/// Parse a `#[names(foo, bar, ...)]`-attribute
+fn parse_names(l: &syn::MetaList) -> Vec<syn::Ident> {
+    l.nested
+        .iter()
+        .map(|nested| match nested {
+            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.get_ident().is_some() => {
+                p.get_ident().unwrap().clone()
+            }
+            _ => panic!("names attribute must be a list of identifiers."),
+        })
+        .collect()
+}
+
+/// Parse a `#[default(field = 0, value = ...)]`-attribute
+fn parse_default(l: &syn::MetaList) -> (usize, syn::Expr) {
+    const USAGE: &str = "Expected `#[default(field = <index>, value = <literal>)]`.";
+    let mut field = None;
+    let mut value = None;
+    for nested in &l.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("field") => {
+                match &nv.lit {
+                    syn::Lit::Int(i) => field = Some(i.base10_parse().expect(USAGE)),
+                    _ => panic!("default attribute: field must be an integer. {}", USAGE),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("value") => {
+                value = Some(match &nv.lit {
+                    syn::Lit::Str(s) => s.parse().expect("Failed to parse default value"),
+                    syn::Lit::ByteStr(b) => syn::parse_quote! { *#b },
+                    lit => syn::parse_quote! { #lit },
+                });
+            }
+            _ => panic!("Unexpected argument to default attribute. {}", USAGE),
+        }
+    }
+    match (field, value) {
+        (Some(field), Some(value)) => (field, value),
+        _ => panic!("default attribute is incomplete. {}", USAGE),
+    }
+}
+
+/// Parse a `#[timestamp(field = 0, kind = "dos")]`-attribute
+fn parse_timestamp(l: &syn::MetaList) -> (usize, generator::Timestamp) {
+    const USAGE: &str = "Expected `#[timestamp(field = <index>, kind = \"dos\" | \"filetime\")]`.";
+    let mut field = None;
+    let mut kind = None;
+    for nested in &l.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("field") => {
+                match &nv.lit {
+                    syn::Lit::Int(i) => field = Some(i.base10_parse().expect(USAGE)),
+                    _ => panic!("timestamp attribute: field must be an integer. {}", USAGE),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("kind") => {
+                kind = Some(match &nv.lit {
+                    syn::Lit::Str(s) => match s.value().as_str() {
+                        "dos" => generator::Timestamp::Dos,
+                        "filetime" => generator::Timestamp::FileTime,
+                        other => panic!("Unknown timestamp kind `{}`. {}", other, USAGE),
+                    },
+                    _ => panic!("timestamp attribute: kind must be a string. {}", USAGE),
+                });
+            }
+            _ => panic!("Unexpected argument to timestamp attribute. {}", USAGE),
+        }
+    }
+    match (field, kind) {
+        (Some(field), Some(kind)) => (field, kind),
+        _ => panic!("timestamp attribute is incomplete. {}", USAGE),
+    }
+}
+
+/// Parse a `#[text(field = 0, padding = "space")]`-attribute
+fn parse_text(l: &syn::MetaList) -> (usize, generator::Padding) {
+    const USAGE: &str =
+        "Expected `#[text(field = <index>, padding = \"nul\" | \"space\")]`, the padding being optional.";
+    let mut field = None;
+    let mut padding = generator::Padding::default();
+    for nested in &l.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("field") => {
+                match &nv.lit {
+                    syn::Lit::Int(i) => field = Some(i.base10_parse().expect(USAGE)),
+                    _ => panic!("text attribute: field must be an integer. {}", USAGE),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("padding") => {
+                padding = match &nv.lit {
+                    syn::Lit::Str(s) => match s.value().as_str() {
+                        "nul" => generator::Padding::Nul,
+                        "space" => generator::Padding::Space,
+                        other => panic!("Unknown text padding `{}`. {}", other, USAGE),
+                    },
+                    _ => panic!("text attribute: padding must be a string. {}", USAGE),
+                }
+            }
+            _ => panic!("Unexpected argument to text attribute. {}", USAGE),
+        }
+    }
+    match field {
+        Some(field) => (field, padding),
+        None => panic!("text attribute is incomplete. {}", USAGE),
+    }
+}
+
+/// Parse a `#[scale(field = 0, factor = 0.01, offset = -40)]`-attribute
+fn parse_scale(l: &syn::MetaList) -> (usize, generator::Scale) {
+    const USAGE: &str =
+        "Expected `#[scale(field = <index>, factor = <number>, offset = <number>)]`, the offset being optional.";
+    let number = |arg: &str, lit: &syn::Lit| -> f64 {
+        let n = match lit {
+            syn::Lit::Float(f) => f.base10_parse().ok(),
+            syn::Lit::Int(i) => i.base10_parse().ok(),
+            syn::Lit::Str(s) => s.value().trim().parse().ok(),
+            _ => None,
+        };
+        match n {
+            Some(n) if f64::is_finite(n) => n,
+            _ => panic!("scale attribute: {} must be a number. {}", arg, USAGE),
+        }
+    };
+    let mut field = None;
+    let mut factor = None;
+    let mut offset = 0.0;
+    for nested in &l.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("field") => {
+                match &nv.lit {
+                    syn::Lit::Int(i) => field = Some(i.base10_parse().expect(USAGE)),
+                    _ => panic!("scale attribute: field must be an integer. {}", USAGE),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("factor") => {
+                factor = Some(number("factor", &nv.lit));
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("offset") => {
+                offset = number("offset", &nv.lit);
+            }
+            _ => panic!("Unexpected argument to scale attribute. {}", USAGE),
+        }
+    }
+    match (field, factor) {
+        (_, Some(0.0)) => panic!("scale attribute: factor must not be zero."),
+        (Some(field), Some(factor)) => (field, generator::Scale { factor, offset }),
+        _ => panic!("scale attribute is incomplete. {}", USAGE),
+    }
+}
+
+/// Parse a `#[transform(field = 0, decode = "...", encode = "...")]`-attribute
+fn parse_transform(l: &syn::MetaList) -> (usize, generator::Transform) {
+    const USAGE: &str =
+        "Expected `#[transform(field = <index>, decode = \"<path>\", encode = \"<path>\")]`.";
+    let path = |arg: &str, lit: &syn::Lit| -> syn::Path {
+        match lit {
+            syn::Lit::Str(s) => s
+                .parse()
+                .unwrap_or_else(|_| panic!("transform attribute: `{}` is not a path.", s.value())),
+            _ => panic!("transform attribute: {} must be a string. {}", arg, USAGE),
+        }
+    };
+    let mut field = None;
+    let mut decode = None;
+    let mut encode = None;
+    for nested in &l.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("field") => {
+                match &nv.lit {
+                    syn::Lit::Int(i) => field = Some(i.base10_parse().expect(USAGE)),
+                    _ => panic!("transform attribute: field must be an integer. {}", USAGE),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("decode") => {
+                decode = Some(path("decode", &nv.lit));
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("encode") => {
+                encode = Some(path("encode", &nv.lit));
+            }
+            _ => panic!("Unexpected argument to transform attribute. {}", USAGE),
+        }
+    }
+    match (field, decode, encode) {
+        (Some(field), Some(decode), Some(encode)) => {
+            (field, generator::Transform { decode, encode })
+        }
+        _ => panic!("transform attribute is incomplete. {}", USAGE),
+    }
+}
+
+/// Parse a `#[frame(length = 0)]`- or `#[frame(count = 0)]`-attribute
+fn parse_frame(l: &syn::MetaList) -> generator::Frame {
+    const USAGE: &str = "Expected `#[frame(length = <index>)]` or `#[frame(count = <index>)]`.";
+    let mut nested = l.nested.iter();
+    match (nested.next(), nested.next()) {
+        (Some(syn::NestedMeta::Meta(syn::Meta::NameValue(nv))), None) => {
+            let idx = match &nv.lit {
+                syn::Lit::Int(i) => i.base10_parse().expect(USAGE),
+                _ => panic!("frame attribute: the field must be an integer. {}", USAGE),
+            };
+            if nv.path.is_ident("length") {
+                generator::Frame::Length(idx)
+            } else if nv.path.is_ident("count") {
+                generator::Frame::Count(idx)
+            } else {
+                panic!("Unexpected argument to frame attribute. {}", USAGE)
+            }
+        }
+        _ => panic!(
+            "frame attribute must be given exactly one argument. {}",
+            USAGE
+        ),
+    }
+}
+
+/// Parse a `#[impl_codec(path::Trait, encode = "encode", decode = "decode")]`-attribute
+fn parse_impl_codec(l: &syn::MetaList) -> generator::Codec {
+    const USAGE: &str =
+        "Expected `#[impl_codec(<trait>)]`, optionally followed by `encode = \"<name>\"` and `decode = \"<name>\"`.";
+    let mut nested = l.nested.iter();
+    let path = match nested.next() {
+        Some(syn::NestedMeta::Meta(syn::Meta::Path(p))) => p.clone(),
+        _ => panic!(
+            "impl_codec attribute must start with the trait to implement. {}",
+            USAGE
+        ),
+    };
+    let mut codec = generator::Codec {
+        path,
+        encode: quote::format_ident!("encode"),
+        decode: quote::format_ident!("decode"),
+    };
+    for nested in nested {
+        let (f, nv) = match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("encode") => {
+                (&mut codec.encode, nv)
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("decode") => {
+                (&mut codec.decode, nv)
+            }
+            _ => panic!("Unexpected argument to impl_codec attribute. {}", USAGE),
+        };
+        *f = match &nv.lit {
+            syn::Lit::Str(s) => s.parse().expect(USAGE),
+            _ => panic!(
+                "impl_codec attribute: function names must be strings. {}",
+                USAGE
+            ),
+        };
+    }
+    codec
+}
+
+/// Parse a `#[field_doc(field = 0, doc = "...")]`-attribute
+fn parse_field_doc(l: &syn::MetaList) -> (usize, String) {
+    const USAGE: &str = "Expected `#[field_doc(field = <index>, doc = \"...\")]`.";
+    let mut field = None;
+    let mut doc = None;
+    for nested in &l.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("field") => {
+                match &nv.lit {
+                    syn::Lit::Int(i) => field = Some(i.base10_parse().expect(USAGE)),
+                    _ => panic!("field_doc attribute: field must be an integer. {}", USAGE),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("doc") => {
+                match &nv.lit {
+                    syn::Lit::Str(s) => doc = Some(s.value()),
+                    _ => panic!("field_doc attribute: doc must be a string. {}", USAGE),
+                }
+            }
+            _ => panic!("Unexpected argument to field_doc attribute. {}", USAGE),
+        }
+    }
+    match (field, doc) {
+        (Some(field), Some(doc)) => (field, doc),
+        _ => panic!("field_doc attribute is incomplete. {}", USAGE),
+    }
+}
+
+/// Parse a `#[test_vector(bytes = "DE AD", values = (...))]`-attribute. As the values are an
+/// expression instead of a literal, this can't go through `parse_meta()`.
+fn parse_test_vector(attr: &syn::Attribute) -> (Vec<u8>, syn::Expr) {
+    const USAGE: &str =
+        "Expected `#[test_vector(bytes = \"<hexadecimal bytes>\", values = <expression>)]`.";
+    let mut bytes = None;
+    let mut values = None;
+    let parser = |input: syn::parse::ParseStream| {
+        while !input.is_empty() {
+            let key = input.parse::<syn::Ident>()?;
+            input.parse::<syn::Token![=]>()?;
+            if key == "bytes" {
+                let hex = input.parse::<syn::LitStr>()?.value();
+                let hex = hex.split_whitespace().collect::<String>();
+                if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    panic!(
+                        "test_vector attribute: bytes must be hexadecimal. {}",
+                        USAGE
+                    );
+                }
+                bytes = Some(
+                    (0..hex.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                        .collect(),
+                );
+            } else if key == "values" {
+                values = Some(input.parse::<syn::Expr>()?);
+            } else {
+                panic!("Unexpected argument to test_vector attribute. {}", USAGE);
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(())
+    };
+    attr.parse_args_with(parser)
+        .unwrap_or_else(|e| panic!("Failed to parse test_vector attribute: {}. {}", e, USAGE));
+    match (bytes, values) {
+        (Some(bytes), Some(values)) => (bytes, values),
+        _ => panic!("test_vector attribute is incomplete. {}", USAGE),
+    }
+}
+
+/// Resolve the given path relative to the directory of the crate being compiled.
+fn manifest_path(path: &str) -> std::path::PathBuf {
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => std::path::Path::new(&dir).join(path),
+        None => std::path::PathBuf::from(path),
+    }
 }
 
 impl Derive {
     fn new(ast: syn::DeriveInput) -> syn::parse::Result<Self> {
         let name = ast.ident;
+        let vis = ast.vis;
         let generics = ast.generics;
 
         let mut format = String::new();
-        let mut debug_output = false;
+        let mut fmt_files = Vec::new();
+        let mut fmt_envs = Vec::new();
+        let mut debug_output = Output::Off;
+        let mut layout_output = Output::Off;
+        let mut names = None;
+        let mut defaults = Vec::new();
+        let mut init_from = None;
+        let mut cfg_fragments = Vec::new();
+        let mut extends = None;
+        let mut map_to = Vec::new();
+        let mut upgrade_from = Vec::new();
+        let mut derives = Vec::new();
+        let mut checksum = None;
+        let mut timestamps = Vec::new();
+        let mut texts = Vec::new();
+        let mut inline = generator::Inline::default();
+        let mut max_align = None;
+        let mut portability = generator::Portability::default();
+        let mut field_docs = Vec::new();
+        let mut scales = Vec::new();
+        let mut transforms = Vec::new();
+        let mut frame = None;
+        let mut test_vectors = Vec::new();
+        let mut module = None;
+        let mut codecs = Vec::new();
+        let mut builder = false;
+        let mut columns = false;
+        let mut csv = false;
+        let mut packed = false;
+        let mut extendable = false;
 
         for attr in ast.attrs {
+            if attr.path.is_ident("test_vector") {
+                test_vectors.push(parse_test_vector(&attr));
+                continue;
+            }
             match attr.parse_meta()? {
                 syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("fmt") => {
                     match &name_value.lit {
-                        syn::Lit::Str(string) => format.push_str(&string.value()),
+                        syn::Lit::Str(string) => push_fragment(&mut format, &string.value()),
                         _ => panic!("fmt attribute must be a string."),
                     }
                 }
+                syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("fmt_file") => {
+                    match &name_value.lit {
+                        syn::Lit::Str(string) => {
+                            let path = manifest_path(&string.value());
+                            let content = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+                                panic!("Failed to read fmt_file `{}`: {}", path.display(), e)
+                            });
+                            push_fragment(&mut format, content.trim());
+                            fmt_files.push(path.to_string_lossy().into_owned());
+                        }
+                        _ => panic!("fmt_file attribute must be a string."),
+                    }
+                }
+                syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("init_from") => {
+                    match &name_value.lit {
+                        syn::Lit::Str(string) => {
+                            let path = manifest_path(&string.value());
+                            init_from = Some(path.to_string_lossy().into_owned());
+                        }
+                        _ => panic!("init_from attribute must be a string."),
+                    }
+                }
+                syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("fmt_env") => {
+                    match &name_value.lit {
+                        syn::Lit::Str(string) => {
+                            let var = string.value();
+                            let content = std::env::var(&var).unwrap_or_else(|e| {
+                                panic!("Failed to read fmt_env `{}`: {}", var, e)
+                            });
+                            push_fragment(&mut format, content.trim());
+                            fmt_envs.push(var);
+                        }
+                        _ => panic!("fmt_env attribute must be a string."),
+                    }
+                }
+                syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("checksum") => {
+                    checksum = Some(match &name_value.lit {
+                        syn::Lit::Str(string) => match string.value().as_str() {
+                            "sum8" => generator::Checksum::Sum8,
+                            "crc16" => generator::Checksum::Crc16,
+                            "crc32" => generator::Checksum::Crc32,
+                            other => panic!(
+                                "Unknown checksum `{}`. Expected `sum8`, `crc16` or `crc32`.",
+                                other
+                            ),
+                        },
+                        _ => panic!("checksum attribute must be a string."),
+                    });
+                }
+                syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("inline_hint") => {
+                    inline = match &name_value.lit {
+                        syn::Lit::Str(string) => match string.value().as_str() {
+                            "none" => generator::Inline::None,
+                            "hint" => generator::Inline::Hint,
+                            "always" => generator::Inline::Always,
+                            "never" => generator::Inline::Never,
+                            other => panic!(
+                                "Unknown inline_hint `{}`. Expected `none`, `hint`, `always` or `never`.",
+                                other
+                            ),
+                        },
+                        _ => panic!("inline_hint attribute must be a string."),
+                    };
+                }
+                syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("portability") => {
+                    portability = match &name_value.lit {
+                        syn::Lit::Str(string) => match string.value().as_str() {
+                            "allow" => generator::Portability::Allow,
+                            "warn" => generator::Portability::Warn,
+                            "deny" => generator::Portability::Deny,
+                            other => panic!(
+                                "Unknown portability `{}`. Expected `allow`, `warn` or `deny`.",
+                                other
+                            ),
+                        },
+                        _ => panic!("portability attribute must be a string."),
+                    };
+                }
+                syn::Meta::NameValue(ref name_value) if name_value.path.is_ident("max_align") => {
+                    max_align = match &name_value.lit {
+                        syn::Lit::Int(i) => match i.base10_parse::<usize>() {
+                            Ok(n) if n.is_power_of_two() => Some(n),
+                            _ => panic!("max_align attribute must be a power of two."),
+                        },
+                        _ => panic!("max_align attribute must be an integer."),
+                    };
+                }
                 syn::Meta::NameValue(ref name_value)
                     if name_value.path.is_ident("debug_output") =>
                 {
-                    match &name_value.lit {
-                        syn::Lit::Bool(b) => debug_output = b.value,
-                        _ => panic!("debug_output attribute must be a bool."),
+                    debug_output = Output::from_lit("debug_output", &name_value.lit);
+                }
+                syn::Meta::NameValue(ref name_value)
+                    if name_value.path.is_ident("layout_output") =>
+                {
+                    layout_output = Output::from_lit("layout_output", &name_value.lit);
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("names") => {
+                    names = Some(parse_names(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("map_to") => {
+                    for nested in &l.nested {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::Path(p)) => map_to.push(p.clone()),
+                            _ => panic!("map_to attribute must be a list of types."),
+                        }
                     }
                 }
+                syn::Meta::List(ref l) if l.path.is_ident("extends") => {
+                    match l.nested.iter().collect::<Vec<_>>()[..] {
+                        [syn::NestedMeta::Meta(syn::Meta::Path(p))] => {
+                            if extends.is_some() {
+                                panic!("extends attribute can only be given once.");
+                            }
+                            extends = Some(p.clone());
+                        }
+                        _ => panic!("extends attribute must be a type, e.g. `#[extends(Header)]`."),
+                    }
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("upgrade_from") => {
+                    for nested in &l.nested {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::List(old)) => {
+                                upgrade_from.push((old.path.clone(), parse_names(old)))
+                            }
+                            _ => panic!(
+                                "upgrade_from attribute must be a list of types and the names of their elements, e.g. `#[upgrade_from(HeaderV1(magic, version))]`."
+                            ),
+                        }
+                    }
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("default") => {
+                    defaults.push(parse_default(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("field_doc") => {
+                    field_docs.push(parse_field_doc(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("timestamp") => {
+                    timestamps.push(parse_timestamp(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("text") => {
+                    texts.push(parse_text(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("scale") => {
+                    scales.push(parse_scale(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("transform") => {
+                    transforms.push(parse_transform(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("restruct") => {
+                    for nested in &l.nested {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                                if nv.path.is_ident("module") =>
+                            {
+                                module = Some(match &nv.lit {
+                                    syn::Lit::Str(s) => s.parse::<syn::Ident>().unwrap_or_else(|_| {
+                                        panic!("restruct attribute: `{}` is not a valid module name.", s.value())
+                                    }),
+                                    _ => panic!("restruct attribute: module must be a string."),
+                                });
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident("builder") => {
+                                builder = true
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident("columns") => {
+                                columns = true
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident("csv") => {
+                                csv = true
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident("packed") => {
+                                packed = true
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::Path(p))
+                                if p.is_ident("extendable") =>
+                            {
+                                extendable = true
+                            }
+                            syn::NestedMeta::Meta(syn::Meta::List(l)) if l.path.is_ident("derive") => {
+                                for nested in &l.nested {
+                                    match nested {
+                                        syn::NestedMeta::Meta(syn::Meta::Path(p)) => {
+                                            derives.push(p.clone())
+                                        }
+                                        _ => panic!("restruct attribute: `derive` must be a list of traits."),
+                                    }
+                                }
+                            }
+                            _ => panic!("Unexpected argument to restruct attribute. Expected `module = \"<name>\"`, `builder`, `columns`, `csv`, `packed`, `extendable` or `derive(...)`."),
+                        }
+                    }
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("impl_codec") => {
+                    codecs.push(parse_impl_codec(l));
+                }
+                syn::Meta::List(ref l) if l.path.is_ident("frame") => {
+                    frame = Some(parse_frame(l));
+                }
                 syn::Meta::List(ref l) if l.path.is_ident("fmt") => {
-                    panic!("fmt attribute does not take a list. Expected `#[fmt=\"...\"]`.");
+                    cfg_fragments.push((format.len(), parse_cfg_fragment(l)));
+                }
+                syn::Meta::Path(ref p) if p.is_ident("debug_output") => {
+                    debug_output = Output::Stderr
+                }
+                syn::Meta::Path(ref p) if p.is_ident("layout_output") => {
+                    layout_output = Output::Stderr
                 }
-                syn::Meta::Path(ref p) if p.is_ident("debug_output") => debug_output = true,
                 _ => {}
             }
         }
 
         Ok(Self {
             name,
+            vis,
             generics,
             format,
+            cfg_fragments,
+            fmt_files,
+            fmt_envs,
             debug_output,
+            layout_output,
+            names,
+            defaults,
+            init_from,
+            extends,
+            map_to,
+            upgrade_from,
+            derives,
+            checksum,
+            timestamps,
+            texts,
+            inline,
+            max_align,
+            portability,
+            field_docs,
+            test_vectors,
+            module,
+            codecs,
+            scales,
+            transforms,
+            frame,
+            builder,
+            columns,
+            csv,
+            packed,
+            extendable,
         })
     }
 }
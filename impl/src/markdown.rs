@@ -0,0 +1,74 @@
+//! Helpers for the `MARKDOWN`-constant, which is generated if the `markdown`-feature is enabled.
+
+use crate::json::ConstWriter;
+
+/// Describe a layout as a Markdown table, headed by the type's name, the given summary and the
+/// size. `fields` are the entries of the generated `FIELDS`-constant; `elements` give for each of
+/// these entries it's label, endianness and description, escaped for use in a table cell.
+pub const fn table<const N: usize>(
+    name: &str,
+    summary: &str,
+    size: usize,
+    fields: &[(&str, usize, usize, usize)],
+    elements: &[(&str, &str, &str)],
+) -> ConstWriter<N> {
+    let mut w = ConstWriter::new()
+        .str("### ")
+        .str(name)
+        .str("\n\n")
+        .str(summary)
+        .str(" Size: ")
+        .usize(size)
+        .str(" bytes.\n\n")
+        .str("| Field | Type | Offset | Size | Endianness | Description |\n")
+        .str("|-------|------|--------|------|------------|-------------|\n");
+    let mut i = 0;
+    while i < fields.len() {
+        let (tipe, offset, alignment, size) = fields[i];
+        let (label, endianness, doc) = elements[i];
+        w = w
+            .str("| ")
+            .str(label)
+            .str(" | `")
+            .str(tipe)
+            .str("` | ")
+            .usize(offset + alignment)
+            .str(" | ")
+            .usize(size - alignment)
+            .str(" | ")
+            .str(endianness)
+            .str(" | ")
+            .str(doc)
+            .str(" |\n");
+        i += 1;
+    }
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table() {
+        const W: ConstWriter<512> = super::table(
+            "Foo",
+            "Byte order: native; native sizes and alignment.",
+            16,
+            &[("u8", 0, 0, 1), ("u64", 1, 7, 15)],
+            &[("`flag`", "-", "A \\| B"), ("1", "little", "")],
+        );
+        assert_eq!(
+            W.as_str(),
+            "### Foo
+
+Byte order: native; native sizes and alignment. Size: 16 bytes.
+
+| Field | Type | Offset | Size | Endianness | Description |
+|-------|------|--------|------|------------|-------------|
+| `flag` | `u8` | 0 | 1 | - | A \\| B |
+| 1 | `u64` | 8 | 8 | little |  |
+"
+        );
+    }
+}
@@ -1,3 +1,17 @@
+//! The parser for Format Strings, as used by the derive and the runtime interpreter.
+//!
+//! Other tools can use this to share the exact same dialect of Format Strings.
+//!
+//! ```
+//! use restruct::parser::{parse, FormatChar, Modifier};
+//!
+//! let format = parse("<I 2H # version").unwrap();
+//! assert_eq!(format.modifier, Some(Modifier::LittleEndian));
+//! assert_eq!(format.codes[1].repeat, Some(2));
+//! assert_eq!(format.codes[1].chr, FormatChar::UShort);
+//! assert_eq!(format.codes[1].to_string(), "2H");
+//! ```
+
 use pest::Parser;
 
 mod fmt {
@@ -6,6 +20,7 @@ mod fmt {
     pub struct Parser;
 }
 
+/// The byte order of numbers in packed form.
 #[derive(Debug, PartialEq)]
 pub enum ByteOrder {
     Native,
@@ -13,78 +28,356 @@ pub enum ByteOrder {
     BigEndian,
 }
 
-#[derive(Debug, PartialEq)]
+/// The first character of a Format String, determining byte order, size and alignment.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
 pub enum Modifier {
+    /// `@`: Native byte order, native size and alignment; the default.
+    #[default]
     Native,
+    /// `^`: Native byte order, native size, no alignment; as C structs declared `packed`.
+    NativePacked,
+    /// `=`: Native byte order, standard size, no alignment.
     NativeStandard,
+    /// `<`: Little-endian, standard size, no alignment.
     LittleEndian,
+    /// `>` or `!`: Big-endian, standard size, no alignment.
     BigEndian,
 }
 
 impl Modifier {
+    /// The byte order of numbers in packed form.
     pub fn byte_order(&self) -> ByteOrder {
         match self {
             Modifier::Native => ByteOrder::Native,
+            Modifier::NativePacked => ByteOrder::Native,
             Modifier::NativeStandard => ByteOrder::Native,
             Modifier::LittleEndian => ByteOrder::LittleEndian,
             Modifier::BigEndian => ByteOrder::BigEndian,
         }
     }
 
+    /// `true` if native types (e.g. `libc::c_long`) are used.
     pub fn native_types(&self) -> bool {
+        matches!(self, Modifier::Native | Modifier::NativePacked)
+    }
+
+    /// `true` if elements are aligned to the native alignment of their type.
+    pub fn aligned(&self) -> bool {
         self == &Modifier::Native
     }
 }
 
-impl Default for Modifier {
-    fn default() -> Self {
-        Modifier::Native
+impl std::fmt::Display for Modifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Modifier::Native => "@",
+            Modifier::NativePacked => "^",
+            Modifier::NativeStandard => "=",
+            Modifier::LittleEndian => "<",
+            Modifier::BigEndian => ">",
+        })
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// The type of a single code in a Format String.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum FormatChar {
+    /// `s`: A byte-array, the count giving it's length
     Array,
+    /// `?`: A `bool`
     Bool,
+    /// `b`: A signed char
     Char,
+    /// `d`: A double
     Double,
+    /// `g`: An x87 80-bit extended precision number, e.g. a `long double` on x86
+    Extended,
+    /// `f`: A float
     Float,
+    /// `` `Foo` ``: A nested type, referred to by name
     Ident(String),
+    /// `i`: A signed int
     Int,
+    /// `l`: A signed long
     Long,
+    /// `G`: An IEEE 754 binary128 number
+    Quad,
+    /// `q`: A signed long long
     LongLong,
+    /// `x`: A padding byte
     Pad,
+    /// `h`: A signed short
     Short,
+    /// `n`: A `ssize_t`
     Size,
+    /// `B`: An unsigned char
     UChar,
+    /// `{time_t}`, ...: A type defined by the operating system, as given by `libc`
+    Typedef(Typedef),
+    /// `I`: An unsigned int
     UInt,
+    /// `L`: An unsigned long
     ULong,
+    /// `Q`: An unsigned long long
     ULongLong,
+    /// `H`: An unsigned short
     UShort,
+    /// `N`: A `size_t`
     USize,
 }
 
-#[derive(Debug, PartialEq)]
+/// A type defined by the operating system, whose size and signedness differ between platforms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Typedef {
+    /// `{dev_t}`
+    Dev,
+    /// `{ino_t}`
+    Ino,
+    /// `{mode_t}`
+    Mode,
+    /// `{off_t}`
+    Off,
+    /// `{pid_t}`
+    Pid,
+    /// `{time_t}`
+    Time,
+    /// `{uid_t}`
+    Uid,
+}
+
+impl Typedef {
+    /// The name of this type, e.g. `time_t`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Typedef::Dev => "dev_t",
+            Typedef::Ino => "ino_t",
+            Typedef::Mode => "mode_t",
+            Typedef::Off => "off_t",
+            Typedef::Pid => "pid_t",
+            Typedef::Time => "time_t",
+            Typedef::Uid => "uid_t",
+        }
+    }
+}
+
+/// A single code in a Format String, e.g. `3s`, `` `Foo` ``, `magic:I` or `l:u64`. Created via
+/// `FormatCode::new()` and the `with_...()`-functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct FormatCode {
+    /// The count preceding the code, if any.
     pub repeat: Option<usize>,
+    /// The type of this code; nested types are referred to by name.
     pub chr: FormatChar,
+    /// The name given inline, if any.
+    pub name: Option<String>,
+    /// The Rust type the elements must resolve to, if given inline, e.g. `u64` in `l:u64`.
+    pub ascription: Option<String>,
+    /// The absolute offset this code starts at, if preceded by a directive like `@0x40`.
+    pub offset: Option<usize>,
 }
 
-#[derive(Debug)]
+impl FormatCode {
+    /// A single code of the given type, without count, name, type ascription or offset.
+    pub fn new(chr: FormatChar) -> Self {
+        Self {
+            repeat: None,
+            chr,
+            name: None,
+            ascription: None,
+            offset: None,
+        }
+    }
+
+    /// The count preceding the code.
+    pub fn with_repeat(mut self, repeat: usize) -> Self {
+        self.repeat = Some(repeat);
+        self
+    }
+
+    /// The name given inline.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// The Rust type the elements must resolve to.
+    pub fn with_ascription(mut self, ascription: &str) -> Self {
+        self.ascription = Some(ascription.to_owned());
+        self
+    }
+
+    /// The absolute offset this code starts at.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// The number of elements this code contributes to the unpacked representation.
+    pub fn elements(&self) -> usize {
+        let repeat = self.repeat.unwrap_or(1);
+        match self.chr {
+            FormatChar::Pad => 0,
+            FormatChar::Array => repeat.min(1),
+            _ => repeat,
+        }
+    }
+}
+
+/// Renders the code without it's name, type ascription and offset, as understood by Python's
+/// `struct`-module.
+impl std::fmt::Display for FormatCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(repeat) = self.repeat {
+            write!(f, "{}", repeat)?;
+        }
+        let chr = match self.chr {
+            FormatChar::Array => "s",
+            FormatChar::Bool => "?",
+            FormatChar::Char => "b",
+            FormatChar::Double => "d",
+            FormatChar::Extended => "g",
+            FormatChar::Float => "f",
+            FormatChar::Ident(ref ident) => return write!(f, "`{}`", ident),
+            FormatChar::Int => "i",
+            FormatChar::Long => "l",
+            FormatChar::Quad => "G",
+            FormatChar::LongLong => "q",
+            FormatChar::Pad => "x",
+            FormatChar::Short => "h",
+            FormatChar::Size => "n",
+            FormatChar::UChar => "B",
+            FormatChar::Typedef(typedef) => return write!(f, "{{{}}}", typedef.name()),
+            FormatChar::UInt => "I",
+            FormatChar::ULong => "L",
+            FormatChar::ULongLong => "Q",
+            FormatChar::UShort => "H",
+            FormatChar::USize => "N",
+        };
+        f.write_str(chr)
+    }
+}
+
+/// A parsed Format String.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct Format {
+    /// The modifier, if one was given.
     pub modifier: Option<Modifier>,
+    /// The codes, in order of appearance.
     pub codes: Vec<FormatCode>,
 }
 
-pub fn parse(inp: &str) -> Result<Format, pest::error::Error<fmt::Rule>> {
-    let parse = fmt::Parser::parse(fmt::Rule::fmt, inp)?.next().unwrap();
+impl Format {
+    /// A Format String made up of the given modifier and codes.
+    pub fn new(modifier: Option<Modifier>, codes: Vec<FormatCode>) -> Self {
+        Self { modifier, codes }
+    }
+
+    /// The names of the elements of the unpacked representation, if any code was named inline.
+    /// Codes with multiple elements name them `name_0`, `name_1`, ...; elements of codes without
+    /// a name are `None`.
+    pub fn names(&self) -> Option<Vec<Option<String>>> {
+        if self.codes.iter().all(|c| c.name.is_none()) {
+            return None;
+        }
+        let mut names = Vec::new();
+        for code in &self.codes {
+            let n = code.elements();
+            match code.name {
+                Some(ref name) if n == 1 => names.push(Some(name.clone())),
+                Some(ref name) => names.extend((0..n).map(|i| Some(format!("{}_{}", name, i)))),
+                None => names.extend((0..n).map(|_| None)),
+            }
+        }
+        Some(names)
+    }
+}
+
+/// Renders the Format String without whitespace, comments, names and type ascriptions.
+/// Absolute offsets are kept, as they change the layout.
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(modifier) = &self.modifier {
+            write!(f, "{}", modifier)?;
+        }
+        self.codes.iter().try_for_each(|code| match code.offset {
+            Some(offset) => write!(f, "@{} {}", offset, code),
+            None => write!(f, "{}", code),
+        })
+    }
+}
+
+/// The error returned if a Format String can't be parsed.
+#[derive(Debug)]
+pub struct ParseError(Box<pest::error::Error<fmt::Rule>>);
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a count, given in decimal, in hexadecimal prefixed by `0x` and terminated by `:`, or in
+/// decimal suffixed by `k` or `K` to multiply it by 1024
+fn parse_repeat(rpt: &pest::iterators::Pair<fmt::Rule>) -> Result<Option<usize>, String> {
+    let s = rpt.as_str();
+    let overflow = || format!("count `{}` is too large", s);
+    match rpt.as_rule() {
+        fmt::Rule::hex_rpt => usize::from_str_radix(&s[2..s.len() - 1], 16)
+            .map(Some)
+            .map_err(|_| overflow()),
+        _ if s.is_empty() => Ok(None),
+        _ => match s.strip_suffix(['k', 'K']) {
+            Some(n) => n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_mul(1024))
+                .map(Some)
+                .ok_or_else(overflow),
+            None => s.parse().map(Some).map_err(|_| overflow()),
+        },
+    }
+}
+
+/// Parse an absolute offset like `@64` or `@0x40`
+fn parse_offset(offset: &pest::iterators::Pair<fmt::Rule>) -> Result<usize, String> {
+    let s = &offset.as_str()[1..];
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("offset `{}` is too large", s))
+}
+
+/// Parse the given Format String. Whitespace and comments (from `#` to the end of the line) are
+/// ignored.
+pub fn parse(inp: &str) -> Result<Format, ParseError> {
+    let custom_error = |message, span| {
+        ParseError(Box::new(pest::error::Error::new_from_span(
+            pest::error::ErrorVariant::CustomError { message },
+            span,
+        )))
+    };
+    let parse = fmt::Parser::parse(fmt::Rule::fmt, inp)
+        .map_err(|e| ParseError(Box::new(e)))?
+        .next()
+        .unwrap();
     let mut modifier = None;
     let mut codes = Vec::new();
+    // The absolute offset the next code starts at
+    let mut offset = None;
     for line in parse.into_inner() {
         match line.as_rule() {
             fmt::Rule::modifier => {
                 modifier = Some(match line.as_str() {
                     "@" => Modifier::Native,
+                    "^" => Modifier::NativePacked,
                     "=" => Modifier::NativeStandard,
                     "<" => Modifier::LittleEndian,
                     ">" => Modifier::BigEndian,
@@ -92,12 +385,26 @@ pub fn parse(inp: &str) -> Result<Format, pest::error::Error<fmt::Rule>> {
                     _ => unreachable!(),
                 });
             }
+            fmt::Rule::offset => {
+                if offset.is_some() {
+                    return Err(custom_error(
+                        "an offset must be followed by a code".to_owned(),
+                        line.as_span(),
+                    ));
+                }
+                offset = Some(parse_offset(&line).map_err(|m| custom_error(m, line.as_span()))?);
+            }
             fmt::Rule::code => {
-                let mut r = line.into_inner();
-                let repeat = r.next().unwrap().as_str().parse().ok();
-                let r = r.next().unwrap();
-                let chr = match r.as_rule() {
-                    fmt::Rule::char => match r.as_str() {
+                let mut r = line.into_inner().peekable();
+                let name = match r.peek().map(|p| p.as_rule()) {
+                    Some(fmt::Rule::name) => Some(r.next().unwrap().as_str().to_owned()),
+                    _ => None,
+                };
+                let rpt = r.next().unwrap();
+                let repeat = parse_repeat(&rpt).map_err(|m| custom_error(m, rpt.as_span()))?;
+                let c = r.next().unwrap();
+                let chr = match c.as_rule() {
+                    fmt::Rule::char => match c.as_str() {
                         "?" => FormatChar::Bool,
                         "B" => FormatChar::UChar,
                         "H" => FormatChar::UShort,
@@ -107,6 +414,8 @@ pub fn parse(inp: &str) -> Result<Format, pest::error::Error<fmt::Rule>> {
                         "Q" => FormatChar::ULongLong,
                         "b" => FormatChar::Char,
                         "d" => FormatChar::Double,
+                        "g" => FormatChar::Extended,
+                        "G" => FormatChar::Quad,
                         "f" => FormatChar::Float,
                         "h" => FormatChar::Short,
                         "i" => FormatChar::Int,
@@ -117,11 +426,36 @@ pub fn parse(inp: &str) -> Result<Format, pest::error::Error<fmt::Rule>> {
                         "x" => FormatChar::Pad,
                         _ => unreachable!(),
                     },
-                    fmt::Rule::ident => FormatChar::Ident(r.as_str().trim_matches('`').to_owned()),
+                    fmt::Rule::ident => FormatChar::Ident(c.as_str().trim_matches('`').to_owned()),
+                    fmt::Rule::typedef => FormatChar::Typedef(match c.as_str() {
+                        "{dev_t}" => Typedef::Dev,
+                        "{ino_t}" => Typedef::Ino,
+                        "{mode_t}" => Typedef::Mode,
+                        "{off_t}" => Typedef::Off,
+                        "{pid_t}" => Typedef::Pid,
+                        "{time_t}" => Typedef::Time,
+                        "{uid_t}" => Typedef::Uid,
+                        _ => unreachable!(),
+                    }),
                     _ => unreachable!(),
                 };
-                codes.push(FormatCode { repeat, chr })
+                let ascription = r.next().map(|a| a.as_str().to_owned());
+                codes.push(FormatCode {
+                    repeat,
+                    chr,
+                    name,
+                    ascription,
+                    offset: offset.take(),
+                })
             }
+            // A trailing offset pads the end
+            fmt::Rule::EOI if offset.is_some() => codes.push(FormatCode {
+                repeat: Some(0),
+                chr: FormatChar::Pad,
+                name: None,
+                ascription: None,
+                offset,
+            }),
             fmt::Rule::EOI => {}
             _ => unreachable!(),
         }
@@ -142,14 +476,20 @@ mod tests {
             p.codes[0]
                 == FormatCode {
                     repeat: Some(2),
-                    chr: FormatChar::Int
+                    chr: FormatChar::Int,
+                    name: None,
+                    ascription: None,
+                    offset: None
                 }
         );
         assert!(
             p.codes[1]
                 == FormatCode {
                     repeat: None,
-                    chr: FormatChar::Bool
+                    chr: FormatChar::Bool,
+                    name: None,
+                    ascription: None,
+                    offset: None
                 }
         );
 
@@ -160,7 +500,10 @@ mod tests {
             p.codes[0]
                 == FormatCode {
                     repeat: Some(2),
-                    chr: FormatChar::Ident("foo".to_owned())
+                    chr: FormatChar::Ident("foo".to_owned()),
+                    name: None,
+                    ascription: None,
+                    offset: None
                 }
         );
 
@@ -171,10 +514,17 @@ mod tests {
             p.codes[0]
                 == FormatCode {
                     repeat: None,
-                    chr: FormatChar::Bool
+                    chr: FormatChar::Bool,
+                    name: None,
+                    ascription: None,
+                    offset: None
                 }
         );
 
+        let p = parse("^q").unwrap();
+        assert_eq!(p.modifier, Some(Modifier::NativePacked));
+        assert!(p.modifier.unwrap().native_types());
+
         let p = parse("! I").unwrap();
         assert_eq!(p.modifier, Some(Modifier::BigEndian));
         assert_eq!(p.codes.len(), 1);
@@ -182,7 +532,10 @@ mod tests {
             p.codes[0]
                 == FormatCode {
                     repeat: None,
-                    chr: FormatChar::UInt
+                    chr: FormatChar::UInt,
+                    name: None,
+                    ascription: None,
+                    offset: None
                 }
         );
     }
@@ -195,15 +548,170 @@ mod tests {
             p.codes.last(),
             Some(&FormatCode {
                 repeat: Some(0),
-                chr: FormatChar::Ident("Foo".to_owned())
+                chr: FormatChar::Ident("Foo".to_owned()),
+                name: None,
+                ascription: None,
+                offset: None
             })
         );
     }
 
+    #[test]
+    fn ascription() {
+        let p = parse("@l:u64 magic:I:u32 b:i8 a:f32s x:i8`Foo` bool:? 2H:u16").unwrap();
+        let codes = p
+            .codes
+            .iter()
+            .map(|c| (c.name.as_deref(), c.ascription.as_deref(), c.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [
+                (None, Some("u64"), "l".to_owned()),
+                (Some("magic"), Some("u32"), "I".to_owned()),
+                (None, Some("i8"), "b".to_owned()),
+                (Some("a"), None, "f".to_owned()),
+                (None, None, "32s".to_owned()),
+                (Some("x"), None, "i".to_owned()),
+                (None, None, "8`Foo`".to_owned()),
+                (Some("bool"), None, "?".to_owned()),
+                (None, Some("u16"), "2H".to_owned()),
+            ]
+        );
+        assert_eq!(p.to_string(), "@lIbf32si8`Foo`?2H");
+        assert!(parse("l:u63").is_err());
+    }
+
+    #[test]
+    fn comments() {
+        let p = parse(
+            "<      # little endian
+             I      # magic
+             2H     # version
+	     3s#no space required
+             # the end",
+        )
+        .unwrap();
+        assert_eq!(p.modifier, Some(Modifier::LittleEndian));
+        assert_eq!(p.codes.len(), 3);
+        assert_eq!(
+            p.codes[2],
+            FormatCode {
+                repeat: Some(3),
+                chr: FormatChar::Array,
+                name: None,
+                ascription: None,
+                offset: None
+            }
+        );
+    }
+
+    #[test]
+    fn counts() {
+        let repeats = |fmt| {
+            parse(fmt)
+                .unwrap()
+                .codes
+                .iter()
+                .map(|c| c.repeat)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            repeats("0x40:s 4Ks 2kB 0x1F:H"),
+            [Some(64), Some(4096), Some(2048), Some(31)]
+        );
+        // Without the colon, `0x` is zero padding bytes, as `b`, `d` and `f` are hex-digits, too
+        assert_eq!(repeats("0x d 0xd"), [Some(0), None, Some(0), None]);
+        assert_eq!(
+            repeats("0xdd 0x10B"),
+            [Some(0), None, None, Some(0), Some(10)]
+        );
+        assert_eq!(repeats("0xd:d 0x10:B"), [Some(13), Some(16)]);
+        assert!(parse("0x:B").is_err());
+        assert!(parse("k s").is_err());
+        let e = parse("99999999999999999999s").unwrap_err().to_string();
+        assert!(
+            e.contains("count `99999999999999999999` is too large"),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(parse("<I2H").unwrap().names(), None);
+        let p = parse("<magic:I ver:2H x _tag9:3s").unwrap();
+        assert_eq!(p.codes[1].name.as_deref(), Some("ver"));
+        assert_eq!(p.to_string(), "<I2Hx3s");
+        assert_eq!(
+            p.names().unwrap(),
+            ["magic", "ver_0", "ver_1", "_tag9"].map(|n| Some(n.to_owned()))
+        );
+        assert_eq!(
+            parse("<a:I H").unwrap().names().unwrap(),
+            [Some("a".to_owned()), None]
+        );
+        assert!(parse("<a :I").is_err());
+        assert!(parse("<1a:I").is_err());
+    }
+
+    #[test]
+    fn display() {
+        let p = parse("<2i ? 3s `Foo` 0Q").unwrap();
+        let codes = p.codes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(codes, ["2i", "?", "3s", "`Foo`", "0Q"]);
+        assert_eq!(p.to_string(), "<2i?3s`Foo`0Q");
+        let p = parse("! 2i # comment\nx").unwrap();
+        assert_eq!(p.to_string(), ">2ix");
+        let codes = vec![
+            FormatCode::new(FormatChar::UInt).with_name("magic"),
+            FormatCode::new(FormatChar::Pad)
+                .with_repeat(2)
+                .with_offset(8),
+            FormatCode::new(FormatChar::Long).with_ascription("u64"),
+        ];
+        let p = Format::new(Some(Modifier::LittleEndian), codes);
+        assert_eq!(p.to_string(), "<I@8 2xl");
+        assert_eq!(parse("<magic:I @8 2x l:u64").unwrap().codes, p.codes);
+    }
+
+    #[test]
+    fn offsets() {
+        let p = parse("@B @0x10 2H @20 # end\n").unwrap();
+        assert_eq!(p.modifier, Some(Modifier::Native));
+        let offsets = p.codes.iter().map(|c| c.offset).collect::<Vec<_>>();
+        assert_eq!(offsets, [None, Some(16), Some(20)]);
+        assert_eq!(p.codes[2].to_string(), "0x");
+        assert_eq!(p.to_string(), "@B@16 2H@20 0x");
+        assert_eq!(parse(&p.to_string()).unwrap().codes, p.codes);
+        let p = parse("@4 B").unwrap();
+        assert_eq!((&p.modifier, p.codes[0].offset), (&None, Some(4)));
+        assert_eq!(p.to_string(), "@4 B");
+        let p = parse("<I @8 `Foo`").unwrap();
+        assert_eq!(p.modifier, Some(Modifier::LittleEndian));
+        assert_eq!(p.codes[1].offset, Some(8));
+        assert_eq!(parse("@2I").unwrap().modifier, Some(Modifier::Native));
+        assert!(parse("<@8I").is_err());
+        assert!(parse("<@8 @16 I").is_err());
+        assert!(parse("<@0x1000000000000000000 I").is_err());
+    }
+
     #[test]
     fn invalid() {
         assert!(parse("!vd").is_err());
-        assert!(parse("@3 b").is_err());
+        assert!(parse("<3 b").is_err());
         assert!(parse("`").is_err());
+        assert!(parse("3 # count\ni").is_err());
+    }
+
+    #[test]
+    fn typedef() {
+        let p = parse("@st:{time_t} 2{off_t} 0x2:{pid_t}").unwrap();
+        assert_eq!(p.codes[0].chr, FormatChar::Typedef(Typedef::Time));
+        assert_eq!(p.codes[1].repeat, Some(2));
+        assert_eq!(p.codes[2].repeat, Some(2));
+        assert_eq!(p.to_string(), "@{time_t}2{off_t}2{pid_t}");
+        assert!(parse("@{size_t}").is_err());
+        assert!(parse("@{ time_t }").is_err());
     }
 }
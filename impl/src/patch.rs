@@ -0,0 +1,114 @@
+//! Writing a header in front of data whose length is only known once it has been written.
+//!
+//! Chunked container formats (e.g. RIFF or PNG) put the length, and sometimes a checksum, of the
+//! data into a header preceding it. A [`PatchWriter`] writes a placeholder for the header, counts
+//! the bytes written after it, and finally seeks back to write the actual header. As it
+//! implements `Seek` itself, writers can be nested, e.g. for chunks inside a RIFF-file.
+//!
+//! ```
+//! #[derive(restruct_derive::Struct)]
+//! #[fmt = "<4sI4s"]
+//! struct RiffHeader;
+//!
+//! use std::io::Write;
+//!
+//! let out = std::io::Cursor::new(Vec::new());
+//! let mut w = restruct::patch::PatchWriter::<_, RiffHeader>::new(out)?;
+//! w.write_all(b"fmt ")?;
+//! let len = w.len() as u32 + 4;
+//! let out = w.finish(&(*b"RIFF", len, *b"WAVE"))?.into_inner();
+//! assert_eq!(out, b"RIFF\x08\0\0\0WAVEfmt ");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{self, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+use crate::Struct;
+
+/// A writer for data preceded by a header of the derived type `T`, which is written last.
+pub struct PatchWriter<W, T> {
+    inner: W,
+    /// The position of the header
+    start: u64,
+    /// The current position, as in the underlying writer
+    pos: u64,
+    len: u64,
+    crc32: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<W: Write + Seek, T: Struct> PatchWriter<W, T> {
+    /// Write `T::SIZE` zero bytes as a placeholder for the header at the current position.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        let start = inner.stream_position()?;
+        inner.write_all(&vec![0; T::SIZE])?;
+        Ok(Self {
+            inner,
+            start,
+            pos: start + T::SIZE as u64,
+            len: 0,
+            crc32: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The number of bytes written after the header so far, up to the furthest position written
+    /// to.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// `true` if nothing was written after the header so far.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The CRC-32 (see `restruct::checksum::crc32()`) of the bytes written after the header so
+    /// far. Only bytes appended at the end are taken into account; bytes overwritten by seeking
+    /// back, e.g. the headers of nested writers, are not.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Seek back to write the given header in place of the placeholder, then seek to the end of
+    /// the data written after it, returning the underlying writer.
+    pub fn finish(mut self, header: &T::Unpacked) -> io::Result<W> {
+        self.inner.flush()?;
+        self.inner.seek(SeekFrom::Start(self.start))?;
+        self.inner.write_all(T::pack_ref(header).as_ref())?;
+        self.inner
+            .seek(SeekFrom::Start(self.start + T::SIZE as u64 + self.len))?;
+        Ok(self.inner)
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+}
+
+impl<W: Write, T: Struct> Write for PatchWriter<W, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        let data_start = self.start + T::SIZE as u64;
+        if self.pos == data_start + self.len {
+            self.crc32 = crate::checksum::crc32_update(self.crc32, &buf[..n]);
+        }
+        self.pos += n as u64;
+        self.len = self.len.max(self.pos.saturating_sub(data_start));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Seeking is relative to the underlying writer, not to the start of the data.
+impl<W: Seek, T> Seek for PatchWriter<W, T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,188 @@
+//! Python bindings for the runtime interpreter, if the `pyo3`-feature is enabled.
+//!
+//! The class mirrors Python's `struct.Struct`, but accepts the Format Strings understood by
+//! `restruct`. It is added to an extension module via [`register()`]:
+//!
+//! ```ignore
+//! #[pyo3::pymodule]
+//! fn formats(m: &pyo3::Bound<'_, pyo3::types::PyModule>) -> pyo3::PyResult<()> {
+//!     restruct::python::register(m)
+//! }
+//! ```
+//!
+//! ```python
+//! from formats import DynStruct
+//! header = DynStruct("<I2H")
+//! assert header.unpack(header.pack(0xa1b2c3d4, 2, 4)) == (0xa1b2c3d4, 2, 4)
+//! ```
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyTuple};
+
+use crate::{dynamic, DynStruct, Value};
+
+/// A Format String, interpreted at runtime; exposed to Python as `DynStruct`.
+#[pyclass(name = "DynStruct", frozen)]
+pub struct PyDynStruct {
+    inner: DynStruct,
+    fmt: String,
+}
+
+impl PyDynStruct {
+    /// Convert a Python object into a value for the element at the given index. Byte-strings
+    /// are zero-padded or truncated to the element's size, like Python's `struct` does.
+    fn extract(&self, index: usize, obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+        let size = self
+            .inner
+            .elements()
+            .nth(index)
+            .map_or(0, |(_, _, size)| size);
+        Ok(match self.inner.zero_value(index) {
+            Value::Bool(_) => Value::Bool(obj.is_truthy()?),
+            Value::I8(_) => Value::I8(obj.extract()?),
+            Value::I16(_) => Value::I16(obj.extract()?),
+            Value::I32(_) => Value::I32(obj.extract()?),
+            Value::I64(_) => Value::I64(obj.extract()?),
+            Value::U8(_) => Value::U8(obj.extract()?),
+            Value::U16(_) => Value::U16(obj.extract()?),
+            Value::U32(_) => Value::U32(obj.extract()?),
+            Value::U64(_) => Value::U64(obj.extract()?),
+            Value::F32(_) => Value::F32(obj.extract()?),
+            Value::F64(_) => Value::F64(obj.extract()?),
+            Value::Bytes(_) => {
+                let bytes = obj.cast::<PyBytes>().map_err(|_| {
+                    PyTypeError::new_err(format!("element {} must be of type bytes", index))
+                })?;
+                let mut v = bytes.as_bytes().to_vec();
+                v.resize(size, 0);
+                Value::Bytes(v)
+            }
+        })
+    }
+}
+
+fn into_py<'py>(py: Python<'py>, value: Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Value::Bool(v) => v.into_pyobject(py)?.to_owned().into_any(),
+        Value::I8(v) => v.into_pyobject(py)?.into_any(),
+        Value::I16(v) => v.into_pyobject(py)?.into_any(),
+        Value::I32(v) => v.into_pyobject(py)?.into_any(),
+        Value::I64(v) => v.into_pyobject(py)?.into_any(),
+        Value::U8(v) => v.into_pyobject(py)?.into_any(),
+        Value::U16(v) => v.into_pyobject(py)?.into_any(),
+        Value::U32(v) => v.into_pyobject(py)?.into_any(),
+        Value::U64(v) => v.into_pyobject(py)?.into_any(),
+        Value::F32(v) => v.into_pyobject(py)?.into_any(),
+        Value::F64(v) => v.into_pyobject(py)?.into_any(),
+        Value::Bytes(v) => PyBytes::new(py, &v).into_any(),
+    })
+}
+
+#[pymethods]
+impl PyDynStruct {
+    #[new]
+    fn new(fmt: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: DynStruct::new(fmt).map_err(|e| PyValueError::new_err(e.to_string()))?,
+            fmt: fmt.to_owned(),
+        })
+    }
+
+    /// The Format String
+    #[getter]
+    fn format(&self) -> &str {
+        &self.fmt
+    }
+
+    /// The size in bytes of the packed representation
+    #[getter]
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    /// Pack the given values into a bytes-object.
+    #[pyo3(signature = (*values))]
+    fn pack<'py>(
+        &self,
+        py: Python<'py>,
+        values: &Bound<'py, PyTuple>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        if values.len() != self.inner.len() {
+            let e = dynamic::Error::Count {
+                expected: self.inner.len(),
+                actual: values.len(),
+            };
+            return Err(PyValueError::new_err(e.to_string()));
+        }
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(i, obj)| self.extract(i, &obj))
+            .collect::<PyResult<Vec<_>>>()?;
+        let packed = self
+            .inner
+            .pack(&values)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyBytes::new(py, &packed))
+    }
+
+    /// Unpack the first `size` bytes of the given buffer into a tuple.
+    fn unpack<'py>(&self, py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyTuple>> {
+        let values = self
+            .inner
+            .unpack(data)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        PyTuple::new(
+            py,
+            values
+                .into_iter()
+                .map(|v| into_py(py, v))
+                .collect::<PyResult<Vec<_>>>()?,
+        )
+    }
+
+    fn __repr__(&self) -> String {
+        format!("DynStruct({:?})", self.fmt)
+    }
+}
+
+/// Add the `DynStruct`-class to the given module.
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyDynStruct>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "formats").unwrap();
+            register(&module).unwrap();
+            let locals = pyo3::types::PyDict::new(py);
+            locals.set_item("formats", module).unwrap();
+            py.run(
+                c"
+s = formats.DynStruct('<?h3sxd')
+assert s.size == 15 and s.format == '<?h3sxd'
+packed = s.pack(1, -2, b'ab', 0.5)
+assert packed == b'\\x01\\xfe\\xffab\\x00\\x00\\x00\\x00\\x00\\x00\\x00\\x00\\xe0\\x3f'
+assert s.unpack(packed) == (True, -2, b'ab\\x00', 0.5)
+for args in [(1, 2), (1, 2, 3, 4.0), (1, 2**15, b'', 0.0)]:
+    try:
+        s.pack(*args)
+    except (ValueError, TypeError, OverflowError):
+        pass
+    else:
+        raise AssertionError(args)
+",
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+}
@@ -0,0 +1,367 @@
+//! Reading and writing sequences of records of a derived type.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use crate::{Error, Struct};
+
+/// An iterator over consecutive records of the derived type `T`, read from any `io::Read`.
+///
+/// Reads are buffered. Reaching the end of input exactly at a record boundary ends iteration;
+/// reaching it within a record yields `Error::Truncated`. Iteration ends after the first error.
+/// To wait for a growing input instead of ending, see `follow()`.
+///
+/// The `RecordReader` also implements `io::Read` and `io::BufRead`, so variable-length data
+/// between records can be read from it.
+///
+/// ```
+/// #[derive(restruct_derive::Struct)]
+/// #[fmt = "<HH"]
+/// struct Point;
+///
+/// let data = [1, 0, 2, 0, 3, 0, 4, 0, 5];
+/// let mut reader = restruct::RecordReader::<_, Point>::new(&data[..]);
+/// assert_eq!(reader.next().unwrap().unwrap(), (1, 2));
+/// assert_eq!(reader.next().unwrap().unwrap(), (3, 4));
+/// assert!(reader.next().unwrap().is_err());
+/// assert!(reader.next().is_none());
+/// ```
+pub struct RecordReader<R, T> {
+    inner: BufReader<R>,
+    buf: Vec<u8>,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: Read, T: Struct> RecordReader<R, T> {
+    /// Create a new `RecordReader` with a default buffer capacity.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(8 * 1024, inner)
+    }
+
+    /// Create a new `RecordReader` with the given buffer capacity.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner: BufReader::with_capacity(capacity.max(T::SIZE), inner),
+            buf: vec![0; T::SIZE],
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Read the next record; `Ok(None)` is returned if the input ends exactly at a record
+    /// boundary.
+    pub fn read_record(&mut self) -> Result<Option<T::Unpacked>, Error> {
+        // Unpack directly from the buffer if it holds a complete record
+        let buf = self.inner.fill_buf()?;
+        if T::SIZE > 0 && buf.len() >= T::SIZE {
+            let res = T::unpack_slice(buf);
+            self.inner.consume(T::SIZE);
+            return Ok(Some(res));
+        }
+        Ok(if self.fill_record()? {
+            Some(T::unpack_slice(&self.buf))
+        } else {
+            None
+        })
+    }
+
+    /// Read the next record into the internal buffer; `false` is returned if the input ends
+    /// exactly at a record boundary.
+    fn fill_record(&mut self) -> Result<bool, Error> {
+        match crate::read_exact(&mut self.inner, &mut self.buf) {
+            Ok(()) => Ok(true),
+            Err(Error::Eof) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read the next record for which `valid` returns `true`, along with the number of bytes
+    /// skipped to find it. After an invalid record, e.g. one with a bad magic number or checksum,
+    /// the input is scanned forward byte-by-byte for the next valid record.
+    pub fn read_valid_record<F: FnMut(&T::Unpacked) -> bool>(
+        &mut self,
+        mut valid: F,
+    ) -> Result<Option<(usize, T::Unpacked)>, Error> {
+        if !self.fill_record()? {
+            return Ok(None);
+        }
+        let mut skipped = 0;
+        loop {
+            let res = T::unpack_slice(&self.buf);
+            if valid(&res) {
+                return Ok(Some((skipped, res)));
+            }
+            if self.buf.is_empty() {
+                return Err(Error::Invalid("empty record".to_owned()));
+            }
+            skipped += 1;
+            self.buf.rotate_left(1);
+            let last = self.buf.len() - 1;
+            match crate::read_exact(&mut self.inner, &mut self.buf[last..]) {
+                Ok(()) => {}
+                Err(Error::Eof) => {
+                    return Err(Error::Invalid(format!(
+                        "no valid record after skipping {} bytes",
+                        skipped
+                    )))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Turn this reader into an iterator over valid records, see `read_valid_record()`.
+    pub fn resync<F: FnMut(&T::Unpacked) -> bool>(self, valid: F) -> Resync<R, T, F> {
+        Resync {
+            reader: self,
+            valid,
+            done: false,
+        }
+    }
+
+    /// Turn this reader into an iterator which, like `tail -f`, waits for the input to grow instead
+    /// of ending, e.g. to consume a log file while it is being written. Whenever the input has no
+    /// more data, the iterator sleeps for `poll_interval` and reads again. Bytes of a partially
+    /// written record are kept until the record is complete. Iteration only ends after an IO
+    /// error.
+    pub fn follow(self, poll_interval: Duration) -> Follow<R, T> {
+        Follow {
+            reader: self,
+            filled: 0,
+            poll_interval,
+            done: false,
+        }
+    }
+
+    /// Discard the next `n` records without unpacking them. If their size overflows an `u64`,
+    /// an error of kind `InvalidInput` is returned.
+    pub fn skip_records(&mut self, n: u64) -> Result<(), Error> {
+        let len = n
+            .checked_mul(T::SIZE as u64)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        let skipped = io::copy(&mut (&mut self.inner).take(len), &mut io::sink())?;
+        match (skipped % T::SIZE.max(1) as u64) as usize {
+            _ if skipped == len => Ok(()),
+            0 => Err(Error::Eof),
+            got => Err(Error::Truncated {
+                got,
+                expected: T::SIZE,
+            }),
+        }
+    }
+
+    /// A reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        self.inner.get_ref()
+    }
+
+    /// A mutable reference to the underlying reader. Reading from it directly skips over
+    /// buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.inner.get_mut()
+    }
+
+    /// Unwrap the underlying reader, discarding buffered data.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+impl<R: Read, T: Struct> Iterator for RecordReader<R, T> {
+    type Item = Result<T::Unpacked, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Records of size zero would never end
+        if self.done || T::SIZE == 0 {
+            return None;
+        }
+        let res = self.read_record().transpose();
+        self.done = !matches!(res, Some(Ok(_)));
+        res
+    }
+}
+
+/// What to do about records failing validation while reading many of them, e.g. via the
+/// generated `iter_valid()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OnInvalid {
+    /// Yield the error and end iteration.
+    Stop,
+    /// Skip the record and continue with the next one.
+    Skip,
+}
+
+/// An iterator over valid records and the number of bytes skipped to find each of them, created
+/// by `RecordReader::resync()`. Iteration ends after the first error.
+pub struct Resync<R, T, F> {
+    reader: RecordReader<R, T>,
+    valid: F,
+    done: bool,
+}
+
+impl<R, T, F> Resync<R, T, F> {
+    /// Unwrap the underlying `RecordReader`.
+    pub fn into_inner(self) -> RecordReader<R, T> {
+        self.reader
+    }
+}
+
+impl<R: Read, T: Struct, F: FnMut(&T::Unpacked) -> bool> Iterator for Resync<R, T, F> {
+    type Item = Result<(usize, T::Unpacked), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.reader.read_valid_record(&mut self.valid).transpose();
+        self.done = !matches!(res, Some(Ok(_)));
+        res
+    }
+}
+
+/// An iterator over records of a growing input, created by `RecordReader::follow()`.
+pub struct Follow<R, T> {
+    reader: RecordReader<R, T>,
+    filled: usize,
+    poll_interval: Duration,
+    done: bool,
+}
+
+impl<R, T> Follow<R, T> {
+    /// The number of bytes read of a record which is not complete yet.
+    pub fn pending(&self) -> usize {
+        self.filled
+    }
+
+    /// Unwrap the underlying `RecordReader`, discarding the bytes of an incomplete record.
+    pub fn into_inner(self) -> RecordReader<R, T> {
+        self.reader
+    }
+}
+
+impl<R: Read, T: Struct> Iterator for Follow<R, T> {
+    type Item = Result<T::Unpacked, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Records of size zero would never end
+        if self.done || T::SIZE == 0 {
+            return None;
+        }
+        loop {
+            match self.reader.inner.read(&mut self.reader.buf[self.filled..]) {
+                Ok(0) => std::thread::sleep(self.poll_interval),
+                Ok(n) => {
+                    self.filled += n;
+                    if self.filled == T::SIZE {
+                        self.filled = 0;
+                        return Some(Ok(T::unpack_slice(&self.reader.buf)));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read, T> Read for RecordReader<R, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Read, T> BufRead for RecordReader<R, T> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+/// A buffered writer of consecutive records of the derived type `T`, to any `io::Write`.
+///
+/// Records are packed into an internal buffer, which is written in large chunks. As with
+/// `BufWriter`, the buffer is flushed when the `RecordWriter` is dropped, ignoring errors; call
+/// `flush()` or `into_inner()` to handle them.
+///
+/// The `RecordWriter` also implements `io::Write`, so variable-length data between records can be
+/// written to it.
+///
+/// ```
+/// #[derive(restruct_derive::Struct)]
+/// #[fmt = "<HH"]
+/// struct Point;
+///
+/// let mut writer = restruct::RecordWriter::<_, Point>::new(Vec::new());
+/// writer.write_record(&(1, 2))?;
+/// writer.write_records(vec![(3, 4), (5, 6)])?;
+/// assert_eq!(writer.into_inner()?, [1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct RecordWriter<W: Write, T> {
+    inner: BufWriter<W>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<W: Write, T: Struct> RecordWriter<W, T> {
+    /// Create a new `RecordWriter` with a default buffer capacity.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(8 * 1024, inner)
+    }
+
+    /// Create a new `RecordWriter` with the given buffer capacity.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner: BufWriter::with_capacity(capacity.max(T::SIZE), inner),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Pack the given record into the buffer, writing the buffer if it is full.
+    pub fn write_record(&mut self, inp: &T::Unpacked) -> io::Result<()> {
+        self.inner.write_all(T::pack_ref(inp).as_ref())
+    }
+
+    /// Pack all given records into the buffer, writing the buffer whenever it is full.
+    pub fn write_records<I: IntoIterator<Item = T::Unpacked>>(
+        &mut self,
+        records: I,
+    ) -> io::Result<()> {
+        records
+            .into_iter()
+            .try_for_each(|inp| self.write_record(&inp))
+    }
+
+    /// A reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.get_ref()
+    }
+
+    /// A mutable reference to the underlying writer. Writing to it directly bypasses buffered
+    /// data.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.get_mut()
+    }
+
+    /// Write the buffer and unwrap the underlying writer.
+    pub fn into_inner(self) -> Result<W, io::IntoInnerError<BufWriter<W>>> {
+        self.inner.into_inner()
+    }
+}
+
+impl<W: Write, T> Write for RecordWriter<W, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
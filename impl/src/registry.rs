@@ -0,0 +1,93 @@
+//! A registry of all derived types linked into the final binary, if the `registry`-feature is
+//! enabled.
+//!
+//! Each derived type registers a [`Descriptor`] via the `inventory`-crate, so an application can
+//! enumerate every format it contains, e.g. for diagnostics or to generate documentation. Generic
+//! types are not registered.
+//!
+//! ```
+//! for desc in restruct::registry::iter() {
+//!     println!("{} ({:?}): {} bytes", desc.name, desc.format, desc.size);
+//! }
+//! ```
+
+#[doc(hidden)]
+pub use inventory;
+
+/// The description of a derived type.
+#[derive(Debug)]
+pub struct Descriptor {
+    /// The path of the type, e.g. `my_crate::proto::Header`
+    pub name: &'static str,
+    /// The Format String, without whitespace, comments and names
+    pub format: &'static str,
+    /// The size in bytes of the packed representation
+    pub size: usize,
+    /// The type's `FIELDS`, giving the type, offset, alignment and total size of each field
+    pub fields: &'static [(&'static str, usize, usize, usize)],
+    /// The names of the elements of the unpacked representation, if given
+    pub names: Option<&'static [&'static str]>,
+}
+
+inventory::collect!(Descriptor);
+
+/// All registered types, in no particular order.
+pub fn iter() -> impl Iterator<Item = &'static Descriptor> {
+    inventory::iter::<Descriptor>.into_iter()
+}
+
+/// The registered type of the given path, e.g. `my_crate::proto::Header`.
+pub fn find(name: &str) -> Option<&'static Descriptor> {
+    iter().find(|desc| desc.name == name)
+}
+
+#[cfg(feature = "serde_json")]
+impl Descriptor {
+    /// Unpack the packed representation via the runtime interpreter into a JSON object, keyed by
+    /// the elements' names or `field_0`, `field_1`, ... if the type has none. Types holding nested
+    /// types or typedefs can't be interpreted at runtime.
+    pub fn to_json(&self, inp: &[u8]) -> Result<serde_json::Value, crate::dynamic::Error> {
+        let fmt = crate::DynStruct::cached(self.format)?;
+        match self.names {
+            Some(names) => fmt.to_json_named(inp, names),
+            None => fmt.to_json(inp),
+        }
+    }
+
+    /// Pack a JSON object as produced by `to_json()` via the runtime interpreter, see
+    /// `DynStruct::from_json()`.
+    pub fn from_json(&self, value: &serde_json::Value) -> Result<Vec<u8>, crate::dynamic::Error> {
+        let fmt = crate::DynStruct::cached(self.format)?;
+        match self.names {
+            Some(names) => fmt.pack_json_named(value, names),
+            None => fmt.from_json(value),
+        }
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl Descriptor {
+    /// Convert the consecutive packed records in the given buffer via the runtime interpreter into
+    /// a `RecordBatch`, naming the columns like `to_json()`. See `restruct::arrow::record_batch()`.
+    pub fn record_batch(
+        &self,
+        buf: &[u8],
+    ) -> Result<arrow_array::RecordBatch, Box<dyn std::error::Error + Send + Sync>> {
+        let fmt = crate::DynStruct::cached(self.format)?;
+        Ok(match self.names {
+            Some(names) => crate::arrow::record_batch_named(&fmt, buf, names)?,
+            None => crate::arrow::record_batch(&fmt, buf)?,
+        })
+    }
+
+    /// Write the consecutive packed records in the given buffer as a Parquet file, see
+    /// `record_batch()`.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W: std::io::Write + Send>(
+        &self,
+        buf: &[u8],
+        w: W,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(crate::arrow::write_batch(self.record_batch(buf)?, w)?)
+    }
+}
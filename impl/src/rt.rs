@@ -0,0 +1,493 @@
+//! Helpers called by the generated code, shared by all derived types instead of being generated
+//! for each of them.
+
+/// The number of bytes needed to align `ptr` to `align`.
+#[inline]
+pub const fn align(align: usize, ptr: usize) -> usize {
+    let offset = ptr % align;
+    (offset != 0) as usize * (align - offset)
+}
+
+/// Compute the offset, the alignment and the total size for each field, given their required
+/// alignment and size. The first field is never aligned.
+pub const fn layout<const N: usize>(fields: [(usize, usize); N]) -> [(usize, usize, usize); N] {
+    let mut res = [(0, 0, 0); N];
+    let mut offset = 0;
+    let mut i = 0;
+    while i < N {
+        let alignment = if i == 0 {
+            0
+        } else {
+            align(fields[i].0, offset)
+        };
+        res[i] = (offset, alignment, alignment + fields[i].1);
+        offset += alignment + fields[i].1;
+        i += 1;
+    }
+    res
+}
+
+/// As `layout()`, but fields may be given an absolute offset, the alignment being the number of
+/// bytes up to it. Fails to evaluate if an offset lies before the end of the preceding fields.
+pub const fn layout_at<const N: usize>(
+    fields: [(usize, usize, Option<usize>); N],
+) -> [(usize, usize, usize); N] {
+    let mut res = [(0, 0, 0); N];
+    let mut offset = 0;
+    let mut i = 0;
+    while i < N {
+        let alignment = match fields[i].2 {
+            Some(at) if at < offset => {
+                panic!("an absolute offset lies before the end of the preceding fields")
+            }
+            Some(at) => at - offset,
+            None if i == 0 => 0,
+            None => align(fields[i].0, offset),
+        };
+        res[i] = (offset, alignment, alignment + fields[i].1);
+        offset += alignment + fields[i].1;
+        i += 1;
+    }
+    res
+}
+
+/// The largest of the given alignments, at least `1`.
+pub const fn max_alignment<const N: usize>(aligns: [usize; N]) -> usize {
+    let mut res = 1;
+    let mut i = 0;
+    while i < N {
+        if aligns[i] > res {
+            res = aligns[i];
+        }
+        i += 1;
+    }
+    res
+}
+
+/// Copy `N` bytes at `offset` as a whole, which the optimizer can usually elide.
+#[inline]
+pub const fn read_field<const N: usize>(inp: &[u8], offset: usize) -> [u8; N] {
+    match inp.split_at(offset).1.first_chunk::<N>() {
+        Some(buf) => *buf,
+        None => panic!("field out of bounds"),
+    }
+}
+
+/// Copy the given `N` bytes into the buffer at `offset`.
+#[inline]
+pub const fn write_field<const S: usize, const N: usize>(
+    mut buf: [u8; S],
+    offset: usize,
+    inp: [u8; N],
+) -> [u8; S] {
+    let mut i = 0;
+    while i < N {
+        buf[offset + i] = inp[i];
+        i += 1;
+    }
+    buf
+}
+
+/// `true` if `a` and `b` are equal in `len` bytes starting at `offset`.
+#[inline]
+pub const fn range_eq(a: &[u8], b: &[u8], offset: usize, len: usize) -> bool {
+    let mut i = offset;
+    while i < offset + len {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Compare `a` and `b` lexicographically in `len` bytes starting at `offset`.
+#[inline]
+pub const fn range_cmp(a: &[u8], b: &[u8], offset: usize, len: usize) -> core::cmp::Ordering {
+    let mut i = offset;
+    while i < offset + len {
+        if a[i] != b[i] {
+            return if a[i] < b[i] {
+                core::cmp::Ordering::Less
+            } else {
+                core::cmp::Ordering::Greater
+            };
+        }
+        i += 1;
+    }
+    core::cmp::Ordering::Equal
+}
+
+/// Reverse the order of the given bytes if `swap` is `true`.
+const fn swap_bytes<const N: usize>(mut inp: [u8; N], swap: bool) -> [u8; N] {
+    if swap {
+        let mut i = 0;
+        while i < N / 2 {
+            let b = inp[i];
+            inp[i] = inp[N - 1 - i];
+            inp[N - 1 - i] = b;
+            i += 1;
+        }
+    }
+    inp
+}
+
+/// Round the number `m * 2^(e - 63)` to the nearest `f64`, ties to even; `m` has it's highest
+/// bit set, `sticky` tells if there are non-zero bits below `m`.
+const fn round_to_f64(negative: bool, e: i32, m: u64, sticky: bool) -> f64 {
+    let sign = (negative as u64) << 63;
+    if e > 1023 {
+        return f64::from_bits(sign | f64::INFINITY.to_bits());
+    }
+    // The number of low bits not fitting the significand, more for subnormals
+    let shift = if e >= -1022 {
+        11
+    } else {
+        11 + (-1022 - e) as u32
+    };
+    if shift > 64 {
+        return f64::from_bits(sign);
+    }
+    let m = m as u128;
+    let mut kept = (m >> shift) as u64;
+    let rem = m & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if rem > half || (rem == half && (sticky || kept & 1 == 1)) {
+        kept += 1;
+    }
+    if e < -1022 {
+        // Rounding up may carry into the smallest normal exponent, which is what the bits say
+        return f64::from_bits(sign | kept);
+    }
+    let (e, kept) = if kept == 1 << 53 {
+        (e + 1, kept >> 1)
+    } else {
+        (e, kept)
+    };
+    if e > 1023 {
+        return f64::from_bits(sign | f64::INFINITY.to_bits());
+    }
+    f64::from_bits(sign | ((e + 1023) as u64) << 52 | (kept & ((1 << 52) - 1)))
+}
+
+/// Split a finite, non-zero `f64` into it's exponent and significand, the latter having it's
+/// highest bit set, such that the value is `m * 2^(e - 63)`.
+const fn split_f64(v: f64) -> (i32, u64) {
+    let bits = v.to_bits();
+    let exp = ((bits >> 52) & 0x7ff) as i32;
+    let frac = bits & ((1 << 52) - 1);
+    if exp == 0 {
+        let lz = frac.leading_zeros();
+        (-1011 - lz as i32, frac << lz)
+    } else {
+        (exp - 1023, 1 << 63 | frac << 11)
+    }
+}
+
+/// Convert an x87 80-bit extended precision number to the nearest `f64`. Numbers too small
+/// for an `f64` become zero, those too large become infinite.
+pub const fn f80_to_f64(inp: [u8; 10], big_endian: bool) -> f64 {
+    let inp = swap_bytes(inp, big_endian);
+    let [m0, m1, m2, m3, m4, m5, m6, m7, e0, e1] = inp;
+    let m = u64::from_le_bytes([m0, m1, m2, m3, m4, m5, m6, m7]);
+    let se = u16::from_le_bytes([e0, e1]);
+    let negative = se >> 15 == 1;
+    let exp = (se & 0x7fff) as i32;
+    let sign = (negative as u64) << 63;
+    if exp == 0x7fff {
+        return if m << 1 == 0 {
+            f64::from_bits(sign | f64::INFINITY.to_bits())
+        } else {
+            f64::from_bits(sign | f64::NAN.to_bits())
+        };
+    }
+    if exp == 0 || m == 0 {
+        // Zero and denormals are far below the range of `f64`
+        return f64::from_bits(sign);
+    }
+    if m >> 63 == 0 {
+        // Unnormals are invalid operands on x87
+        return f64::from_bits(sign | f64::NAN.to_bits());
+    }
+    round_to_f64(negative, exp - 16383, m, false)
+}
+
+/// Convert an `f64` to an x87 80-bit extended precision number, which is exact.
+pub const fn f64_to_f80(inp: f64, big_endian: bool) -> [u8; 10] {
+    let bits = inp.to_bits();
+    let sign = ((bits >> 63) as u16) << 15;
+    let (exp, m) = if inp.is_nan() {
+        (
+            0x7fff,
+            0xc000_0000_0000_0000 | (bits & ((1 << 52) - 1)) << 11,
+        )
+    } else if inp.is_infinite() {
+        (0x7fff, 1 << 63)
+    } else if inp == 0.0 {
+        (0, 0)
+    } else {
+        let (e, m) = split_f64(inp);
+        ((e + 16383) as u16, m)
+    };
+    let [m0, m1, m2, m3, m4, m5, m6, m7] = m.to_le_bytes();
+    let [e0, e1] = (sign | exp).to_le_bytes();
+    swap_bytes([m0, m1, m2, m3, m4, m5, m6, m7, e0, e1], big_endian)
+}
+
+/// Convert an IEEE 754 binary128 number to the nearest `f64`. Numbers too small for an `f64`
+/// become zero, those too large become infinite.
+pub const fn f128_to_f64(inp: [u8; 16], big_endian: bool) -> f64 {
+    let bits = u128::from_le_bytes(swap_bytes(inp, big_endian));
+    let negative = bits >> 127 == 1;
+    let exp = ((bits >> 112) & 0x7fff) as i32;
+    let frac = bits & ((1 << 112) - 1);
+    let sign = (negative as u64) << 63;
+    if exp == 0x7fff {
+        return if frac == 0 {
+            f64::from_bits(sign | f64::INFINITY.to_bits())
+        } else {
+            f64::from_bits(sign | f64::NAN.to_bits())
+        };
+    }
+    if exp == 0 {
+        // Zero and subnormals are far below the range of `f64`
+        return f64::from_bits(sign);
+    }
+    let m = 1 << 63 | (frac >> 49) as u64;
+    round_to_f64(negative, exp - 16383, m, frac & ((1 << 49) - 1) != 0)
+}
+
+/// Convert an `f64` to an IEEE 754 binary128 number, which is exact.
+pub const fn f64_to_f128(inp: f64, big_endian: bool) -> [u8; 16] {
+    let bits = inp.to_bits();
+    let sign = ((bits >> 63) as u128) << 127;
+    let (exp, frac) = if inp.is_nan() {
+        (0x7fff, 1 << 111 | ((bits & ((1 << 52) - 1)) as u128) << 60)
+    } else if inp.is_infinite() {
+        (0x7fff, 0)
+    } else if inp == 0.0 {
+        (0, 0)
+    } else {
+        let (e, m) = split_f64(inp);
+        ((e + 16383) as u128, ((m as u128) << 49) & ((1 << 112) - 1))
+    };
+    swap_bytes((sign | exp << 112 | frac).to_le_bytes(), big_endian)
+}
+
+/// Render the given bytes as lowercase hexadecimal digits.
+pub fn to_hex(inp: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut res = String::with_capacity(inp.len() * 2);
+    for b in inp {
+        res.push(DIGITS[usize::from(b >> 4)] as char);
+        res.push(DIGITS[usize::from(b & 0xf)] as char);
+    }
+    res
+}
+
+/// Parse exactly `N` bytes given as pairs of hexadecimal digits; ASCII whitespace between
+/// pairs is ignored.
+pub fn from_hex<const N: usize>(inp: &str) -> Result<[u8; N], crate::Error> {
+    let mut res = [0; N];
+    let mut found = 0;
+    let mut digits = inp
+        .char_indices()
+        .filter(|(_, c)| !c.is_ascii_whitespace())
+        .map(|(i, c)| {
+            c.to_digit(16).map(|d| d as u8).ok_or_else(|| {
+                crate::Error::Invalid(format!("invalid hexadecimal digit `{}` at {}", c, i))
+            })
+        });
+    while let Some(hi) = digits.next() {
+        let hi = hi?;
+        let lo = digits.next().ok_or_else(|| {
+            crate::Error::Invalid("odd number of hexadecimal digits".to_owned())
+        })??;
+        if let Some(b) = res.get_mut(found) {
+            *b = hi << 4 | lo;
+        }
+        found += 1;
+    }
+    if found != N {
+        return Err(crate::UnpackError::Size { expected: N, found }.into());
+    }
+    Ok(res)
+}
+
+/// Render the given bytes in standard Base64 with padding.
+#[cfg(feature = "base64")]
+pub fn to_base64(inp: &[u8]) -> String {
+    use ::base64::Engine;
+    ::base64::engine::general_purpose::STANDARD.encode(inp)
+}
+
+/// Parse exactly `N` bytes given in standard Base64.
+#[cfg(feature = "base64")]
+pub fn from_base64<const N: usize>(inp: &str) -> Result<[u8; N], crate::Error> {
+    use ::base64::Engine;
+    let buf = ::base64::engine::general_purpose::STANDARD
+        .decode(inp)
+        .map_err(|e| crate::Error::Invalid(e.to_string()))?;
+    buf.as_slice().try_into().map_err(|_| {
+        crate::UnpackError::Size {
+            expected: N,
+            found: buf.len(),
+        }
+        .into()
+    })
+}
+
+/// The text held by a byte-array padded by `pad`, without the padding.
+pub fn text(inp: &[u8], pad: u8) -> Result<&str, std::str::Utf8Error> {
+    let len = inp.iter().rposition(|b| *b != pad).map_or(0, |i| i + 1);
+    std::str::from_utf8(&inp[..len])
+}
+
+/// Copy the given text into a byte-array, padding the rest by `pad`; `false` if it does not fit.
+pub fn set_text(out: &mut [u8], value: &str, pad: u8) -> bool {
+    if value.len() > out.len() {
+        return false;
+    }
+    let (head, tail) = out.split_at_mut(value.len());
+    head.copy_from_slice(value.as_bytes());
+    tail.fill(pad);
+    true
+}
+
+/// Read exactly `len` bytes of variable-length data following a record. The buffer grows while
+/// reading, so a corrupt length does not allocate more than the input holds.
+pub fn read_payload<R: std::io::Read>(r: &mut R, len: usize) -> Result<Vec<u8>, crate::Error> {
+    use std::io::Read;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(crate::Error::Truncated {
+            got: buf.len(),
+            expected: len,
+        });
+    }
+    Ok(buf)
+}
+
+/// Binary search consecutive records of `size` bytes, sorted by their bytes in `range`, for
+/// one whose bytes in `range` equal `key`. A trailing partial record is ignored.
+pub fn binary_search_records(
+    records: &[u8],
+    size: usize,
+    range: core::ops::Range<usize>,
+    key: &[u8],
+) -> Result<usize, usize> {
+    let (mut lo, mut hi) = (0, records.len().checked_div(size).unwrap_or(0));
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let record = &records[mid * size..];
+        match record[range.clone()].cmp(key) {
+            core::cmp::Ordering::Less => lo = mid + 1,
+            core::cmp::Ordering::Greater => hi = mid,
+            core::cmp::Ordering::Equal => return Ok(mid),
+        }
+    }
+    Err(lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn helpers() {
+        assert_eq!((align(4, 0), align(4, 1), align(8, 12)), (0, 3, 4));
+        assert_eq!(
+            layout([(1, 1), (4, 4), (2, 2)]),
+            [(0, 0, 1), (1, 3, 7), (8, 0, 2)]
+        );
+        assert_eq!(read_field::<2>(&[1, 2, 3], 1), [2, 3]);
+        assert_eq!(write_field([0; 4], 1, [1, 2]), [0, 1, 2, 0]);
+        assert!(range_eq(&[1, 2, 3], &[0, 2, 3], 1, 2));
+        assert!(!range_eq(&[1, 2, 3], &[0, 2, 3], 0, 2));
+        assert!(range_cmp(&[1, 2, 3], &[0, 2, 3], 1, 2).is_eq());
+        assert!(range_cmp(&[1, 2, 3], &[0, 2, 4], 1, 2).is_lt());
+        assert!(range_cmp(&[1, 2, 3], &[0, 2, 3], 0, 2).is_gt());
+    }
+
+    #[test]
+    fn extended() {
+        let values = [
+            0.0,
+            -0.0,
+            1.0,
+            -2.5,
+            std::f64::consts::PI,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            5e-324,
+            1.5e-310,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for v in values {
+            for big_endian in [false, true] {
+                let f80 = f80_to_f64(f64_to_f80(v, big_endian), big_endian);
+                let f128 = f128_to_f64(f64_to_f128(v, big_endian), big_endian);
+                assert_eq!((f80.to_bits(), f128.to_bits()), (v.to_bits(), v.to_bits()));
+            }
+        }
+        assert!(f80_to_f64(f64_to_f80(f64::NAN, false), false).is_nan());
+        assert!(f128_to_f64(f64_to_f128(f64::NAN, true), true).is_nan());
+
+        // 1.0 and pi as written by x87, the latter having more precision than an f64
+        assert_eq!(
+            f64_to_f80(1.0, false),
+            [0, 0, 0, 0, 0, 0, 0, 0x80, 0xff, 0x3f]
+        );
+        let pi = [0x35, 0xc2, 0x68, 0x21, 0xa2, 0xda, 0x0f, 0xc9, 0x00, 0x40];
+        assert_eq!(f80_to_f64(pi, false), std::f64::consts::PI);
+        assert_eq!(f64_to_f128(1.0, true)[..2], [0x3f, 0xff]);
+
+        // Out of range for f64
+        let huge = [0, 0, 0, 0, 0, 0, 0, 0x80, 0xfe, 0x7f];
+        assert_eq!(f80_to_f64(huge, false), f64::INFINITY);
+        let tiny = [0, 0, 0, 0, 0, 0, 0, 0x80, 0x01, 0x80];
+        assert_eq!(f80_to_f64(tiny, false).to_bits(), (-0.0f64).to_bits());
+        // Rounding to nearest, ties to even, into the subnormal range
+        let half_min = [0, 0, 0, 0, 0, 0, 0, 0xc0, 0xcd, 0x3b];
+        assert_eq!(f80_to_f64(half_min, false), 1e-323);
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(to_hex(&[0x01, 0xab, 0xff]), "01abff");
+        assert_eq!(from_hex::<3>("01 AB\nff").unwrap(), [0x01, 0xab, 0xff]);
+        assert_eq!(
+            from_hex::<2>("01abg0").unwrap_err().to_string(),
+            "invalid record: invalid hexadecimal digit `g` at 4"
+        );
+        assert!(from_hex::<2>("01a").is_err());
+        assert!(matches!(
+            from_hex::<2>("010203"),
+            Err(crate::Error::Unpack(crate::UnpackError::Size {
+                expected: 2,
+                found: 3
+            }))
+        ));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn base64() {
+        assert_eq!(to_base64(&[0, 1, 2, 3]), "AAECAw==");
+        assert_eq!(from_base64::<4>("AAECAw==").unwrap(), [0, 1, 2, 3]);
+        assert!(from_base64::<3>("AAECAw==").is_err());
+        assert!(from_base64::<4>("AAEC!w==").is_err());
+    }
+
+    #[test]
+    fn binary_search() {
+        let records = [9, 1, 9, 3, 9, 5, 9];
+        assert_eq!(binary_search_records(&records, 2, 1..2, &[3]), Ok(1));
+        assert_eq!(binary_search_records(&records, 2, 1..2, &[4]), Err(2));
+        assert_eq!(binary_search_records(&records, 2, 1..2, &[0]), Err(0));
+        assert_eq!(binary_search_records(&records, 2, 1..2, &[6]), Err(3));
+        assert_eq!(binary_search_records(&[], 0, 0..0, &[]), Err(0));
+    }
+}
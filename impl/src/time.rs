@@ -0,0 +1,188 @@
+//! Civil dates and times, as decoded from timestamps found in binary formats.
+//!
+//! Elements holding MS-DOS timestamps (as used by FAT and ZIP) or Windows `FILETIME`s are
+//! decoded via the `timestamp`-attribute, or directly via [`DateTime::from_dos()`] and
+//! [`DateTime::from_filetime()`].
+//!
+//! ```
+//! use restruct::time::DateTime;
+//!
+//! let t = DateTime::from_dos(0x5821_6a4f);
+//! assert_eq!(t.to_string(), "2024-01-01T13:18:30");
+//! assert_eq!(t.to_dos(), Some(0x5821_6a4f));
+//! assert_eq!(DateTime::from_filetime(116_444_736_000_000_000).to_string(), "1970-01-01T00:00:00");
+//! ```
+
+/// The number of days between 1601-01-01, the epoch of `FILETIME`, and 1970-01-01
+const FILETIME_EPOCH_DAYS: i64 = 134_774;
+
+/// A date and time without a timezone. MS-DOS timestamps are in local time, `FILETIME`s in UTC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u8,
+    /// 1 to 31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+/// The year, month and day of the given number of days since 1970-01-01
+const fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year as i32, month as u8, day as u8)
+}
+
+/// The number of days since 1970-01-01 of the given year, month and day
+const fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let y = year as i64 - (month <= 2) as i64;
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+impl DateTime {
+    /// `true` if all fields are within their range; the day is not checked against the month.
+    pub const fn is_valid(&self) -> bool {
+        self.month >= 1
+            && self.month <= 12
+            && self.day >= 1
+            && self.day <= 31
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+            && self.nanosecond < 1_000_000_000
+    }
+
+    /// Decode an MS-DOS timestamp, the date in the upper and the time in the lower 16 bits. This
+    /// is how a little-endian `I` reads the time followed by the date, as stored by FAT and ZIP.
+    /// Fields are decoded as they are, so invalid timestamps (e.g. all zeros) give invalid dates.
+    pub const fn from_dos(inp: u32) -> Self {
+        let (date, time) = ((inp >> 16) as u16, inp as u16);
+        Self {
+            year: 1980 + (date >> 9) as i32,
+            month: ((date >> 5) & 0xf) as u8,
+            day: (date & 0x1f) as u8,
+            hour: (time >> 11) as u8,
+            minute: ((time >> 5) & 0x3f) as u8,
+            second: ((time & 0x1f) * 2) as u8,
+            nanosecond: 0,
+        }
+    }
+
+    /// Encode as an MS-DOS timestamp, see `from_dos()`, or `None` if invalid or outside of the
+    /// years 1980 to 2107. Seconds are rounded down to an even number.
+    pub const fn to_dos(&self) -> Option<u32> {
+        if !self.is_valid() || self.year < 1980 || self.year > 2107 {
+            return None;
+        }
+        let date = ((self.year - 1980) as u32) << 9 | (self.month as u32) << 5 | self.day as u32;
+        let time = (self.hour as u32) << 11 | (self.minute as u32) << 5 | (self.second as u32 / 2);
+        Some(date << 16 | time)
+    }
+
+    /// Decode a Windows `FILETIME`, the number of 100-nanosecond intervals since 1601-01-01 UTC.
+    pub const fn from_filetime(inp: u64) -> Self {
+        let secs = (inp / 10_000_000) as i64;
+        let (year, month, day) = civil_from_days(secs / 86_400 - FILETIME_EPOCH_DAYS);
+        let secs = secs % 86_400;
+        Self {
+            year,
+            month,
+            day,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+            nanosecond: (inp % 10_000_000) as u32 * 100,
+        }
+    }
+
+    /// Encode as a Windows `FILETIME`, see `from_filetime()`, or `None` if invalid or before
+    /// 1601. Nanoseconds are rounded down to a multiple of 100.
+    pub const fn to_filetime(&self) -> Option<u64> {
+        if !self.is_valid() || self.year < 1601 {
+            return None;
+        }
+        let days = days_from_civil(self.year, self.month, self.day) + FILETIME_EPOCH_DAYS;
+        let secs =
+            days * 86_400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        match (secs as u64).checked_mul(10_000_000) {
+            Some(t) => t.checked_add(self.nanosecond as u64 / 100),
+            None => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    /// Render as ISO 8601, e.g. `2024-01-01T13:18:30`, with a fraction if there are nanoseconds.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        if self.nanosecond > 0 {
+            write!(f, ".{:09}", self.nanosecond)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil() {
+        for days in [-719_468, -1, 0, 59, 365, 11_016, 19_723, 2_932_896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(-FILETIME_EPOCH_DAYS), (1601, 1, 1));
+    }
+
+    #[test]
+    fn filetime() {
+        assert_eq!(
+            DateTime::from_filetime(0).to_string(),
+            "1601-01-01T00:00:00"
+        );
+        let t = DateTime::from_filetime(133_485_408_001_234_567);
+        assert_eq!(t.to_string(), "2024-01-01T00:00:00.123456700");
+        assert_eq!(t.to_filetime(), Some(133_485_408_001_234_567));
+        let t = DateTime::from_filetime(u64::MAX);
+        assert_eq!(t.to_filetime(), Some(u64::MAX));
+        let t = DateTime {
+            year: 1600,
+            ..DateTime::from_filetime(0)
+        };
+        assert_eq!(t.to_filetime(), None);
+    }
+
+    #[test]
+    fn dos() {
+        let t = DateTime::from_dos(0);
+        assert_eq!((t.year, t.month, t.day), (1980, 0, 0));
+        assert!(!t.is_valid());
+        assert_eq!(t.to_dos(), None);
+        let t = DateTime::from_dos(0xff9f_bf7d);
+        assert_eq!(t.to_string(), "2107-12-31T23:59:58");
+        assert_eq!(t.to_dos(), Some(0xff9f_bf7d));
+    }
+}
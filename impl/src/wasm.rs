@@ -0,0 +1,16 @@
+//! Helpers for the `pack_to_uint8array()` and `unpack_from_uint8array()`-functions, which are
+//! generated if the `wasm`-feature is enabled. These exchange packed data with JavaScript via
+//! `wasm-bindgen`, without going through an intermediate `Vec<u8>`.
+
+pub use js_sys::Uint8Array;
+
+/// Copy the first `N` bytes of the given array into Rust's memory.
+pub fn read_array<const N: usize>(inp: &Uint8Array) -> Result<[u8; N], crate::UnpackError> {
+    let found = inp.length() as usize;
+    if found < N {
+        return Err(crate::UnpackError::Size { expected: N, found });
+    }
+    let mut buf = [0; N];
+    inp.subarray(0, N as u32).copy_to(&mut buf);
+    Ok(buf)
+}
@@ -18,7 +18,7 @@ fn fmt_not_a_string() {
 }
 
 #[test]
-#[should_panic(expected = "fmt attribute does not take a list. Expected `#[fmt=\"...\"]`.")]
+#[should_panic(expected = "fmt attribute takes a list only to give a `cfg`-predicate.")]
 fn fmt_not_a_thing() {
     let tokens = quote::quote! {
         #[fmt(">B")]
@@ -37,3 +37,493 @@ fn fmt_attr_not_bool() {
     };
     restruct::derive(tokens);
 }
+
+#[test]
+#[should_panic(expected = "`{time_t}` has no standard size; it requires native mode (`@` or `^`).")]
+fn typedef_standard() {
+    let tokens = quote::quote! {
+        #[fmt="<I{time_t}"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "`Foo` uses native mode (`@`), so it's packed representation depends")]
+fn portability_deny_native() {
+    let tokens = quote::quote! {
+        #[fmt="bhl"]
+        #[portability="deny"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "`Foo` uses `n` or `N`, whose size depends on the platform")]
+fn portability_deny_size() {
+    let tokens = quote::quote! {
+        #[fmt="<IN"]
+        #[portability="deny"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "field_doc attribute refers to field 2, but there are only 2 fields.")]
+fn field_doc_out_of_range() {
+    let tokens = quote::quote! {
+        #[fmt="<2H"]
+        #[field_doc(field = 2, doc = "Version")]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "max_align attribute requires native mode (`@`).")]
+fn max_align_standard() {
+    let tokens = quote::quote! {
+        #[fmt="<bq"]
+        #[max_align=4]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "max_align attribute must be a power of two.")]
+fn max_align_power_of_two() {
+    let tokens = quote::quote! {
+        #[fmt="@bq"]
+        #[max_align=3]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "`2g` has no native size and alignment")]
+fn extended_native() {
+    let tokens = quote::quote! {
+        #[fmt="I2g"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(
+    expected = "extends attribute: `Base` uses the modifier `<`, but the Format String uses `>`."
+)]
+fn extends_other_modifier() {
+    restruct::derive_extends(quote::quote! {
+        { #[fmt="<I"] struct Base; }
+        #[fmt=">H"]
+        #[extends(Base)]
+        struct Foo;
+    });
+}
+
+#[test]
+fn extends_per_field() {
+    let tokens = restruct::derive(quote::quote! {
+        #[fmt="<H"]
+        #[scale(field = 0, factor = 0.5)]
+        #[restruct(extendable)]
+        struct Base;
+    })
+    .to_string();
+    assert!(tokens
+        .contains("extends attribute: `Base` can't be extended, as it uses the scale attribute."));
+}
+
+#[test]
+#[should_panic(expected = "fmt attribute takes a list only to give a `cfg`-predicate.")]
+fn fmt_cfg_without_fragment() {
+    restruct::derive(quote::quote! {
+        #[fmt(cfg(unix))]
+        struct Foo;
+    });
+}
+
+#[test]
+fn fmt_cfg_invalid_variant() {
+    // Without any fragment, there are too few elements; this is only reported if selected
+    let tokens = restruct::derive(quote::quote! {
+        #[fmt = "<H"]
+        #[fmt(cfg(target_os = "linux"), "B")]
+        #[fmt(cfg(target_os = "macos"), "H")]
+        #[names(kind, size)]
+        struct Foo;
+    })
+    .to_string();
+    assert_eq!(
+        tokens
+            .matches("names attribute gives 2 names, but there are 1 fields.")
+            .count(),
+        1
+    );
+    assert!(tokens.contains("not (target_os = \"linux\") , not (target_os = \"macos\")"));
+}
+
+#[test]
+#[should_panic(expected = "fmt attribute: At most 12 distinct `cfg`-predicates can be given.")]
+fn fmt_cfg_too_many() {
+    let fragments = (0..13).map(|i| {
+        let feature = format!("f{}", i);
+        quote::quote! { #[fmt(cfg(feature = #feature), "B")] }
+    });
+    restruct::derive(quote::quote! {
+        #[fmt = "<H"]
+        #(#fragments)*
+        struct Foo;
+    });
+}
+
+#[test]
+#[should_panic(expected = "init_from and default attributes can't be used together.")]
+fn init_from_and_default() {
+    let tokens = quote::quote! {
+        #[fmt="<I"]
+        #[init_from = "header.bin"]
+        #[default(field = 0, value = 1)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "upgrade_from attribute: `flags` is not the name of an element")]
+fn upgrade_from_unknown_name() {
+    let tokens = quote::quote! {
+        #[fmt="<magic:I version:H"]
+        #[upgrade_from(HeaderV1(magic, flags))]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "timestamp attribute requires field 0 to be of type `u64`.")]
+fn timestamp_wrong_type() {
+    let tokens = quote::quote! {
+        #[fmt="<I"]
+        #[timestamp(field = 0, kind = "filetime")]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "scale attribute requires field 1 to be an integer.")]
+fn scale_wrong_type() {
+    let tokens = quote::quote! {
+        #[fmt="<If"]
+        #[scale(field = 1, factor = 0.5)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "scale attribute: factor must not be zero.")]
+fn scale_zero_factor() {
+    let tokens = quote::quote! {
+        #[fmt="<I"]
+        #[scale(field = 0, factor = 0)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "transform attribute refers to field 2, but there are only 2 fields.")]
+fn transform_out_of_range() {
+    let tokens = quote::quote! {
+        #[fmt="<Ix2s"]
+        #[transform(field = 2, decode = "a", encode = "b")]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "checksum attribute requires the last field to be of type `u16`.")]
+fn checksum_wrong_type() {
+    let tokens = quote::quote! {
+        #[fmt="<4sI"]
+        #[checksum="crc16"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "The Format String names some elements, but not element 1.")]
+fn inline_names_incomplete() {
+    let tokens = quote::quote! {
+        #[fmt="<magic:I H"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "names are given both in the Format String and via the names attribute.")]
+fn inline_names_and_attribute() {
+    let tokens = quote::quote! {
+        #[fmt="<magic:I"]
+        #[names(magic)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "Failed to read fmt_file")]
+fn fmt_file_missing() {
+    let tokens = quote::quote! {
+        #[fmt_file="does/not/exist.fmt"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+fn fmt_env() {
+    std::env::set_var("RESTRUCT_TEST_FMT_ENV", "<2I");
+    let tokens = quote::quote! {
+        #[fmt_env="RESTRUCT_TEST_FMT_ENV"]
+        struct Foo;
+    };
+    let output = restruct::derive(tokens).to_string();
+    assert!(output.contains("env ! (\"RESTRUCT_TEST_FMT_ENV\")"));
+}
+
+#[test]
+#[should_panic(expected = "Failed to read fmt_env `RESTRUCT_TEST_FMT_ENV_MISSING`")]
+fn fmt_env_missing() {
+    let tokens = quote::quote! {
+        #[fmt_env="RESTRUCT_TEST_FMT_ENV_MISSING"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+fn debug_output_to_file() {
+    let path = std::env::temp_dir().join("restruct_debug_output_to_file.rs");
+    let _ = std::fs::remove_file(&path);
+    let path_str = path.to_str().unwrap();
+    let tokens = quote::quote! {
+        #[fmt="<I"]
+        #[debug_output=#path_str]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("Token stream for `Foo`"));
+    assert!(content.contains("pack"));
+}
+
+#[test]
+fn debug_output_is_formatted() {
+    let path = std::env::temp_dir().join("restruct_debug_output_is_formatted.rs");
+    let path_str = path.to_str().unwrap();
+    let tokens = quote::quote! {
+        #[fmt="<I"]
+        #[debug_output=#path_str]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("\n    pub const fn pack("));
+}
+
+#[test]
+fn layout_doc() {
+    let tokens = quote::quote! {
+        #[fmt="<I2H3sx?"]
+        struct Foo;
+    };
+    let output = restruct::derive(tokens).to_string();
+    assert!(output.contains("Byte order: little-endian; standard sizes, no alignment."));
+    assert!(output.contains("| 1..=2 | `2H` | `u16` | 4 | 4 |"));
+    assert!(output.contains("| - | `x` | padding | 11 | 1 |"));
+    assert!(output.contains("| 4 | `?` | `bool` | 12 | 1 |"));
+}
+
+#[test]
+fn layout_output() {
+    let path = std::env::temp_dir().join("restruct_layout_output.txt");
+    let path_str = path.to_str().unwrap();
+    let tokens = quote::quote! {
+        #[fmt=">I2H3sx?"]
+        #[layout_output=#path_str]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("13 bytes"));
+    assert!(content.contains("0000:   0  0  0  0  1  1  2  2  3  3  3 xx  4"));
+    assert!(content.contains("   3: `3s` [u8; 3] (offset 8, size 3)"));
+}
+
+#[test]
+#[should_panic(expected = "default attribute refers to field 2, but there are only 2 fields.")]
+fn default_out_of_range() {
+    let tokens = quote::quote! {
+        #[fmt="<IH"]
+        #[default(field = 2, value = 1)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "names attribute gives 1 names, but there are 2 fields.")]
+fn names_mismatch() {
+    let tokens = quote::quote! {
+        #[fmt="<IH"]
+        #[names(foo)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+fn codegen() {
+    let code = restruct::codegen("Foo", "<I", restruct::Options::default());
+    assert!(code.starts_with("struct Foo;\n"));
+    assert!(code.contains("\n    pub const fn pack("));
+    let options = restruct::Options::default()
+        .with_vis("pub(crate)")
+        .with_names(["magic"])
+        .with_default(0, "0xa1b2c3d4");
+    let code = restruct::codegen("Foo", "<I", options);
+    assert!(code.starts_with("pub(crate) struct Foo;\n"));
+    assert!(code.contains("Some(\"magic\")"));
+    // Optional families are only generated if asked for
+    assert!(!code.contains("FooBuilder") && !code.contains("Column<0>"));
+    assert!(!code.contains("CsvRecord") && !code.contains("FooPacked"));
+    let options = restruct::Options::default()
+        .with_names(["magic"])
+        .with_builder()
+        .with_columns()
+        .with_csv()
+        .with_packed();
+    let code = restruct::codegen("Foo", "<I", options);
+    assert!(code.contains("struct FooBuilder(") && code.contains("impl restruct::Column<0usize>"));
+    assert!(code.contains("impl restruct::csv::CsvRecord for Foo"));
+    assert!(code.contains("struct FooPacked("));
+}
+
+#[test]
+#[should_panic(expected = "restruct attribute: `builder` requires the elements to be named.")]
+fn builder_without_names() {
+    let tokens = quote::quote! {
+        #[fmt="<I"]
+        #[restruct(builder)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+fn codegen_native_byte_order() {
+    // No byte order conversion is generated for native byte order; whether the copies of the
+    // fields are elided is up to the optimizer
+    let code = restruct::codegen("Foo", "=IHq", restruct::Options::default());
+    let unpack = &code[code.find("pub const fn unpack(").unwrap()..];
+    let unpack = &unpack[..unpack.find("\n    }\n").unwrap()];
+    assert_eq!(unpack.matches("from_ne_bytes(").count(), 3);
+    assert!(!unpack.contains("from_le_bytes") && !unpack.contains("from_be_bytes"));
+    assert!(!code.contains("swap_bytes"));
+}
+
+#[test]
+fn codegen_volatile() {
+    // Numbers are accessed at their full width, through a pointer to their primitive type
+    let code = restruct::codegen("Foo", ">Hd3s", restruct::Options::default());
+    let read = &code[code.find("pub unsafe fn read_volatile").unwrap()..];
+    let read = &read[..read.find("\n    }\n").unwrap()];
+    assert!(read.contains("as *const u16") && read.contains("as *const u64"));
+    let write = &code[code.find("pub unsafe fn write_volatile").unwrap()..];
+    let write = &write[..write.find("\n    }\n").unwrap()];
+    assert!(write.contains("as *mut u16") && write.contains("as *mut u64"));
+}
+
+#[test]
+fn codegen_inline() {
+    let code = restruct::codegen("Foo", "<I", restruct::Options::default());
+    assert!(code.contains("    #[inline]\n    pub const fn unpack("));
+    let options = restruct::Options::default().with_inline(restruct::Inline::Always);
+    let code = restruct::codegen("Foo", "<I", options);
+    assert!(code.contains("    #[inline(always)]\n    pub const fn pack("));
+    assert!(!code.contains("#[inline]"));
+}
+
+#[test]
+#[should_panic(expected = "Unknown inline_hint `sometimes`.")]
+fn inline_hint_unknown() {
+    let tokens = quote::quote! {
+        #[fmt="<I"]
+        #[inline_hint="sometimes"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "`3s` can't be ascribed the type `u8`; only numbers and booleans can.")]
+fn ascription_array() {
+    let tokens = quote::quote! {
+        #[fmt="<3s:u8"]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "test_vector attribute: bytes must be hexadecimal.")]
+fn test_vector_odd() {
+    let tokens = quote::quote! {
+        #[fmt="<H"]
+        #[test_vector(bytes = "DE A", values = (0xade,))]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+fn codegen_module() {
+    let options = restruct::Options::default().with_module("wire");
+    let code = restruct::codegen("Foo", "<I", options);
+    assert!(code.contains("mod wire {"));
+    assert!(code.contains("    pub(super) struct Foo;"));
+    assert!(code.contains("impl restruct::Struct for Foo {"));
+}
+
+#[test]
+#[should_panic(expected = "impl_codec attribute must start with the trait to implement.")]
+fn impl_codec_without_trait() {
+    let tokens = quote::quote! {
+        #[fmt="<H"]
+        #[impl_codec(encode = "write")]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}
+
+#[test]
+#[should_panic(expected = "text attribute requires field 1 to be a `s`.")]
+fn text_not_array() {
+    let tokens = quote::quote! {
+        #[fmt="<4sH"]
+        #[text(field = 1)]
+        struct Foo;
+    };
+    restruct::derive(tokens);
+}